@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MODES: [&str; 3] = ["auto", "on", "off"];
+
+const SCAN_ENTRIES: usize = 64;
+
+pub fn resolve(mode: &str, roots: &[PathBuf]) -> bool {
+    match mode {
+        "on" => true,
+        "off" => false,
+        _ => roots.iter().any(|r| probe(r)),
+    }
+}
+
+pub fn probe(root: &Path) -> bool {
+    let dir = if root.is_dir() {
+        root
+    } else {
+        match root.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        }
+    };
+    existing_entry(dir)
+        .or_else(|| ancestor_entry(dir))
+        .unwrap_or(cfg!(any(windows, target_os = "macos")))
+}
+
+pub fn key(path: &Path, fold: bool) -> PathBuf {
+    if fold {
+        PathBuf::from(path.to_string_lossy().to_lowercase())
+    } else {
+        path.to_path_buf()
+    }
+}
+
+pub fn same(a: &str, b: &str, fold: bool) -> bool {
+    if fold {
+        a.to_lowercase() == b.to_lowercase()
+    } else {
+        a == b
+    }
+}
+
+fn swap_case(name: &str) -> String {
+    name.chars()
+        .flat_map(|c| {
+            if c.is_lowercase() {
+                c.to_uppercase().collect::<Vec<_>>()
+            } else {
+                c.to_lowercase().collect()
+            }
+        })
+        .collect()
+}
+
+fn existing_entry(dir: &Path) -> Option<bool> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .take(SCAN_ENTRIES)
+        .find_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let swapped = swap_case(&name);
+            (swapped != name).then(|| same_file(&entry.path(), &dir.join(swapped)))
+        })
+}
+
+fn ancestor_entry(dir: &Path) -> Option<bool> {
+    let dir = fs::canonicalize(dir).ok()?;
+    dir.ancestors().find_map(|path| {
+        let name = path.file_name()?.to_str()?;
+        let swapped = swap_case(name);
+        (swapped != name).then(|| same_file(path, &path.with_file_name(swapped)))
+    })
+}
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::symlink_metadata(a), fs::symlink_metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(_: &Path, b: &Path) -> bool {
+    fs::symlink_metadata(b).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn sensitive_fs(dir: &Path) -> bool {
+        fs::write(dir.join("casecheck"), "").unwrap();
+        let sensitive = fs::symlink_metadata(dir.join("CASECHECK")).is_err();
+        fs::remove_file(dir.join("casecheck")).unwrap();
+        sensitive
+    }
+
+    #[test]
+    fn swap_case_flips_letters_only() {
+        assert_eq!(swap_case("Readme-2.md"), "rEADME-2.MD");
+        assert_eq!(swap_case("123"), "123");
+    }
+
+    #[test]
+    fn probe_reads_existing_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("Readme.md"), "x").unwrap();
+        assert_eq!(existing_entry(tmp.path()), Some(!sensitive_fs(tmp.path())));
+    }
+
+    #[test]
+    fn probe_without_swappable_entries_falls_back_to_ancestors() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("Evidence").join("123");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("456"), "x").unwrap();
+        assert_eq!(existing_entry(&dir), None);
+        assert_eq!(ancestor_entry(&dir), Some(!sensitive_fs(tmp.path())));
+    }
+
+    #[test]
+    fn probe_never_writes_into_the_searched_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("123");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("456"), "x").unwrap();
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_577_836_800);
+        fs::File::open(&dir).unwrap().set_modified(old).unwrap();
+        probe(&dir);
+        assert_eq!(fs::metadata(&dir).unwrap().modified().unwrap(), old);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn linux_defaults_to_case_sensitive() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::write(tmp.path().join("Readme.md"), "x").unwrap();
+        assert!(!resolve("auto", &[tmp.path().to_path_buf()]));
+        assert!(!same("Readme.md", "README.md", false));
+        assert!(same("Readme.md", "README.md", true));
+        assert_eq!(key(Path::new("A/B"), true), PathBuf::from("a/b"));
+    }
+}
@@ -0,0 +1,71 @@
+use crate::MatchResult;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub version: u32,
+    pub fingerprint: String,
+    pub counters: BTreeMap<String, usize>,
+    pub processed: Vec<PathBuf>,
+    pub results: Vec<MatchResult>,
+}
+
+impl Checkpoint {
+    pub fn new(fingerprint: String) -> Self {
+        Checkpoint {
+            version: CHECKPOINT_VERSION,
+            fingerprint,
+            counters: BTreeMap::new(),
+            processed: Vec::new(),
+            results: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path, fingerprint: &str) -> Result<Option<Checkpoint>, String> {
+        let data = match fs::read(path) {
+            Ok(d) => d,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("cannot read checkpoint {}: {e}", path.display())),
+        };
+        let corrupt = |e: serde_json::Error| {
+            format!(
+                "checkpoint {} is corrupt ({e}); delete it to start a fresh run",
+                path.display()
+            )
+        };
+        let value: serde_json::Value = serde_json::from_slice(&data).map_err(corrupt)?;
+        let version = value.get("version").and_then(serde_json::Value::as_u64);
+        if version != Some(u64::from(CHECKPOINT_VERSION)) {
+            return Err(format!(
+                "checkpoint {} has format version {}, this build expects {CHECKPOINT_VERSION}; \
+                 delete it to start a fresh run",
+                path.display(),
+                version.map_or_else(|| "unknown".to_string(), |v| v.to_string())
+            ));
+        }
+        let checkpoint: Checkpoint = serde_json::from_value(value).map_err(corrupt)?;
+        if checkpoint.fingerprint != fingerprint {
+            return Err(format!(
+                "checkpoint {} was written by a search with different options; \
+                 rerun with the same options or delete it to start a fresh run",
+                path.display()
+            ));
+        }
+        Ok(Some(checkpoint))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp, path)
+    }
+}
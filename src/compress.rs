@@ -0,0 +1,100 @@
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+pub const FORMATS: [&str; 2] = ["gzip", "zstd"];
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+pub fn with_extension(path: &Path, format: &str) -> PathBuf {
+    let ext = match format {
+        "gzip" => "gz",
+        "zstd" => "zst",
+        _ => return path.to_path_buf(),
+    };
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+    {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(ext);
+    PathBuf::from(name)
+}
+
+enum Sink {
+    Plain(BufWriter<File>),
+    Gzip(BufWriter<GzEncoder<File>>),
+    Zstd(BufWriter<zstd::Encoder<'static, File>>),
+}
+
+pub struct Output {
+    sink: Sink,
+    written: u64,
+}
+
+impl Output {
+    pub fn create(path: &Path, format: Option<&str>) -> io::Result<Output> {
+        let file = File::create(path)?;
+        let sink = match format {
+            Some("gzip") => {
+                Sink::Gzip(BufWriter::new(GzEncoder::new(file, Compression::default())))
+            }
+            Some("zstd") => Sink::Zstd(BufWriter::new(zstd::Encoder::new(file, 0)?)),
+            _ => Sink::Plain(BufWriter::new(file)),
+        };
+        Ok(Output { sink, written: 0 })
+    }
+
+    pub fn finish(self) -> io::Result<u64> {
+        match self.sink {
+            Sink::Plain(mut w) => w.flush()?,
+            Sink::Gzip(w) => {
+                w.into_inner().map_err(|e| e.into_error())?.finish()?;
+            }
+            Sink::Zstd(w) => {
+                w.into_inner().map_err(|e| e.into_error())?.finish()?;
+            }
+        }
+        Ok(self.written)
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = match &mut self.sink {
+            Sink::Plain(w) => w.write(buf)?,
+            Sink::Gzip(w) => w.write(buf)?,
+            Sink::Zstd(w) => w.write(buf)?,
+        };
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.sink {
+            Sink::Plain(w) => w.flush(),
+            Sink::Gzip(w) => w.flush(),
+            Sink::Zstd(w) => w.flush(),
+        }
+    }
+}
+
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let data = fs::read(path)?;
+    let mut out = Vec::new();
+    if data.starts_with(&GZIP_MAGIC) {
+        MultiGzDecoder::new(data.as_slice()).read_to_end(&mut out)?;
+    } else if data.starts_with(&ZSTD_MAGIC) {
+        zstd::Decoder::new(data.as_slice())?.read_to_end(&mut out)?;
+    } else {
+        return Ok(data);
+    }
+    Ok(out)
+}
@@ -0,0 +1,147 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static MD_HEADING: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s{0,3}(#{1,6})\s+(.+?)\s*#*\s*$").unwrap());
+
+static RUST_ITEM: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:const\s+)?(?:async\s+)?(?:unsafe\s+)?(fn|struct|enum|trait|mod|impl)\b(?:<[^>]*>)?\s+([A-Za-z_][\w:]*)")
+        .unwrap()
+});
+
+static PYTHON_ITEM: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(?:async\s+)?(def|class)\s+([A-Za-z_]\w*)").unwrap());
+
+static JS_ITEM: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^\s*(?:export\s+)?(?:default\s+)?(?:async\s+)?(function\*?|class)\s+([A-Za-z_$][\w$]*)",
+    )
+    .unwrap()
+});
+
+static JAVA_ITEM: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*(?:(?:public|protected|private|static|final|abstract|synchronized)\s+)*(class|interface|enum|[\w<>\[\],]+)\s+([A-Za-z_]\w*)\s*[({<]")
+        .unwrap()
+});
+
+static C_ITEM: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(struct|class|namespace|[A-Za-z_][\w\s\*&:<>,]*?)\s+\**([A-Za-z_][\w:~]*)\s*[({][^;]*$",
+    )
+    .unwrap()
+});
+
+fn marker_for(ext: &str) -> Option<&'static Lazy<Regex>> {
+    match ext {
+        "md" | "markdown" => Some(&MD_HEADING),
+        "rs" => Some(&RUST_ITEM),
+        "py" => Some(&PYTHON_ITEM),
+        "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => Some(&JS_ITEM),
+        "java" => Some(&JAVA_ITEM),
+        "c" | "h" | "cpp" | "hpp" | "cc" | "cxx" => Some(&C_ITEM),
+        _ => None,
+    }
+}
+
+pub fn enclosing_heading(text: &str, match_start: usize, ext: Option<&str>) -> Option<String> {
+    let ext = ext?.to_ascii_lowercase();
+    let marker = marker_for(&ext)?;
+
+    let line_end = text[match_start.min(text.len())..]
+        .find(['\n', '\r'])
+        .map(|i| match_start + i)
+        .unwrap_or(text.len());
+
+    text[..line_end].split(['\n', '\r']).rev().find_map(|line| {
+        let caps = marker.captures(line)?;
+        let kind = caps.get(1)?.as_str();
+        let name = caps.get(2)?.as_str();
+        if ext == "md" || ext == "markdown" {
+            return Some(format!("{kind} {name}"));
+        }
+        match kind {
+            "fn" | "struct" | "enum" | "trait" | "mod" | "impl" | "def" | "class" | "function"
+            | "function*" | "interface" | "namespace" => Some(format!("{kind} {name}")),
+            _ => Some(name.to_string()),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heading(text: &str, needle: &str, ext: Option<&str>) -> Option<String> {
+        enclosing_heading(text, text.find(needle).unwrap(), ext)
+    }
+
+    #[test]
+    fn rust_items() {
+        let text = "use std::fs;\n\npub(crate) async fn parse_config() {\n    let x = needle;\n}\n";
+        assert_eq!(
+            heading(text, "needle", Some("rs")).as_deref(),
+            Some("fn parse_config")
+        );
+
+        let text = "impl<T> Parser {\n    const LIMIT: usize = 1;\n    // needle\n}\n";
+        assert_eq!(
+            heading(text, "needle", Some("RS")).as_deref(),
+            Some("impl Parser")
+        );
+    }
+
+    #[test]
+    fn python_items() {
+        let text = "class Loader:\n    def read(self):\n        return needle\n";
+        assert_eq!(
+            heading(text, "needle", Some("py")).as_deref(),
+            Some("def read")
+        );
+        assert_eq!(
+            heading(text, "read", Some("py")).as_deref(),
+            Some("def read")
+        );
+        let text = "import os\n\nasync def fetch():\n    await needle()\n";
+        assert_eq!(
+            heading(text, "needle", Some("py")).as_deref(),
+            Some("def fetch")
+        );
+    }
+
+    #[test]
+    fn markdown_headings() {
+        let text = "# Guide\n\nintro\n\n## Install ##\n\nrun needle\n";
+        assert_eq!(
+            heading(text, "needle", Some("md")).as_deref(),
+            Some("## Install")
+        );
+        assert_eq!(
+            heading(text, "intro", Some("markdown")).as_deref(),
+            Some("# Guide")
+        );
+        assert_eq!(heading("#hashtag needle\n", "needle", Some("md")), None);
+    }
+
+    #[test]
+    fn crlf_lines_are_split() {
+        let text = "fn first() {}\r\nfn second() {\r\n    needle\r\n}\r\n";
+        assert_eq!(
+            heading(text, "needle", Some("rs")).as_deref(),
+            Some("fn second")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_none() {
+        assert_eq!(heading("fn main() { needle }", "needle", None), None);
+        assert_eq!(
+            heading("fn main() {\n needle\n}", "needle", Some("txt")),
+            None
+        );
+        assert_eq!(
+            heading("needle before any item\n", "needle", Some("rs")),
+            None
+        );
+        assert_eq!(heading("needle\n", "needle", Some("md")), None);
+    }
+}
@@ -0,0 +1,305 @@
+use crate::{memory, RunStats, SearchArgs};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+const TOP_SKIPS: usize = 3;
+const MOSTLY: usize = 90;
+const OFTEN: usize = 50;
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+pub struct Diagnosis {
+    pub stages: Vec<Stage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub top_skips: Vec<Stage>,
+    pub hints: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+pub struct Stage {
+    pub label: String,
+    pub files: usize,
+}
+
+struct Facts<'a> {
+    stats: &'a RunStats,
+    args: &'a SearchArgs,
+    walked: usize,
+    candidates: usize,
+    filtered: usize,
+    considered: usize,
+    scanned: usize,
+}
+
+impl Facts<'_> {
+    fn skipped(&self, key: &str) -> usize {
+        self.stats.skips.get(key).copied().unwrap_or_default()
+    }
+
+    fn share(&self, n: usize) -> usize {
+        (n * 100).checked_div(self.considered).unwrap_or_default()
+    }
+
+    fn content_search(&self) -> bool {
+        !self.args.content.is_empty()
+            || self.args.content_file.is_some()
+            || !self.args.rule.is_empty()
+    }
+}
+
+struct Rule {
+    applies: fn(&Facts) -> bool,
+    hint: fn(&Facts) -> String,
+}
+
+const RULES: &[Rule] = &[
+    Rule {
+        applies: |f| f.walked == 0 && f.stats.paths_ignored == 0,
+        hint: |f| {
+            let dirs: Vec<String> = f.args.dir.iter().map(|d| d.display().to_string()).collect();
+            format!(
+                "no files were found under `{}`; check --dir",
+                dirs.join("`, `")
+            )
+        },
+    },
+    Rule {
+        applies: |f| f.walked == 0 && f.stats.paths_ignored > 0,
+        hint: |f| {
+            format!(
+                "all {} paths were skipped by ignore files; try --no-ignore-vcs or \
+                 --no-ignore-global, or --explain-file PATH to see the rule",
+                grouped(f.stats.paths_ignored)
+            )
+        },
+    },
+    Rule {
+        applies: |f| f.candidates == 0 && f.stats.files_excluded_by_ext > 0,
+        hint: |f| match &f.args.ext {
+            Some(ext) => format!(
+                "all {} candidate files were excluded by --ext {ext}",
+                grouped(f.stats.files_excluded_by_ext)
+            ),
+            None => format!(
+                "all {} candidate files were excluded by the default text extension set; \
+                 pass --ext or --include-no-ext",
+                grouped(f.stats.files_excluded_by_ext)
+            ),
+        },
+    },
+    Rule {
+        applies: |f| f.candidates == 0 && f.stats.files_unchanged > 0,
+        hint: |f| {
+            format!(
+                "all {} files were unchanged since {}",
+                grouped(f.stats.files_unchanged),
+                f.stats
+                    .changed_since
+                    .as_deref()
+                    .unwrap_or("--changed-since")
+            )
+        },
+    },
+    Rule {
+        applies: |f| f.filtered == 0 && f.stats.files_excluded_by_kind > 0,
+        hint: |f| {
+            format!(
+                "all {} remaining files were excluded by --kind {}",
+                grouped(f.stats.files_excluded_by_kind),
+                f.args.kind.as_deref().unwrap_or_default()
+            )
+        },
+    },
+    Rule {
+        applies: |f| f.filtered == 0 && f.stats.files_excluded_by_lang > 0,
+        hint: |f| {
+            format!(
+                "all {} remaining files were excluded by --lang {}",
+                grouped(f.stats.files_excluded_by_lang),
+                f.args.lang.as_deref().unwrap_or_default()
+            )
+        },
+    },
+    Rule {
+        applies: |f| f.considered == 0 && f.stats.files_excluded_by_name > 0,
+        hint: |f| {
+            format!(
+                "all {} files were excluded by --name or --not-name",
+                grouped(f.stats.files_excluded_by_name)
+            )
+        },
+    },
+    Rule {
+        applies: |f| f.considered == 0 && f.stats.files_excluded_by_mime > 0,
+        hint: |f| {
+            format!(
+                "all {} files were excluded by --mime or --exclude-mime",
+                grouped(f.stats.files_excluded_by_mime)
+            )
+        },
+    },
+    Rule {
+        applies: |f| {
+            f.content_search() && !f.args.binary && f.share(f.skipped("non_text")) >= MOSTLY
+        },
+        hint: |f| {
+            format!(
+                "content search was requested but {}% of files were skipped as non-text; \
+                 pass --binary to match raw bytes",
+                f.share(f.skipped("non_text"))
+            )
+        },
+    },
+    Rule {
+        applies: |f| f.share(f.skipped("too_large")) >= OFTEN,
+        hint: |f| {
+            format!(
+                "{} files were larger than --max-bytes {}; raise --max-bytes",
+                grouped(f.skipped("too_large")),
+                memory::format_size(f.args.max_bytes)
+            )
+        },
+    },
+    Rule {
+        applies: |f| !f.args.binary && f.share(f.skipped("non_utf8")) >= OFTEN,
+        hint: |f| {
+            format!(
+                "{} files were skipped as non-UTF-8; pass --binary to match raw bytes",
+                grouped(f.skipped("non_utf8"))
+            )
+        },
+    },
+    Rule {
+        applies: |f| f.stats.pdfs_skipped_disabled > 0,
+        hint: |f| {
+            format!(
+                "{} PDF files were not searched; pass --include-pdf to include them",
+                grouped(f.stats.pdfs_skipped_disabled)
+            )
+        },
+    },
+    Rule {
+        applies: |f| f.stats.matches_excluded_by_provenance > 0,
+        hint: |f| {
+            format!(
+                "{} matches were dropped by --provenance",
+                grouped(f.stats.matches_excluded_by_provenance)
+            )
+        },
+    },
+    Rule {
+        applies: |f| f.stats.discovery_truncated || f.stats.dirs_depth_limited > 0,
+        hint: |_| {
+            "discovery stopped early (--max-total-bytes or --max-depth); some files were never \
+             considered"
+                .to_string()
+        },
+    },
+    Rule {
+        applies: |f| {
+            f.scanned > 0
+                && !f.args.fixed_strings
+                && f.args.content.iter().any(|p| {
+                    p.contains(['.', '*', '+', '?', '(', ')', '[', '{', '|', '\\', '$', '^'])
+                })
+        },
+        hint: |f| {
+            format!(
+                "{} files were read without a match; the pattern has regex metacharacters, try \
+                 --fixed-strings for a literal search",
+                grouped(f.scanned)
+            )
+        },
+    },
+];
+
+pub fn diagnose(stats: &RunStats, args: &SearchArgs) -> Diagnosis {
+    let walked = stats.files_discovered + stats.files_excluded_by_ext + stats.files_unchanged;
+    let candidates = stats.files_discovered;
+    let filtered = candidates.saturating_sub(
+        stats.files_excluded_self
+            + stats.files_excluded_by_kind
+            + stats.files_excluded_by_lang
+            + stats.files_deduplicated
+            + stats.files_deduped_hardlinks,
+    );
+    let considered =
+        filtered.saturating_sub(stats.files_excluded_by_name + stats.files_excluded_by_mime);
+    let scanned =
+        stats.files_scanned_text + stats.files_scanned_pdf + stats.files_scanned_extractor;
+    let facts = Facts {
+        stats,
+        args,
+        walked,
+        candidates,
+        filtered,
+        considered,
+        scanned,
+    };
+
+    let stage = |label: &str, files| Stage {
+        label: label.to_string(),
+        files,
+    };
+    let stages = vec![
+        stage("walked", walked),
+        stage("after extension filter", candidates),
+        stage("after kind, language and dedup filters", filtered),
+        stage("after name and mime filters", considered),
+        stage("read for content", scanned),
+    ];
+
+    let mut skips: Vec<(&str, usize)> = stats
+        .skips
+        .iter()
+        .filter(|(_, n)| **n > 0)
+        .map(|(k, n)| (*k, *n))
+        .collect();
+    skips.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let top_skips = skips
+        .into_iter()
+        .take(TOP_SKIPS)
+        .map(|(k, n)| stage(k, n))
+        .collect();
+
+    let mut hints: Vec<String> = RULES
+        .iter()
+        .filter(|r| (r.applies)(&facts))
+        .map(|r| (r.hint)(&facts))
+        .collect();
+    if hints.is_empty() && scanned > 0 {
+        hints.push(format!(
+            "{} files were read and none matched the pattern",
+            grouped(scanned)
+        ));
+    }
+    Diagnosis {
+        stages,
+        top_skips,
+        hints,
+    }
+}
+
+pub fn grouped(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grouped_inserts_thousands_separators() {
+        assert_eq!(grouped(0), "0");
+        assert_eq!(grouped(999), "999");
+        assert_eq!(grouped(1_204), "1,204");
+        assert_eq!(grouped(12_345_678), "12,345,678");
+    }
+}
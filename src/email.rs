@@ -0,0 +1,259 @@
+use mailparse::{DispositionType, MailHeader, ParsedMail};
+use std::io::{self, BufRead, Read};
+
+const MAX_DEPTH: usize = 8;
+
+pub struct Message {
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Message {
+    pub fn admits(&self, filters: &[(String, String)]) -> bool {
+        filters.iter().all(|(name, needle)| {
+            self.headers.iter().any(|(n, v)| {
+                n.eq_ignore_ascii_case(name) && v.to_lowercase().contains(needle.as_str())
+            })
+        })
+    }
+}
+
+pub fn is_email(ext: Option<&str>) -> bool {
+    ext.is_some_and(|e| e.eq_ignore_ascii_case("eml") || e.eq_ignore_ascii_case("mbox"))
+}
+
+pub fn is_mbox(ext: Option<&str>) -> bool {
+    ext.is_some_and(|e| e.eq_ignore_ascii_case("mbox"))
+}
+
+pub fn parse_filter(spec: &str) -> Result<(String, String), String> {
+    match spec.split_once(':') {
+        Some((name, needle)) if !name.trim().is_empty() && !needle.is_empty() => {
+            Ok((name.trim().to_string(), needle.to_lowercase()))
+        }
+        _ => Err(format!(
+            "invalid --email-header `{spec}`: expected NAME:SUBSTRING"
+        )),
+    }
+}
+
+pub struct Mbox<R> {
+    reader: R,
+    raw_limit: usize,
+    line: Vec<u8>,
+    seen_from: bool,
+    blank: bool,
+    done: bool,
+}
+
+pub struct RawMessage {
+    pub bytes: Vec<u8>,
+    pub read: usize,
+    pub truncated: bool,
+}
+
+impl<R: BufRead> Mbox<R> {
+    pub fn new(reader: R, raw_limit: usize) -> Self {
+        Mbox {
+            reader,
+            raw_limit,
+            line: Vec::new(),
+            seen_from: false,
+            blank: true,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Mbox<R> {
+    type Item = io::Result<RawMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut message = RawMessage {
+            bytes: Vec::new(),
+            read: 0,
+            truncated: false,
+        };
+        loop {
+            self.line.clear();
+            match self.reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => {
+                    self.done = true;
+                    let last = self.seen_from || !message.bytes.is_empty();
+                    return last.then_some(Ok(message));
+                }
+                Ok(n) => message.read += n,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+            let line = self.line.strip_suffix(b"\n").unwrap_or(&self.line);
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            if self.blank && line.starts_with(b"From ") {
+                self.blank = false;
+                if self.seen_from {
+                    return Some(Ok(message));
+                }
+                self.seen_from = true;
+                message.bytes.clear();
+                message.truncated = false;
+                continue;
+            }
+            self.blank = line.is_empty();
+            let quoted = line.iter().take_while(|b| **b == b'>').count();
+            let line = if quoted > 0 && line[quoted..].starts_with(b"From ") {
+                &line[1..]
+            } else {
+                line
+            };
+            if message.bytes.len() + line.len() < self.raw_limit {
+                message.bytes.extend_from_slice(line);
+                message.bytes.push(b'\n');
+            } else {
+                message.truncated = true;
+            }
+        }
+    }
+}
+
+pub fn single<R: Read>(reader: R, raw_limit: usize) -> io::Result<RawMessage> {
+    let mut bytes = Vec::new();
+    let read = reader.take(raw_limit as u64 + 1).read_to_end(&mut bytes)?;
+    let truncated = bytes.len() > raw_limit;
+    bytes.truncate(raw_limit);
+    Ok(RawMessage {
+        bytes,
+        read,
+        truncated,
+    })
+}
+
+pub fn parse(raw: &[u8]) -> Result<Message, String> {
+    let raw = match raw.strip_prefix(b"From ") {
+        Some(rest) => rest
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(&[][..], |i| &rest[i + 1..]),
+        None => raw,
+    };
+    let mail = mailparse::parse_mail(raw).map_err(|e| e.to_string())?;
+    if mail.headers.is_empty() {
+        return Err("no header block".to_string());
+    }
+    let headers = headers(&mail.headers);
+    let mut body = String::new();
+    entity_text(&mail, 0, &mut body)?;
+    Ok(Message { headers, body })
+}
+
+fn headers(headers: &[MailHeader]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|h| (h.get_key(), h.get_value()))
+        .collect()
+}
+
+fn entity_text(part: &ParsedMail, depth: usize, out: &mut String) -> Result<(), String> {
+    if depth > MAX_DEPTH {
+        return Err("multipart nesting too deep".to_string());
+    }
+    let mime = part.ctype.mimetype.as_str();
+    if mime.starts_with("multipart/") {
+        for sub in &part.subparts {
+            entity_text(sub, depth + 1, out)?;
+        }
+    } else if mime == "message/rfc822" {
+        let raw = part.get_body_raw().map_err(|e| e.to_string())?;
+        let inner = mailparse::parse_mail(&raw).map_err(|e| e.to_string())?;
+        for (name, value) in headers(&inner.headers) {
+            push_text(out, &format!("{name}: {value}"));
+        }
+        entity_text(&inner, depth + 1, out)?;
+    } else if mime.starts_with("text/")
+        && part.get_content_disposition().disposition != DispositionType::Attachment
+    {
+        push_text(out, &part.get_body().map_err(|e| e.to_string())?);
+    }
+    Ok(())
+}
+
+fn push_text(out: &mut String, text: &str) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(mbox: &str, limit: usize) -> Vec<RawMessage> {
+        Mbox::new(mbox.as_bytes(), limit)
+            .collect::<io::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn decodes_quoted_printable_and_base64_text() {
+        let raw = "Subject: t\r\nContent-Type: multipart/mixed; boundary=b\r\n\r\n\
+--b\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: quoted-printable\r\n\r\n\
+caf=C3=A9 ok\r\n\
+--b\r\nContent-Type: text/plain\r\nContent-Transfer-Encoding: base64\r\n\r\n\
+aGVsbG8gYmFzZTY0\r\n\
+--b\r\nContent-Type: application/octet-stream\r\nContent-Disposition: attachment\r\nContent-Transfer-Encoding: base64\r\n\r\n\
+c2VjcmV0IGJsb2I=\r\n\
+--b--\r\n";
+        let message = parse(raw.as_bytes()).unwrap();
+        assert!(message.body.contains("café ok"));
+        assert!(message.body.contains("hello base64"));
+        assert!(!message.body.contains("secret blob"));
+    }
+
+    #[test]
+    fn decodes_encoded_word_headers() {
+        let raw = "Subject: =?utf-8?B?Y2lhbyDDqA==?=\r\nFrom: a@b\r\n\r\nbody\r\n";
+        let message = parse(raw.as_bytes()).unwrap();
+        assert!(message
+            .headers
+            .iter()
+            .any(|(n, v)| n == "Subject" && v == "ciao è"));
+        assert!(message.admits(&[("subject".to_string(), "ciao".to_string())]));
+    }
+
+    #[test]
+    fn splits_mbox_and_unescapes_from_lines() {
+        let mbox = "From a@b Mon Jan  1 00:00:00 2024\nSubject: one\n\n>From here\n\n\
+From c@d Mon Jan  1 00:00:00 2024\nSubject: two\n\n>>From there\n";
+        let raw = messages(mbox, 1 << 20);
+        assert_eq!(raw.len(), 2);
+        let first = parse(&raw[0].bytes).unwrap();
+        let second = parse(&raw[1].bytes).unwrap();
+        assert!(first.body.starts_with("From here"));
+        assert!(second.body.starts_with(">From there"));
+        assert_eq!(raw.iter().map(|m| m.read).sum::<usize>(), mbox.len());
+    }
+
+    #[test]
+    fn caps_raw_message_size() {
+        let mbox = format!(
+            "From a@b Mon Jan  1 00:00:00 2024\nSubject: big\n\n{}\n",
+            "x\n".repeat(1000)
+        );
+        let raw = messages(&mbox, 64);
+        assert_eq!(raw.len(), 1);
+        assert!(raw[0].truncated);
+        assert!(raw[0].bytes.len() <= 64);
+    }
+
+    #[test]
+    fn single_message_without_from_line() {
+        let raw = single("Subject: x\n\nbody\n".as_bytes(), 1024).unwrap();
+        assert!(!raw.truncated);
+        assert_eq!(parse(&raw.bytes).unwrap().body.trim(), "body");
+    }
+}
@@ -0,0 +1,153 @@
+use regex::Regex;
+
+pub fn regex(glob: &str, case_insensitive: bool) -> Result<Regex, String> {
+    let flags = if case_insensitive { "(?i)" } else { "" };
+    compile(glob, &format!("{flags}^{}$", translate(glob)))
+}
+
+pub fn path_regex(glob: &str) -> Result<Regex, String> {
+    let glob = glob.trim().replace('\\', "/");
+    let (body, prefix, suffix) = match glob.strip_suffix('/') {
+        Some(dir) if dir.contains('/') => (dir.trim_start_matches('/'), "^", "/"),
+        Some(dir) => (dir, "(^|/)", "/"),
+        None if glob.contains('/') => (glob.trim_start_matches('/'), "^", "$"),
+        None => (glob.as_str(), "(^|/)", "$"),
+    };
+    if body.is_empty() {
+        return Err(format!("invalid glob `{glob}`"));
+    }
+    compile(&glob, &format!("(?i){prefix}{}{suffix}", translate(body)))
+}
+
+fn compile(glob: &str, pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("invalid glob `{glob}`: {e}"))
+}
+
+fn translate(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut re = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_start = i == 0 || chars[i - 1] == '/';
+                if at_start && chars.get(i + 2) == Some(&'/') {
+                    re.push_str("(?:.*/)?");
+                    i += 3;
+                } else if at_start && i + 2 == chars.len() {
+                    re.push_str(".*");
+                    i += 2;
+                } else {
+                    re.push_str("[^/]*");
+                    i += 2;
+                }
+            }
+            '*' => {
+                re.push_str("[^/]*");
+                i += 1;
+            }
+            '?' => {
+                re.push_str("[^/]");
+                i += 1;
+            }
+            '[' => {
+                let start = i + 1;
+                let first = if matches!(chars.get(start), Some('!' | '^')) {
+                    start + 1
+                } else {
+                    start
+                };
+                let close = (first + 1..chars.len()).find(|&j| chars[j] == ']');
+                let Some(close) = close else {
+                    re.push_str(r"\[");
+                    i += 1;
+                    continue;
+                };
+                re.push('[');
+                if first > start {
+                    re.push('^');
+                }
+                for &c in &chars[first..close] {
+                    if c == '-' {
+                        re.push('-');
+                    } else {
+                        re.push_str(&regex::escape(&c.to_string()));
+                    }
+                }
+                re.push(']');
+                i = close + 1;
+            }
+            '\\' => {
+                if let Some(c) = chars.get(i + 1) {
+                    re.push_str(&regex::escape(&c.to_string()));
+                }
+                i += 2;
+            }
+            c => {
+                re.push_str(&regex::escape(&c.to_string()));
+                i += 1;
+            }
+        }
+    }
+    re
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_string_globs() {
+        let cases = [
+            ("*.log", "debug.log", true),
+            ("*.log", "logs/debug.log", false),
+            ("**/build", "a/b/build", true),
+            ("**/build", "build", true),
+            ("src/**", "src/a/b.rs", true),
+            ("a/**/z", "a/z", true),
+            ("a/**/z", "a/b/c/z", true),
+            ("file?.txt", "file1.txt", true),
+            ("file?.txt", "file/.txt", false),
+            ("[!a]*.rs", "main.rs", true),
+            ("[!a]*.rs", "app.rs", false),
+            ("[a-c].md", "b.md", true),
+            ("\\*.md", "*.md", true),
+            ("\\*.md", "x.md", false),
+            ("[oops", "[oops", true),
+        ];
+        for (glob, text, want) in cases {
+            assert_eq!(
+                regex(glob, false).unwrap().is_match(text),
+                want,
+                "{glob} ~ {text}"
+            );
+        }
+        assert!(regex("*.LOG", true).unwrap().is_match("x.log"));
+        assert!(!regex("*.LOG", false).unwrap().is_match("x.log"));
+    }
+
+    #[test]
+    fn path_globs_match_components() {
+        let cases = [
+            ("vendor/", "vendor/lib/a.go", true),
+            ("vendor/", "src/vendor/a.go", true),
+            ("vendor/", "vendored/a.go", false),
+            ("*.min.js", "static/app.min.js", true),
+            ("*.min.js", "app.min.js.map", false),
+            ("src/gen/", "src/gen/a.rs", true),
+            ("src/gen/", "lib/src/gen/a.rs", false),
+            ("docs/*.md", "docs/a.md", true),
+            ("docs/*.md", "docs/x/a.md", false),
+            ("**/fixtures/*.json", "a/b/fixtures/x.json", true),
+            ("*.PB.GO", "api/x.pb.go", true),
+        ];
+        for (glob, path, want) in cases {
+            assert_eq!(
+                path_regex(glob).unwrap().is_match(path),
+                want,
+                "{glob} ~ {path}"
+            );
+        }
+        assert!(path_regex("/").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::glob;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Source {
+    Global,
+    Vcs,
+    Ignore,
+    Rff,
+}
+
+const DIR_FILES: &[(Source, &str)] = &[
+    (Source::Vcs, ".gitignore"),
+    (Source::Ignore, ".ignore"),
+    (Source::Ignore, ".rgignore"),
+    (Source::Rff, ".rffignore"),
+];
+
+pub struct Rule {
+    pub file: PathBuf,
+    pub line: usize,
+    pub text: String,
+    pub negated: bool,
+    dir_only: bool,
+    anchored: bool,
+    regex: Regex,
+}
+
+impl Rule {
+    fn parse(raw: &str, file: &Path, line: usize) -> Option<Rule> {
+        let mut glob = raw.trim_end_matches('\r');
+        while glob.ends_with(' ') && !glob.ends_with("\\ ") {
+            glob = &glob[..glob.len() - 1];
+        }
+        if glob.is_empty() || glob.starts_with('#') {
+            return None;
+        }
+        let text = glob.to_string();
+        let negated = glob.starts_with('!');
+        if negated || glob.starts_with("\\!") || glob.starts_with("\\#") {
+            glob = &glob[1..];
+        }
+        let dir_only = glob.ends_with('/');
+        let glob = glob.trim_end_matches('/');
+        let anchored = glob.contains('/');
+        let glob = glob.trim_start_matches('/');
+        if glob.is_empty() {
+            return None;
+        }
+        Some(Rule {
+            file: file.to_path_buf(),
+            line,
+            text,
+            negated,
+            dir_only,
+            anchored,
+            regex: glob::regex(glob, false).ok()?,
+        })
+    }
+
+    fn matches(&self, relative: &str, name: &str, is_dir: bool) -> bool {
+        (is_dir || !self.dir_only)
+            && self
+                .regex
+                .is_match(if self.anchored { relative } else { name })
+    }
+}
+
+struct IgnoreFile {
+    source: Source,
+    rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+    fn load(path: &Path, source: Source) -> Option<IgnoreFile> {
+        let data = fs::read(path).ok()?;
+        let text = String::from_utf8_lossy(&data);
+        let rules: Vec<Rule> = text
+            .lines()
+            .enumerate()
+            .filter_map(|(i, l)| Rule::parse(l, path, i + 1))
+            .collect();
+        (!rules.is_empty()).then_some(IgnoreFile { source, rules })
+    }
+}
+
+fn global_file() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join("rustfilefinder").join("ignore"))
+}
+
+pub struct Ignores {
+    global: Option<IgnoreFile>,
+    vcs: bool,
+    dirs: HashMap<PathBuf, Vec<IgnoreFile>>,
+    work_trees: HashMap<PathBuf, bool>,
+}
+
+impl Ignores {
+    pub fn new(global: bool, vcs: bool) -> Ignores {
+        Ignores {
+            global: global_file()
+                .filter(|_| global)
+                .and_then(|p| IgnoreFile::load(&p, Source::Global)),
+            vcs,
+            dirs: HashMap::new(),
+            work_trees: HashMap::new(),
+        }
+    }
+
+    fn in_work_tree(&mut self, dir: &Path) -> bool {
+        let abs = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let mut unknown = Vec::new();
+        let mut found = false;
+        for ancestor in abs.ancestors() {
+            if let Some(&known) = self.work_trees.get(ancestor) {
+                found = known;
+                break;
+            }
+            unknown.push(ancestor.to_path_buf());
+            if ancestor.join(".git").exists() {
+                found = true;
+                break;
+            }
+        }
+        for dir in unknown {
+            self.work_trees.insert(dir, found);
+        }
+        found
+    }
+
+    pub fn load(&mut self, dir: &Path) {
+        if self.dirs.contains_key(dir) {
+            return;
+        }
+        let vcs = self.vcs && self.in_work_tree(dir);
+        let files = DIR_FILES
+            .iter()
+            .filter(|(source, _)| vcs || *source != Source::Vcs)
+            .filter_map(|(source, name)| IgnoreFile::load(&dir.join(name), *source))
+            .collect();
+        self.dirs.insert(dir.to_path_buf(), files);
+    }
+
+    pub fn decide(&self, root: &Path, path: &Path, is_dir: bool) -> Option<&Rule> {
+        let name = path.file_name()?.to_string_lossy();
+        let bases: Vec<&Path> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|a| a.starts_with(root))
+            .collect();
+        let relative = |base: &Path| {
+            path.strip_prefix(base)
+                .map(|r| r.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default()
+        };
+
+        let mut files: Vec<(&Path, &IgnoreFile)> = Vec::new();
+        files.extend(self.global.as_ref().map(|g| (root, g)));
+        for base in bases.iter().rev() {
+            for file in self.dirs.get(*base).into_iter().flatten() {
+                files.push((base, file));
+            }
+        }
+        files.sort_by_key(|(_, f)| f.source);
+
+        let mut decided = None;
+        for (base, file) in files {
+            let relative = relative(base);
+            for rule in &file.rules {
+                if rule.matches(&relative, &name, is_dir) {
+                    decided = Some(rule);
+                }
+            }
+        }
+        decided
+    }
+
+    pub fn ignored(&self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        self.decide(root, path, is_dir).is_some_and(|r| !r.negated)
+    }
+}
@@ -0,0 +1,350 @@
+use regex_syntax::hir::{Class, Hir, HirKind};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+
+pub const INDEX_VERSION: u32 = 1;
+
+pub const DEFAULT_INDEX_DIR: &str = ".rustfilefinder-index";
+
+const META_FILE: &str = "index.json";
+const TRIGRAMS_FILE: &str = "trigrams.bin";
+
+#[derive(Serialize, Deserialize)]
+pub struct Index {
+    pub version: u32,
+    pub roots: Vec<PathBuf>,
+    pub include_pdf: bool,
+    pub max_bytes: u64,
+    pub built_at: u64,
+    pub entries: Vec<IndexEntry>,
+    #[serde(skip)]
+    pub trigrams: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct IndexEntry {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<u64>,
+    pub kind: String,
+    pub trigrams: Option<(u64, u64)>,
+}
+
+impl IndexEntry {
+    pub fn fresh(&self, meta: &fs::Metadata) -> bool {
+        self.size == meta.len() && self.modified == modified_ns(meta)
+    }
+}
+
+impl Index {
+    pub fn load(dir: &Path) -> Result<Index, String> {
+        let meta_path = dir.join(META_FILE);
+        let data = fs::read(&meta_path).map_err(|e| {
+            format!(
+                "cannot read index {}: {e}; build it with `index build`",
+                meta_path.display()
+            )
+        })?;
+        let value: serde_json::Value = serde_json::from_slice(&data)
+            .map_err(|e| format!("index {} is corrupt ({e}); rebuild it", meta_path.display()))?;
+        let version = value.get("version").and_then(serde_json::Value::as_u64);
+        if version != Some(u64::from(INDEX_VERSION)) {
+            return Err(format!(
+                "index {} has format version {}, this build expects {INDEX_VERSION}; rebuild it",
+                dir.display(),
+                version.map_or_else(|| "unknown".to_string(), |v| v.to_string())
+            ));
+        }
+        let mut index: Index = serde_json::from_value(value)
+            .map_err(|e| format!("index {} is corrupt ({e}); rebuild it", meta_path.display()))?;
+        let trigrams_path = dir.join(TRIGRAMS_FILE);
+        index.trigrams = fs::read(&trigrams_path)
+            .map_err(|e| format!("cannot read index {}: {e}", trigrams_path.display()))?;
+        Ok(index)
+    }
+
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let write = |name: &str, data: &[u8]| {
+            let path = dir.join(name);
+            let tmp = dir.join(format!("{name}.tmp"));
+            fs::write(&tmp, data)?;
+            fs::rename(&tmp, path)
+        };
+        write(TRIGRAMS_FILE, &self.trigrams)?;
+        write(META_FILE, &serde_json::to_vec(self)?)
+    }
+
+    pub fn postings(&self, entry: &IndexEntry) -> Option<&[u8]> {
+        let (offset, len) = entry.trigrams?;
+        self.trigrams
+            .get(offset as usize..offset as usize + len as usize)
+    }
+}
+
+pub fn modified_ns(meta: &fs::Metadata) -> Option<u64> {
+    meta.modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_nanos() as u64)
+}
+
+fn fold(s: &str) -> String {
+    s.to_lowercase().replace('ς', "σ")
+}
+
+fn trigram_set(folded: &str) -> impl Iterator<Item = u32> + '_ {
+    folded
+        .as_bytes()
+        .windows(3)
+        .map(|w| (u32::from(w[0]) << 16) | (u32::from(w[1]) << 8) | u32::from(w[2]))
+}
+
+pub fn encode_trigrams(texts: &[&str]) -> Vec<u8> {
+    let mut all: Vec<u32> = Vec::new();
+    for text in texts {
+        all.extend(trigram_set(&fold(text)));
+    }
+    all.sort_unstable();
+    all.dedup();
+
+    let mut out = Vec::with_capacity(all.len() * 2);
+    let mut prev = 0;
+    for t in all {
+        let mut delta = t - prev;
+        prev = t;
+        loop {
+            let byte = (delta & 0x7f) as u8;
+            delta >>= 7;
+            if delta == 0 {
+                out.push(byte);
+                break;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+    out
+}
+
+fn decode_trigrams(data: &[u8]) -> Vec<u32> {
+    let mut out = Vec::new();
+    let mut prev = 0u32;
+    let mut delta = 0u32;
+    let mut shift = 0;
+    for &b in data {
+        delta |= u32::from(b & 0x7f) << shift;
+        if b & 0x80 == 0 {
+            prev = prev.wrapping_add(delta);
+            out.push(prev);
+            delta = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+        }
+    }
+    out
+}
+
+pub enum Query {
+    Any,
+    Literal(Vec<u32>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+impl Query {
+    pub fn literals(list: &[String]) -> Query {
+        Query::Or(list.iter().map(|l| Query::literal(l)).collect())
+    }
+
+    pub fn regex(pattern: &str) -> Query {
+        regex_syntax::Parser::new()
+            .parse(pattern)
+            .map_or(Query::Any, |hir| hir_query(&hir))
+    }
+
+    fn literal(text: &str) -> Query {
+        let folded = fold(text);
+        let set: BTreeSet<u32> = trigram_set(&folded).collect();
+        if set.is_empty() {
+            Query::Any
+        } else {
+            Query::Literal(set.into_iter().collect())
+        }
+    }
+
+    fn matches(&self, trigrams: &[u32]) -> bool {
+        match self {
+            Query::Any => true,
+            Query::Literal(needed) => needed.iter().all(|t| trigrams.binary_search(t).is_ok()),
+            Query::And(parts) => parts.iter().all(|q| q.matches(trigrams)),
+            Query::Or(parts) => parts.iter().any(|q| q.matches(trigrams)),
+        }
+    }
+}
+
+fn hir_query(hir: &Hir) -> Query {
+    if let Some(text) = literal_text(hir) {
+        return Query::literal(&text);
+    }
+    match hir.kind() {
+        HirKind::Concat(subs) => {
+            let mut parts = Vec::new();
+            let mut run = String::new();
+            for sub in subs {
+                if let Some(text) = literal_text(sub) {
+                    run.push_str(&text);
+                    continue;
+                }
+                if !run.is_empty() {
+                    parts.push(Query::literal(&std::mem::take(&mut run)));
+                }
+                parts.push(hir_query(sub));
+            }
+            if !run.is_empty() {
+                parts.push(Query::literal(&run));
+            }
+            Query::And(parts)
+        }
+        HirKind::Alternation(subs) => Query::Or(subs.iter().map(hir_query).collect()),
+        HirKind::Repetition(rep) if rep.min > 0 => hir_query(&rep.sub),
+        HirKind::Capture(cap) => hir_query(&cap.sub),
+        _ => Query::Any,
+    }
+}
+
+fn literal_text(hir: &Hir) -> Option<String> {
+    match hir.kind() {
+        HirKind::Literal(lit) => std::str::from_utf8(&lit.0).ok().map(fold),
+        HirKind::Class(Class::Unicode(class)) => {
+            let mut folded = BTreeSet::new();
+            for range in class.iter() {
+                if u32::from(range.end()) - u32::from(range.start()) > 4 {
+                    return None;
+                }
+                for c in range.start()..=range.end() {
+                    folded.insert(fold(&c.to_string()));
+                }
+            }
+            single(folded)
+        }
+        HirKind::Class(Class::Bytes(class)) => {
+            let mut folded = BTreeSet::new();
+            for range in class.iter() {
+                if !range.end().is_ascii() || range.end() - range.start() > 4 {
+                    return None;
+                }
+                for b in range.start()..=range.end() {
+                    folded.insert(fold(&char::from(b).to_string()));
+                }
+            }
+            single(folded)
+        }
+        HirKind::Capture(cap) => literal_text(&cap.sub),
+        HirKind::Concat(subs) => subs.iter().map(literal_text).collect(),
+        _ => None,
+    }
+}
+
+fn single(set: BTreeSet<String>) -> Option<String> {
+    let mut it = set.into_iter();
+    match (it.next(), it.next()) {
+        (Some(only), None) => Some(only),
+        _ => None,
+    }
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+pub struct IndexStats {
+    pub dir: String,
+    pub entries: usize,
+    pub fresh: usize,
+    pub stale: usize,
+    pub not_indexed: usize,
+    pub ruled_out: usize,
+    pub hit_rate: f64,
+}
+
+pub struct IndexFilter {
+    dir: PathBuf,
+    index: Index,
+    by_path: HashMap<PathBuf, usize>,
+    query: Query,
+    fresh: AtomicUsize,
+    stale: AtomicUsize,
+    not_indexed: AtomicUsize,
+    ruled_out: AtomicUsize,
+}
+
+impl IndexFilter {
+    pub fn open(dir: &Path, query: Query) -> Result<IndexFilter, String> {
+        let index = Index::load(dir)?;
+        let by_path = index
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.path.clone(), i))
+            .collect();
+        Ok(IndexFilter {
+            dir: dir.to_path_buf(),
+            index,
+            by_path,
+            query,
+            fresh: AtomicUsize::new(0),
+            stale: AtomicUsize::new(0),
+            not_indexed: AtomicUsize::new(0),
+            ruled_out: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn rules_out(&self, path: &Path) -> bool {
+        let entry = std::path::absolute(path)
+            .ok()
+            .and_then(|p| self.by_path.get(&p))
+            .map(|&i| &self.index.entries[i]);
+        let Some(entry) = entry else {
+            self.not_indexed.fetch_add(1, Ordering::Relaxed);
+            return false;
+        };
+        if !fs::metadata(path).is_ok_and(|m| entry.fresh(&m)) {
+            self.stale.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        self.fresh.fetch_add(1, Ordering::Relaxed);
+        let Some(postings) = self.index.postings(entry) else {
+            return false;
+        };
+        if self.query.matches(&decode_trigrams(postings)) {
+            return false;
+        }
+        self.ruled_out.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    pub fn stats(&self) -> IndexStats {
+        let fresh = self.fresh.load(Ordering::Relaxed);
+        let stale = self.stale.load(Ordering::Relaxed);
+        let not_indexed = self.not_indexed.load(Ordering::Relaxed);
+        let looked_up = fresh + stale + not_indexed;
+        IndexStats {
+            dir: self.dir.display().to_string(),
+            entries: self.index.entries.len(),
+            fresh,
+            stale,
+            not_indexed,
+            ruled_out: self.ruled_out.load(Ordering::Relaxed),
+            hit_rate: if looked_up == 0 {
+                0.0
+            } else {
+                fresh as f64 / looked_up as f64
+            },
+        }
+    }
+}
@@ -0,0 +1,481 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+pub struct Language {
+    pub name: &'static str,
+    pub kind: &'static str,
+    pub extensions: &'static [&'static str],
+    pub ignore_dirs: &'static [&'static str],
+}
+
+pub const LANGUAGES: &[Language] = &[
+    Language {
+        name: "c",
+        kind: "code",
+        extensions: &["c", "h"],
+        ignore_dirs: &["build", "cmake-build-debug", "cmake-build-release"],
+    },
+    Language {
+        name: "cpp",
+        kind: "code",
+        extensions: &["cc", "cpp", "cxx", "c++", "hpp", "hh", "hxx"],
+        ignore_dirs: &["build", "cmake-build-debug", "cmake-build-release"],
+    },
+    Language {
+        name: "csharp",
+        kind: "code",
+        extensions: &["cs"],
+        ignore_dirs: &["bin", "obj"],
+    },
+    Language {
+        name: "css",
+        kind: "code",
+        extensions: &["css", "scss", "sass", "less"],
+        ignore_dirs: &["node_modules", "dist", ".next", "bower_components"],
+    },
+    Language {
+        name: "docs",
+        kind: "doc",
+        extensions: &["md", "markdown", "rst", "txt", "adoc"],
+        ignore_dirs: &["_build", "site"],
+    },
+    Language {
+        name: "go",
+        kind: "code",
+        extensions: &["go"],
+        ignore_dirs: &["vendor"],
+    },
+    Language {
+        name: "html",
+        kind: "code",
+        extensions: &["html", "htm"],
+        ignore_dirs: &["node_modules", "dist", ".next", "bower_components"],
+    },
+    Language {
+        name: "java",
+        kind: "code",
+        extensions: &["java", "gradle"],
+        ignore_dirs: &["build", "out", ".gradle"],
+    },
+    Language {
+        name: "javascript",
+        kind: "code",
+        extensions: &["js", "jsx", "mjs", "cjs"],
+        ignore_dirs: &["node_modules", "dist", ".next", "bower_components"],
+    },
+    Language {
+        name: "kotlin",
+        kind: "code",
+        extensions: &["kt", "kts"],
+        ignore_dirs: &["build", "out", ".gradle"],
+    },
+    Language {
+        name: "lua",
+        kind: "code",
+        extensions: &["lua"],
+        ignore_dirs: &[],
+    },
+    Language {
+        name: "perl",
+        kind: "code",
+        extensions: &["pl", "pm"],
+        ignore_dirs: &[],
+    },
+    Language {
+        name: "php",
+        kind: "code",
+        extensions: &["php"],
+        ignore_dirs: &["vendor"],
+    },
+    Language {
+        name: "python",
+        kind: "code",
+        extensions: &["py", "pyi", "pyw", "ipynb"],
+        ignore_dirs: &[
+            "__pycache__",
+            ".venv",
+            "venv",
+            ".tox",
+            ".mypy_cache",
+            ".pytest_cache",
+        ],
+    },
+    Language {
+        name: "ruby",
+        kind: "code",
+        extensions: &["rb"],
+        ignore_dirs: &["vendor"],
+    },
+    Language {
+        name: "rust",
+        kind: "code",
+        extensions: &["rs"],
+        ignore_dirs: &["target"],
+    },
+    Language {
+        name: "shell",
+        kind: "code",
+        extensions: &["sh", "bash", "zsh", "ksh"],
+        ignore_dirs: &[],
+    },
+    Language {
+        name: "sql",
+        kind: "code",
+        extensions: &["sql"],
+        ignore_dirs: &[],
+    },
+    Language {
+        name: "svelte",
+        kind: "code",
+        extensions: &["svelte"],
+        ignore_dirs: &["node_modules", "dist", ".next", "bower_components"],
+    },
+    Language {
+        name: "swift",
+        kind: "code",
+        extensions: &["swift"],
+        ignore_dirs: &[".build"],
+    },
+    Language {
+        name: "typescript",
+        kind: "code",
+        extensions: &["ts", "tsx"],
+        ignore_dirs: &["node_modules", "dist", ".next", "bower_components"],
+    },
+    Language {
+        name: "vue",
+        kind: "code",
+        extensions: &["vue"],
+        ignore_dirs: &["node_modules", "dist", ".next", "bower_components"],
+    },
+];
+
+pub const GROUPS: &[(&str, &[&str])] = &[(
+    "web",
+    &["html", "css", "javascript", "typescript", "vue", "svelte"],
+)];
+
+const KIND_EXTENSIONS: &[(&str, &[&str])] = &[
+    (
+        "archive",
+        &[
+            "zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "jar",
+        ],
+    ),
+    (
+        "config",
+        &[
+            "toml",
+            "yaml",
+            "yml",
+            "ini",
+            "cfg",
+            "conf",
+            "env",
+            "properties",
+        ],
+    ),
+    (
+        "data",
+        &[
+            "json", "jsonl", "ndjson", "csv", "tsv", "xml", "parquet", "sqlite",
+        ],
+    ),
+    ("pdf", &["pdf"]),
+];
+
+pub struct Preset {
+    pub extensions: Vec<&'static str>,
+    pub ignore_dirs: Vec<&'static str>,
+}
+
+pub fn lookup(name: &str) -> Option<Preset> {
+    let name = name.trim();
+    let find = |n: &str| LANGUAGES.iter().find(|l| l.name.eq_ignore_ascii_case(n));
+    let members: Vec<&Language> = match GROUPS.iter().find(|(g, _)| g.eq_ignore_ascii_case(name)) {
+        Some((_, members)) => members.iter().filter_map(|m| find(m)).collect(),
+        None => vec![find(name)?],
+    };
+    let mut preset = Preset {
+        extensions: Vec::new(),
+        ignore_dirs: Vec::new(),
+    };
+    for l in members {
+        preset.extensions.extend(l.extensions);
+        for d in l.ignore_dirs {
+            if !preset.ignore_dirs.contains(d) {
+                preset.ignore_dirs.push(d);
+            }
+        }
+    }
+    Some(preset)
+}
+
+pub fn detected() -> impl Iterator<Item = &'static str> {
+    LANGUAGES
+        .iter()
+        .filter(|l| l.kind == "code")
+        .map(|l| l.name)
+}
+
+pub const KINDS: &[&str] = &["code", "doc", "data", "config", "archive", "pdf", "other"];
+
+pub fn kind_of(ext: Option<&str>, custom: &BTreeMap<String, String>) -> String {
+    let Some(ext) = ext.map(str::to_lowercase) else {
+        return "other".to_string();
+    };
+    if let Some(kind) = custom.get(&ext) {
+        return kind.clone();
+    }
+    let ext = ext.as_str();
+    LANGUAGES
+        .iter()
+        .find(|l| l.extensions.contains(&ext))
+        .map(|l| l.kind)
+        .or_else(|| {
+            KIND_EXTENSIONS
+                .iter()
+                .find(|(_, exts)| exts.contains(&ext))
+                .map(|(kind, _)| *kind)
+        })
+        .unwrap_or("other")
+        .to_string()
+}
+
+const SYNTAX: &[(&str, &[&str])] = &[
+    ("json", &["json", "jsonl", "ndjson"]),
+    ("yaml", &["yaml", "yml"]),
+    ("toml", &["toml"]),
+    ("ini", &["ini", "cfg", "conf"]),
+    ("xml", &["xml"]),
+    ("csv", &["csv", "tsv"]),
+];
+
+pub fn syntax(language: Option<&str>, ext: Option<&str>, kind: &str) -> Option<String> {
+    if let Some(language) = language {
+        return Some(language.to_string());
+    }
+    let ext = ext.map(str::to_lowercase);
+    if let Some((name, _)) = SYNTAX
+        .iter()
+        .find(|(_, exts)| ext.as_deref().is_some_and(|e| exts.contains(&e)))
+    {
+        return Some(name.to_string());
+    }
+    matches!(kind, "doc" | "pdf").then(|| "text".to_string())
+}
+
+const AMBIGUOUS: &[(&str, &[&str])] = &[("h", &["cpp", "c"])];
+
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("python", "python"),
+    ("bash", "shell"),
+    ("sh", "shell"),
+    ("zsh", "shell"),
+    ("dash", "shell"),
+    ("ksh", "shell"),
+    ("node", "javascript"),
+    ("deno", "typescript"),
+    ("ruby", "ruby"),
+    ("perl", "perl"),
+    ("php", "php"),
+    ("lua", "lua"),
+];
+
+const KEYWORDS: &[(&str, &[&str])] = &[
+    (
+        "cpp",
+        &[
+            "class ",
+            "namespace ",
+            "template <",
+            "template<",
+            "std::",
+            "public:",
+            "virtual ",
+        ],
+    ),
+    (
+        "c",
+        &[
+            "#include <stdio.h>",
+            "#include <stdlib.h>",
+            "typedef struct",
+            "malloc(",
+            "struct ",
+            "void ",
+        ],
+    ),
+    (
+        "python",
+        &["def ", "import ", "self.", "elif ", "__name__", "print("],
+    ),
+    (
+        "rust",
+        &[
+            "fn ",
+            "let mut ",
+            "impl ",
+            "pub fn",
+            "use std::",
+            "println!",
+        ],
+    ),
+    ("go", &["package ", "func ", ":= ", "import ("]),
+    (
+        "shell",
+        &["then\n", "fi\n", "esac", "echo ", "$@", "done\n"],
+    ),
+    (
+        "javascript",
+        &["function ", "const ", "=> ", "require(", "console.log"],
+    ),
+    ("ruby", &["puts ", "require '", "end\n", "attr_accessor"]),
+];
+
+const HEAD_BYTES: u64 = 4096;
+const MIN_SCORE: usize = 2;
+
+pub fn detect(ext: Option<&str>, file: &Path) -> Option<&'static str> {
+    let ext = ext.map(str::to_lowercase);
+    if let Some(ext) = &ext {
+        if let Some((_, candidates)) = AMBIGUOUS.iter().find(|(e, _)| e == ext) {
+            let head = head(file).unwrap_or_default();
+            return Some(guess(&head, candidates, 1).unwrap_or(candidates[candidates.len() - 1]));
+        }
+        return LANGUAGES
+            .iter()
+            .find(|l| l.kind == "code" && l.extensions.contains(&ext.as_str()))
+            .map(|l| l.name);
+    }
+    let head = head(file)?;
+    shebang(&head).or_else(|| {
+        let all: Vec<&str> = KEYWORDS.iter().map(|(name, _)| *name).collect();
+        guess(&head, &all, MIN_SCORE)
+    })
+}
+
+fn head(file: &Path) -> Option<String> {
+    let mut buf = Vec::new();
+    File::open(file)
+        .and_then(|f| f.take(HEAD_BYTES).read_to_end(&mut buf))
+        .ok()?;
+    Some(String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn shebang(head: &str) -> Option<&'static str> {
+    let line = head.lines().next()?.strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|w| !w.starts_with('-'))?;
+    }
+    INTERPRETERS
+        .iter()
+        .find(|(prefix, _)| program.starts_with(prefix))
+        .map(|(_, name)| *name)
+}
+
+fn guess(head: &str, candidates: &[&str], min_score: usize) -> Option<&'static str> {
+    let mut best: Option<(&'static str, usize)> = None;
+    for candidate in candidates {
+        let Some((name, markers)) = KEYWORDS.iter().find(|(n, _)| n == candidate) else {
+            continue;
+        };
+        let score = markers.iter().filter(|m| head.contains(*m)).count();
+        if score >= min_score && best.is_none_or(|(_, b)| score > b) {
+            best = Some((name, score));
+        }
+    }
+    best.map(|(name, _)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn detects_language_from_extension() {
+        let cases = [
+            ("rs", "rust"),
+            ("py", "python"),
+            ("ipynb", "python"),
+            ("cpp", "cpp"),
+            ("hpp", "cpp"),
+            ("c", "c"),
+            ("kt", "kotlin"),
+            ("java", "java"),
+            ("ts", "typescript"),
+            ("mjs", "javascript"),
+            ("scss", "css"),
+            ("ksh", "shell"),
+        ];
+        for (ext, want) in cases {
+            assert_eq!(detect(Some(ext), Path::new("missing")), Some(want), "{ext}");
+        }
+        for ext in ["md", "json", "pdf", "zip", "xyz"] {
+            assert_eq!(detect(Some(ext), Path::new("missing")), None, "{ext}");
+        }
+    }
+
+    #[test]
+    fn detects_ambiguous_and_extensionless_files_from_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let cases = [
+            (Some("h"), "class Foo {\npublic:\n};\n", "cpp"),
+            (Some("h"), "typedef struct foo foo;\n", "c"),
+            (None, "#!/usr/bin/env python3\nprint(1)\n", "python"),
+            (None, "#!/bin/bash\necho hi\n", "shell"),
+            (None, "fn main() {\n    let mut x = 1;\n}\n", "rust"),
+        ];
+        for (i, (ext, text, want)) in cases.into_iter().enumerate() {
+            let file = dir.path().join(format!("f{i}"));
+            fs::write(&file, text).unwrap();
+            assert_eq!(detect(ext, &file), Some(want), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn every_detected_language_is_a_preset_covering_its_extensions() {
+        for l in LANGUAGES.iter().filter(|l| l.kind == "code") {
+            let preset = lookup(l.name).unwrap();
+            for ext in l.extensions {
+                assert!(preset.extensions.contains(ext));
+                if *ext != "h" {
+                    assert_eq!(detect(Some(ext), Path::new("missing")), Some(l.name));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn kinds_are_not_presets() {
+        for kind in ["archive", "config", "data", "pdf"] {
+            assert!(lookup(kind).is_none());
+            assert!(!detected().any(|l| l == kind));
+        }
+        let none = BTreeMap::new();
+        assert_eq!(kind_of(Some("zip"), &none), "archive");
+        assert_eq!(kind_of(Some("toml"), &none), "config");
+        assert_eq!(kind_of(Some("md"), &none), "doc");
+        assert_eq!(kind_of(Some("kt"), &none), "code");
+    }
+
+    #[test]
+    fn web_group_unions_its_members() {
+        let web = lookup("web").unwrap();
+        for ext in ["html", "css", "js", "ts", "vue"] {
+            assert!(web.extensions.contains(&ext));
+        }
+        assert_eq!(
+            web.ignore_dirs
+                .iter()
+                .filter(|d| **d == "node_modules")
+                .count(),
+            1
+        );
+    }
+}
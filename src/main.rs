@@ -1,22 +1,64 @@
+use aho_corasick::{AhoCorasick, AhoCorasickKind, MatchKind};
 use clap::{Parser, Subcommand};
+use encoding_rs::{DecoderResult, Encoding, UTF_16BE, UTF_16LE, UTF_8};
 use gag::Gag;
+use memchr::memmem;
 use once_cell::sync::Lazy;
-use pdf_extract::extract_text;
+use pdf_extract::{Document, Object, OutputError, PlainTextOutput};
 use rayon::prelude::*;
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::io::Read;
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Mutex;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use terminal_size::{terminal_size_of, Width};
+use unicode_normalization::UnicodeNormalization;
 use walkdir::WalkDir;
 
+mod casefold;
+mod checkpoint;
+mod compress;
+mod context;
+mod diagnose;
+mod email;
+mod glob;
+mod ignore;
+mod index;
+mod language;
+mod memory;
+mod merge;
+mod progress;
+mod provenance;
+mod rootglob;
+mod scope;
+mod scratch;
+mod staging;
+mod throttle;
+
+#[cfg(test)]
+mod tests;
+
+use checkpoint::Checkpoint;
+use ignore::Ignores;
+use index::{Index, IndexEntry, IndexFilter, IndexStats, Query};
+use memory::MemoryBudget;
+use scope::Scope;
+use throttle::IoThrottle;
+
 static PDF_EXTRACT_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
-static SILENCE_PANICS: Lazy<std::sync::atomic::AtomicBool> =
-    Lazy::new(|| std::sync::atomic::AtomicBool::new(false));
+static SILENCE_PANICS: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
+
+thread_local! {
+    static FILE_STARTED: std::cell::Cell<Option<Instant>> = const { std::cell::Cell::new(None) };
+}
 
 #[derive(Parser, Debug)]
 #[command(
@@ -30,6 +72,7 @@ struct Cli {
 }
 
 #[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     Search(SearchArgs),
 
@@ -38,527 +81,9304 @@ enum Commands {
         path: Option<PathBuf>,
     },
 
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+
     Presets {
         #[arg(long)]
         config: Option<PathBuf>,
     },
+
+    Schema,
+
+    Languages,
+
+    Index {
+        #[command(subcommand)]
+        action: IndexCommand,
+    },
+
+    Show(ShowArgs),
+
+    Merge(MergeArgs),
+
+    #[command(hide = true)]
+    Bench(BenchArgs),
 }
 
-#[derive(Parser, Debug, Clone)]
-struct SearchArgs {
-    #[arg(long)]
-    config: Option<PathBuf>,
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    Migrate {
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+}
 
-    #[arg(long)]
-    preset: Option<String>,
+#[derive(Subcommand, Debug)]
+enum IndexCommand {
+    Build(IndexBuildArgs),
 
-    #[arg(long, default_value = ".")]
-    dir: PathBuf,
+    Update {
+        #[arg(long, default_value = index::DEFAULT_INDEX_DIR)]
+        index: PathBuf,
 
-    #[arg(long, default_value_t = false)]
-    include_pdf: bool,
+        #[arg(long, default_value_t = false)]
+        verbose: bool,
+    },
+}
 
-    #[arg(long)]
-    name: Option<String>,
+#[derive(Parser, Debug, Clone)]
+struct IndexBuildArgs {
+    #[arg(long, required = true)]
+    dir: Vec<PathBuf>,
 
-    #[arg(long)]
-    content: Option<String>,
+    #[arg(long, default_value = index::DEFAULT_INDEX_DIR)]
+    index: PathBuf,
 
-    #[arg(long, default_value = "md")]
-    format: String,
+    #[arg(long, default_value_t = false)]
+    include_pdf: bool,
 
     #[arg(long, default_value_t = 2_000_000)]
     max_bytes: u64,
 
-    #[arg(long)]
-    ext: Option<String>,
-
-    #[arg(long)]
-    limit: Option<usize>,
-
     #[arg(long, default_value_t = false)]
     verbose: bool,
 }
 
-#[derive(Debug, Deserialize, Clone, Default)]
-struct AppConfig {
-    defaults: Option<SearchConfig>,
-    presets: Option<std::collections::HashMap<String, SearchConfig>>,
-}
+#[derive(Parser, Debug, Clone)]
+struct ShowArgs {
+    #[arg(help = "JSON report written by `search --format json`")]
+    report: PathBuf,
 
-#[derive(Debug, Deserialize, Clone, Default)]
-struct SearchConfig {
-    dir: Option<PathBuf>,
-    include_pdf: Option<bool>,
-    name: Option<String>,
-    content: Option<String>,
-    format: Option<String>,
-    max_bytes: Option<u64>,
-    ext: Option<String>,
-    limit: Option<usize>,
-}
+    #[arg(help = "Result number, counting from 1 in report order")]
+    index: Option<usize>,
 
-#[derive(Serialize, Debug, Clone)]
-struct MatchResult {
-    path: String,
-    matched_name: bool,
-    matched_content: bool,
-    snippet: Option<String>,
-}
+    #[arg(
+        long,
+        conflicts_with = "index",
+        help = "Show every result for this path"
+    )]
+    path: Option<String>,
 
-struct Counters<'a> {
-    scanned_text: &'a AtomicUsize,
-    scanned_pdf: &'a AtomicUsize,
-    skipped_non_text: &'a AtomicUsize,
-    skipped_too_large: &'a AtomicUsize,
-    skipped_non_utf8: &'a AtomicUsize,
-    skipped_unreadable_text: &'a AtomicUsize,
-    skipped_unreadable_pdf: &'a AtomicUsize,
+    #[arg(
+        long,
+        default_value = "auto",
+        value_parser = casefold::MODES,
+        help = "Match --path ignoring case: auto probes the report's directory"
+    )]
+    case_insensitive_paths: String,
+
+    #[arg(short = 'B', long, default_value_t = 5)]
+    before: usize,
+
+    #[arg(short = 'A', long, default_value_t = 5)]
+    after: usize,
 }
 
-#[derive(Serialize, Debug, Clone)]
-struct RunStats {
-    files_discovered: usize,
+#[derive(Parser, Debug, Clone)]
+struct MergeArgs {
+    #[arg(
+        required = true,
+        help = "JSON reports written by `search --format json`, one per shard"
+    )]
+    reports: Vec<PathBuf>,
 
-    files_scanned_text: usize,
-    files_scanned_pdf: usize,
+    #[arg(long, default_value = "json", value_parser = OUTPUT_FORMATS.to_vec())]
+    format: String,
 
-    files_skipped_non_text: usize,
-    files_skipped_too_large: usize,
-    files_skipped_non_utf8: usize,
+    #[arg(long)]
+    output: Option<PathBuf>,
 
-    files_skipped_unreadable_text: usize,
-    files_skipped_unreadable_pdf: usize,
+    #[arg(
+        long,
+        default_value = "path",
+        value_parser = SORT_KEYS
+    )]
+    sort: String,
 
-    matches_total: usize,
-    matches_printed: usize,
-    elapsed_ms: u128,
+    #[arg(long, default_value_t = false)]
+    reverse: bool,
 }
 
-fn main() {
-
-    let default_hook = std::panic::take_hook();
-    std::panic::set_hook(Box::new(move |info| {
-        if !SILENCE_PANICS.load(Ordering::Relaxed) {
-            default_hook(info);
-        }
-    }));
+#[derive(Parser, Debug, Clone)]
+struct BenchArgs {
+    #[arg(long, default_value_t = 2_000)]
+    text_files: usize,
 
-    let cli = Cli::parse();
+    #[arg(long, default_value_t = 8_192)]
+    text_size: usize,
 
-    match cli.command {
-        Commands::ConfigInit { path } => {
-            let p = path.unwrap_or_else(|| PathBuf::from("rustfilefinder.toml"));
-            write_sample_config(&p).expect("failed to write config");
-            eprintln!("Config created at: {}", p.display());
-        }
+    #[arg(long, default_value_t = 200)]
+    binary_files: usize,
 
-        Commands::Presets { config } => {
-            let cfg_path = resolve_config_path(&config);
-            let cfg = cfg_path.as_deref().and_then(load_config);
-            if let Some(cfg) = cfg {
-                if let Some(presets) = cfg.presets {
-                    for k in presets.keys() {
-                        println!("{k}");
-                    }
-                }
-            }
-        }
+    #[arg(long, default_value_t = 65_536)]
+    binary_size: usize,
 
-        Commands::Search(mut args) => {
-            let started = Instant::now();
+    #[arg(long, default_value_t = 0)]
+    pdf_files: usize,
 
-            let cfg_path = resolve_config_path(&args.config);
-            let cfg = cfg_path.as_deref().and_then(load_config);
-            args = merge_search_args(args, cfg);
+    #[arg(long, default_value_t = 0)]
+    small_files: usize,
 
-            if args.name.is_none() && args.content.is_none() {
-                eprintln!("Error: you must provide at least --name or --content");
-                std::process::exit(2);
-            }
+    #[arg(long, default_value = "md")]
+    format: String,
 
-            run_search(args, started);
-        }
-    }
+    #[arg(long, default_value_t = false)]
+    keep: bool,
 }
 
-fn run_search(args: SearchArgs, started: Instant) {
-    let content_re: Option<Regex> = args.content.as_ref().map(|pat| {
-        Regex::new(pat).unwrap_or_else(|e| {
-            eprintln!("Invalid regex for --content: {e}");
-            std::process::exit(2);
-        })
-    });
+#[derive(Parser, Serialize, Debug, Clone)]
+struct SearchArgs {
+    #[arg(
+        value_name = "PATTERN",
+        long_help = "Content pattern, same as --content PATTERN. With --names-only it is the \
+                     file name query instead, same as --name PATTERN."
+    )]
+    pattern: Option<String>,
 
-    let allowed_ext: Option<Vec<String>> = args.ext.as_ref().map(|s| {
-        s.split(',')
-            .map(|x| x.trim().to_lowercase())
-            .filter(|x| !x.is_empty())
-            .collect()
-    });
+    #[arg(
+        value_name = "PATH",
+        conflicts_with = "dir",
+        long_help = "Directory to search, same as --dir PATH."
+    )]
+    path: Option<PathBuf>,
 
-    let files: Vec<PathBuf> = WalkDir::new(&args.dir)
-        .follow_links(false)
-        .into_iter()
-        .filter_entry(|e| !is_ignored_dir(e.path()))
-        .filter_map(Result::ok)
-        .filter(|e| e.file_type().is_file())
-        .map(|e| e.into_path())
-        .filter(|p| ext_allowed(p, allowed_ext.as_ref()))
-        .collect();
+    #[arg(long)]
+    config: Option<PathBuf>,
 
-    let files_discovered = files.len();
+    #[arg(long)]
+    preset: Option<String>,
 
-    let scanned_text = AtomicUsize::new(0);
-    let scanned_pdf = AtomicUsize::new(0);
+    #[arg(
+        long,
+        value_name = "A,B",
+        long_help = "Dry-run two presets side by side: discover files once, match both presets' \
+                     content patterns in the same pass and report the files matched only by A, \
+                     only by B and by both. Each preset's ext, include_pdf, name and ignore_dir \
+                     decide which files count for it; other options come from A."
+    )]
+    compare_presets: Option<String>,
 
-    let skipped_non_text = AtomicUsize::new(0);
-    let skipped_too_large = AtomicUsize::new(0);
-    let skipped_non_utf8 = AtomicUsize::new(0);
+    #[arg(skip)]
+    #[serde(skip)]
+    compare: Option<[PresetScope; 2]>,
 
-    let skipped_unreadable_text = AtomicUsize::new(0);
-    let skipped_unreadable_pdf = AtomicUsize::new(0);
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "compare_presets",
+        long_help = "Run several queries from a TOML file ([[query]] tables with label, content, \
+                     name, ext, include_pdf) in one pass: files are discovered and read once, \
+                     every query's patterns are matched against the same buffer, each query's \
+                     report goes to --output-dir as LABEL.FORMAT and stdout gets the combined \
+                     report with a per-query summary."
+    )]
+    queries_file: Option<PathBuf>,
 
-    let counters = Counters {
-        scanned_text: &scanned_text,
-        scanned_pdf: &scanned_pdf,
-        skipped_non_text: &skipped_non_text,
-        skipped_too_large: &skipped_too_large,
-        skipped_non_utf8: &skipped_non_utf8,
-        skipped_unreadable_text: &skipped_unreadable_text,
-        skipped_unreadable_pdf: &skipped_unreadable_pdf,
-    };
+    #[arg(skip)]
+    #[serde(skip)]
+    queries: Vec<NamedQuery>,
 
-    let mut results: Vec<MatchResult> = files
-        .par_iter()
-        .filter_map(|path| {
-            let attempt = catch_unwind(AssertUnwindSafe(|| {
-                analyze_file(
-                    path,
-                    args.name.as_deref(),
-                    content_re.as_ref(),
-                    args.max_bytes,
-                    allowed_ext.as_ref(),
-                    args.include_pdf,
-                    args.verbose,
-                    &counters,
-                )
-            }));
+    #[arg(
+        long,
+        default_value = ".",
+        long_help = "Directory to search; repeat for several roots. A leading `~` is expanded \
+                     to the home directory, and a value with glob metacharacters (`*`, `?`, \
+                     `[...]`, `**` for any depth) is expanded against the filesystem to every \
+                     matching directory, e.g. \"packages/*/src\"."
+    )]
+    dir: Vec<PathBuf>,
 
-            match attempt {
-                Ok(v) => v,
-                Err(_) => {
-                    if is_pdf(path) {
-                        counters
-                            .skipped_unreadable_pdf
-                            .fetch_add(1, Ordering::Relaxed);
-                    } else {
-                        counters
-                            .skipped_unreadable_text
-                            .fetch_add(1, Ordering::Relaxed);
-                    }
-                    None
-                }
-            }
-        })
-        .collect();
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Search nothing instead of failing when a --dir glob matches no directory."
+    )]
+    allow_empty_dir_glob: bool,
 
-    results.sort_by(|a, b| a.path.cmp(&b.path));
+    #[arg(skip)]
+    dir_globs: Vec<(String, Vec<PathBuf>)>,
 
-    let matches_total = results.len();
+    #[arg(
+        long,
+        value_name = "REPORT",
+        long_help = "Use the matched paths of an earlier JSON report as the candidate files \
+                     instead of walking --dir. Other filters (--ext, --name, --not-name, \
+                     --mime, ...) still apply; paths that no longer exist are counted in \
+                     files_missing."
+    )]
+    within: Option<PathBuf>,
 
-    let mut results_print = results;
-    if let Some(limit) = args.limit {
-        if results_print.len() > limit {
-            results_print.truncate(limit);
-        }
-    }
-    let matches_printed = results_print.len();
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Print --name hits on stderr once the candidate list is final (phase 1), \
+                     then print content hits as the scan finds them (phase 2). With --limit \
+                     or --sample both phases are printed after the selection, for the kept \
+                     results only. The report merges both: each result's `phase` says which \
+                     pass produced it, and a file whose content also matched appears once, \
+                     as a phase 2 result."
+    )]
+    two_phase: bool,
 
-    let elapsed_ms = started.elapsed().as_millis();
+    #[arg(long, default_value_t = false)]
+    follow_links: bool,
 
-    let stats = RunStats {
-        files_discovered,
-        files_scanned_text: scanned_text.load(Ordering::Relaxed),
-        files_scanned_pdf: scanned_pdf.load(Ordering::Relaxed),
-        files_skipped_non_text: skipped_non_text.load(Ordering::Relaxed),
-        files_skipped_too_large: skipped_too_large.load(Ordering::Relaxed),
-        files_skipped_non_utf8: skipped_non_utf8.load(Ordering::Relaxed),
-        files_skipped_unreadable_text: skipped_unreadable_text.load(Ordering::Relaxed),
-        files_skipped_unreadable_pdf: skipped_unreadable_pdf.load(Ordering::Relaxed),
-        matches_total,
-        matches_printed,
-        elapsed_ms,
-    };
+    #[arg(long, default_value_t = false)]
+    no_dedup: bool,
 
-    match args.format.as_str() {
-        "json" => {
-            #[derive(Serialize)]
-            struct JsonOut<'a> {
-                stats: RunStats,
-                results: &'a [MatchResult],
-            }
-            let out = JsonOut {
-                stats,
-                results: &results_print,
-            };
-            let json = serde_json::to_string_pretty(&out).unwrap();
-            println!("{json}");
-        }
-        _ => {
-            print_markdown(&args, &stats, &results_print);
-        }
-    }
-}
+    #[arg(
+        long,
+        default_value = "auto",
+        value_parser = casefold::MODES,
+        long_help = "Treat paths differing only by case as the same file when deduplicating \
+                     roots and sorting results. `auto` probes each --dir for a case-insensitive \
+                     filesystem (the macOS and Windows defaults); `on` and `off` override the \
+                     probe. Results keep their on-disk casing."
+    )]
+    case_insensitive_paths: String,
 
-fn ext_allowed(path: &Path, allowed: Option<&Vec<String>>) -> bool {
-    let Some(list) = allowed else { return true };
-    let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
-        return false;
-    };
-    list.iter().any(|x| x == &ext.to_lowercase())
-}
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Scan files with several hard links once: results go to one of the paths \
+                     and list the other links in `also_at`. Unix only; a no-op elsewhere."
+    )]
+    dedup_hardlinks: bool,
 
-fn analyze_file(
-    path: &Path,
-    name_query: Option<&str>,
-    content_re: Option<&Regex>,
-    max_bytes: u64,
-    allowed_ext: Option<&Vec<String>>,
+    #[arg(long, default_value = "size", value_parser = SCHEDULES)]
+    schedule: String,
+
+    #[arg(
+        long,
+        default_value = "dfs",
+        value_parser = WALK_ORDERS,
+        long_help = "Order in which files are discovered and handed to the scanners: `dfs` \
+                     walks each directory tree depth-first, `bfs` lists shallow files before \
+                     deeper ones, `mtime` visits the newest entries of each directory first. \
+                     `bfs` and `mtime` replace the largest-first --schedule. The order of the \
+                     report itself is still set by --sort."
+    )]
+    walk_order: String,
+
+    #[arg(long, default_value_t = false)]
     include_pdf: bool,
-    verbose: bool,
-    counters: &Counters,
-) -> Option<MatchResult> {
-    let file_name = path.file_name()?.to_string_lossy().to_string();
 
-    let matched_name = name_query
-        .map(|q| file_name.to_lowercase().contains(&q.to_lowercase()))
-        .unwrap_or(false);
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        long_help = "Extract text from the first N pages of each PDF only. 0 extracts the \
+                     whole document."
+    )]
+    pdf_max_pages: usize,
 
-    let mut matched_content = false;
-    let mut snippet: Option<String> = None;
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Parse .eml and .mbox files as email: headers are unfolded and decoded, \
+                     quoted-printable and base64 text parts are decoded, binary attachments \
+                     are skipped. Each match reports the message index (0-based within an \
+                     mbox) and whether it hit a header or the body. --max-bytes bounds the \
+                     decoded text of each message; unparseable messages count as unreadable."
+    )]
+    include_email: bool,
 
-    if let Some(re) = content_re {
-        let pdf = is_pdf(path);
+    #[arg(
+        long,
+        value_name = "NAME:SUBSTRING",
+        value_parser = email::parse_filter,
+        long_help = "With --include-email, search only messages whose NAME header contains \
+                     SUBSTRING (case-insensitive), e.g. From:alice@. Repeatable; every filter \
+                     must match."
+    )]
+    email_header: Vec<(String, String)>,
 
-        if allowed_ext.is_none() {
-            if pdf {
-                if !include_pdf {
-                    counters.skipped_non_text.fetch_add(1, Ordering::Relaxed);
-                    return some_if_name_only(path, name_query, matched_name);
-                }
-            } else if !is_probably_text(path) {
-                counters.skipped_non_text.fetch_add(1, Ordering::Relaxed);
-                return some_if_name_only(path, name_query, matched_name);
-            }
-        } else {
-            if pdf && !include_pdf {
-                counters.skipped_non_text.fetch_add(1, Ordering::Relaxed);
-                return some_if_name_only(path, name_query, matched_name);
-            }
-        }
+    #[arg(
+        long,
+        default_value = "pdf",
+        value_parser = ["pdf", "all", "off"],
+        long_help = "Collapse whitespace runs to one space and expand ligatures (ﬁ → fi) \
+                     before matching, so phrases broken across lines still match. `pdf` \
+                     (default) applies it to PDF and extractor text, `all` to every file, \
+                     `off` disables it. Offsets and snippets refer to the original text."
+    )]
+    normalize_whitespace: String,
 
-        if pdf {
-            counters.scanned_pdf.fetch_add(1, Ordering::Relaxed);
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Strip a UTF-8 byte order mark, trailing whitespace at the end of each line \
+                     (including the CR of CRLF) and at the end of the file before matching, so \
+                     `^pattern` matches at the start of BOM-prefixed files and `pattern$` or \
+                     `pattern\\z` matches before a final newline. Offsets and snippets refer to \
+                     the original text."
+    )]
+    trim_for_anchors: bool,
 
-            let _lock = match PDF_EXTRACT_LOCK.lock() {
-                Ok(g) => g,
-                Err(_) => {
-                    counters
-                        .skipped_unreadable_pdf
-                        .fetch_add(1, Ordering::Relaxed);
-                    return some_if_name_only(path, name_query, matched_name);
-                }
-            };
+    #[arg(long)]
+    name: Option<String>,
 
-            SILENCE_PANICS.store(true, Ordering::Relaxed);
-            let pdf_text_result = catch_unwind(AssertUnwindSafe(|| {
-                let _gag_out = Gag::stdout().ok();
-                let _gag_err = gag::Gag::stderr().ok();
-                extract_text(path)
-            }));
-            SILENCE_PANICS.store(false, Ordering::Relaxed);
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Treat the positional PATTERN as a file name query (--name) instead of \
+                     a content pattern (--content)."
+    )]
+    names_only: bool,
 
-            let pdf_text = match pdf_text_result {
-                Ok(Ok(t)) => t,
-                _ => {
-                    counters
-                        .skipped_unreadable_pdf
-                        .fetch_add(1, Ordering::Relaxed);
+    #[arg(long, default_value_t = false)]
+    no_unicode_fold: bool,
 
-                    if verbose {
-                        eprintln!("[pdf] unreadable: {}", path.display());
-                    }
+    #[arg(long)]
+    not_name: Vec<String>,
 
-                    return some_if_name_only(path, name_query, matched_name);
-                }
-            };
+    #[arg(long)]
+    not_name_glob: Vec<String>,
 
-            if let Some(m) = re.find(&pdf_text) {
-                matched_content = true;
-                snippet = Some(snippet_around_match(&pdf_text, m.start(), m.end(), 40, 120));
-            }
-        } else {
-            let meta = match fs::metadata(path) {
-                Ok(v) => v,
-                Err(_) => {
-                    counters
-                        .skipped_unreadable_text
-                        .fetch_add(1, Ordering::Relaxed);
-                    return some_if_name_only(path, name_query, matched_name);
-                }
-            };
+    #[arg(
+        long,
+        value_name = "GLOB",
+        long_help = "Path globs marking vendored code, replacing the defaults (vendor/, \
+                     vendors/, third_party/, third-party/, node_modules/, bower_components/). \
+                     `name/` matches a directory at any depth, a glob without `/` matches the \
+                     file name, others the path relative to --dir. Repeatable."
+    )]
+    vendored_glob: Vec<String>,
 
-            if meta.len() > max_bytes {
-                counters.skipped_too_large.fetch_add(1, Ordering::Relaxed);
-                return some_if_name_only(path, name_query, matched_name);
-            }
+    #[arg(
+        long,
+        value_name = "GLOB",
+        long_help = "Path globs marking generated code, replacing the defaults (*.min.js, \
+                     *.min.css, *.bundle.js, *.pb.go, *.pb.cc, *.pb.h, *_pb2.py, *.g.dart, \
+                     *.generated.*, *.designer.cs). Files whose first KB mentions @generated, \
+                     DO NOT EDIT, Code generated or auto-generated count as generated too."
+    )]
+    generated_glob: Vec<String>,
 
-            let f = match fs::File::open(path) {
-                Ok(v) => v,
-                Err(_) => {
-                    counters
-                        .skipped_unreadable_text
-                        .fetch_add(1, Ordering::Relaxed);
-                    return some_if_name_only(path, name_query, matched_name);
-                }
-            };
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = provenance::ALL,
+        long_help = "Keep only matches whose file is first_party, vendored or generated \
+                     (comma-separated). Dropped matches are counted in \
+                     matches_excluded_by_provenance."
+    )]
+    provenance: Vec<String>,
 
-            counters.scanned_text.fetch_add(1, Ordering::Relaxed);
+    #[arg(
+        long,
+        value_name = "GLOB",
+        long_help = "Keep matches whose path matches GLOB but list them after all other \
+                     results (the normal sort applies within each group), so --limit fills \
+                     from the rest first. Same glob rules as --vendored-glob. Marked \
+                     `deprioritized: true` in JSON. Repeatable."
+    )]
+    deprioritize: Vec<String>,
 
-            let mut buf = Vec::new();
-            if f.take(max_bytes).read_to_end(&mut buf).is_ok() {
-                match std::str::from_utf8(&buf) {
-                    Ok(text) => {
-                        if let Some(m) = re.find(text) {
-                            matched_content = true;
-                            snippet = Some(snippet_around_match(text, m.start(), m.end(), 40, 120));
-                        }
-                    }
-                    Err(_) => {
-                        counters.skipped_non_utf8.fetch_add(1, Ordering::Relaxed);
-                    }
-                }
-            } else {
-                counters
-                    .skipped_unreadable_text
-                    .fetch_add(1, Ordering::Relaxed);
-            }
-        }
-    }
+    #[arg(
+        long,
+        long_help = "Content pattern to search for. `-` reads a single pattern from stdin \
+                     until EOF, dropping one trailing newline and keeping all other \
+                     whitespace as is."
+    )]
+    content: Vec<String>,
 
-    let any = (name_query.is_some() && matched_name) || (content_re.is_some() && matched_content);
+    #[arg(
+        long,
+        long_help = "File with one content pattern per line. `-` reads the list from stdin."
+    )]
+    content_file: Option<PathBuf>,
 
-    if any {
-        Some(MatchResult {
-            path: path.to_string_lossy().to_string(),
-            matched_name,
-            matched_content,
-            snippet,
-        })
+    #[arg(short = 'F', long, default_value_t = false)]
+    fixed_strings: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Match content patterns against the raw bytes of every file instead of \
+                     decoded text. Escapes like \\x89 match that exact byte; the text/binary \
+                     check and UTF-8 decoding are skipped, while --max-bytes and the \
+                     extension filters still apply. Snippets are hex dumps."
+    )]
+    binary: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Build --binary regexes with Unicode word semantics: \\b, \\B and \\w \
+                     treat letters, combining marks, digits and connector punctuation of every \
+                     script as word characters. Text regexes always use these rules, so the \
+                     flag only changes --binary searches."
+    )]
+    unicode_word_boundaries: bool,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_delimiter = ',',
+        long_help = "Keep a content match only when at least half of the letters in a small \
+                     window around it belong to one of these Unicode scripts (Cyrillic, Greek, \
+                     Han, Arabic, ...). Rejected matches are counted in \
+                     matches_rejected_by_script."
+    )]
+    require_script: Vec<String>,
+
+    #[arg(
+        long,
+        default_value = "all",
+        value_parser = ["all", "comments", "strings", "code"],
+        long_help = "Search only comments, string literals or the remaining code of rs, py, \
+                     js/ts, c/cpp and java files; other regions are blanked so offsets stay \
+                     exact. Files in other languages are searched whole."
+    )]
+    scope: String,
+
+    #[arg(long, value_name = "NAME=REGEX")]
+    rule: Vec<String>,
+
+    #[arg(
+        long,
+        long_help = "Output format(s): md, json, jsonl or paths. When not given here, in \
+                     RUSTFILEFINDER_FORMAT or in the config, md is used for terminals and \
+                     files, and --pipe-format when stdout is a pipe."
+    )]
+    format: Option<String>,
+
+    #[arg(long, value_name = "FORMAT", default_value = "jsonl")]
+    pipe_format: String,
+
+    #[arg(
+        long,
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        long_help = "Only write these result fields (comma-separated, e.g. \
+                     path,matched_content,match_start) in json and jsonl output; names are \
+                     checked against the JSON schema of a result. Markdown and paths output \
+                     ignore it."
+    )]
+    fields: Vec<String>,
+
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_parser = compress::FORMATS,
+        long_help = "Compress the --output report (and the extra --output-dir formats) as it is \
+                     written, appending the matching extension when the path lacks it. The \
+                     uncompressed size is reported on stderr. `show` and --within read gzip \
+                     and zstd reports transparently."
+    )]
+    compress: Option<String>,
+
+    #[arg(long, value_name = "N")]
+    paginate: Option<usize>,
+
+    #[arg(
+        long,
+        default_value = "path",
+        value_parser = SORT_KEYS,
+        long_help = "Sort key for results. Ties are always broken by a fixed total order: \
+                     path, then source kind (file, notebook cell, pdf text), then entry \
+                     index, rule, match offset, and name-only before content matches, so \
+                     repeated runs produce identical output."
+    )]
+    sort: String,
+
+    #[arg(long, default_value_t = false)]
+    reverse: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Report every match of each rule in a file instead of only the first one. \
+                     Snippets per file are capped by --max-snippets-per-file; `occurrences` \
+                     keeps the full count."
+    )]
+    all_matches: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 5,
+        long_help = "With --all-matches, keep at most N snippets per file and only count the \
+                     remaining matches (`matches_truncated: true` in JSON)."
+    )]
+    max_snippets_per_file: usize,
+
+    #[arg(long, default_value_t = 40)]
+    snippet_context: usize,
+
+    #[arg(long, default_value_t = 120)]
+    snippet_max: usize,
+
+    #[arg(
+        long,
+        value_name = "N",
+        long_help = "In markdown output, show N lines before and after each text match, read \
+                     from the file, in a fenced code block tagged with the result's `syntax` so \
+                     renderers such as GitHub highlight it. Falls back to the snippet for \
+                     extracted text, binary matches and files that can no longer be read."
+    )]
+    context_lines: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "CHARS",
+        long_help = "Ignore lines longer than CHARS characters (minified code, base64 blobs, \
+                     data URIs): nothing inside them can match, and snippets stop where such \
+                     a line begins. Skipped lines are counted in long_lines_skipped. \
+                     2000 is a good value."
+    )]
+    skip_long_lines: Option<usize>,
+
+    #[arg(long, default_value_t = false)]
+    show_context_heading: bool,
+
+    #[arg(long, default_value_t = false)]
+    raw: bool,
+
+    #[arg(long, default_value = "auto", value_parser = COLOR_MODES)]
+    color: String,
+
+    #[arg(long)]
+    width: Option<usize>,
+
+    #[arg(long, default_value_t = false)]
+    full_paths: bool,
+
+    #[arg(long, default_value_t = false)]
+    quiet: bool,
+
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "stdout",
+        value_parser = ["stdout", "stderr"]
+    )]
+    summary_line: Option<String>,
+
+    #[arg(long, default_value_t = 2_000_000)]
+    max_bytes: u64,
+
+    #[arg(
+        long,
+        value_name = "EXT=SIZE",
+        value_parser = parse_max_bytes_ext,
+        long_help = "Per-extension size limit replacing --max-bytes for matching files, e.g. \
+                     json=256k, log=10MB, rs=unlimited; `*` sets the limit for every other \
+                     extension. Repeatable; overrides the config's [max_bytes_by_ext] table."
+    )]
+    max_bytes_ext: Vec<(String, Option<u64>)>,
+
+    #[arg(skip)]
+    max_bytes_by_ext: BTreeMap<String, Option<u64>>,
+
+    #[arg(
+        long,
+        default_value = "512M",
+        value_parser = memory::parse_size,
+        long_help = "Memory budget for file contents held at once. A file that does not fit \
+                     is streamed in windows, unless a content pattern has no maximum match \
+                     length (e.g. `x+`): then the file waits for the budget and is read whole."
+    )]
+    memory_limit: u64,
+
+    #[arg(long, value_parser = memory::parse_size)]
+    max_dir_bytes: Option<u64>,
+
+    #[arg(long, value_name = "MB/s", value_parser = parse_throttle)]
+    throttle: Option<f64>,
+
+    #[arg(long, default_value_t = false)]
+    nice_io: bool,
+
+    #[arg(long, value_parser = memory::parse_size)]
+    max_total_bytes: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_MAX_DEPTH,
+        long_help = "Do not descend into directories more than N levels below each --dir: 0 \
+                     scans only the files directly in --dir, 1 also their subdirectories. \
+                     Directories left unvisited are counted in dirs_depth_limited."
+    )]
+    max_depth: usize,
+
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value_t = DEFAULT_MAX_PATH_LEN,
+        long_help = "Skip files and directories whose path is longer than BYTES; they are \
+                     counted in paths_too_long."
+    )]
+    max_path_len: usize,
+
+    #[arg(
+        long,
+        value_name = "N",
+        long_help = "Abort discovery with an error once more than N files and directories have \
+                     been walked, instead of letting a runaway tree exhaust memory."
+    )]
+    max_entries: Option<usize>,
+
+    #[arg(long)]
+    ext: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "KINDS",
+        long_help = "Only scan files of these kinds (comma-separated): code, doc, data, config, \
+                     archive, pdf, other, or any kind named in the config's [kinds] table. \
+                     Kinds are derived from the extension using the --language presets."
+    )]
+    kind: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "LANGS",
+        long_help = "Only scan files detected as these languages (comma-separated, listed by \
+                     `rustfilefinder languages`). Detection uses the extension first, then the \
+                     shebang line, then a small keyword heuristic for ambiguous extensions \
+                     (.h) and extensionless files; add --include-no-ext to search the latter. \
+                     Combines with --ext."
+    )]
+    lang: Option<String>,
+
+    #[arg(long, default_value_t = false)]
+    include_no_ext: bool,
+
+    #[arg(long, value_name = "NAME")]
+    ignore_dir: Vec<String>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Do not read the personal ignore file (rustfilefinder/ignore in the user \
+                     config directory, e.g. ~/.config/rustfilefinder/ignore)."
+    )]
+    no_ignore_global: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Do not honor .gitignore files. .ignore, .rgignore and .rffignore files \
+                     still apply; later ones win over earlier ones, in the order global \
+                     ignore file, .gitignore, .ignore/.rgignore, .rffignore."
+    )]
+    no_ignore_vcs: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        long_help = "Print to stderr whether PATH is skipped by an ignore file or --ignore-dir, \
+                     and which rule (file and line) decided it. Repeatable."
+    )]
+    explain_file: Vec<PathBuf>,
+
+    #[arg(long, value_name = "TYPE/SUBTYPE")]
+    mime: Vec<String>,
+
+    #[arg(long, value_name = "TYPE/SUBTYPE")]
+    exclude_mime: Vec<String>,
+
+    #[arg(long, default_value_t = false)]
+    show_mime: bool,
+
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "relative",
+        value_parser = ["relative", "absolute"],
+        long_help = "Show each matched file's size and modification time in the markdown \
+                     report, as an age (`relative`, the default) or a UTC timestamp \
+                     (`absolute`)."
+    )]
+    show_meta: Option<String>,
+
+    #[arg(long, default_value_t = false)]
+    no_hotspots: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "For every pair of named patterns (--rule NAME=REGEX), count the files \
+                     that match both and those that match only one of them, and list the \
+                     files matching both."
+    )]
+    cooccurrence: bool,
+
+    #[arg(long, default_value_t = false)]
+    summarize_dirs: bool,
+
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    summarize_depth: usize,
+
+    #[arg(long, default_value_t = false)]
+    no_meta: bool,
+
+    #[arg(
+        long,
+        value_name = "FROM=TO",
+        value_parser = parse_path_rewrite,
+        long_help = "Show result paths under FROM as under TO in every output format, e.g. \
+                     /data=//fileserver/corpus. Repeatable; the longest matching FROM wins. \
+                     Matching, sorting and dedup use the real paths, which stay in \
+                     `original_path`; the rewrites are listed in the report's meta."
+    )]
+    path_rewrite: Vec<(String, String)>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Replace every match inside snippets and context lines with \
+                     `[REDACTED:<rule>]` (or `[REDACTED]` without a rule label), drop matched \
+                     patterns and match offsets from the results and hide pattern text in the \
+                     report. Fingerprints are computed before redaction."
+    )]
+    redact: bool,
+
+    #[arg(long)]
+    limit: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with = "limit",
+        long_help = "Print a uniform random sample of N results instead of the first ones. \
+                     Cannot be combined with --limit on the command line; a `limit` from the \
+                     configuration is ignored while sampling. --seed makes the sample \
+                     reproducible."
+    )]
+    sample: Option<usize>,
+
+    #[arg(long)]
+    seed: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        long_help = "After the scan, search N randomly chosen files that were skipped as \
+                     non-text anyway and report how many would have matched (seeded by \
+                     --seed)."
+    )]
+    verify_skips: Option<usize>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "List the files that disappeared between discovery and scan: \
+                     `vanished_paths` in the JSON stats, under the vanished count in Markdown."
+    )]
+    show_skipped: bool,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        long_help = "Use the index in DIR (see `index build`) to rule out files whose \
+                     indexed text cannot contain the content patterns, without reading them. \
+                     Files that changed since the index was built, or are not in it, are \
+                     scanned as usual."
+    )]
+    use_index: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "TIME",
+        long_help = "Only scan files modified (or whose status changed) at or after TIME: an \
+                     RFC 3339 timestamp or date (2026-10-15, 2026-10-15T08:00:00+02:00), a \
+                     duration back from now (90m, 24h, 7d, 2w) or `last-run`, the start of \
+                     the previous --changed-since run of the same query over the same \
+                     directories."
+    )]
+    changed_since: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "changed_since",
+        long_help = "With --changed-since, skip whole directories whose own modification and \
+                     status-change times are older than the cutoff. A directory's time only \
+                     moves when entries are added, removed or renamed, so files edited in \
+                     place inside such a directory are missed."
+    )]
+    trust_dir_mtime: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        long_help = "Keep at most N results from each directory and report how many were \
+                     suppressed there. 0 means no cap, as with --limit."
+    )]
+    limit_per_dir: Option<usize>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Group results whose snippet text is identical once whitespace is \
+                     collapsed: one representative is printed with the group size and the \
+                     first paths, and JSON lists every path under `duplicates`. Grouping runs \
+                     after sorting, and --limit counts each group as one result."
+    )]
+    collapse_duplicates: bool,
+
+    #[arg(long)]
+    max_results: Option<usize>,
+
+    #[arg(long)]
+    regex_size_limit: Option<usize>,
+
+    #[arg(long, default_value_t = 5)]
+    per_file_timeout: u64,
+
+    #[arg(long, default_value_t = false)]
+    strict_snapshot: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Exit with status 4 when the content search skipped files it could have \
+                     read had it been enabled: PDFs found while --include-pdf is off \
+                     (pdfs_skipped_disabled)."
+    )]
+    strict: bool,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        long_help = "After the scan, copy every matched file into DIR, keeping its path \
+                     relative to --dir. When the same relative path comes from several \
+                     --dir roots, later roots are copied under a directory named after the \
+                     root's index (2/, 3/, ...). Existing files in DIR are never overwritten. \
+                     Files that cannot be copied become warnings on their results."
+    )]
+    copy_matches: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FILES[,SIZE]",
+        value_parser = staging::parse_limit,
+        long_help = "Skip --copy-matches when it would copy more than FILES files or SIZE \
+                     bytes (K, M, G suffixes), unless --yes is given. The report is still \
+                     written and `copied.error` says why nothing was copied. Default 1000,1G."
+    )]
+    copy_limit: Option<staging::Limit>,
+
+    #[arg(long, default_value_t = false, help = "Copy past --copy-limit")]
+    yes: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Write line-delimited JSON events to stderr for wrapping tools: `start` \
+                     with the effective arguments, `progress` every --progress-interval with \
+                     processed, total, matches and elapsed_ms, and `done` with the final \
+                     stats. An interrupted scan ends with a `cancelled` event and exit \
+                     status 130."
+    )]
+    progress_json: bool,
+
+    #[arg(long, value_name = "MS", default_value_t = progress::DEFAULT_INTERVAL_MS)]
+    progress_interval: u64,
+
+    #[arg(long, default_value_t = false)]
+    no_extractors: bool,
+
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 1000)]
+    checkpoint_every: usize,
+
+    #[arg(skip)]
+    extractors: BTreeMap<String, String>,
+
+    #[arg(skip)]
+    kinds: BTreeMap<String, String>,
+
+    #[arg(
+        long,
+        long_help = "Write run statistics to PATH in Prometheus textfile-collector format. \
+                     Every numeric RunStats field is exported as a gauge named \
+                     `rustfilefinder_<field>` (e.g. rustfilefinder_files_discovered, \
+                     rustfilefinder_matches_total, rustfilefinder_elapsed_ms), labelled \
+                     with `preset` and `dir`; skip counters are exported as \
+                     `rustfilefinder_files_skipped` with a `reason` label using the keys \
+                     non_text, too_large, non_utf8, unreadable_text, unreadable_pdf, timeout, \
+                     changed_during_scan, extractor_failed. \
+                     The file is replaced atomically."
+    )]
+    metrics_out: Option<PathBuf>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Also emit the flat files_skipped_* fields in JSON stats. Deprecated: \
+                     use the `skips` map, whose keys (non_text, too_large, non_utf8, \
+                     unreadable_text, unreadable_pdf, timeout) are stable."
+    )]
+    legacy_stats: bool,
+
+    #[arg(long, default_value_t = false)]
+    verbose: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Print to stderr how each content pattern is turned into what actually \
+                     runs: the original text, its escaped form under --fixed-strings, the \
+                     engine chosen (regex, or a literal search when the pattern has no \
+                     regex metacharacters), the compiled pattern and the flags applied."
+    )]
+    explain: bool,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        long_help = "Debugging aid: do not catch panics while scanning files, so a crash \
+                     aborts the run with a real backtrace (set RUST_BACKTRACE=1)."
+    )]
+    no_catch: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+struct AppConfig {
+    version: Option<u32>,
+    defaults: Option<SearchConfig>,
+    presets: Option<std::collections::HashMap<String, SearchConfig>>,
+    extractors: Option<BTreeMap<String, String>>,
+    kinds: Option<BTreeMap<String, String>>,
+    max_bytes_by_ext: Option<BTreeMap<String, String>>,
+    hosts: Option<HashMap<String, HostConfig>>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+struct HostConfig {
+    defaults: Option<SearchConfig>,
+    presets: Option<HashMap<String, SearchConfig>>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+struct SearchConfig {
+    dir: Option<PathBuf>,
+    allow_empty_dir_glob: Option<bool>,
+    include_pdf: Option<bool>,
+    pdf_max_pages: Option<usize>,
+    include_email: Option<bool>,
+    email_header: Option<Vec<String>>,
+    normalize_whitespace: Option<String>,
+    trim_for_anchors: Option<bool>,
+    name: Option<String>,
+    not_name: Option<Vec<String>>,
+    not_name_glob: Option<Vec<String>>,
+    vendored_glob: Option<Vec<String>>,
+    generated_glob: Option<Vec<String>>,
+    deprioritize: Option<Vec<String>>,
+    provenance: Option<Vec<String>>,
+    content: Option<String>,
+    content_file: Option<PathBuf>,
+    fixed_strings: Option<bool>,
+    binary: Option<bool>,
+    unicode_word_boundaries: Option<bool>,
+    require_script: Option<Vec<String>>,
+    scope: Option<String>,
+    rules: Option<BTreeMap<String, String>>,
+    format: Option<String>,
+    pipe_format: Option<String>,
+    fields: Option<Vec<String>>,
+    output: Option<PathBuf>,
+    output_dir: Option<PathBuf>,
+    compress: Option<String>,
+    sort: Option<String>,
+    reverse: Option<bool>,
+    all_matches: Option<bool>,
+    max_snippets_per_file: Option<usize>,
+    snippet_context: Option<usize>,
+    context_lines: Option<usize>,
+    snippet_max: Option<usize>,
+    show_context_heading: Option<bool>,
+    raw: Option<bool>,
+    color: Option<String>,
+    width: Option<usize>,
+    full_paths: Option<bool>,
+    quiet: Option<bool>,
+    summary_line: Option<String>,
+    max_bytes: Option<u64>,
+    memory_limit: Option<String>,
+    throttle: Option<f64>,
+    nice_io: Option<bool>,
+    max_dir_bytes: Option<String>,
+    max_total_bytes: Option<String>,
+    max_depth: Option<usize>,
+    max_path_len: Option<usize>,
+    max_entries: Option<usize>,
+    paginate: Option<usize>,
+    skip_long_lines: Option<usize>,
+    ext: Option<String>,
+    kind: Option<String>,
+    lang: Option<String>,
+    language: Option<String>,
+    ignore_dir: Option<Vec<String>>,
+    include_no_ext: Option<bool>,
+    mime: Option<Vec<String>>,
+    exclude_mime: Option<Vec<String>>,
+    show_mime: Option<bool>,
+    show_meta: Option<String>,
+    limit: Option<usize>,
+    sample: Option<usize>,
+    seed: Option<u64>,
+    verify_skips: Option<usize>,
+    use_index: Option<PathBuf>,
+    changed_since: Option<String>,
+    trust_dir_mtime: Option<bool>,
+    dedup_hardlinks: Option<bool>,
+    no_ignore_global: Option<bool>,
+    no_ignore_vcs: Option<bool>,
+    cooccurrence: Option<bool>,
+    summarize_dirs: Option<bool>,
+    summarize_depth: Option<usize>,
+    limit_per_dir: Option<usize>,
+    collapse_duplicates: Option<bool>,
+    max_results: Option<usize>,
+    follow_links: Option<bool>,
+    schedule: Option<String>,
+    walk_order: Option<String>,
+    case_insensitive_paths: Option<String>,
+    regex_size_limit: Option<usize>,
+    per_file_timeout: Option<u64>,
+    strict_snapshot: Option<bool>,
+    strict: Option<bool>,
+    copy_limit: Option<String>,
+    progress_interval: Option<u64>,
+    checkpoint: Option<PathBuf>,
+    checkpoint_every: Option<usize>,
+    metrics_out: Option<PathBuf>,
+    no_meta: Option<bool>,
+    two_phase: Option<bool>,
+    path_rewrite: Option<Vec<String>>,
+    redact: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+struct MatchResult {
+    path: String,
+    #[serde(default)]
+    #[schemars(
+        description = "Stable identity of the finding: 16 hex digits of FNV-1a 64 over the \
+                       path relative to its --dir, the sorted rule labels (or matched \
+                       patterns) and the matched text of the snippet with whitespace \
+                       collapsed. Unchanged when the match only moves to another line."
+    )]
+    fingerprint: String,
+    matched_name: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name_match_range: Option<(usize, usize)>,
+    matched_content: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    rules: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    matched_patterns: Vec<String>,
+    snippet: Option<String>,
+    #[serde(default)]
+    snippet_kind: SnippetKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notebook_cell: Option<NotebookCell>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<EmailPart>,
+    #[serde(flatten)]
+    offsets: Option<MatchOffsets>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    doc_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    doc_author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    doc_created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mime: Option<String>,
+    #[serde(default)]
+    #[schemars(
+        description = "File kind derived from the extension: code, doc, data, config, archive, \
+                       pdf, other, or a kind from the config's [kinds] table."
+    )]
+    kind: String,
+    #[serde(default)]
+    #[schemars(
+        description = "Where the file's code comes from: first_party, vendored (vendor/, \
+                       third_party/, ...) or generated (minified bundles, generated sources, \
+                       files with a generation marker in their first KB)."
+    )]
+    provenance: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "Language detected from the extension, the shebang line or a keyword \
+                       heuristic (c, cpp, python, rust, shell, ...)."
+    )]
+    language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "Code fence tag for syntax highlighting the snippet: the detected \
+                       language, a data format from the extension (json, yaml, toml, ...), \
+                       `text` for documents and PDFs, absent when unknown."
+    )]
+    syntax: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "With --two-phase: 1 for a file-name hit from the discovery pass, 2 for \
+                       a content hit from the scan (which supersedes the name hit)."
+    )]
+    phase: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symlink_target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_exists: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    also_at: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "With --copy-matches, where the file was copied.")]
+    copied_to: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "With --collapse-duplicates, the results sharing this snippet text: \
+                       their number and every path, this one first."
+    )]
+    duplicates: Option<DuplicateGroup>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The real path when --path-rewrite changed `path` for display.")]
+    original_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Size of the file in bytes when it was scanned.")]
+    file_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "Modification time of the file when it was scanned, in nanoseconds since \
+                       the Unix epoch. `show` compares it and `file_size` with the file on disk."
+    )]
+    file_mtime_ns: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "With --all-matches, the number of matches in the file, including those \
+                       beyond --max-snippets-per-file."
+    )]
+    occurrences: Option<usize>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    matches_truncated: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[schemars(description = "The path matched --deprioritize; listed after the other results.")]
+    deprioritized: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum SnippetKind {
+    #[default]
+    Text,
+    Hex,
+}
+
+struct ContentRule {
+    label: Option<String>,
+    matcher: Matcher,
+}
+
+enum Matcher {
+    Regex(Vec<(Regex, Option<usize>)>),
+    Literal(Box<memmem::Finder<'static>>, String),
+    MultiLiteral(AhoCorasick, Vec<String>),
+    Bytes(Vec<(regex::bytes::Regex, Option<usize>)>),
+}
+
+impl Matcher {
+    fn max_len(&self) -> Option<usize> {
+        match self {
+            Matcher::Regex(list) => list.iter().try_fold(0, |m, (_, len)| Some(m.max((*len)?))),
+            Matcher::Literal(_, literal) => Some(literal.len()),
+            Matcher::MultiLiteral(_, literals) => literals.iter().map(String::len).max(),
+            Matcher::Bytes(list) => list.iter().try_fold(0, |m, (_, len)| Some(m.max((*len)?))),
+        }
+    }
+}
+
+struct MatcherHit {
+    start: usize,
+    end: usize,
+    patterns: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+struct MatchOffsets {
+    match_start: usize,
+    match_end: usize,
+    snippet_start: usize,
+    offsets_are_extracted_text: bool,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct DirSuppressed {
+    dir: String,
+    suppressed: usize,
+}
+
+struct Counters<'a> {
+    scanned_text: &'a AtomicUsize,
+    scanned_pdf: &'a AtomicUsize,
+    scanned_extractor: &'a AtomicUsize,
+    skipped: &'a [AtomicUsize; SkipReason::ALL.len()],
+    transcoded_utf16: &'a AtomicUsize,
+    streamed: &'a AtomicUsize,
+    changed: &'a AtomicUsize,
+    excluded_by_mime: &'a AtomicUsize,
+    excluded_by_name: &'a AtomicUsize,
+    panicked: &'a AtomicUsize,
+    long_lines: &'a AtomicUsize,
+    script_rejected: &'a AtomicUsize,
+    bytes_read: &'a AtomicUsize,
+    bytes_pdf_text: &'a AtomicUsize,
+    bytes_too_large: &'a AtomicUsize,
+    pdf_truncated: &'a AtomicUsize,
+    email_messages: &'a AtomicUsize,
+    email_truncated: &'a AtomicUsize,
+    pdf_disabled: &'a AtomicUsize,
+    panics: &'a Mutex<Vec<FilePanic>>,
+    diagnostics: Option<&'a Mutex<Vec<Diagnostic>>>,
+    diagnostics_dropped: &'a AtomicUsize,
+    non_text: Option<&'a Mutex<Vec<PathBuf>>>,
+    vanished: Option<&'a Mutex<Vec<PathBuf>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SkipReason {
+    NonText,
+    TooLarge,
+    NonUtf8,
+    UnreadableText,
+    UnreadablePdf,
+    Timeout,
+    ChangedDuringScan,
+    ExtractorFailed,
+    Vanished,
+}
+
+impl SkipReason {
+    const ALL: [SkipReason; 9] = [
+        SkipReason::NonText,
+        SkipReason::TooLarge,
+        SkipReason::NonUtf8,
+        SkipReason::UnreadableText,
+        SkipReason::UnreadablePdf,
+        SkipReason::Timeout,
+        SkipReason::ChangedDuringScan,
+        SkipReason::ExtractorFailed,
+        SkipReason::Vanished,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            SkipReason::NonText => "non_text",
+            SkipReason::TooLarge => "too_large",
+            SkipReason::NonUtf8 => "non_utf8",
+            SkipReason::UnreadableText => "unreadable_text",
+            SkipReason::UnreadablePdf => "unreadable_pdf",
+            SkipReason::Timeout => "timeout",
+            SkipReason::ChangedDuringScan => "changed_during_scan",
+            SkipReason::ExtractorFailed => "extractor_failed",
+            SkipReason::Vanished => "vanished",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SkipReason::NonText => "non-text",
+            SkipReason::TooLarge => "too large",
+            SkipReason::NonUtf8 => "non-UTF8",
+            SkipReason::UnreadableText => "unreadable text",
+            SkipReason::UnreadablePdf => "unreadable pdf",
+            SkipReason::Timeout => "scan timeout",
+            SkipReason::ChangedDuringScan => "changed during scan",
+            SkipReason::ExtractorFailed => "extractor failed",
+            SkipReason::Vanished => "vanished before scan",
+        }
+    }
+}
+
+impl Counters<'_> {
+    fn skip(&self, reason: SkipReason, path: &Path, detail: impl FnOnce() -> String) {
+        self.skipped[reason as usize].fetch_add(1, Ordering::Relaxed);
+        if let Some(non_text) = self.non_text.filter(|_| reason == SkipReason::NonText) {
+            non_text
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(path.to_path_buf());
+        }
+        if let Some(vanished) = self.vanished.filter(|_| reason == SkipReason::Vanished) {
+            vanished
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(path.to_path_buf());
+        }
+        let Some(diagnostics) = self.diagnostics else {
+            return;
+        };
+        let mut diagnostics = diagnostics.lock().unwrap_or_else(|e| e.into_inner());
+        if diagnostics.len() >= MAX_DIAGNOSTICS {
+            self.diagnostics_dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        diagnostics.push(Diagnostic {
+            path: display_path(path),
+            category: reason.key(),
+            detail: detail(),
+            elapsed_ms: FILE_STARTED
+                .get()
+                .map_or(0, |started| started.elapsed().as_millis()),
+        });
+    }
+
+    fn panicked(&self, path: &Path, payload: &(dyn std::any::Any + Send), verbose: bool) -> String {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "non-string panic payload".to_string());
+        if verbose {
+            eprintln!("[panic] {}: {message}", path.display());
+        }
+        self.panicked.fetch_add(1, Ordering::Relaxed);
+        let mut panics = self.panics.lock().unwrap_or_else(|e| e.into_inner());
+        if panics.len() < MAX_PANIC_RECORDS {
+            panics.push(FilePanic {
+                path: display_path(path),
+                message: message.clone(),
+            });
+        }
+        message
+    }
+
+    fn named(&self) -> Vec<(&'static str, &AtomicUsize)> {
+        let mut all = vec![
+            ("scanned_text", self.scanned_text),
+            ("scanned_pdf", self.scanned_pdf),
+            ("scanned_extractor", self.scanned_extractor),
+            ("transcoded_utf16", self.transcoded_utf16),
+            ("streamed", self.streamed),
+            ("changed", self.changed),
+            ("excluded_by_mime", self.excluded_by_mime),
+            ("excluded_by_name", self.excluded_by_name),
+            ("panicked", self.panicked),
+            ("long_lines", self.long_lines),
+            ("script_rejected", self.script_rejected),
+            ("bytes_read", self.bytes_read),
+            ("bytes_pdf_text", self.bytes_pdf_text),
+            ("bytes_too_large", self.bytes_too_large),
+            ("pdf_truncated", self.pdf_truncated),
+            ("email_messages", self.email_messages),
+            ("email_truncated", self.email_truncated),
+            ("pdf_disabled", self.pdf_disabled),
+        ];
+        for r in SkipReason::ALL {
+            all.push((r.key(), &self.skipped[r as usize]));
+        }
+        all
+    }
+
+    fn snapshot(&self) -> BTreeMap<String, usize> {
+        self.named()
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    fn restore(&self, saved: &BTreeMap<String, usize>) {
+        for (k, v) in self.named() {
+            v.store(saved.get(k).copied().unwrap_or(0), Ordering::Relaxed);
+        }
+    }
+}
+
+struct ScanOptions<'a> {
+    name_query: Option<&'a str>,
+    unicode_fold: bool,
+    not_name: &'a [String],
+    not_name_glob: &'a [Regex],
+    rules: &'a [ContentRule],
+    stream_overlap: Option<usize>,
+    scope: Scope,
+    max_bytes: u64,
+    memory: &'a MemoryBudget,
+    io: &'a IoThrottle,
+    snapshots: &'a HashMap<PathBuf, FileSnapshot>,
+    strict_snapshot: bool,
+    allowed_ext: Option<&'a Vec<String>>,
+    include_no_ext: bool,
+    include_pdf: bool,
+    pdf_max_pages: usize,
+    include_email: bool,
+    email_header: &'a [(String, String)],
+    normalize_extracted: bool,
+    normalize_text: bool,
+    trim_for_anchors: bool,
+    verbose: bool,
+    per_file_timeout: Duration,
+    all_matches: bool,
+    max_snippets_per_file: usize,
+    snippet_context: usize,
+    snippet_max: usize,
+    skip_long_lines: Option<usize>,
+    show_context_heading: bool,
+    raw_notebooks: bool,
+    follow_links: bool,
+    no_catch: bool,
+    mime: &'a [String],
+    exclude_mime: &'a [String],
+    show_mime: bool,
+    extractors: &'a BTreeMap<String, String>,
+    max_bytes_by_ext: &'a BTreeMap<String, Option<u64>>,
+    index: Option<&'a IndexFilter>,
+    redact: bool,
+    binary: bool,
+    script: Option<&'a Regex>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct RunStats {
+    files_discovered: usize,
+    files_deduplicated: usize,
+    #[serde(default)]
+    files_deduped_hardlinks: usize,
+    files_excluded_by_name: usize,
+    files_excluded_by_mime: usize,
+    files_excluded_self: usize,
+    #[serde(default)]
+    files_excluded_by_ext: usize,
+    #[serde(default)]
+    files_excluded_by_kind: usize,
+    #[serde(default)]
+    files_excluded_by_lang: usize,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    matches_by_language: BTreeMap<String, usize>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    matches_by_provenance: BTreeMap<String, usize>,
+    #[serde(default)]
+    matches_excluded_by_provenance: usize,
+    #[serde(default)]
+    matches_collapsed: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pruned_dirs: Vec<PrunedDir>,
+    discovery_truncated: bool,
+    files_missing: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changed_since: Option<String>,
+    files_unchanged: usize,
+    dirs_unchanged: usize,
+    #[serde(default)]
+    paths_ignored: usize,
+    #[serde(default)]
+    dirs_depth_limited: usize,
+    #[serde(default)]
+    paths_too_long: usize,
+
+    files_scanned_text: usize,
+    files_scanned_pdf: usize,
+    files_pdf_truncated: usize,
+    #[serde(default)]
+    email_messages_scanned: usize,
+    #[serde(default)]
+    email_messages_truncated: usize,
+    #[serde(default)]
+    pdfs_skipped_disabled: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    copied: Option<CopySummary>,
+    files_scanned_extractor: usize,
+    files_transcoded_utf16: usize,
+    files_streamed: usize,
+    files_changed_during_scan: usize,
+    #[serde(default)]
+    files_vanished: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    vanished_paths: Vec<String>,
+    files_panicked: usize,
+    long_lines_skipped: usize,
+    #[serde(default)]
+    matches_rejected_by_script: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    panics: Vec<FilePanic>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diagnostics: Option<Vec<Diagnostic>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diagnostics_dropped: Option<usize>,
+    memory_peak_bytes: u64,
+    bytes_read: u64,
+    read_bytes_per_sec: u64,
+    bytes_extracted_pdf: u64,
+    bytes_skipped_too_large: u64,
+
+    skips: BTreeMap<&'static str, usize>,
+    #[serde(flatten)]
+    legacy: Option<LegacySkipStats>,
+
+    matches_total: usize,
+    matches_printed: usize,
+    results_overflowed: bool,
+    matches_truncated: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    truncation: Option<TruncationBreakdown>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sample: Option<SampleInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skip_verification: Option<SkipVerification>,
+    index: Option<IndexStats>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    rules: BTreeMap<String, usize>,
+    discovery_ms: u128,
+    scan_ms: u128,
+    elapsed_ms: u128,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct LegacySkipStats {
+    files_skipped_non_text: usize,
+    files_skipped_too_large: usize,
+    files_skipped_non_utf8: usize,
+    files_skipped_unreadable_text: usize,
+    files_skipped_unreadable_pdf: usize,
+    files_skipped_timeout: usize,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct TruncationBreakdown {
+    content: usize,
+    name_only: usize,
+    top_dirs: Vec<DirDropped>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct SkipVerification {
+    population: usize,
+    sampled: usize,
+    seed: u64,
+    decodable: usize,
+    would_match: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    matched_paths: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct CopySummary {
+    dir: String,
+    files: usize,
+    bytes: u64,
+    failed: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+struct DuplicateGroup {
+    count: usize,
+    paths: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct SampleInfo {
+    population: usize,
+    size: usize,
+    seed: u64,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct PrunedDir {
+    dir: String,
+    bytes: u64,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct FilePanic {
+    path: String,
+    message: String,
+}
+
+const MAX_PANIC_RECORDS: usize = 20;
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct Diagnostic {
+    path: String,
+    category: &'static str,
+    detail: String,
+    elapsed_ms: u128,
+}
+
+const MAX_DIAGNOSTICS: usize = 1000;
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct DirDropped {
+    dir: String,
+    dropped: usize,
+}
+
+fn main() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if !SILENCE_PANICS.load(Ordering::Relaxed) {
+            default_hook(info);
+        }
+    }));
+
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::ConfigInit { path } => {
+            let p = path.unwrap_or_else(|| PathBuf::from("rustfilefinder.toml"));
+            write_sample_config(&p).expect("failed to write config");
+            eprintln!("Config created at: {}", p.display());
+        }
+
+        Commands::Config {
+            action: ConfigCommand::Migrate { path },
+        } => {
+            let Some(p) = resolve_config_path(&path) else {
+                eprintln!("Error: no config file found; pass --path");
+                std::process::exit(2);
+            };
+            if let Err(e) = migrate_config_file(&p) {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Schema => {
+            let stdout = io::stdout();
+            let mut out = BufWriter::new(stdout.lock());
+            let written = print_schema(&mut out);
+            finish_output(written.and_then(|_| out.flush()));
+        }
+
+        Commands::Languages => {
+            let stdout = io::stdout();
+            let mut out = BufWriter::new(stdout.lock());
+            let written = print_languages(&mut out);
+            finish_output(written.and_then(|_| out.flush()));
+        }
+
+        Commands::Presets { config } => {
+            let cfg_path = resolve_config_path(&config);
+            let cfg = cfg_path.as_deref().and_then(load_config);
+            let stdout = io::stdout();
+            let mut out = BufWriter::new(stdout.lock());
+            let written = print_presets(&mut out, cfg);
+            finish_output(written.and_then(|_| out.flush()));
+        }
+
+        Commands::Search(mut args) => {
+            let started = Instant::now();
+
+            let cfg_path = resolve_config_path(&args.config);
+            let cfg = cfg_path.as_deref().and_then(load_config);
+            if let (Some(path), Some(c), false) = (&cfg_path, &cfg, args.no_extractors) {
+                args.extractors = config_extractors(path, c);
+            }
+            if let Some(kinds) = cfg.as_ref().and_then(|c| c.kinds.as_ref()) {
+                args.kinds = kinds
+                    .iter()
+                    .map(|(ext, kind)| {
+                        (
+                            ext.trim().trim_start_matches('.').to_lowercase(),
+                            kind.trim().to_lowercase(),
+                        )
+                    })
+                    .collect();
+            }
+            if let Some(limits) = cfg.as_ref().and_then(|c| c.max_bytes_by_ext.as_ref()) {
+                for (ext, size) in limits {
+                    match parse_max_bytes_ext(&format!("{ext}={size}")) {
+                        Ok((ext, limit)) => {
+                            args.max_bytes_by_ext.insert(ext, limit);
+                        }
+                        Err(e) => {
+                            eprintln!("Error: [max_bytes_by_ext] in the config: {e}");
+                            std::process::exit(2);
+                        }
+                    }
+                }
+            }
+            args.max_bytes_by_ext
+                .extend(args.max_bytes_ext.iter().cloned());
+            args.config = cfg_path.filter(|_| cfg.is_some());
+            if let Err(e) = apply_positionals(&mut args) {
+                eprintln!("Error: {e}");
+                std::process::exit(2);
+            }
+            apply_format_env(&mut args);
+            args = match args.compare_presets.clone() {
+                Some(spec) => compare_presets_args(args, &spec, cfg).unwrap_or_else(|e| {
+                    eprintln!("Error: {e}");
+                    std::process::exit(2);
+                }),
+                None => merge_search_args(args, cfg),
+            };
+            if let Some(path) = args.queries_file.clone() {
+                args = queries_file_args(args, &path).unwrap_or_else(|e| {
+                    eprintln!("Error: {e}");
+                    std::process::exit(2);
+                });
+            }
+            if let Err(e) = read_stdin_patterns(&mut args) {
+                eprintln!("Error: {e}");
+                std::process::exit(2);
+            }
+            if let Err(e) = expand_dir_globs(&mut args) {
+                eprintln!("Error: {e}");
+                std::process::exit(2);
+            }
+
+            if args.name.is_none()
+                && args.content.is_empty()
+                && args.content_file.is_none()
+                && args.rule.is_empty()
+                && args.queries.is_empty()
+            {
+                eprintln!("Error: you must provide at least --name, --content or --rule");
+                std::process::exit(2);
+            }
+            if reads_stdin(&args) {
+                if args.name.is_some() || args.within.is_some() {
+                    eprintln!("Error: --dir - searches stdin and cannot be combined with --name or --within");
+                    std::process::exit(2);
+                }
+                if args.changed_since.is_some() {
+                    eprintln!("Error: --changed-since applies to directory scans, not --dir -");
+                    std::process::exit(2);
+                }
+                if args.content.is_empty()
+                    && args.content_file.is_none()
+                    && args.rule.is_empty()
+                    && args.queries.is_empty()
+                {
+                    eprintln!("Error: --dir - needs --content or --rule");
+                    std::process::exit(2);
+                }
+            }
+
+            if let Some(note) = resolve_default_format(&mut args, stdout_kind()) {
+                if !args.quiet {
+                    eprintln!("{note}");
+                }
+            }
+            let formats = match output_formats(format_spec(&args)) {
+                Ok(f) => f,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(2);
+                }
+            };
+            if formats.len() > 1 && args.output_dir.is_none() {
+                eprintln!("Error: --format with several formats requires --output-dir");
+                std::process::exit(2);
+            }
+            if args.changed_since.is_some() && args.within.is_some() {
+                eprintln!("Error: --changed-since cannot be combined with --within");
+                std::process::exit(2);
+            }
+            if args.sample == Some(0) {
+                eprintln!("Error: --sample must be at least 1");
+                std::process::exit(2);
+            }
+            if args.paginate == Some(0) {
+                eprintln!("Error: --paginate must be at least 1");
+                std::process::exit(2);
+            }
+            if args.max_snippets_per_file == 0 {
+                eprintln!("Error: --max-snippets-per-file must be at least 1");
+                std::process::exit(2);
+            }
+            if args.two_phase && args.name.is_none() {
+                eprintln!("Error: --two-phase needs --name");
+                std::process::exit(2);
+            }
+            if !args.email_header.is_empty() && !args.include_email {
+                eprintln!("Error: --email-header requires --include-email");
+                std::process::exit(2);
+            }
+            if let Some(bad) = args.fields.iter().find(|f| !result_fields().contains(f)) {
+                eprintln!(
+                    "Error: unknown --fields name `{bad}` (expected one of {})",
+                    result_fields().join(", ")
+                );
+                std::process::exit(2);
+            }
+            if args.paginate.is_some() && args.output_dir.is_none() {
+                eprintln!("Error: --paginate requires --output-dir");
+                std::process::exit(2);
+            }
+            if !SORT_KEYS.contains(&args.sort.as_str()) {
+                eprintln!(
+                    "Error: unknown sort key `{}` in the config (expected one of {})",
+                    args.sort,
+                    SORT_KEYS.join(", ")
+                );
+                std::process::exit(2);
+            }
+            if !SCHEDULES.contains(&args.schedule.as_str()) {
+                eprintln!(
+                    "Error: unknown schedule `{}` in the config (expected one of {})",
+                    args.schedule,
+                    SCHEDULES.join(", ")
+                );
+                std::process::exit(2);
+            }
+            if !WALK_ORDERS.contains(&args.walk_order.as_str()) {
+                eprintln!(
+                    "Error: unknown walk order `{}` in the config (expected one of {})",
+                    args.walk_order,
+                    WALK_ORDERS.join(", ")
+                );
+                std::process::exit(2);
+            }
+            if !casefold::MODES.contains(&args.case_insensitive_paths.as_str()) {
+                eprintln!(
+                    "Error: unknown case_insensitive_paths mode `{}` in the config (expected \
+                     one of {})",
+                    args.case_insensitive_paths,
+                    casefold::MODES.join(", ")
+                );
+                std::process::exit(2);
+            }
+            if !COLOR_MODES.contains(&args.color.as_str()) {
+                eprintln!(
+                    "Error: unknown color mode `{}` in the config (expected one of {})",
+                    args.color,
+                    COLOR_MODES.join(", ")
+                );
+                std::process::exit(2);
+            }
+            if let Some(format) = args.compress.as_deref() {
+                if !compress::FORMATS.contains(&format) {
+                    eprintln!(
+                        "Error: unknown --compress format `{format}` (expected {})",
+                        compress::FORMATS.join(", ")
+                    );
+                    std::process::exit(2);
+                }
+                if args.output.is_none() && args.output_dir.is_none() {
+                    eprintln!("Error: --compress requires --output or --output-dir");
+                    std::process::exit(2);
+                }
+                args.output = args.output.map(|p| compress::with_extension(&p, format));
+            }
+
+            run_search(args, started);
+        }
+
+        Commands::Index { action } => {
+            let (dir, previous, verbose) = match action {
+                IndexCommand::Build(build) => {
+                    let previous = Index {
+                        version: index::INDEX_VERSION,
+                        roots: build
+                            .dir
+                            .iter()
+                            .map(|d| std::path::absolute(d).unwrap_or_else(|_| d.clone()))
+                            .collect(),
+                        include_pdf: build.include_pdf,
+                        max_bytes: build.max_bytes,
+                        built_at: 0,
+                        entries: Vec::new(),
+                        trigrams: Vec::new(),
+                    };
+                    (build.index, previous, build.verbose)
+                }
+                IndexCommand::Update { index, verbose } => match Index::load(&index) {
+                    Ok(previous) => (index, previous, verbose),
+                    Err(e) => {
+                        eprintln!("Error: {e}");
+                        std::process::exit(2);
+                    }
+                },
+            };
+            run_index(&dir, previous, verbose);
+        }
+
+        Commands::Show(show) => run_show(&show),
+
+        Commands::Merge(merge) => run_merge(&merge),
+
+        Commands::Bench(bench) => run_bench(bench),
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+struct SearchOutcome {
+    schema_version: u32,
+    stats: RunStats,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    effective_patterns: Vec<EffectivePattern>,
+    results: Vec<MatchResult>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    suppressed_per_dir: Vec<DirSuppressed>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hotspots: Option<Hotspots>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<RunMeta>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    directories: Option<Vec<DirSummary>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cooccurrence: Option<Vec<Cooccurrence>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comparison: Option<PresetComparison>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queries: Option<Vec<QueryReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diagnosis: Option<diagnose::Diagnosis>,
+}
+
+#[derive(Debug, Clone)]
+struct NamedQuery {
+    scope: PresetScope,
+    content: Vec<String>,
+    fixed_strings: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QueriesFile {
+    query: Vec<QuerySpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QuerySpec {
+    label: Option<String>,
+    #[serde(default)]
+    content: Vec<String>,
+    name: Option<String>,
+    ext: Option<String>,
+    include_no_ext: Option<bool>,
+    include_pdf: Option<bool>,
+    fixed_strings: Option<bool>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct QueryReport {
+    label: String,
+    files: usize,
+    matches: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    reports: Vec<String>,
+    #[serde(skip)]
+    #[schemars(skip)]
+    results: Vec<MatchResult>,
+}
+
+#[derive(Debug, Clone)]
+struct PresetScope {
+    name: String,
+    allowed_ext: Option<Vec<String>>,
+    include_no_ext: bool,
+    include_pdf: bool,
+    name_query: Option<String>,
+    ignore_dir: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct PresetComparison {
+    presets: [String; 2],
+    only_first: Vec<String>,
+    only_second: Vec<String>,
+    common: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct Cooccurrence {
+    patterns: [String; 2],
+    count: usize,
+    only: [usize; 2],
+    paths: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct DirSummary {
+    dir: String,
+    files: usize,
+    occurrences: usize,
+    bytes: u64,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct RunMeta {
+    generated_at: String,
+    hostname: Option<String>,
+    cwd: Option<String>,
+    config: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    path_rewrites: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    dir_globs: Vec<DirGlob>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+struct DirGlob {
+    pattern: String,
+    roots: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct Hotspots {
+    dirs: Vec<Hotspot>,
+    extensions: Vec<Hotspot>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct Hotspot {
+    key: String,
+    matches: usize,
+    percent: f64,
+}
+
+const HOTSPOT_LIMIT: usize = 10;
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct EffectivePattern {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule: Option<String>,
+    engine: &'static str,
+    patterns: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    flags: Vec<String>,
+}
+
+const OUTPUT_FORMATS: &[&str] = &["md", "json", "jsonl", "paths"];
+const JSONL_FLUSH_EVERY: usize = 256;
+const DEFAULT_MAX_DEPTH: usize = 512;
+const DEFAULT_MAX_PATH_LEN: usize = 4096;
+const DUPLICATE_PATHS_SHOWN: usize = 5;
+
+fn apply_positionals(args: &mut SearchArgs) -> Result<(), String> {
+    if let Some(pattern) = args.pattern.take() {
+        if args.names_only {
+            if args.name.is_some() {
+                return Err(
+                    "give the name query either as PATTERN or with --name, not both".to_string(),
+                );
+            }
+            args.name = Some(pattern);
+        } else {
+            if !args.content.is_empty() {
+                return Err(
+                    "give the content pattern either as PATTERN or with --content, not both"
+                        .to_string(),
+                );
+            }
+            args.content = vec![pattern];
+        }
+    }
+    if let Some(path) = args.path.take() {
+        args.dir = vec![path];
+    }
+    Ok(())
+}
+
+fn expand_dir_globs(args: &mut SearchArgs) -> Result<(), String> {
+    let mut dirs = Vec::with_capacity(args.dir.len());
+    for dir in std::mem::take(&mut args.dir) {
+        let dir = rootglob::expand_home(&dir.to_string_lossy());
+        let raw = dir.to_string_lossy().into_owned();
+        if !rootglob::is_glob(&raw) || dir.exists() {
+            dirs.push(dir);
+            continue;
+        }
+        let roots = rootglob::expand(&raw)?;
+        if roots.is_empty() && !args.allow_empty_dir_glob {
+            return Err(format!(
+                "--dir `{raw}` matched no directories (pass --allow-empty-dir-glob to search nothing)"
+            ));
+        }
+        if args.verbose {
+            eprintln!("--dir `{raw}` expanded to {} directories", roots.len());
+        }
+        dirs.extend(roots.iter().cloned());
+        args.dir_globs.push((raw, roots));
+    }
+    args.dir = dirs;
+    Ok(())
+}
+
+fn reads_stdin(args: &SearchArgs) -> bool {
+    args.dir == [PathBuf::from("-")]
+}
+
+fn read_stdin_patterns(args: &mut SearchArgs) -> Result<(), String> {
+    let from_content = args.content.iter().filter(|c| *c == "-").count();
+    let from_file = args.content_file.as_deref() == Some(Path::new("-"));
+    let searched = reads_stdin(args);
+    if from_content + usize::from(from_file) + usize::from(searched) > 1 {
+        return Err(
+            "only one of --content -, --content-file - and --dir - can read from stdin".to_string(),
+        );
+    }
+    if from_content == 0 && !from_file {
+        return Ok(());
+    }
+
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|e| format!("cannot read patterns from stdin: {e}"))?;
+    if input.ends_with('\n') {
+        input.pop();
+        if input.ends_with('\r') {
+            input.pop();
+        }
+    }
+
+    if from_file {
+        args.content_file = None;
+        args.content.extend(
+            input
+                .lines()
+                .map(str::trim_end)
+                .filter(|l| !l.is_empty())
+                .map(str::to_string),
+        );
+    } else if let Some(c) = args.content.iter_mut().find(|c| *c == "-") {
+        *c = input;
+    }
+    Ok(())
+}
+
+fn format_spec(args: &SearchArgs) -> &str {
+    args.format.as_deref().unwrap_or("md")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StdoutKind {
+    Terminal,
+    Pipe,
+    Other,
+}
+
+fn stdout_kind() -> StdoutKind {
+    if io::stdout().is_terminal() {
+        StdoutKind::Terminal
+    } else if stdout_is_pipe() {
+        StdoutKind::Pipe
+    } else {
+        StdoutKind::Other
+    }
+}
+
+#[cfg(unix)]
+fn stdout_is_pipe() -> bool {
+    use std::os::fd::AsFd;
+    use std::os::unix::fs::FileTypeExt;
+    io::stdout()
+        .as_fd()
+        .try_clone_to_owned()
+        .map(fs::File::from)
+        .and_then(|f| f.metadata())
+        .is_ok_and(|m| m.file_type().is_fifo() || m.file_type().is_socket())
+}
+
+#[cfg(not(unix))]
+fn stdout_is_pipe() -> bool {
+    false
+}
+
+fn apply_format_env(args: &mut SearchArgs) {
+    if args.format.is_some() {
+        return;
+    }
+    args.format = std::env::var("RUSTFILEFINDER_FORMAT")
+        .ok()
+        .filter(|f| !f.trim().is_empty());
+}
+
+fn resolve_default_format(args: &mut SearchArgs, stdout: StdoutKind) -> Option<String> {
+    if args.format.is_some() {
+        return None;
+    }
+    if stdout != StdoutKind::Pipe || args.output.is_some() {
+        args.format = Some("md".to_string());
+        return (stdout == StdoutKind::Terminal && args.output.is_none()).then(|| {
+            "Note: stdout is a terminal, using --format md; pass --format or set \
+             RUSTFILEFINDER_FORMAT to change it"
+                .to_string()
+        });
+    }
+    args.format = Some(args.pipe_format.clone());
+    Some(format!(
+        "Note: stdout is a pipe, using --format {}; pass --format, set \
+         RUSTFILEFINDER_FORMAT or `pipe_format` in the config to change it",
+        args.pipe_format
+    ))
+}
+
+fn output_formats(spec: &str) -> Result<Vec<&str>, String> {
+    let mut formats: Vec<&str> = Vec::new();
+    for f in spec.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        if !OUTPUT_FORMATS.contains(&f) {
+            return Err(format!(
+                "unknown format `{f}` (expected one of: {})",
+                OUTPUT_FORMATS.join(", ")
+            ));
+        }
+        if !formats.contains(&f) {
+            formats.push(f);
+        }
+    }
+    if formats.is_empty() {
+        return Err("--format must name at least one format".to_string());
+    }
+    Ok(formats)
+}
+
+fn write_report(
+    out: &mut impl Write,
+    format: &str,
+    args: &SearchArgs,
+    outcome: &SearchOutcome,
+    layout: &MarkdownLayout,
+) -> io::Result<()> {
+    match format {
+        "json" => print_json(out, outcome, &args.fields),
+        "jsonl" => print_jsonl(out, outcome, &args.fields),
+        "paths" => print_paths(out, outcome),
+        _ => print_markdown(out, args, outcome, layout),
+    }
+}
+
+fn print_jsonl(out: &mut impl Write, outcome: &SearchOutcome, fields: &[String]) -> io::Result<()> {
+    for (i, r) in outcome.results.iter().enumerate() {
+        if fields.is_empty() {
+            serde_json::to_writer(&mut *out, r)?;
+        } else {
+            serde_json::to_writer(&mut *out, &Selected { result: r, fields })?;
+        }
+        writeln!(out)?;
+        if (i + 1) % JSONL_FLUSH_EVERY == 0 {
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn print_paths(out: &mut impl Write, outcome: &SearchOutcome) -> io::Result<()> {
+    let mut seen = HashSet::new();
+    for r in &outcome.results {
+        let paths = r.duplicates.as_ref().map_or(&[][..], |g| &g.paths[..]);
+        for path in std::iter::once(&r.path).chain(paths) {
+            if seen.insert(path.as_str()) {
+                writeln!(out, "{path}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct PageIndex {
+    schema_version: u32,
+    matches_total: usize,
+    per_part: usize,
+    parts: Vec<PageEntry>,
+}
+
+#[derive(Serialize)]
+struct PageEntry {
+    file: String,
+    first_match: usize,
+    last_match: usize,
+    matches: usize,
+}
+
+fn write_pages(
+    dir: &Path,
+    format: &str,
+    per_part: usize,
+    args: &SearchArgs,
+    outcome: &SearchOutcome,
+) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let plain = MarkdownLayout {
+        color: false,
+        width: None,
+        full_paths: true,
+        show_meta: args.show_meta.as_deref(),
+        context_lines: args.context_lines,
+    };
+    let chunks: Vec<&[MatchResult]> = if outcome.results.is_empty() {
+        vec![&[]]
+    } else {
+        outcome.results.chunks(per_part).collect()
+    };
+    let file_name = |part: usize| format!("results.part{part}.{format}");
+
+    let mut index = PageIndex {
+        schema_version: JSON_SCHEMA_VERSION,
+        matches_total: outcome.results.len(),
+        per_part,
+        parts: Vec::new(),
+    };
+    for (i, chunk) in chunks.iter().enumerate() {
+        let part = i + 1;
+        let page = SearchOutcome {
+            schema_version: outcome.schema_version,
+            stats: outcome.stats.clone(),
+            effective_patterns: outcome.effective_patterns.clone(),
+            results: chunk.to_vec(),
+            suppressed_per_dir: outcome.suppressed_per_dir.clone(),
+            hotspots: outcome.hotspots.clone(),
+            meta: outcome.meta.clone(),
+            directories: outcome.directories.clone(),
+            cooccurrence: outcome.cooccurrence.clone(),
+            comparison: outcome.comparison.clone(),
+            queries: outcome.queries.clone(),
+            diagnosis: outcome.diagnosis.clone(),
+        };
+        let mut out = BufWriter::new(fs::File::create(dir.join(file_name(part)))?);
+        write_report(&mut out, format, args, &page, &plain)?;
+        if format == "md" {
+            let mut nav = vec![format!("Part {part} of {}", chunks.len())];
+            if part > 1 {
+                nav.push(format!("[previous]({})", file_name(part - 1)));
+            }
+            if part < chunks.len() {
+                nav.push(format!("[next]({})", file_name(part + 1)));
+            }
+            writeln!(out, "---\n\n{}", nav.join(" · "))?;
+        }
+        out.flush()?;
+
+        let first = i * per_part;
+        index.parts.push(PageEntry {
+            file: file_name(part),
+            first_match: first + usize::from(!chunk.is_empty()),
+            last_match: first + chunk.len(),
+            matches: chunk.len(),
+        });
+    }
+
+    if format == "json" {
+        let out = BufWriter::new(fs::File::create(dir.join("results.index.json"))?);
+        serde_json::to_writer_pretty(out, &index)?;
+    }
+    Ok(())
+}
+
+fn run_search(args: SearchArgs, started: Instant) {
+    if args.nice_io {
+        if let Err(e) = throttle::set_idle_io_priority() {
+            eprintln!("Warning: --nice-io ignored: {e}");
+        }
+    }
+    if args.progress_json {
+        let mut effective = serde_json::to_value(&args).unwrap_or_default();
+        if args.redact {
+            effective["content"] = serde_json::json!(vec![REDACTED; args.content.len()]);
+            effective["rule"] = serde_json::json!(vec![REDACTED; args.rule.len()]);
+        }
+        progress::emit(&serde_json::json!({ "event": "start", "args": effective }));
+    }
+    let mut outcome = execute_search(&args, started);
+    if !args.no_hotspots {
+        outcome.hotspots = Some(hotspots(&outcome.results));
+    }
+    if !args.no_meta {
+        outcome.meta = Some(run_meta(&args));
+    }
+    if !args.path_rewrite.is_empty() {
+        rewrite_report_paths(&mut outcome, &args.path_rewrite);
+    }
+    let formats = output_formats(format_spec(&args)).unwrap_or_default();
+
+    if let Some(dir) = args
+        .output_dir
+        .as_ref()
+        .filter(|_| !args.queries.is_empty())
+    {
+        if let Err(e) = write_query_reports(dir, &formats, &args, &mut outcome) {
+            eprintln!("Cannot write query reports to {}: {e}", dir.display());
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(path) = &args.metrics_out {
+        if let Err(e) = write_metrics(path, &args, &outcome.stats) {
+            eprintln!("Failed to write metrics to {}: {e}", path.display());
+        }
+    }
+
+    let badge_only = args.summary_line.is_some() && args.output.is_none();
+    if let (Some(per_part), Some(dir)) = (args.paginate, &args.output_dir) {
+        for format in &formats {
+            if let Err(e) = write_pages(dir, format, per_part, &args, &outcome) {
+                eprintln!(
+                    "Cannot write paginated {format} report to {}: {e}",
+                    dir.display()
+                );
+                std::process::exit(1);
+            }
+        }
+    } else if !args.quiet && !badge_only {
+        let stdout = io::stdout();
+        let mut file = args.output.as_ref().map(|p| {
+            compress::Output::create(p, args.compress.as_deref()).unwrap_or_else(|e| {
+                eprintln!("Cannot create output file {}: {e}", p.display());
+                std::process::exit(2);
+            })
+        });
+        let mut out: Box<dyn Write> = match file.as_mut() {
+            Some(f) => Box::new(f),
+            None => Box::new(BufWriter::new(stdout.lock())),
+        };
+        let to_terminal = args.output.is_none() && io::stdout().is_terminal();
+        let layout = MarkdownLayout {
+            color: match args.color.as_str() {
+                "always" => true,
+                "never" => false,
+                _ => to_terminal,
+            },
+            width: args
+                .width
+                .or_else(|| to_terminal.then(terminal_width).flatten())
+                .filter(|&w| w > 0)
+                .map(|w| w.max(MIN_LAYOUT_WIDTH)),
+            full_paths: args.full_paths,
+            show_meta: args.show_meta.as_deref(),
+            context_lines: args.context_lines,
+        };
+
+        let written = write_report(&mut out, formats[0], &args, &outcome, &layout);
+        finish_output(written.and_then(|_| out.flush()));
+        drop(out);
+        if let (Some(f), Some(p)) = (file, args.output.as_ref()) {
+            match f.finish() {
+                Ok(size) => report_compressed(&args, p, size),
+                Err(e) => finish_output(Err(e)),
+            }
+        }
+    }
+
+    if let Some(dir) = args.output_dir.as_ref().filter(|_| args.paginate.is_none()) {
+        let plain = MarkdownLayout {
+            color: false,
+            width: None,
+            full_paths: true,
+            show_meta: args.show_meta.as_deref(),
+            context_lines: args.context_lines,
+        };
+        for format in formats.iter().skip(1) {
+            let mut path = dir.join(format!("results.{format}"));
+            if let Some(compression) = args.compress.as_deref() {
+                path = compress::with_extension(&path, compression);
+            }
+            let written = fs::create_dir_all(dir)
+                .and_then(|_| compress::Output::create(&path, args.compress.as_deref()))
+                .and_then(|mut out| {
+                    write_report(&mut out, format, &args, &outcome, &plain)?;
+                    out.finish()
+                });
+            match written {
+                Ok(size) => report_compressed(&args, &path, size),
+                Err(e) => {
+                    eprintln!("Cannot write {}: {e}", path.display());
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+
+    if let Some(dest) = args.summary_line.as_deref().filter(|_| !args.quiet) {
+        let line = summary_line(&outcome.stats);
+        if dest == "stderr" {
+            eprintln!("{line}");
+        } else {
+            let mut out = io::stdout().lock();
+            finish_output(writeln!(out, "{line}").and_then(|_| out.flush()));
+        }
+    }
+
+    if args.progress_json {
+        progress::emit(&serde_json::json!({
+            "event": "done",
+            "stats": outcome.stats,
+            "elapsed_ms": started.elapsed().as_millis(),
+        }));
+    }
+
+    if outcome.stats.results_overflowed {
+        eprintln!(
+            "Warning: stopped after {} results (--max-results); the query is too broad, \
+             narrow it with --name, --ext or a more specific --content pattern",
+            args.max_results.unwrap_or_default()
+        );
+        std::process::exit(3);
+    }
+
+    let pdfs_skipped = outcome.stats.pdfs_skipped_disabled;
+    if pdfs_skipped > 0 && !args.quiet {
+        eprintln!(
+            "Note: {pdfs_skipped} PDF files were not searched; pass --include-pdf to include them"
+        );
+    }
+    if args.strict && pdfs_skipped > 0 {
+        std::process::exit(4);
+    }
+
+    if args.quiet && outcome.stats.matches_total == 0 {
+        std::process::exit(1);
+    }
+}
+
+fn execute_search(args: &SearchArgs, started: Instant) -> SearchOutcome {
+    let build = |patterns: &[String], fixed: bool, what: &str| {
+        build_matcher(
+            patterns,
+            fixed,
+            args.binary,
+            args.unicode_word_boundaries,
+            args.regex_size_limit,
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("Invalid pattern for {what}: {e}");
+            std::process::exit(2);
+        })
+    };
+
+    let mut content_patterns = args.content.clone();
+    if let Some(p) = &args.content_file {
+        let list = fs::read_to_string(p).unwrap_or_else(|e| {
+            eprintln!("Cannot read --content-file {}: {e}", p.display());
+            std::process::exit(2);
+        });
+        content_patterns.extend(
+            list.lines()
+                .map(str::trim_end)
+                .filter(|l| !l.is_empty())
+                .map(str::to_string),
+        );
+    }
+
+    let mut rules: Vec<ContentRule> = Vec::new();
+    let mut effective_patterns: Vec<EffectivePattern> = Vec::new();
+    if !content_patterns.is_empty() {
+        rules.push(ContentRule {
+            label: args.preset.clone(),
+            matcher: build(&content_patterns, args.fixed_strings, "--content"),
+        });
+    }
+    for r in &args.rule {
+        let Some((label, pat)) = r.split_once('=') else {
+            eprintln!("Invalid --rule `{r}`: expected NAME=REGEX");
+            std::process::exit(2);
+        };
+        rules.push(ContentRule {
+            label: Some(label.to_string()),
+            matcher: build(&[pat.to_string()], false, &format!("rule `{label}`")),
+        });
+    }
+    for q in &args.queries {
+        rules.push(ContentRule {
+            label: Some(q.scope.name.clone()),
+            matcher: build(
+                &q.content,
+                q.fixed_strings,
+                &format!("query `{}`", q.scope.name),
+            ),
+        });
+    }
+    if args.explain && !args.max_bytes_by_ext.is_empty() {
+        let limits: Vec<String> = args
+            .max_bytes_by_ext
+            .iter()
+            .map(|(ext, limit)| {
+                let size = limit.map_or_else(|| "unlimited".to_string(), memory::format_size);
+                format!("{ext}={size}")
+            })
+            .collect();
+        let fallback = if args.max_bytes_by_ext.contains_key("*") {
+            String::new()
+        } else {
+            format!("; otherwise --max-bytes {}", args.max_bytes)
+        };
+        eprintln!(
+            "[explain] max bytes per extension: {}{fallback}",
+            limits.join(", ")
+        );
+    }
+    for (i, rule) in rules.iter().enumerate() {
+        let from_content = i == 0 && !content_patterns.is_empty();
+        let fixed = from_content && args.fixed_strings;
+        let effective = effective_pattern(rule, fixed, args);
+        if args.explain {
+            let original = if from_content {
+                content_patterns.clone()
+            } else {
+                effective.patterns.clone()
+            };
+            let source = if from_content { "--content" } else { "--rule" };
+            eprint!("{}", explain_pattern(source, &original, fixed, &effective));
+        }
+        effective_patterns.push(effective);
+    }
+
+    let unicode_fold = !args.no_unicode_fold;
+    let name_query: Option<String> = args.name.as_deref().map(|q| fold_name(q, unicode_fold));
+    let not_name: Vec<String> = args
+        .not_name
+        .iter()
+        .map(|s| fold_name(s, unicode_fold))
+        .collect();
+    let classifier = provenance::Classifier::new(&args.vendored_glob, &args.generated_glob)
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(2);
+        });
+    let deprioritize: Vec<Regex> = args
+        .deprioritize
+        .iter()
+        .map(|g| {
+            glob::path_regex(g).unwrap_or_else(|e| {
+                eprintln!("Error: --deprioritize: {e}");
+                std::process::exit(2);
+            })
+        })
+        .collect();
+    let not_name_glob: Vec<Regex> = args
+        .not_name_glob
+        .iter()
+        .map(|g| {
+            glob::regex(g, true).unwrap_or_else(|e| {
+                eprintln!("Invalid glob for --not-name-glob: {e}");
+                std::process::exit(2);
+            })
+        })
+        .collect();
+
+    let allowed_ext: Option<Vec<String>> = args.ext.as_ref().map(|s| {
+        s.split(',')
+            .map(|x| x.trim().to_lowercase())
+            .filter(|x| !x.is_empty())
+            .collect()
+    });
+
+    let allowed_kinds: Option<Vec<String>> = args.kind.as_ref().map(|s| {
+        s.split(',')
+            .map(|x| x.trim().to_lowercase())
+            .filter(|x| !x.is_empty())
+            .collect()
+    });
+    let allowed_langs: Option<Vec<String>> = args.lang.as_ref().map(|s| {
+        s.split(',')
+            .map(|x| x.trim().to_lowercase())
+            .filter(|x| !x.is_empty())
+            .collect()
+    });
+    if let Some(langs) = &allowed_langs {
+        let known = |l: &String| language::detected().any(|name| name == l);
+        if let Some(bad) = langs.iter().find(|l| !known(l)) {
+            let names: Vec<&str> = language::detected().collect();
+            eprintln!(
+                "Error: unknown --lang `{bad}` (expected one of: {})",
+                names.join(", ")
+            );
+            std::process::exit(2);
+        }
+    }
+    if let Some(kinds) = &allowed_kinds {
+        let known = |k: &String| {
+            language::KINDS.contains(&k.as_str()) || args.kinds.values().any(|v| v == k)
+        };
+        if let Some(bad) = kinds.iter().find(|k| !known(k)) {
+            eprintln!(
+                "Error: unknown --kind `{bad}` (expected one of: {}, or a kind from [kinds])",
+                language::KINDS.join(", ")
+            );
+            std::process::exit(2);
+        }
+    }
+
+    for path in &args.explain_file {
+        eprintln!(
+            "[explain-file] {}: {}",
+            path.display(),
+            explain_file(args, path)
+        );
+    }
+
+    let stdin_mode = reads_stdin(args);
+    let run_started = SystemTime::now();
+    let last_run_key = args.changed_since.as_ref().map(|_| last_run_key(args));
+    let cutoff = match args.changed_since.as_deref() {
+        None => None,
+        Some("last-run") => {
+            let previous = last_run_key.as_deref().and_then(load_last_run);
+            if previous.is_none() && !args.quiet {
+                eprintln!("Note: no previous --changed-since run over these directories; scanning everything");
+            }
+            previous
+        }
+        Some(spec) => Some(parse_changed_since(spec, run_started).unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(2);
+        })),
+    };
+    let discovery = match &args.within {
+        None if stdin_mode => Discovery {
+            files: Vec::new(),
+            snapshots: HashMap::new(),
+            pruned_dirs: Vec::new(),
+            truncated: false,
+            missing: 0,
+            unchanged_files: 0,
+            unchanged_dirs: 0,
+            ignored: 0,
+            excluded_ext: 0,
+            depth_limited: 0,
+            too_long: 0,
+        },
+        Some(report) => within_files(
+            report,
+            allowed_ext.as_ref(),
+            args.include_no_ext,
+            !rules.is_empty(),
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(2);
+        }),
+        None => discover_files(args, allowed_ext.as_ref(), !rules.is_empty(), cutoff),
+    };
+    let Discovery {
+        mut files,
+        snapshots,
+        pruned_dirs,
+        truncated: discovery_truncated,
+        missing: files_missing,
+        unchanged_files,
+        unchanged_dirs,
+        ignored: ignored_paths,
+        excluded_ext,
+        depth_limited,
+        too_long,
+    } = discovery;
+    if files_missing > 0 {
+        eprintln!("Warning: {files_missing} files listed in --within no longer exist");
+    }
+    for d in &pruned_dirs {
+        eprintln!(
+            "Warning: skipped {} (over --max-dir-bytes after {})",
+            d.dir,
+            memory::format_size(d.bytes)
+        );
+    }
+    if discovery_truncated {
+        eprintln!("Warning: discovery stopped at --max-total-bytes; the run is truncated");
+    }
+    if depth_limited > 0 {
+        eprintln!(
+            "Warning: {depth_limited} directories not descended past --max-depth {}",
+            args.max_depth
+        );
+    }
+    if too_long > 0 {
+        eprintln!(
+            "Warning: skipped {too_long} paths longer than --max-path-len {} bytes",
+            args.max_path_len
+        );
+    }
+
+    let files_discovered = files.len() + usize::from(stdin_mode);
+
+    let artifacts = report_artifacts(args);
+    let mut files_excluded_self = 0;
+    files.retain(|f| {
+        if !artifacts.contains(f) {
+            return true;
+        }
+        files_excluded_self += 1;
+        if args.verbose {
+            eprintln!("[self] excluded report artifact: {}", f.display());
+        }
+        false
+    });
+
+    let mut files_excluded_kind = 0;
+    if let Some(kinds) = &allowed_kinds {
+        files.retain(|f| {
+            let kind = language::kind_of(f.extension().and_then(|e| e.to_str()), &args.kinds);
+            if kinds.contains(&kind) {
+                return true;
+            }
+            files_excluded_kind += 1;
+            false
+        });
+    }
+    let mut files_excluded_lang = 0;
+    if let Some(langs) = &allowed_langs {
+        files.retain(|f| {
+            let lang = language::detect(f.extension().and_then(|e| e.to_str()), &io_path(f));
+            if lang.is_some_and(|l| langs.iter().any(|x| x == l)) {
+                return true;
+            }
+            files_excluded_lang += 1;
+            false
+        });
+    }
+
+    let fold_case = casefold::resolve(&args.case_insensitive_paths, &args.dir);
+    if fold_case && args.verbose {
+        eprintln!("[paths] comparing paths case-insensitively");
+    }
+    let needs_dedup = !args.no_dedup && (args.dir.len() > 1 || args.follow_links);
+    let (files, files_deduplicated) = if needs_dedup {
+        dedup_files(files, fold_case)
+    } else {
+        (files, 0)
+    };
+    let (mut files, hardlinks) = if args.dedup_hardlinks {
+        dedup_hardlinks(files)
+    } else {
+        (files, HashMap::new())
+    };
+    let files_deduped_hardlinks = hardlinks.values().map(Vec::len).sum();
+
+    let phases_after_selection = args.sample.is_some() || args.limit.is_some_and(|l| l > 0);
+    if args.two_phase && !phases_after_selection {
+        for f in &files {
+            let matched = file_name_match(
+                f,
+                name_query.as_deref(),
+                unicode_fold,
+                &not_name,
+                &not_name_glob,
+            );
+            if matched == Some(true) {
+                eprintln!("[phase 1] {}", display_path(f));
+            }
+        }
+    }
+
+    let by_size = args.schedule == "size" && args.walk_order == "dfs";
+    if by_size {
+        schedule_largest_first(&mut files);
+    }
+    let fine_grained = by_size || args.walk_order != "dfs";
+    let discovery_ms = started.elapsed().as_millis();
+    let scan_started = Instant::now();
+
+    let scanned_text = AtomicUsize::new(0);
+    let scanned_pdf = AtomicUsize::new(0);
+    let scanned_extractor = AtomicUsize::new(0);
+
+    let skipped: [AtomicUsize; SkipReason::ALL.len()] = Default::default();
+    let transcoded_utf16 = AtomicUsize::new(0);
+    let streamed = AtomicUsize::new(0);
+    let changed = AtomicUsize::new(0);
+    let excluded_by_mime = AtomicUsize::new(0);
+    let excluded_by_name = AtomicUsize::new(0);
+    let panicked = AtomicUsize::new(0);
+    let long_lines = AtomicUsize::new(0);
+    let script_rejected = AtomicUsize::new(0);
+    let bytes_read = AtomicUsize::new(0);
+    let bytes_pdf_text = AtomicUsize::new(0);
+    let bytes_too_large = AtomicUsize::new(0);
+    let pdf_truncated = AtomicUsize::new(0);
+    let email_messages = AtomicUsize::new(0);
+    let email_truncated = AtomicUsize::new(0);
+    let pdf_disabled = AtomicUsize::new(0);
+    let panics = Mutex::new(Vec::new());
+    let diagnose =
+        args.verbose && output_formats(format_spec(args)).is_ok_and(|f| f.contains(&"json"));
+    let diagnostics = Mutex::new(Vec::new());
+    let diagnostics_dropped = AtomicUsize::new(0);
+    let non_text = Mutex::new(Vec::new());
+    let vanished = Mutex::new(Vec::new());
+    let memory = MemoryBudget::new(args.memory_limit);
+    let io = IoThrottle::new(args.throttle.map(|mb| mb * 1024.0 * 1024.0));
+
+    let counters = Counters {
+        scanned_text: &scanned_text,
+        scanned_pdf: &scanned_pdf,
+        scanned_extractor: &scanned_extractor,
+        skipped: &skipped,
+        transcoded_utf16: &transcoded_utf16,
+        streamed: &streamed,
+        changed: &changed,
+        excluded_by_mime: &excluded_by_mime,
+        excluded_by_name: &excluded_by_name,
+        panicked: &panicked,
+        long_lines: &long_lines,
+        script_rejected: &script_rejected,
+        bytes_read: &bytes_read,
+        bytes_pdf_text: &bytes_pdf_text,
+        bytes_too_large: &bytes_too_large,
+        pdf_truncated: &pdf_truncated,
+        email_messages: &email_messages,
+        email_truncated: &email_truncated,
+        pdf_disabled: &pdf_disabled,
+        panics: &panics,
+        diagnostics: diagnose.then_some(&diagnostics),
+        diagnostics_dropped: &diagnostics_dropped,
+        non_text: args.verify_skips.map(|_| &non_text),
+        vanished: args.show_skipped.then_some(&vanished),
+    };
+
+    let mut checkpoint = None;
+    if let Some(cp_path) = &args.checkpoint {
+        let fingerprint = search_fingerprint(args);
+        let state = match Checkpoint::load(cp_path, &fingerprint) {
+            Ok(Some(saved)) => {
+                counters.restore(&saved.counters);
+                let done: HashSet<&PathBuf> = saved.processed.iter().collect();
+                files.retain(|f| !done.contains(f));
+                if args.verbose {
+                    eprintln!(
+                        "Resuming from {}: {} files already processed",
+                        cp_path.display(),
+                        saved.processed.len()
+                    );
+                }
+                saved
+            }
+            Ok(None) => Checkpoint::new(fingerprint),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(2);
+            }
+        };
+        checkpoint = Some((cp_path, Mutex::new(state)));
+    }
+    let restored_results = checkpoint.as_ref().map_or(0, |(_, state)| {
+        state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .results
+            .len()
+    });
+
+    if args.cooccurrence && rules.iter().filter(|r| r.label.is_some()).count() < 2 {
+        eprintln!("Error: --cooccurrence needs at least two named patterns (--rule NAME=REGEX)");
+        std::process::exit(2);
+    }
+
+    let index_filter = args.use_index.as_ref().map(|dir| {
+        let query = Query::Or(rules.iter().map(|r| rule_query(&r.matcher)).collect());
+        IndexFilter::open(dir, query).unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(2);
+        })
+    });
+
+    let script = (!args.require_script.is_empty()).then(|| {
+        script_regex(&args.require_script).unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(2);
+        })
+    });
+
+    let opts = ScanOptions {
+        name_query: name_query.as_deref(),
+        unicode_fold,
+        not_name: &not_name,
+        not_name_glob: &not_name_glob,
+        rules: &rules,
+        stream_overlap: stream_overlap(&rules),
+        scope: Scope::parse(&args.scope).unwrap_or(Scope::All),
+        max_bytes: args.max_bytes,
+        memory: &memory,
+        io: &io,
+        snapshots: &snapshots,
+        strict_snapshot: args.strict_snapshot,
+        allowed_ext: allowed_ext.as_ref(),
+        include_no_ext: args.include_no_ext,
+        include_pdf: args.include_pdf,
+        pdf_max_pages: args.pdf_max_pages,
+        include_email: args.include_email,
+        email_header: &args.email_header,
+        normalize_extracted: args.normalize_whitespace != "off",
+        normalize_text: args.normalize_whitespace == "all",
+        trim_for_anchors: args.trim_for_anchors,
+        verbose: args.verbose,
+        per_file_timeout: file_timeout(args.per_file_timeout),
+        all_matches: args.all_matches,
+        max_snippets_per_file: args.max_snippets_per_file,
+        snippet_context: args.snippet_context,
+        snippet_max: args.snippet_max,
+        skip_long_lines: args.skip_long_lines.filter(|&n| n > 0),
+        show_context_heading: args.show_context_heading,
+        raw_notebooks: args.raw,
+        follow_links: args.follow_links,
+        no_catch: args.no_catch,
+        mime: &args.mime,
+        exclude_mime: &args.exclude_mime,
+        show_mime: args.show_mime,
+        extractors: &args.extractors,
+        max_bytes_by_ext: &args.max_bytes_by_ext,
+        index: index_filter.as_ref(),
+        redact: args.redact,
+        binary: args.binary,
+        script: script.as_ref(),
+    };
+
+    let collected = AtomicUsize::new(restored_results);
+    let reporter = args.progress_json.then(|| {
+        progress::Reporter::spawn(
+            files.len(),
+            Duration::from_millis(args.progress_interval),
+            started,
+        )
+    });
+    let overflowed = AtomicBool::new(false);
+
+    let from_stdin = if stdin_mode {
+        scan_stdin(&opts, &counters)
+    } else {
+        Vec::new()
+    };
+
+    let mut results: Vec<MatchResult> = files
+        .par_iter()
+        .with_max_len(if fine_grained { 1 } else { usize::MAX })
+        .flat_map_iter(|path| {
+            if overflowed.load(Ordering::Relaxed)
+                || reporter.as_ref().is_some_and(|r| r.cancelled())
+            {
+                return Vec::new();
+            }
+
+            let attempt = if args.no_catch {
+                Ok(analyze_file(path, &opts, &counters))
+            } else {
+                catch_unwind(AssertUnwindSafe(|| analyze_file(path, &opts, &counters)))
+            };
+
+            let found = match attempt {
+                Ok(mut found) => {
+                    let link = if found.is_empty() {
+                        None
+                    } else {
+                        symlink_target(path)
+                    };
+                    if let Some((target, exists)) = link {
+                        for r in &mut found {
+                            r.symlink_target = Some(target.clone());
+                            r.target_exists = Some(exists);
+                        }
+                    }
+                    if let Some(max) = args.max_results {
+                        let before = collected.fetch_add(found.len(), Ordering::Relaxed);
+                        if before + found.len() > max {
+                            overflowed.store(true, Ordering::Relaxed);
+                            found.truncate(max.saturating_sub(before));
+                        }
+                    }
+                    if args.two_phase && !phases_after_selection {
+                        for r in found.iter().filter(|r| r.matched_content) {
+                            print_phase_two(r, &rules, args);
+                        }
+                    }
+                    found
+                }
+                Err(payload) => {
+                    let message = counters.panicked(path, payload.as_ref(), args.verbose);
+                    let reason = if is_pdf(path) {
+                        SkipReason::UnreadablePdf
+                    } else {
+                        SkipReason::UnreadableText
+                    };
+                    counters.skip(reason, path, || format!("panic: {message}"));
+                    Vec::new()
+                }
+            };
+            if let Some(reporter) = &reporter {
+                reporter.file_done(found.len());
+            }
+
+            if let Some((cp_path, state)) = &checkpoint {
+                let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                state.processed.push(path.clone());
+                state
+                    .results
+                    .extend(found.iter().map(|r| redacted_copy(r, &rules, args)));
+                if state
+                    .processed
+                    .len()
+                    .is_multiple_of(args.checkpoint_every.max(1))
+                {
+                    state.counters = counters.snapshot();
+                    if let Err(e) = state.save(cp_path) {
+                        eprintln!(
+                            "Warning: cannot write checkpoint {}: {e}",
+                            cp_path.display()
+                        );
+                    }
+                }
+            }
+            found
+        })
+        .collect();
+    if reporter.is_some_and(|r| r.stop()) {
+        std::process::exit(progress::CANCELLED_EXIT);
+    }
+
+    if let Some((cp_path, state)) = checkpoint {
+        results = state
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner())
+            .results;
+        match fs::remove_file(cp_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                eprintln!(
+                    "Warning: cannot remove checkpoint {}: {e}",
+                    cp_path.display()
+                );
+            }
+            _ => {}
+        }
+    }
+
+    results.extend(from_stdin);
+    if let (Some(key), false) = (&last_run_key, stdin_mode) {
+        record_last_run(key, run_started);
+    }
+
+    let seed = run_seed(args);
+    let skip_verification = args.verify_skips.map(|n| {
+        let skipped = non_text.into_inner().unwrap_or_else(|e| e.into_inner());
+        verify_skips(skipped, n, seed, &opts)
+    });
+
+    let scan_ms = scan_started.elapsed().as_millis();
+
+    let mut provenance_of: HashMap<String, &'static str> = HashMap::new();
+    let mut languages: HashMap<String, Option<&'static str>> = HashMap::new();
+    let relative_of = |path: &Path| {
+        args.dir
+            .iter()
+            .find_map(|d| path.strip_prefix(d).ok())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    };
+    for r in &mut results {
+        r.fingerprint = result_fingerprint(r, &args.dir);
+        r.provenance = provenance_of
+            .entry(r.path.clone())
+            .or_insert_with(|| {
+                let path = Path::new(&r.path);
+                classifier.classify(&relative_of(path), &io_path(path))
+            })
+            .to_string();
+        if !deprioritize.is_empty() {
+            let relative = relative_of(Path::new(&r.path));
+            r.deprioritized = deprioritize.iter().any(|g| g.is_match(&relative));
+        }
+        r.kind = language::kind_of(
+            Path::new(&r.path).extension().and_then(|e| e.to_str()),
+            &args.kinds,
+        );
+        r.language = languages
+            .entry(r.path.clone())
+            .or_insert_with(|| {
+                let path = Path::new(&r.path);
+                language::detect(path.extension().and_then(|e| e.to_str()), &io_path(path))
+            })
+            .map(str::to_string);
+        r.syntax = language::syntax(
+            r.language.as_deref(),
+            Path::new(&r.path).extension().and_then(|e| e.to_str()),
+            &r.kind,
+        );
+        if let Some(links) = hardlinks.get(&r.path) {
+            r.also_at.clone_from(links);
+        }
+        if r.path != STDIN_PATH {
+            if let Ok(m) = fs::metadata(io_path(Path::new(&r.path))) {
+                r.file_size = Some(m.len());
+                r.file_mtime_ns = mtime_ns(&m);
+            }
+        }
+        if args.two_phase {
+            r.phase = Some(if r.matched_content { 2 } else { 1 });
+        }
+    }
+
+    let mut matches_by_provenance: BTreeMap<String, usize> = BTreeMap::new();
+    let mut matches_by_language: BTreeMap<String, usize> = BTreeMap::new();
+    for r in &results {
+        if let Some(l) = &r.language {
+            *matches_by_language.entry(l.clone()).or_insert(0) += 1;
+        }
+        *matches_by_provenance
+            .entry(r.provenance.clone())
+            .or_insert(0) += 1;
+    }
+    let before_provenance = results.len();
+    if !args.provenance.is_empty() {
+        results.retain(|r| args.provenance.contains(&r.provenance));
+    }
+    let matches_excluded_by_provenance = before_provenance - results.len();
+
+    sort_results(&mut results, &args.sort, args.reverse, fold_case);
+    if !args.queries.is_empty() {
+        let unicode_fold = !args.no_unicode_fold;
+        results.retain(|r| {
+            args.queries.iter().any(|q| {
+                (r.rule.as_deref() == Some(q.scope.name.as_str())
+                    || r.rules.contains(&q.scope.name))
+                    && scope_admits(&q.scope, Path::new(&r.path), unicode_fold)
+            })
+        });
+    }
+
+    let matches_total = results.len();
+
+    let mut rules_summary: BTreeMap<String, usize> = BTreeMap::new();
+    for rule in results.iter().flat_map(|r| &r.rules) {
+        *rules_summary.entry(rule.clone()).or_insert(0) += 1;
+    }
+
+    let cooccurrence = args.cooccurrence.then(|| {
+        let labels: BTreeSet<&str> = rules.iter().filter_map(|r| r.label.as_deref()).collect();
+        cooccurrence_pairs(&results, &labels)
+    });
+
+    let comparison = args
+        .compare
+        .as_ref()
+        .map(|scopes| preset_comparison(&results, scopes, !args.no_unicode_fold));
+
+    let mut queries = (!args.queries.is_empty())
+        .then(|| split_queries(&results, &args.queries, &args.dir, !args.no_unicode_fold));
+
+    let mut directories = None;
+    if args.summarize_dirs {
+        directories = Some(summarize_dirs(&results, &args.dir, args.summarize_depth));
+        results.clear();
+    }
+
+    let matches_collapsed = if args.collapse_duplicates {
+        collapse_duplicates(&mut results)
+    } else {
+        0
+    };
+
+    let (mut results_print, suppressed_per_dir) = match args.limit_per_dir.filter(|&n| n > 0) {
+        Some(n) => limit_per_dir(results, n),
+        None => (results, Vec::new()),
+    };
+    let mut truncation = None;
+    let mut sample = None;
+    if let Some(n) = args.sample {
+        let population = results_print.len();
+        results_print = reservoir_sample(results_print, n, seed);
+        sample = Some(SampleInfo {
+            population,
+            size: results_print.len(),
+            seed,
+        });
+    } else if let Some(limit) = args.limit.filter(|&l| l > 0) {
+        if results_print.len() > limit {
+            truncation = Some(truncation_breakdown(&results_print[limit..]));
+            results_print.truncate(limit);
+        }
+    }
+    let matches_printed = results_print.len();
+    if args.two_phase && phases_after_selection {
+        for r in &results_print {
+            match r.phase {
+                Some(1) => eprintln!("[phase 1] {}", r.path),
+                Some(2) => print_phase_two(r, &rules, args),
+                _ => {}
+            }
+        }
+    }
+
+    let copied = args
+        .copy_matches
+        .as_ref()
+        .map(|dir| copy_matches(dir, &mut results_print, args));
+
+    if args.redact {
+        let deadline = Instant::now() + file_timeout(args.per_file_timeout);
+        for r in &mut results_print {
+            redact_result(r, &rules, deadline);
+        }
+        for q in queries.iter_mut().flatten() {
+            for r in &mut q.results {
+                redact_result(r, &rules, deadline);
+            }
+        }
+        for e in &mut effective_patterns {
+            e.patterns = vec![REDACTED.to_string(); e.patterns.len()];
+        }
+    }
+
+    let elapsed_ms = started.elapsed().as_millis();
+
+    let skip_count = |r: SkipReason| skipped[r as usize].load(Ordering::Relaxed);
+
+    let stats = RunStats {
+        files_discovered,
+        files_deduplicated,
+        files_deduped_hardlinks,
+        files_excluded_by_name: excluded_by_name.load(Ordering::Relaxed),
+        files_excluded_by_mime: excluded_by_mime.load(Ordering::Relaxed),
+        files_excluded_self,
+        files_excluded_by_ext: excluded_ext,
+        files_excluded_by_kind: files_excluded_kind,
+        files_excluded_by_lang: files_excluded_lang,
+        matches_by_language,
+        matches_by_provenance,
+        matches_excluded_by_provenance,
+        matches_collapsed,
+        pruned_dirs,
+        discovery_truncated,
+        files_missing,
+        changed_since: cutoff.map(rfc3339_utc),
+        files_unchanged: unchanged_files,
+        dirs_unchanged: unchanged_dirs,
+        paths_ignored: ignored_paths,
+        dirs_depth_limited: depth_limited,
+        paths_too_long: too_long,
+        files_scanned_text: scanned_text.load(Ordering::Relaxed),
+        files_scanned_pdf: scanned_pdf.load(Ordering::Relaxed),
+        files_pdf_truncated: pdf_truncated.load(Ordering::Relaxed),
+        email_messages_scanned: email_messages.load(Ordering::Relaxed),
+        email_messages_truncated: email_truncated.load(Ordering::Relaxed),
+        pdfs_skipped_disabled: pdf_disabled.load(Ordering::Relaxed),
+        copied,
+        files_scanned_extractor: scanned_extractor.load(Ordering::Relaxed),
+        files_transcoded_utf16: transcoded_utf16.load(Ordering::Relaxed),
+        files_streamed: streamed.load(Ordering::Relaxed),
+        files_changed_during_scan: changed.load(Ordering::Relaxed),
+        files_vanished: skip_count(SkipReason::Vanished),
+        vanished_paths: {
+            let mut paths: Vec<String> = vanished
+                .into_inner()
+                .unwrap_or_else(|e| e.into_inner())
+                .iter()
+                .map(|p| display_path(p))
+                .collect();
+            paths.sort();
+            paths
+        },
+        files_panicked: panicked.load(Ordering::Relaxed),
+        long_lines_skipped: long_lines.load(Ordering::Relaxed),
+        matches_rejected_by_script: script_rejected.load(Ordering::Relaxed),
+        bytes_extracted_pdf: bytes_pdf_text.load(Ordering::Relaxed) as u64,
+        bytes_skipped_too_large: bytes_too_large.load(Ordering::Relaxed) as u64,
+        panics: panics.into_inner().unwrap_or_else(|e| e.into_inner()),
+        diagnostics: diagnose.then(|| diagnostics.into_inner().unwrap_or_else(|e| e.into_inner())),
+        diagnostics_dropped: diagnose.then(|| diagnostics_dropped.load(Ordering::Relaxed)),
+        memory_peak_bytes: memory.peak(),
+        bytes_read: bytes_read.load(Ordering::Relaxed) as u64,
+        read_bytes_per_sec: (bytes_read.load(Ordering::Relaxed) as f64
+            / (scan_ms.max(1) as f64 / 1000.0)) as u64,
+        skips: SkipReason::ALL
+            .iter()
+            .map(|r| (r.key(), skip_count(*r)))
+            .collect(),
+        legacy: args.legacy_stats.then(|| LegacySkipStats {
+            files_skipped_non_text: skip_count(SkipReason::NonText),
+            files_skipped_too_large: skip_count(SkipReason::TooLarge),
+            files_skipped_non_utf8: skip_count(SkipReason::NonUtf8),
+            files_skipped_unreadable_text: skip_count(SkipReason::UnreadableText),
+            files_skipped_unreadable_pdf: skip_count(SkipReason::UnreadablePdf),
+            files_skipped_timeout: skip_count(SkipReason::Timeout),
+        }),
+        matches_total,
+        matches_printed,
+        results_overflowed: overflowed.load(Ordering::Relaxed),
+        matches_truncated: truncation.as_ref().map_or(0, |t| t.content + t.name_only),
+        truncation,
+        sample,
+        skip_verification,
+        index: index_filter.as_ref().map(IndexFilter::stats),
+        rules: rules_summary,
+        discovery_ms,
+        scan_ms,
+        elapsed_ms,
+    };
+    let diagnosis = (matches_total == 0).then(|| diagnose::diagnose(&stats, args));
+
+    SearchOutcome {
+        schema_version: JSON_SCHEMA_VERSION,
+        stats,
+        effective_patterns,
+        results: results_print,
+        suppressed_per_dir,
+        hotspots: None,
+        meta: None,
+        directories,
+        cooccurrence,
+        comparison,
+        queries,
+        diagnosis,
+    }
+}
+
+const REDACTED: &str = "[REDACTED]";
+
+fn redaction_mask(label: Option<&str>) -> String {
+    label.map_or_else(|| REDACTED.to_string(), |l| format!("[REDACTED:{l}]"))
+}
+
+fn redact_result(r: &mut MatchResult, rules: &[ContentRule], deadline: Instant) {
+    let snippet = r.snippet.as_deref().map(|s| match r.snippet_kind {
+        SnippetKind::Hex => redaction_mask(r.rule.as_deref()),
+        SnippetKind::Text => {
+            let primary = r.offsets.as_ref().map(|o| {
+                (
+                    clamp_to_char_boundary(s, o.match_start.saturating_sub(o.snippet_start)),
+                    clamp_to_char_boundary(s, o.match_end.saturating_sub(o.snippet_start)),
+                    r.rule.as_deref(),
+                )
+            });
+            redact_text(s, primary, rules, deadline)
+        }
+    });
+    let context = r
+        .context
+        .as_deref()
+        .map(|c| redact_text(c, None, rules, deadline));
+    r.snippet = snippet;
+    r.context = context;
+    r.matched_patterns.clear();
+    r.offsets = None;
+}
+
+fn redacted_copy(r: &MatchResult, rules: &[ContentRule], args: &SearchArgs) -> MatchResult {
+    let mut r = r.clone();
+    if args.redact {
+        let deadline = Instant::now() + file_timeout(args.per_file_timeout);
+        redact_result(&mut r, rules, deadline);
+    }
+    r
+}
+
+fn print_phase_two(r: &MatchResult, rules: &[ContentRule], args: &SearchArgs) {
+    let r = redacted_copy(r, rules, args);
+    let snippet = r.snippet.as_deref().unwrap_or_default();
+    eprintln!("[phase 2] {}: {}", r.path, collapse_whitespace(snippet));
+}
+
+fn redact_text(
+    text: &str,
+    primary: Option<(usize, usize, Option<&str>)>,
+    rules: &[ContentRule],
+    deadline: Instant,
+) -> String {
+    let mut spans: Vec<(usize, usize, Option<&str>)> = primary.into_iter().collect();
+    for rule in rules {
+        spans.extend(
+            find_all_matches(&rule.matcher, text, deadline)
+                .into_iter()
+                .map(|(start, end)| (start, end, rule.label.as_deref())),
+        );
+    }
+    spans.retain(|&(start, end, _)| start < end);
+    spans.sort_by_key(|&(start, end, _)| (start, std::cmp::Reverse(end)));
+
+    let mut out = String::with_capacity(text.len());
+    let mut pos = 0;
+    for (start, end, label) in spans {
+        if end <= pos {
+            continue;
+        }
+        out.push_str(&text[pos..start.max(pos)]);
+        out.push_str(&redaction_mask(label));
+        pos = end;
+    }
+    out.push_str(&text[pos..]);
+    out
+}
+
+fn find_all_matches(matcher: &Matcher, text: &str, deadline: Instant) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let mut pos = 0;
+    while pos < text.len() {
+        let Ok(Some(hit)) = find_matcher(matcher, text, pos, deadline) else {
+            break;
+        };
+        if hit.end > hit.start {
+            found.push((hit.start, hit.end));
+            pos = hit.end;
+        } else {
+            pos = hit.start + text[hit.start..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    found
+}
+
+const REDACT_LOOKAROUND_BYTES: usize = 1024;
+
+fn trim_partial_matches(
+    text: &str,
+    snippet_start: usize,
+    snippet: String,
+    primary: (usize, usize),
+    rules: &[ContentRule],
+    deadline: Instant,
+) -> (usize, String) {
+    let snippet_end = snippet_start + snippet.len();
+    let lo = clamp_to_char_boundary(text, snippet_start.saturating_sub(REDACT_LOOKAROUND_BYTES));
+    let hi = clamp_to_char_boundary(text, snippet_end + REDACT_LOOKAROUND_BYTES);
+    let (mut from, mut to) = (snippet_start, snippet_end);
+    for rule in rules {
+        for (a, b) in find_all_matches(&rule.matcher, &text[lo..hi], deadline) {
+            let (a, b) = (lo + a, lo + b);
+            if a < snippet_start && b > snippet_start {
+                from = from.max(b.min(primary.0));
+            }
+            if a < snippet_end && b > snippet_end {
+                to = to.min(a.max(primary.1));
+            }
+        }
+    }
+    if (from, to) == (snippet_start, snippet_end) || from >= to {
+        return (snippet_start, snippet);
+    }
+    let start = clamp_to_char_boundary(&snippet, from - snippet_start);
+    let end = clamp_to_char_boundary(&snippet, to - snippet_start);
+    (snippet_start + start, snippet[start..end].to_string())
+}
+
+fn run_seed(args: &SearchArgs) -> u64 {
+    args.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    })
+}
+
+fn verify_skips(
+    mut skipped: Vec<PathBuf>,
+    n: usize,
+    seed: u64,
+    opts: &ScanOptions,
+) -> SkipVerification {
+    skipped.sort();
+    let population = skipped.len();
+    let sample = reservoir_sample(skipped, n, seed);
+    let mut decodable = 0;
+    let mut matched_paths = Vec::new();
+    for path in &sample {
+        let mut buf = Vec::new();
+        let read = fs::File::open(io_path(path))
+            .and_then(|f| opts.io.reader(f).take(opts.max_bytes).read_to_end(&mut buf));
+        let Some((text, _)) = read.ok().and_then(|_| decode_text(&buf)) else {
+            continue;
+        };
+        decodable += 1;
+        let deadline = Instant::now() + opts.per_file_timeout;
+        let hit = opts
+            .rules
+            .iter()
+            .any(|rule| find_matcher(&rule.matcher, &text, 0, deadline).is_ok_and(|m| m.is_some()));
+        if hit {
+            matched_paths.push(display_path(path));
+        }
+    }
+    if !matched_paths.is_empty() {
+        eprintln!(
+            "Warning: {} of {} sampled non-text skips would have matched; check --ext",
+            matched_paths.len(),
+            sample.len()
+        );
+    }
+    SkipVerification {
+        population,
+        sampled: sample.len(),
+        seed,
+        decodable,
+        would_match: matched_paths.len(),
+        matched_paths,
+    }
+}
+
+fn reservoir_sample<T>(items: Vec<T>, n: usize, seed: u64) -> Vec<T> {
+    let mut state = seed;
+    let mut next = move || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+
+    let mut picked: Vec<usize> = (0..n.min(items.len())).collect();
+    for i in n..items.len() {
+        let j = ((u128::from(next()) * (i as u128 + 1)) >> 64) as usize;
+        if j < n {
+            picked[j] = i;
+        }
+    }
+
+    let mut keep = vec![false; items.len()];
+    for i in picked {
+        keep[i] = true;
+    }
+    items
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(item, k)| k.then_some(item))
+        .collect()
+}
+
+fn parse_path_rewrite(entry: &str) -> Result<(String, String), String> {
+    let Some((from, to)) = entry.split_once('=') else {
+        return Err(format!(
+            "invalid `{entry}`: expected FROM=TO, e.g. /data=//fileserver/corpus"
+        ));
+    };
+    let from = from.trim_end_matches(['/', '\\']);
+    if from.is_empty() {
+        return Err(format!("invalid `{entry}`: FROM must not be empty or `/`"));
+    }
+    Ok((
+        from.to_string(),
+        to.trim_end_matches(['/', '\\']).to_string(),
+    ))
+}
+
+fn rewrite_path(path: &str, rewrites: &[(String, String)]) -> Option<String> {
+    let (to, rest) = rewrites
+        .iter()
+        .filter_map(|(from, to)| {
+            let rest = path.strip_prefix(from.as_str())?;
+            (rest.is_empty() || rest.starts_with(['/', '\\'])).then_some((from.len(), to, rest))
+        })
+        .max_by_key(|(len, _, _)| *len)
+        .map(|(_, to, rest)| (to, rest))?;
+    if to.contains('\\') && !to.contains('/') {
+        return Some(format!("{to}{}", rest.replace('/', "\\")));
+    }
+    Some(format!("{to}{rest}"))
+}
+
+fn rewrite_report_paths(outcome: &mut SearchOutcome, rewrites: &[(String, String)]) {
+    let rewrite = |p: &mut String| {
+        if let Some(shown) = rewrite_path(p, rewrites) {
+            *p = shown;
+        }
+    };
+    let queries = outcome.queries.iter_mut().flatten();
+    for r in outcome
+        .results
+        .iter_mut()
+        .chain(queries.flat_map(|q| q.results.iter_mut()))
+    {
+        if let Some(shown) = rewrite_path(&r.path, rewrites) {
+            r.original_path = Some(std::mem::replace(&mut r.path, shown));
+        }
+        r.also_at.iter_mut().for_each(rewrite);
+        if let Some(d) = &mut r.duplicates {
+            d.paths.iter_mut().for_each(rewrite);
+        }
+    }
+    for h in outcome.hotspots.iter_mut().flat_map(|h| h.dirs.iter_mut()) {
+        rewrite(&mut h.key);
+    }
+    outcome
+        .suppressed_per_dir
+        .iter_mut()
+        .for_each(|d| rewrite(&mut d.dir));
+    for d in outcome.directories.iter_mut().flatten() {
+        rewrite(&mut d.dir);
+    }
+    for c in outcome.cooccurrence.iter_mut().flatten() {
+        c.paths.iter_mut().for_each(rewrite);
+    }
+    if let Some(c) = &mut outcome.comparison {
+        c.only_first
+            .iter_mut()
+            .chain(c.only_second.iter_mut())
+            .chain(c.common.iter_mut())
+            .for_each(rewrite);
+    }
+    let stats = &mut outcome.stats;
+    stats
+        .pruned_dirs
+        .iter_mut()
+        .for_each(|d| rewrite(&mut d.dir));
+    stats.panics.iter_mut().for_each(|p| rewrite(&mut p.path));
+    for d in stats.diagnostics.iter_mut().flatten() {
+        rewrite(&mut d.path);
+    }
+    for d in stats
+        .truncation
+        .iter_mut()
+        .flat_map(|t| t.top_dirs.iter_mut())
+    {
+        rewrite(&mut d.dir);
+    }
+    if let Some(v) = &mut stats.skip_verification {
+        v.matched_paths.iter_mut().for_each(rewrite);
+    }
+}
+
+fn run_meta(args: &SearchArgs) -> RunMeta {
+    RunMeta {
+        generated_at: rfc3339_utc(SystemTime::now()),
+        hostname: hostname(),
+        cwd: std::env::current_dir()
+            .ok()
+            .map(|d| d.to_string_lossy().to_string()),
+        config: args.config.as_ref().map(|p| {
+            fs::canonicalize(p)
+                .unwrap_or_else(|_| p.clone())
+                .to_string_lossy()
+                .to_string()
+        }),
+        path_rewrites: args
+            .path_rewrite
+            .iter()
+            .map(|(from, to)| format!("{from}={to}"))
+            .collect(),
+        dir_globs: args
+            .dir_globs
+            .iter()
+            .map(|(pattern, roots)| DirGlob {
+                pattern: pattern.clone(),
+                roots: roots
+                    .iter()
+                    .map(|r| r.to_string_lossy().into_owned())
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+fn relative_age(t: SystemTime, now: SystemTime) -> String {
+    let Ok(age) = now.duration_since(t) else {
+        return "in the future (clock skew?)".to_string();
+    };
+    let secs = age.as_secs();
+    let (n, unit) = match secs {
+        0..=59 => return "just now".to_string(),
+        60..=3_599 => (secs / 60, "minute"),
+        3_600..=86_399 => (secs / 3_600, "hour"),
+        86_400..=2_591_999 => (secs / 86_400, "day"),
+        2_592_000..=31_535_999 => (secs / 2_592_000, "month"),
+        _ => (secs / 31_536_000, "year"),
+    };
+    format!("{n} {unit}{} ago", if n == 1 { "" } else { "s" })
+}
+
+fn parse_changed_since(spec: &str, now: SystemTime) -> Result<SystemTime, String> {
+    static DURATION: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d+)\s*([smhdw])$").unwrap());
+    static TIMESTAMP: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"^(\d{4})-(\d{2})-(\d{2})(?:[T ](\d{2}):(\d{2})(?::(\d{2})(?:\.\d+)?)?)?\s*(Z|[+-]\d{2}:?\d{2})?$",
+        )
+        .unwrap()
+    });
+    let spec = spec.trim();
+    let invalid = || {
+        format!(
+            "invalid --changed-since `{spec}`: expected a date, an RFC 3339 timestamp, a \
+             duration like 24h or 7d, or last-run"
+        )
+    };
+
+    if let Some(c) = DURATION.captures(spec) {
+        let n: u64 = c[1].parse().map_err(|_| invalid())?;
+        let unit = match &c[2] {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3_600,
+            "d" => 86_400,
+            _ => 604_800,
+        };
+        return n
+            .checked_mul(unit)
+            .and_then(|secs| now.checked_sub(Duration::from_secs(secs)))
+            .ok_or_else(invalid);
+    }
+
+    let c = TIMESTAMP.captures(spec).ok_or_else(invalid)?;
+    let num = |i: usize| c.get(i).map_or(Ok(0), |m| m.as_str().parse::<i64>());
+    let (year, month, day) = (num(1), num(2), num(3));
+    let (hour, minute, second) = (num(4), num(5), num(6));
+    let (Ok(year), Ok(month), Ok(day), Ok(hour), Ok(minute), Ok(second)) =
+        (year, month, day, hour, minute, second)
+    else {
+        return Err(invalid());
+    };
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return Err(invalid());
+    }
+    let offset = match c.get(7).map(|m| m.as_str().replace(':', "")) {
+        None => 0,
+        Some(z) if z == "Z" => 0,
+        Some(z) => {
+            let sign = if z.starts_with('-') { -1 } else { 1 };
+            let hh: i64 = z[1..3].parse().map_err(|_| invalid())?;
+            let mm: i64 = z[3..5].parse().map_err(|_| invalid())?;
+            sign * (hh * 3_600 + mm * 60)
+        }
+    };
+
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let secs = days * 86_400 + hour * 3_600 + minute * 60 + second - offset;
+    u64::try_from(secs)
+        .map(|s| UNIX_EPOCH + Duration::from_secs(s))
+        .map_err(|_| invalid())
+}
+
+fn last_run_key(args: &SearchArgs) -> String {
+    let mut dirs: Vec<String> = args
+        .dir
+        .iter()
+        .map(|d| {
+            std::path::absolute(d)
+                .unwrap_or_else(|_| d.clone())
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    dirs.sort();
+    let query = format!(
+        "{:?}",
+        (
+            &args.preset,
+            &args.name,
+            &args.content,
+            &args.content_file,
+            &args.rule,
+            &args.ext,
+            &args.kind,
+            &args.lang,
+            args.fixed_strings,
+            args.binary,
+        )
+    );
+    format!("{}\nquery {}", dirs.join("\n"), fnv1a_hex(&query))
+}
+
+fn last_run_file() -> Option<PathBuf> {
+    Some(
+        dirs::cache_dir()?
+            .join("rustfilefinder")
+            .join("last-runs.json"),
+    )
+}
+
+fn load_last_run(key: &str) -> Option<SystemTime> {
+    let data = fs::read(last_run_file()?).ok()?;
+    let runs: BTreeMap<String, u64> = serde_json::from_slice(&data).ok()?;
+    runs.get(key).map(|&s| UNIX_EPOCH + Duration::from_secs(s))
+}
+
+fn record_last_run(key: &str, started: SystemTime) {
+    let Some(path) = last_run_file() else { return };
+    let mut runs: BTreeMap<String, u64> = fs::read(&path)
+        .ok()
+        .and_then(|d| serde_json::from_slice(&d).ok())
+        .unwrap_or_default();
+    runs.insert(
+        key.to_string(),
+        started
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+    );
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&tmp, serde_json::to_vec(&runs)?))
+        .and_then(|_| fs::rename(&tmp, &path));
+    if let Err(e) = written {
+        eprintln!("Warning: cannot record this run in {}: {e}", path.display());
+    }
+}
+
+#[cfg(unix)]
+fn changed_at(meta: &fs::Metadata) -> SystemTime {
+    use std::os::unix::fs::MetadataExt;
+    let ctime = u64::try_from(meta.ctime())
+        .map(|s| UNIX_EPOCH + Duration::new(s, meta.ctime_nsec() as u32))
+        .unwrap_or(UNIX_EPOCH);
+    meta.modified().map_or(ctime, |m| m.max(ctime))
+}
+
+#[cfg(not(unix))]
+fn changed_at(meta: &fs::Metadata) -> SystemTime {
+    meta.modified().unwrap_or(UNIX_EPOCH)
+}
+
+fn mtime_ns(meta: &fs::Metadata) -> Option<u64> {
+    let since = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(since.as_nanos()).ok()
+}
+
+fn rfc3339_utc(t: SystemTime) -> String {
+    let secs = t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}+00:00",
+        rem / 3_600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+fn effective_pattern(rule: &ContentRule, fixed: bool, args: &SearchArgs) -> EffectivePattern {
+    let (engine, patterns) = match &rule.matcher {
+        Matcher::Regex(list) => (
+            "regex",
+            list.iter().map(|(r, _)| r.as_str().to_string()).collect(),
+        ),
+        Matcher::Literal(_, lit) => ("literal", vec![lit.clone()]),
+        Matcher::MultiLiteral(_, list) => ("multi-literal", list.clone()),
+        Matcher::Bytes(list) => (
+            "bytes-regex",
+            list.iter().map(|(r, _)| r.as_str().to_string()).collect(),
+        ),
+    };
+    let mut flags = Vec::new();
+    if fixed {
+        flags.push("fixed-strings".to_string());
+    }
+    if let (Some(limit), "regex" | "bytes-regex") = (args.regex_size_limit, engine) {
+        flags.push(format!("size-limit={limit}"));
+    }
+    if args.scope != "all" {
+        flags.push(format!("scope={}", args.scope));
+    }
+    if args.unicode_word_boundaries && engine == "bytes-regex" {
+        flags.push("unicode-word-boundaries".to_string());
+    }
+    if !args.require_script.is_empty() {
+        flags.push(format!("script={}", args.require_script.join(",")));
+    }
+    EffectivePattern {
+        rule: rule.label.clone(),
+        engine,
+        patterns,
+        flags,
+    }
+}
+
+fn explain_pattern(
+    source: &str,
+    original: &[String],
+    fixed: bool,
+    effective: &EffectivePattern,
+) -> String {
+    let name = effective
+        .rule
+        .as_deref()
+        .map_or_else(|| source.to_string(), |r| format!("{source} `{r}`"));
+    let mut text = format!("[explain] {name}\n");
+    for p in original {
+        text.push_str(&format!("  original:   `{p}`\n"));
+        if fixed {
+            text.push_str(&format!(
+                "  escaped:    `{}` (--fixed-strings)\n",
+                regex::escape(p)
+            ));
+        }
+    }
+    let engine = match effective.engine {
+        "literal" if !fixed => "literal (no regex metacharacters)",
+        "multi-literal" if !fixed => "multi-literal (no regex metacharacters)",
+        e => e,
+    };
+    text.push_str(&format!("  engine:     {engine}\n"));
+    for p in &effective.patterns {
+        text.push_str(&format!("  compiled:   `{p}`\n"));
+    }
+    if !effective.flags.is_empty() {
+        text.push_str(&format!("  flags:      {}\n", effective.flags.join(", ")));
+    }
+    text
+}
+
+fn run_show(args: &ShowArgs) {
+    let fail = |e: String| -> ! {
+        eprintln!("Error: {e}");
+        std::process::exit(2);
+    };
+    let data = compress::read(&args.report)
+        .unwrap_or_else(|e| fail(format!("cannot read report {}: {e}", args.report.display())));
+    let report: serde_json::Value = serde_json::from_slice(&data).unwrap_or_else(|e| {
+        fail(format!(
+            "report {} is not valid JSON: {e}",
+            args.report.display()
+        ))
+    });
+    let results: Vec<MatchResult> = report
+        .get("results")
+        .cloned()
+        .and_then(|r| serde_json::from_value(r).ok())
+        .unwrap_or_else(|| {
+            fail(format!(
+                "report {} has no readable `results` list; pass a report written with --format json",
+                args.report.display()
+            ))
+        });
+    let generated_at = report
+        .pointer("/meta/generated_at")
+        .and_then(serde_json::Value::as_str)
+        .and_then(|t| parse_changed_since(t, SystemTime::now()).ok());
+    let cwd = report
+        .pointer("/meta/cwd")
+        .and_then(serde_json::Value::as_str)
+        .map(PathBuf::from);
+
+    let picked: Vec<&MatchResult> = match (args.index, &args.path) {
+        (Some(i), _) => match i.checked_sub(1).and_then(|i| results.get(i)) {
+            Some(r) => vec![r],
+            None => fail(format!(
+                "no result #{i}: the report has {} results, numbered from 1",
+                results.len()
+            )),
+        },
+        (None, Some(p)) => {
+            let wanted = p.trim_start_matches("./");
+            let root = cwd.clone().unwrap_or_else(|| PathBuf::from("."));
+            let fold_case = casefold::resolve(&args.case_insensitive_paths, &[root]);
+            let found: Vec<&MatchResult> = results
+                .iter()
+                .filter(|r| casefold::same(r.path.trim_start_matches("./"), wanted, fold_case))
+                .collect();
+            if found.is_empty() {
+                fail(format!("no result for `{p}` in the report"));
+            }
+            found
+        }
+        (None, None) => fail("give a result number or --path".to_string()),
+    };
+
+    let stdout = io::stdout();
+    let color = stdout.is_terminal();
+    let mut out = BufWriter::new(stdout.lock());
+    for (i, r) in picked.iter().enumerate() {
+        if i > 0 {
+            finish_output(writeln!(out));
+        }
+        let written = show_result(&mut out, r, args, cwd.as_deref(), generated_at, color);
+        finish_output(written);
+    }
+    finish_output(out.flush());
+}
+
+fn run_merge(args: &MergeArgs) {
+    let fail = |e: String| -> ! {
+        eprintln!("Error: {e}");
+        std::process::exit(2);
+    };
+    let shards: Vec<merge::Shard> = args
+        .reports
+        .iter()
+        .map(|p| merge::load(p).unwrap_or_else(|e| fail(e)))
+        .collect();
+    merge::check_versions(&shards, JSON_SCHEMA_VERSION).unwrap_or_else(|e| fail(e));
+
+    let stats = merge::merge_stats(&shards);
+    let duplicate_paths = merge::duplicate_paths(&shards);
+    let mut sources = Vec::with_capacity(shards.len());
+    let mut tagged = Vec::new();
+    for shard in shards {
+        let report = shard.source.report.clone();
+        sources.push(shard.source);
+        tagged.extend(shard.results.into_iter().map(|r| (report.clone(), r)));
+    }
+    merge::flag_duplicates(&mut tagged, &duplicate_paths);
+    let mut results: Vec<MatchResult> = tagged.into_iter().map(|(_, r)| r).collect();
+    sort_results(&mut results, &args.sort, args.reverse, false);
+
+    let report = merge::MergedReport {
+        schema_version: JSON_SCHEMA_VERSION,
+        stats,
+        results,
+        meta: merge::MergeMeta {
+            generated_at: rfc3339_utc(SystemTime::now()),
+            hostname: hostname(),
+            sources,
+        },
+        duplicate_paths,
+    };
+
+    let stdout = io::stdout();
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(p) => Box::new(BufWriter::new(fs::File::create(p).unwrap_or_else(|e| {
+            fail(format!("cannot create output file {}: {e}", p.display()))
+        }))),
+        None => Box::new(BufWriter::new(stdout.lock())),
+    };
+    let written = match args.format.as_str() {
+        "json" => serde_json::to_writer_pretty(&mut out, &report)
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(out)),
+        "jsonl" => report.results.iter().try_for_each(|r| {
+            serde_json::to_writer(&mut out, r)?;
+            writeln!(out)
+        }),
+        "paths" => {
+            let mut seen = HashSet::new();
+            report
+                .results
+                .iter()
+                .filter(|r| seen.insert(r.path.as_str()))
+                .try_for_each(|r| writeln!(out, "{}", r.path))
+        }
+        _ => print_merged_markdown(&mut out, &report),
+    };
+    finish_output(written.and_then(|_| out.flush()));
+}
+
+fn print_merged_markdown(out: &mut impl Write, report: &merge::MergedReport) -> io::Result<()> {
+    writeln!(
+        out,
+        "# RustFileFinder merged results
+"
+    )?;
+    for s in &report.meta.sources {
+        let host = s
+            .hostname
+            .as_deref()
+            .map(|h| format!(" on `{h}`"))
+            .unwrap_or_default();
+        let at = s
+            .generated_at
+            .as_deref()
+            .map(|t| format!(", {t}"))
+            .unwrap_or_default();
+        writeln!(
+            out,
+            "- Report: `{}`{host}{at} ({} results)",
+            s.report, s.results
+        )?;
+    }
+    let stat = |key: &str| {
+        report
+            .stats
+            .get(key)
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or_default()
+    };
+    writeln!(out)?;
+    writeln!(out, "## Run statistics")?;
+    writeln!(out, "- Files discovered: **{}**", stat("files_discovered"))?;
+    writeln!(
+        out,
+        "- Files scanned: **{}** text, **{}** pdf",
+        stat("files_scanned_text"),
+        stat("files_scanned_pdf")
+    )?;
+    writeln!(out, "- Matches: **{}**", stat("matches_total"))?;
+    writeln!(
+        out,
+        "- Elapsed: **{} ms** longest shard, **{} ms** total",
+        stat("elapsed_ms"),
+        stat("elapsed_ms_total")
+    )?;
+    if !report.duplicate_paths.is_empty() {
+        writeln!(
+            out,
+            "- **Warning: {} paths appear in more than one report**",
+            report.duplicate_paths.len()
+        )?;
+        for d in &report.duplicate_paths {
+            writeln!(out, "  - `{}`: `{}`", d.path, d.reports.join("`, `"))?;
+        }
+    }
+    writeln!(out)?;
+
+    writeln!(out, "## Matches\n")?;
+    let layout = MarkdownLayout {
+        color: false,
+        width: None,
+        full_paths: true,
+        show_meta: None,
+        context_lines: None,
+    };
+    for r in &report.results {
+        print_markdown_result(out, r, &layout)?;
+    }
+    Ok(())
+}
+
+fn show_result(
+    out: &mut impl Write,
+    r: &MatchResult,
+    args: &ShowArgs,
+    cwd: Option<&Path>,
+    generated_at: Option<SystemTime>,
+    color: bool,
+) -> io::Result<()> {
+    let mut file = PathBuf::from(r.original_path.as_deref().unwrap_or(&r.path));
+    if let Some(alt) = cwd
+        .map(|c| c.join(&file))
+        .filter(|alt| !file.exists() && alt.exists())
+    {
+        file = alt;
+    }
+    let label = r
+        .rule
+        .as_deref()
+        .map(|l| format!(" (rule `{l}`)"))
+        .unwrap_or_default();
+    let stored = |out: &mut dyn Write, note: &str| -> io::Result<()> {
+        eprintln!("Warning: {}: {note}; showing the stored snippet", r.path);
+        writeln!(out, "{}{label}", r.path)?;
+        writeln!(out, "{}", r.snippet.as_deref().unwrap_or(""))
+    };
+
+    let meta = match fs::metadata(io_path(&file)) {
+        Ok(m) => m,
+        Err(e) => return stored(out, &format!("cannot read the file ({e})")),
+    };
+    let changed = match (r.file_size, r.file_mtime_ns) {
+        (Some(size), mtime) => meta.len() != size || mtime_ns(&meta) != mtime,
+        (None, _) => generated_at.is_some_and(|g| {
+            meta.modified()
+                .is_ok_and(|m| m > g + Duration::from_secs(1))
+        }),
+    };
+    if changed {
+        eprintln!(
+            "Warning: {} changed since the report was generated; the offsets may be stale",
+            r.path
+        );
+    }
+    let Some(o) = r.offsets.as_ref() else {
+        return stored(out, "the result has no match offsets");
+    };
+    if o.offsets_are_extracted_text {
+        return stored(out, "the offsets refer to extracted or transcoded text");
+    }
+    let bytes = fs::read(io_path(&file))?;
+    if o.match_end > bytes.len() || o.match_start > o.match_end {
+        return stored(out, "the offsets are past the end of the file");
+    }
+
+    if r.snippet_kind == SnippetKind::Hex {
+        writeln!(
+            out,
+            "{}{label} (bytes {}..{})",
+            r.path, o.match_start, o.match_end
+        )?;
+        let first = (o.match_start / HEX_ROW_BYTES).saturating_sub(args.before) * HEX_ROW_BYTES;
+        let last =
+            ((o.match_end / HEX_ROW_BYTES + args.after + 1) * HEX_ROW_BYTES).min(bytes.len());
+        for (i, chunk) in bytes[first..last].chunks(HEX_ROW_BYTES).enumerate() {
+            let at = first + i * HEX_ROW_BYTES;
+            let (_, row) = hex_dump_around(chunk, 0, 0);
+            let row = row.lines().next().unwrap_or("");
+            let hit = at < o.match_end.max(o.match_start + 1) && o.match_start < at + HEX_ROW_BYTES;
+            writeln!(out, "{} {at:08x}{}", if hit { '>' } else { ' ' }, &row[8..])?;
+        }
+        return Ok(());
+    }
+
+    let line_starts = line_starts(&bytes);
+    let line_of = |offset: usize| line_starts.partition_point(|&s| s <= offset) - 1;
+    let (first_hit, last_hit) = (
+        line_of(o.match_start),
+        line_of(o.match_end.saturating_sub(1).max(o.match_start)),
+    );
+    let first = first_hit.saturating_sub(args.before);
+    let last = (last_hit + args.after).min(line_starts.len() - 1);
+    let width = (last + 1).to_string().len();
+
+    let column = String::from_utf8_lossy(&bytes[line_starts[first_hit]..o.match_start])
+        .chars()
+        .count()
+        + 1;
+    writeln!(out, "{}:{}:{column}{label}", r.path, first_hit + 1)?;
+    for line in first..=last {
+        let start = line_starts[line];
+        let end = line_starts.get(line + 1).map_or(bytes.len(), |&e| e);
+        let raw = &bytes[start..end];
+        let raw = raw.strip_suffix(b"\n").unwrap_or(raw);
+        let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+        let hit = (first_hit..=last_hit).contains(&line);
+        let text = if hit && color {
+            let from = o.match_start.clamp(start, start + raw.len()) - start;
+            let to = o.match_end.clamp(start, start + raw.len()) - start;
+            format!(
+                "{}\x1b[1;31m{}\x1b[0m{}",
+                String::from_utf8_lossy(&raw[..from]),
+                String::from_utf8_lossy(&raw[from..to]),
+                String::from_utf8_lossy(&raw[to..])
+            )
+        } else {
+            String::from_utf8_lossy(raw).into_owned()
+        };
+        writeln!(
+            out,
+            "{} {:>width$} | {text}",
+            if hit { '>' } else { ' ' },
+            line + 1
+        )?;
+    }
+    Ok(())
+}
+
+fn line_starts(bytes: &[u8]) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(
+            memchr::memchr2_iter(b'\n', b'\r', bytes)
+                .filter(|&i| bytes[i] == b'\n' || bytes.get(i + 1) != Some(&b'\n'))
+                .map(|i| i + 1),
+        )
+        .collect()
+}
+
+fn context_block(r: &MatchResult, lines: usize) -> Option<(usize, Vec<String>)> {
+    let o = r
+        .offsets
+        .as_ref()
+        .filter(|o| !o.offsets_are_extracted_text && r.snippet_kind == SnippetKind::Text)?;
+    let file = r.original_path.as_deref().unwrap_or(&r.path);
+    let bytes = fs::read(io_path(Path::new(file))).ok()?;
+    if o.match_end > bytes.len() || o.match_start > o.match_end {
+        return None;
+    }
+    let mut starts = line_starts(&bytes);
+    if starts.len() > 1 && starts.last() == Some(&bytes.len()) {
+        starts.pop();
+    }
+    let line_of = |offset: usize| starts.partition_point(|&s| s <= offset) - 1;
+    let first = line_of(o.match_start).saturating_sub(lines);
+    let last =
+        (line_of(o.match_end.saturating_sub(1).max(o.match_start)) + lines).min(starts.len() - 1);
+    let text = (first..=last)
+        .map(|line| {
+            let end = starts.get(line + 1).map_or(bytes.len(), |&e| e);
+            let raw = &bytes[starts[line]..end];
+            let raw = raw.strip_suffix(b"\n").unwrap_or(raw);
+            let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+            String::from_utf8_lossy(raw).into_owned()
+        })
+        .collect();
+    Some((first + 1, text))
+}
+
+fn run_index(dir: &Path, previous: Index, verbose: bool) {
+    let started = Instant::now();
+    let mut files = Vec::new();
+    for root in &previous.roots {
+        let walker = WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|e| !(e.file_type().is_dir() && is_ignored_dir(e.path(), &[])));
+        for entry in walker {
+            match entry {
+                Ok(e) if e.file_type().is_file() => files.push(e.into_path()),
+                Ok(_) => {}
+                Err(e) if verbose => eprintln!("[index] {e}"),
+                Err(_) => {}
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+
+    let old: HashMap<&Path, &IndexEntry> = previous
+        .entries
+        .iter()
+        .map(|e| (e.path.as_path(), e))
+        .collect();
+    let reused = AtomicUsize::new(0);
+    let built: Vec<(IndexEntry, Option<Cow<'_, [u8]>>)> = files
+        .par_iter()
+        .filter_map(|path| {
+            let meta = fs::metadata(io_path(path)).ok()?;
+            if let Some(entry) = old.get(path.as_path()).filter(|e| e.fresh(&meta)) {
+                reused.fetch_add(1, Ordering::Relaxed);
+                return Some((
+                    (*entry).clone(),
+                    previous.postings(entry).map(Cow::Borrowed),
+                ));
+            }
+            let (kind, text) = index_text(path, &meta, &previous, verbose);
+            let postings = text.map(|t| {
+                Cow::Owned(index::encode_trigrams(&[
+                    &t,
+                    &normalize_whitespace(&t).text,
+                ]))
+            });
+            let entry = IndexEntry {
+                path: path.clone(),
+                size: meta.len(),
+                modified: index::modified_ns(&meta),
+                kind: kind.to_string(),
+                trigrams: None,
+            };
+            Some((entry, postings))
+        })
+        .collect();
+
+    let mut next = Index {
+        version: index::INDEX_VERSION,
+        roots: previous.roots.clone(),
+        include_pdf: previous.include_pdf,
+        max_bytes: previous.max_bytes,
+        built_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        entries: Vec::with_capacity(built.len()),
+        trigrams: Vec::new(),
+    };
+    let mut with_text = 0;
+    for (mut entry, postings) in built {
+        if let Some(p) = postings {
+            entry.trigrams = Some((next.trigrams.len() as u64, p.len() as u64));
+            next.trigrams.extend_from_slice(&p);
+            with_text += 1;
+        }
+        next.entries.push(entry);
+    }
+    let removed = previous
+        .entries
+        .iter()
+        .filter(|e| files.binary_search(&e.path).is_err())
+        .count();
+    let reused = reused.into_inner();
+
+    if let Err(e) = next.save(dir) {
+        eprintln!("Cannot write index {}: {e}", dir.display());
+        std::process::exit(1);
+    }
+    eprintln!(
+        "Index {}: {} files ({with_text} with indexed text), {reused} unchanged, {} scanned, \
+         {removed} removed, {} of trigrams in {}",
+        dir.display(),
+        next.entries.len(),
+        next.entries.len() - reused,
+        memory::format_size(next.trigrams.len() as u64),
+        human_duration(started.elapsed().as_millis())
+    );
+}
+
+fn index_text(
+    path: &Path,
+    meta: &fs::Metadata,
+    settings: &Index,
+    verbose: bool,
+) -> (&'static str, Option<String>) {
+    if is_pdf(path) {
+        if !settings.include_pdf {
+            return ("pdf", None);
+        }
+        let _lock = PDF_EXTRACT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        SILENCE_PANICS.store(true, Ordering::Relaxed);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let _gag_out = Gag::stdout().ok();
+            let _gag_err = gag::Gag::stderr().ok();
+            pdf_document(&io_path(path), 0)
+        }));
+        SILENCE_PANICS.store(false, Ordering::Relaxed);
+        return match result {
+            Ok(Ok((text, _, _))) => ("pdf", Some(text)),
+            _ => {
+                if verbose {
+                    eprintln!("[index] unreadable pdf: {}", path.display());
+                }
+                ("pdf", None)
+            }
+        };
+    }
+    let kind = if is_probably_text(path, false) {
+        "text"
+    } else {
+        "other"
+    };
+    if is_notebook(path) || meta.len() > settings.max_bytes {
+        return (kind, None);
+    }
+    let Ok(buf) = fs::read(io_path(path)) else {
+        return (kind, None);
+    };
+    (kind, decode_text(&buf).map(|(t, _)| t.into_owned()))
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct BenchRow {
+    name: &'static str,
+    elapsed_ms: u128,
+    files_discovered: usize,
+    files_scanned: usize,
+    matches_total: usize,
+    files_per_sec: f64,
+    corpus_mb_per_sec: f64,
+    buffer_allocations: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct BenchReport {
+    corpus_dir: String,
+    corpus_files: usize,
+    corpus_bytes: u64,
+    generate_ms: u128,
+    runs: Vec<BenchRow>,
+}
+
+fn run_bench(bench: BenchArgs) {
+    let root = std::env::temp_dir().join(format!("rustfilefinder-bench-{}", std::process::id()));
+
+    let generated = Instant::now();
+    let corpus_bytes = generate_bench_corpus(&root, &bench).unwrap_or_else(|e| {
+        eprintln!("Failed to generate bench corpus: {e}");
+        std::process::exit(1);
+    });
+    let generate_ms = generated.elapsed().as_millis();
+
+    let literals: Vec<String> = std::iter::once("needle".to_string())
+        .chain((1..5_000).map(|i| format!("term{i}x")))
+        .collect();
+    let literals_file = root.with_extension("patterns");
+    if let Err(e) = fs::write(&literals_file, literals.join("\n")) {
+        eprintln!("Failed to write bench pattern list: {e}");
+        std::process::exit(1);
+    }
+    let literals_arg = literals_file.to_string_lossy().to_string();
+    let alternation = literals.join("|");
+
+    let root_arg = root.to_string_lossy().to_string();
+    let mut cases: Vec<(&'static str, Vec<&str>)> = vec![
+        ("name-only", vec!["--name", "file_1"]),
+        ("literal content", vec!["--content", "needle"]),
+        (
+            "complex regex",
+            vec![
+                "--content",
+                r"(?i)\b(compil\w+|interpret\w+)\s+\w+\s+(rust|python)\b",
+            ],
+        ),
+        (
+            "literal content, ext filter",
+            vec!["--content", "needle", "--ext", "txt"],
+        ),
+        (
+            "multi-literal (5000, aho-corasick)",
+            vec!["--content-file", literals_arg.as_str(), "--fixed-strings"],
+        ),
+        (
+            "multi-literal (5000, alternation regex)",
+            vec!["--content", alternation.as_str()],
+        ),
+    ];
+    if bench.pdf_files > 0 {
+        cases.push((
+            "pdf content",
+            vec!["--content", "needle", "--ext", "pdf", "--include-pdf"],
+        ));
+    }
+
+    let mut runs = Vec::new();
+    for (name, extra) in cases {
+        let mut argv = vec!["search", "--dir", root_arg.as_str()];
+        argv.extend(extra);
+        runs.push(bench_run(name, argv, corpus_bytes));
+    }
+
+    let small = root.with_extension("small");
+    let mut small_bytes = 0;
+    if bench.small_files > 0 {
+        small_bytes = generate_small_corpus(&small, bench.small_files).unwrap_or_else(|e| {
+            eprintln!("Failed to generate small-file bench corpus: {e}");
+            std::process::exit(1);
+        });
+        let small_arg = small.to_string_lossy().to_string();
+        for (name, reuse) in [
+            ("small files, fresh buffers", false),
+            ("small files, reused buffers", true),
+        ] {
+            scratch::set_reuse(reuse);
+            let argv = vec!["search", "--dir", small_arg.as_str(), "--content", "needle"];
+            runs.push(bench_run(name, argv, small_bytes));
+        }
+        scratch::set_reuse(true);
+    }
+
+    let _ = fs::remove_file(&literals_file);
+    if !bench.keep {
+        let _ = fs::remove_dir_all(&root);
+        let _ = fs::remove_dir_all(&small);
+    }
+
+    let report = BenchReport {
+        corpus_dir: root_arg,
+        corpus_files: bench.text_files + bench.binary_files + bench.pdf_files + bench.small_files,
+        corpus_bytes: corpus_bytes + small_bytes,
+        generate_ms,
+        runs,
+    };
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let written = match bench.format.as_str() {
+        "json" => serde_json::to_writer_pretty(&mut out, &report)
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(out)),
+        _ => print_bench_markdown(&mut out, &report),
+    };
+    finish_output(written.and_then(|_| out.flush()));
+}
+
+fn bench_run(name: &'static str, argv: Vec<&str>, corpus_bytes: u64) -> BenchRow {
+    let args = SearchArgs::try_parse_from(argv).expect("bench arguments are valid");
+
+    scratch::take_allocations();
+    let started = Instant::now();
+    let stats = execute_search(&args, started).stats;
+    let secs = started.elapsed().as_secs_f64().max(f64::EPSILON);
+
+    BenchRow {
+        name,
+        elapsed_ms: stats.elapsed_ms,
+        files_discovered: stats.files_discovered,
+        files_scanned: stats.files_scanned_text + stats.files_scanned_pdf,
+        matches_total: stats.matches_total,
+        files_per_sec: stats.files_discovered as f64 / secs,
+        corpus_mb_per_sec: corpus_bytes as f64 / (1024.0 * 1024.0) / secs,
+        buffer_allocations: scratch::take_allocations(),
+    }
+}
+
+fn generate_small_corpus(root: &Path, files: usize) -> io::Result<u64> {
+    let mut rng = 0x5ca1_u64;
+    let mut total = 0u64;
+    for i in 0..files {
+        let dir = root.join(format!("s{:03}", i / 1000));
+        fs::create_dir_all(&dir)?;
+        let mut text = String::with_capacity(SMALL_FILE_BYTES + 16);
+        if i % 10 == 0 {
+            text.push_str("needle ");
+        }
+        while text.len() < SMALL_FILE_BYTES {
+            text.push_str(BENCH_WORDS[next_rand(&mut rng) as usize % BENCH_WORDS.len()]);
+            text.push(' ');
+        }
+        fs::write(dir.join(format!("small_{i}.txt")), &text)?;
+        total += text.len() as u64;
+    }
+    Ok(total)
+}
+
+fn print_bench_markdown(out: &mut impl Write, report: &BenchReport) -> io::Result<()> {
+    writeln!(out, "# RustFileFinder bench\n")?;
+    writeln!(out, "- Corpus: `{}`", report.corpus_dir)?;
+    writeln!(
+        out,
+        "- Corpus files: **{}** ({} bytes)",
+        report.corpus_files, report.corpus_bytes
+    )?;
+    writeln!(out, "- Corpus generation: **{} ms**", report.generate_ms)?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "| search | elapsed ms | discovered | scanned | matches | files/s | corpus MB/s | buffer allocs |"
+    )?;
+    writeln!(out, "|---|---:|---:|---:|---:|---:|---:|---:|")?;
+    for r in &report.runs {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {:.0} | {:.1} | {} |",
+            r.name,
+            r.elapsed_ms,
+            r.files_discovered,
+            r.files_scanned,
+            r.matches_total,
+            r.files_per_sec,
+            r.corpus_mb_per_sec,
+            r.buffer_allocations
+        )?;
+    }
+    Ok(())
+}
+
+const SMALL_FILE_BYTES: usize = 512;
+
+const BENCH_WORDS: &[&str] = &[
+    "il",
+    "compilatore",
+    "interprete",
+    "traduce",
+    "il",
+    "codice",
+    "rust",
+    "python",
+    "semantica",
+    "tipi",
+    "grammatica",
+    "parser",
+    "lessico",
+    "memoria",
+    "funzione",
+    "modulo",
+    "valore",
+];
+
+fn next_rand(state: &mut u64) -> u64 {
+    *state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    *state >> 33
+}
+
+fn generate_bench_corpus(root: &Path, bench: &BenchArgs) -> io::Result<u64> {
+    let mut rng = 0x5eed_u64;
+    let mut total = 0u64;
+
+    for i in 0..bench.text_files {
+        let dir = root.join(format!("d{:03}", i / 100));
+        fs::create_dir_all(&dir)?;
+
+        let mut text = String::with_capacity(bench.text_size + 16);
+        if i % 10 == 0 {
+            text.push_str("needle ");
+        }
+        while text.len() < bench.text_size {
+            let w = BENCH_WORDS[next_rand(&mut rng) as usize % BENCH_WORDS.len()];
+            text.push_str(w);
+            text.push(if next_rand(&mut rng).is_multiple_of(12) {
+                '\n'
+            } else {
+                ' '
+            });
+        }
+        fs::write(dir.join(format!("file_{i}.txt")), &text)?;
+        total += text.len() as u64;
+    }
+
+    for i in 0..bench.binary_files {
+        let dir = root.join(format!("b{:03}", i / 100));
+        fs::create_dir_all(&dir)?;
+
+        let bytes: Vec<u8> = (0..bench.binary_size)
+            .map(|_| next_rand(&mut rng) as u8)
+            .collect();
+        fs::write(dir.join(format!("blob_{i}.bin")), &bytes)?;
+        total += bytes.len() as u64;
+    }
+
+    for i in 0..bench.pdf_files {
+        let dir = root.join(format!("p{:03}", i / 100));
+        fs::create_dir_all(&dir)?;
+
+        let text = if i % 10 == 0 {
+            "needle in a pdf"
+        } else {
+            "il compilatore rust"
+        };
+        let bytes = minimal_pdf(text);
+        fs::write(dir.join(format!("doc_{i}.pdf")), &bytes)?;
+        total += bytes.len() as u64;
+    }
+
+    Ok(total)
+}
+
+fn minimal_pdf(text: &str) -> Vec<u8> {
+    let stream = format!("BT /F1 12 Tf 72 720 Td ({text}) Tj ET");
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Contents 4 0 R \
+         /Resources << /Font << /F1 5 0 R >> >> >>"
+            .to_string(),
+        format!(
+            "<< /Length {} >>\nstream\n{stream}\nendstream",
+            stream.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+
+    let mut out = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, obj) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n{obj}\nendobj\n", i + 1).as_bytes());
+    }
+
+    let xref = out.len();
+    out.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for o in offsets {
+        out.extend_from_slice(format!("{o:010} 00000 n \n").as_bytes());
+    }
+    out.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+    out
+}
+
+fn prometheus_label(v: &str) -> String {
+    v.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn render_metrics(args: &SearchArgs, stats: &RunStats) -> String {
+    let preset = prometheus_label(args.preset.as_deref().unwrap_or(""));
+    let dirs: Vec<String> = args
+        .dir
+        .iter()
+        .map(|d| d.to_string_lossy().to_string())
+        .collect();
+    let dir = prometheus_label(&dirs.join(","));
+
+    let mut text = String::new();
+    let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(stats) else {
+        return text;
+    };
+    for (field, value) in fields {
+        let number = match value {
+            serde_json::Value::Number(n) => n.to_string(),
+            serde_json::Value::Bool(b) => u8::from(b).to_string(),
+            _ => continue,
+        };
+        let name = format!("rustfilefinder_{field}");
+        text.push_str(&format!("# TYPE {name} gauge\n"));
+        text.push_str(&format!(
+            "{name}{{preset=\"{preset}\",dir=\"{dir}\"}} {number}\n"
+        ));
+    }
+
+    text.push_str("# TYPE rustfilefinder_files_skipped gauge\n");
+    for (reason, n) in &stats.skips {
+        text.push_str(&format!(
+            "rustfilefinder_files_skipped{{preset=\"{preset}\",dir=\"{dir}\",reason=\"{reason}\"}} {n}\n"
+        ));
+    }
+    text
+}
+
+fn search_fingerprint(args: &SearchArgs) -> String {
+    let mut resolved = args.clone();
+    resolved.config = None;
+    resolved.checkpoint = None;
+    resolved.checkpoint_every = 0;
+    resolved.format = None;
+    resolved.pipe_format.clear();
+    resolved.output = None;
+    resolved.output_dir = None;
+    resolved.paginate = None;
+    resolved.color.clear();
+    resolved.width = None;
+    resolved.full_paths = false;
+    resolved.quiet = false;
+    resolved.summary_line = None;
+    resolved.metrics_out = None;
+    resolved.verbose = false;
+    resolved.explain = false;
+    resolved.throttle = None;
+    resolved.nice_io = false;
+    resolved.no_meta = false;
+    fnv1a_hex(&format!("{resolved:?}"))
+}
+
+const NO_FILE_TIMEOUT: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+
+fn file_timeout(secs: u64) -> Duration {
+    if secs == 0 {
+        NO_FILE_TIMEOUT
+    } else {
+        Duration::from_secs(secs)
+    }
+}
+
+fn parse_throttle(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(v) if v.is_finite() && v > 0.0 => Ok(v),
+        _ => Err(format!(
+            "invalid rate `{s}` (expected MB/s, e.g. 20 or 2.5)"
+        )),
+    }
+}
+
+fn write_metrics(path: &Path, args: &SearchArgs, stats: &RunStats) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    fs::write(&tmp, render_metrics(args, stats))?;
+    fs::rename(&tmp, path)
+}
+
+fn report_compressed(args: &SearchArgs, path: &Path, size: u64) {
+    if args.compress.is_none() {
+        return;
+    }
+    let on_disk = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    eprintln!(
+        "Wrote {} ({} compressed, {} uncompressed)",
+        path.display(),
+        memory::format_size(on_disk),
+        memory::format_size(size)
+    );
+}
+
+fn finish_output(res: io::Result<()>) {
+    let Err(e) = res else { return };
+    if e.kind() == io::ErrorKind::BrokenPipe {
+        std::process::exit(0);
+    }
+    eprintln!("Error writing output: {e}");
+    std::process::exit(1);
+}
+
+fn print_presets(out: &mut impl Write, cfg: Option<AppConfig>) -> io::Result<()> {
+    let Some(cfg) = cfg else { return Ok(()) };
+    let host = hostname().and_then(|h| cfg.hosts.as_ref()?.get(&h).cloned());
+    let mut names: Vec<&String> = cfg.presets.iter().flat_map(|p| p.keys()).collect();
+    names.extend(
+        host.iter()
+            .flat_map(|h| h.presets.iter().flat_map(|p| p.keys())),
+    );
+    names.sort();
+    names.dedup();
+    for k in names {
+        writeln!(out, "{k}")?;
+    }
+    Ok(())
+}
+
+fn print_languages(out: &mut impl Write) -> io::Result<()> {
+    for l in language::LANGUAGES {
+        writeln!(out, "{:<10} kind: {}", l.name, l.kind)?;
+        writeln!(out, "{:<10} ext: {}", "", l.extensions.join(","))?;
+        if !l.ignore_dirs.is_empty() {
+            writeln!(out, "{:<10} ignore: {}", "", l.ignore_dirs.join(","))?;
+        }
+    }
+    for (name, members) in language::GROUPS {
+        writeln!(out, "{name:<10} group: {}", members.join(","))?;
+    }
+    let detected: Vec<&str> = language::detected().collect();
+    writeln!(out, "\n--lang: {}", detected.join(", "))?;
+    Ok(())
+}
+
+fn summary_line(stats: &RunStats) -> String {
+    let files = stats.files_discovered - stats.files_deduplicated - stats.files_deduped_hardlinks;
+    let skipped: usize = stats.skips.values().sum();
+    let mut line = format!(
+        "rff: {} {} in {} {}",
+        thousands(stats.matches_total),
+        if stats.matches_total == 1 {
+            "match"
+        } else {
+            "matches"
+        },
+        thousands(files),
+        if files == 1 { "file" } else { "files" }
+    );
+    if skipped > 0 {
+        line.push_str(&format!(" ({} skipped)", thousands(skipped)));
+    }
+    if stats.results_overflowed || stats.discovery_truncated || stats.matches_truncated > 0 {
+        line.push_str(" (truncated)");
+    }
+    line.push_str(&format!(" [{}]", human_duration(stats.elapsed_ms)));
+    line
+}
+
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn human_duration(ms: u128) -> String {
+    match ms {
+        0..=999 => format!("{ms}ms"),
+        1_000..=59_999 => format!("{:.1}s", ms as f64 / 1000.0),
+        _ => format!("{}m {:02}s", ms / 60_000, (ms % 60_000) / 1000),
+    }
+}
+
+const JSON_SCHEMA_VERSION: u32 = 1;
+
+fn print_schema(out: &mut impl Write) -> io::Result<()> {
+    let mut schema = schemars::schema_for!(SearchOutcome);
+    schema.schema.metadata().title = Some(format!(
+        "RustFileFinder --format json output (schema_version {JSON_SCHEMA_VERSION})"
+    ));
+    serde_json::to_writer_pretty(&mut *out, &schema)?;
+    writeln!(out)
+}
+
+fn print_json(out: &mut impl Write, outcome: &SearchOutcome, fields: &[String]) -> io::Result<()> {
+    if fields.is_empty() {
+        serde_json::to_writer_pretty(&mut *out, outcome)?;
+        return writeln!(out);
+    }
+    let mut value = serde_json::to_value(outcome)?;
+    value["results"] = outcome
+        .results
+        .iter()
+        .map(|result| serde_json::to_value(Selected { result, fields }))
+        .collect::<Result<Vec<_>, _>>()?
+        .into();
+    serde_json::to_writer_pretty(&mut *out, &value)?;
+    writeln!(out)
+}
+
+struct Selected<'a> {
+    result: &'a MatchResult,
+    fields: &'a [String],
+}
+
+impl Serialize for Selected<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeMap};
+        let value = serde_json::to_value(self.result).map_err(S::Error::custom)?;
+        let mut map = serializer.serialize_map(None)?;
+        for field in self.fields {
+            if let Some(v) = value.get(field) {
+                map.serialize_entry(field, v)?;
+            }
+        }
+        map.end()
+    }
+}
+
+fn result_fields() -> &'static [String] {
+    static FIELDS: Lazy<Vec<String>> = Lazy::new(|| {
+        let schema = schemars::schema_for!(MatchResult);
+        schema
+            .schema
+            .object
+            .map(|o| o.properties.into_keys().collect())
+            .unwrap_or_default()
+    });
+    &FIELDS
+}
+
+fn result_fingerprint(r: &MatchResult, dirs: &[PathBuf]) -> String {
+    let path = Path::new(&r.path);
+    let rel = dirs
+        .iter()
+        .find_map(|d| path.strip_prefix(d).ok())
+        .unwrap_or(path);
+    let rel: Vec<Cow<str>> = rel
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+
+    let mut rules: Vec<&str> = r.rules.iter().map(String::as_str).collect();
+    rules.extend(r.rule.as_deref().filter(|_| r.rules.is_empty()));
+    if rules.is_empty() {
+        rules.extend(r.matched_patterns.iter().map(String::as_str));
+    }
+    rules.sort_unstable();
+
+    let snippet = r.snippet.as_deref().unwrap_or_default();
+    let snippet = match r
+        .offsets
+        .as_ref()
+        .filter(|_| r.snippet_kind == SnippetKind::Text)
+    {
+        Some(o) => {
+            let start =
+                clamp_to_char_boundary(snippet, o.match_start.saturating_sub(o.snippet_start));
+            let end = clamp_to_char_boundary(snippet, o.match_end.saturating_sub(o.snippet_start));
+            &snippet[start..end.max(start)]
+        }
+        None => snippet,
+    };
+    fnv1a_hex(&format!(
+        "{}\x1f{}\x1f{}",
+        rel.join("/"),
+        rules.join("\x1e"),
+        collapse_whitespace(snippet)
+    ))
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn copy_matches(dir: &Path, results: &mut [MatchResult], args: &SearchArgs) -> CopySummary {
+    let mut sources: Vec<String> = results
+        .iter()
+        .map(|r| r.path.clone())
+        .filter(|p| p != STDIN_PATH)
+        .collect();
+    sources.sort();
+    sources.dedup();
+    let bytes: u64 = sources
+        .iter()
+        .filter_map(|p| fs::metadata(io_path(Path::new(p))).ok())
+        .map(|m| m.len())
+        .sum();
+    let mut summary = CopySummary {
+        dir: dir.display().to_string(),
+        files: 0,
+        bytes: 0,
+        failed: 0,
+        error: None,
+    };
+    let limit = args.copy_limit.unwrap_or(staging::DEFAULT_LIMIT);
+    if !args.yes && (sources.len() > limit.files || bytes > limit.bytes) {
+        let error = format!(
+            "--copy-matches would copy {} files ({}), over the --copy-limit of {limit}; \
+             pass --yes to copy anyway",
+            sources.len(),
+            memory::format_size(bytes)
+        );
+        eprintln!("Error: {error}; nothing was copied");
+        summary.error = Some(error);
+        return summary;
+    }
+
+    let mut planner = staging::Planner::new(&args.dir);
+    let mut copied: HashMap<String, Result<String, String>> = HashMap::new();
+    for source in sources {
+        let to = dir.join(planner.destination(Path::new(&source)));
+        let outcome = match staging::copy(&io_path(Path::new(&source)), &io_path(&to)) {
+            Ok(n) => {
+                summary.files += 1;
+                summary.bytes += n;
+                Ok(to.display().to_string())
+            }
+            Err(e) => {
+                summary.failed += 1;
+                eprintln!("Warning: cannot copy {source} to {}: {e}", to.display());
+                Err(format!("not copied to {}: {e}", to.display()))
+            }
+        };
+        copied.insert(source, outcome);
+    }
+    for r in results {
+        match copied.get(&r.path) {
+            Some(Ok(to)) => r.copied_to = Some(to.clone()),
+            Some(Err(e)) => r.warnings.push(e.clone()),
+            None => {}
+        }
+    }
+    summary
+}
+
+fn collapse_duplicates(results: &mut Vec<MatchResult>) -> usize {
+    let before = results.len();
+    let mut groups: HashMap<String, usize> = HashMap::new();
+    let mut kept: Vec<MatchResult> = Vec::with_capacity(before);
+    for r in results.drain(..) {
+        let Some(key) = r.snippet.as_deref().map(collapse_whitespace) else {
+            kept.push(r);
+            continue;
+        };
+        let Some(&i) = groups.get(&key) else {
+            groups.insert(key, kept.len());
+            kept.push(r);
+            continue;
+        };
+        let rep = &mut kept[i];
+        let group = rep.duplicates.get_or_insert_with(|| DuplicateGroup {
+            count: 1,
+            paths: vec![rep.path.clone()],
+        });
+        group.count += 1;
+        if !group.paths.contains(&r.path) {
+            group.paths.push(r.path);
+        }
+    }
+    *results = kept;
+    before - results.len()
+}
+
+fn fnv1a_hex(data: &str) -> String {
+    let hash = data.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+const SORT_KEYS: [&str; 5] = ["path", "name", "size", "mtime", "doc-title"];
+
+fn sort_results(results: &mut [MatchResult], sort: &str, reverse: bool, fold_case: bool) {
+    results.sort_by(result_order);
+    if fold_case {
+        results.sort_by_cached_key(|r| r.path.to_lowercase());
+    }
+    match sort {
+        "name" => results.sort_by_cached_key(|r| {
+            Path::new(&r.path)
+                .file_name()
+                .map(|n| casefold::key(Path::new(n), fold_case))
+        }),
+        "size" => {
+            results.sort_by_cached_key(|r| fs::metadata(&r.path).map(|m| m.len()).unwrap_or(0))
+        }
+        "mtime" => {
+            results.sort_by_cached_key(|r| fs::metadata(&r.path).and_then(|m| m.modified()).ok())
+        }
+        "doc-title" => results.sort_by_cached_key(|r| {
+            (
+                r.doc_title.is_none(),
+                r.doc_title.as_deref().map(str::to_lowercase),
+            )
+        }),
+        _ => {}
+    }
+    if reverse {
+        results.reverse();
+    }
+    results.sort_by_key(|r| r.deprioritized);
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SourceKind {
+    File,
+    NotebookCell,
+    EmailMessage,
+    PdfText,
+}
+
+fn source_kind(r: &MatchResult) -> SourceKind {
+    if r.notebook_cell.is_some() {
+        SourceKind::NotebookCell
+    } else if r.email.is_some() {
+        SourceKind::EmailMessage
+    } else if r
+        .offsets
+        .as_ref()
+        .is_some_and(|o| o.offsets_are_extracted_text)
+        && is_pdf(Path::new(&r.path))
+    {
+        SourceKind::PdfText
+    } else {
+        SourceKind::File
+    }
+}
+
+fn result_order(a: &MatchResult, b: &MatchResult) -> std::cmp::Ordering {
+    a.path
+        .cmp(&b.path)
+        .then_with(|| source_kind(a).cmp(&source_kind(b)))
+        .then_with(|| {
+            let ai = a.notebook_cell.as_ref().map(|c| c.index);
+            let bi = b.notebook_cell.as_ref().map(|c| c.index);
+            ai.cmp(&bi)
+        })
+        .then_with(|| {
+            let am = a.email.as_ref().map(|e| e.message);
+            let bm = b.email.as_ref().map(|e| e.message);
+            am.cmp(&bm)
+        })
+        .then_with(|| a.rule.cmp(&b.rule))
+        .then_with(|| {
+            let am = a.offsets.as_ref().map(|o| (o.match_start, o.match_end));
+            let bm = b.offsets.as_ref().map(|o| (o.match_start, o.match_end));
+            am.cmp(&bm)
+        })
+        .then_with(|| a.matched_content.cmp(&b.matched_content))
+}
+
+fn parent_dir_of(path: &str) -> String {
+    Path::new(path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn hotspots(results: &[MatchResult]) -> Hotspots {
+    let mut dirs: HashMap<String, usize> = HashMap::new();
+    let mut extensions: HashMap<String, usize> = HashMap::new();
+    for r in results {
+        *dirs.entry(parent_dir_of(&r.path)).or_insert(0) += 1;
+        let ext = Path::new(&r.path).extension().map_or_else(
+            || "(none)".to_string(),
+            |e| e.to_string_lossy().to_lowercase(),
+        );
+        *extensions.entry(ext).or_insert(0) += 1;
+    }
+
+    let rank = |counts: HashMap<String, usize>| {
+        let mut ranked: Vec<Hotspot> = counts
+            .into_iter()
+            .map(|(key, matches)| Hotspot {
+                key,
+                matches,
+                percent: (matches as f64 * 1000.0 / results.len() as f64).round() / 10.0,
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.matches.cmp(&a.matches).then_with(|| a.key.cmp(&b.key)));
+        ranked.truncate(HOTSPOT_LIMIT);
+        ranked
+    };
+    Hotspots {
+        dirs: rank(dirs),
+        extensions: rank(extensions),
+    }
+}
+
+fn preset_comparison(
+    results: &[MatchResult],
+    scopes: &[PresetScope; 2],
+    unicode_fold: bool,
+) -> PresetComparison {
+    let mut by_file: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for r in results {
+        let matched = by_file.entry(r.path.as_str()).or_default();
+        matched.extend(r.rules.iter().map(String::as_str));
+        matched.extend(r.rule.as_deref());
+    }
+    let mut comparison = PresetComparison {
+        presets: [scopes[0].name.clone(), scopes[1].name.clone()],
+        only_first: Vec::new(),
+        only_second: Vec::new(),
+        common: Vec::new(),
+    };
+    for (path, matched) in by_file {
+        let hit = |scope: &PresetScope| {
+            matched.contains(scope.name.as_str())
+                && scope_admits(scope, Path::new(path), unicode_fold)
+        };
+        match (hit(&scopes[0]), hit(&scopes[1])) {
+            (true, true) => comparison.common.push(path.to_string()),
+            (true, false) => comparison.only_first.push(path.to_string()),
+            (false, true) => comparison.only_second.push(path.to_string()),
+            (false, false) => {}
+        }
+    }
+    comparison
+}
+
+fn scope_admits(scope: &PresetScope, path: &Path, unicode_fold: bool) -> bool {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    ext_allowed(path, scope.allowed_ext.as_ref(), scope.include_no_ext)
+        && (scope.include_pdf || !is_pdf(path))
+        && scope.name_query.as_deref().is_none_or(|q| {
+            fold_name(&file_name, unicode_fold).contains(&fold_name(q, unicode_fold))
+        })
+        && !path
+            .ancestors()
+            .skip(1)
+            .any(|a| is_ignored_dir(a, &scope.ignore_dir))
+}
+
+fn split_queries(
+    results: &[MatchResult],
+    queries: &[NamedQuery],
+    roots: &[PathBuf],
+    unicode_fold: bool,
+) -> Vec<QueryReport> {
+    queries
+        .iter()
+        .map(|q| {
+            let label = q.scope.name.as_str();
+            let mut own: Vec<MatchResult> = results
+                .iter()
+                .filter(|r| {
+                    (r.rule.as_deref() == Some(label) || r.rules.iter().any(|l| l == label))
+                        && scope_admits(&q.scope, Path::new(&r.path), unicode_fold)
+                })
+                .cloned()
+                .collect();
+            for r in &mut own {
+                r.rule = None;
+                r.rules.clear();
+                r.fingerprint = result_fingerprint(r, roots);
+            }
+            let files: HashSet<&str> = own.iter().map(|r| r.path.as_str()).collect();
+            QueryReport {
+                label: label.to_string(),
+                files: files.len(),
+                matches: own.len(),
+                reports: Vec::new(),
+                results: own,
+            }
+        })
+        .collect()
+}
+
+fn write_query_reports(
+    dir: &Path,
+    formats: &[&str],
+    args: &SearchArgs,
+    outcome: &mut SearchOutcome,
+) -> io::Result<()> {
+    let plain = MarkdownLayout {
+        color: false,
+        width: None,
+        full_paths: true,
+        show_meta: args.show_meta.as_deref(),
+        context_lines: args.context_lines,
+    };
+    fs::create_dir_all(dir)?;
+    let Some(queries) = outcome.queries.as_mut() else {
+        return Ok(());
+    };
+    for (report, query) in queries.iter_mut().zip(&args.queries) {
+        let mut own = args.clone();
+        own.rule = Vec::new();
+        own.content.clone_from(&query.content);
+        own.fixed_strings = query.fixed_strings;
+        own.name.clone_from(&query.scope.name_query);
+        own.ext
+            .clone_from(&query.scope.allowed_ext.as_ref().map(|e| e.join(",")));
+        own.include_no_ext = query.scope.include_no_ext;
+        own.include_pdf = query.scope.include_pdf;
+        own.queries = Vec::new();
+
+        let mut stats = outcome.stats.clone();
+        stats.matches_total = report.results.len();
+        stats.matches_printed = report.results.len();
+        stats.rules = BTreeMap::new();
+        let results = std::mem::take(&mut report.results);
+        let single = SearchOutcome {
+            schema_version: outcome.schema_version,
+            stats,
+            effective_patterns: outcome
+                .effective_patterns
+                .iter()
+                .filter(|e| e.rule.as_deref() == Some(report.label.as_str()))
+                .map(|e| EffectivePattern {
+                    rule: None,
+                    ..e.clone()
+                })
+                .collect(),
+            suppressed_per_dir: Vec::new(),
+            hotspots: (!args.no_hotspots).then(|| hotspots(&results)),
+            results,
+            meta: outcome.meta.clone(),
+            directories: None,
+            cooccurrence: None,
+            comparison: None,
+            queries: None,
+            diagnosis: None,
+        };
+        for format in formats {
+            let path = dir.join(format!("{}.{format}", report.label));
+            let mut out = BufWriter::new(fs::File::create(&path)?);
+            write_report(&mut out, format, &own, &single, &plain)?;
+            out.flush()?;
+            report.reports.push(display_path(&path));
+        }
+    }
+    Ok(())
+}
+
+fn cooccurrence_pairs(results: &[MatchResult], labels: &BTreeSet<&str>) -> Vec<Cooccurrence> {
+    let mut by_file: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for r in results {
+        let matched = by_file.entry(r.path.as_str()).or_default();
+        matched.extend(r.rules.iter().map(String::as_str));
+        matched.extend(r.rule.as_deref());
+    }
+    let labels: Vec<&str> = labels.iter().copied().collect();
+    let mut pairs = Vec::new();
+    for (i, a) in labels.iter().enumerate() {
+        for b in &labels[i + 1..] {
+            let mut pair = Cooccurrence {
+                patterns: [a.to_string(), b.to_string()],
+                count: 0,
+                only: [0, 0],
+                paths: Vec::new(),
+            };
+            for (path, matched) in &by_file {
+                match (matched.contains(a), matched.contains(b)) {
+                    (true, true) => {
+                        pair.count += 1;
+                        pair.paths.push(path.to_string());
+                    }
+                    (true, false) => pair.only[0] += 1,
+                    (false, true) => pair.only[1] += 1,
+                    (false, false) => {}
+                }
+            }
+            pairs.push(pair);
+        }
+    }
+    pairs
+}
+
+fn summarize_dirs(results: &[MatchResult], roots: &[PathBuf], depth: usize) -> Vec<DirSummary> {
+    let mut per_dir: HashMap<PathBuf, HashMap<&str, usize>> = HashMap::new();
+    for r in results {
+        let parent = Path::new(&r.path).parent().unwrap_or(Path::new(""));
+        let dir = roots
+            .iter()
+            .find_map(|root| {
+                let rel = parent.strip_prefix(root).ok()?;
+                Some(
+                    rel.components()
+                        .take(depth)
+                        .fold(root.clone(), |acc, c| acc.join(c)),
+                )
+            })
+            .unwrap_or_else(|| parent.to_path_buf());
+        let count = per_dir.entry(dir).or_default().entry(&r.path).or_default();
+        *count = r.occurrences.unwrap_or(*count + 1);
+    }
+
+    let mut summaries: Vec<DirSummary> = per_dir
+        .into_iter()
+        .map(|(dir, files)| DirSummary {
+            dir: display_path(&dir),
+            files: files.len(),
+            occurrences: files.values().sum(),
+            bytes: files
+                .keys()
+                .map(|p| fs::metadata(io_path(Path::new(p))).map_or(0, |m| m.len()))
+                .sum(),
+        })
+        .collect();
+    summaries.sort_by(|a, b| {
+        b.files
+            .cmp(&a.files)
+            .then_with(|| b.occurrences.cmp(&a.occurrences))
+            .then_with(|| a.dir.cmp(&b.dir))
+    });
+    summaries
+}
+
+fn truncation_breakdown(dropped: &[MatchResult]) -> TruncationBreakdown {
+    let content = dropped.iter().filter(|r| r.matched_content).count();
+
+    let mut per_dir: HashMap<String, usize> = HashMap::new();
+    for r in dropped {
+        *per_dir.entry(parent_dir_of(&r.path)).or_insert(0) += 1;
+    }
+    let mut top_dirs: Vec<DirDropped> = per_dir
+        .into_iter()
+        .map(|(dir, dropped)| DirDropped { dir, dropped })
+        .collect();
+    top_dirs.sort_by(|a, b| b.dropped.cmp(&a.dropped).then_with(|| a.dir.cmp(&b.dir)));
+    top_dirs.truncate(3);
+
+    TruncationBreakdown {
+        content,
+        name_only: dropped.len() - content,
+        top_dirs,
+    }
+}
+
+fn limit_per_dir(results: Vec<MatchResult>, n: usize) -> (Vec<MatchResult>, Vec<DirSuppressed>) {
+    let mut kept_per_dir: HashMap<String, usize> = HashMap::new();
+    let mut suppressed: BTreeMap<String, usize> = BTreeMap::new();
+    let mut kept = Vec::with_capacity(results.len());
+
+    for r in results {
+        let dir = parent_dir_of(&r.path);
+        let count = kept_per_dir.entry(dir.clone()).or_insert(0);
+        if *count < n {
+            *count += 1;
+            kept.push(r);
+        } else {
+            *suppressed.entry(dir).or_insert(0) += 1;
+        }
+    }
+
+    let suppressed = suppressed
+        .into_iter()
+        .map(|(dir, suppressed)| DirSuppressed { dir, suppressed })
+        .collect();
+
+    (kept, suppressed)
+}
+
+fn dedup_files(files: Vec<PathBuf>, fold_case: bool) -> (Vec<PathBuf>, usize) {
+    let mut seen: HashMap<PathBuf, usize> = HashMap::new();
+    let mut kept: Vec<PathBuf> = Vec::with_capacity(files.len());
+    let mut removed = 0;
+
+    for p in files {
+        let Ok(canon) = fs::canonicalize(&p) else {
+            kept.push(p);
+            continue;
+        };
+        let canon = casefold::key(&canon, fold_case);
+        match seen.get(&canon) {
+            Some(&i) => {
+                removed += 1;
+                if p.as_os_str().len() < kept[i].as_os_str().len() {
+                    kept[i] = p;
+                }
+            }
+            None => {
+                seen.insert(canon, kept.len());
+                kept.push(p);
+            }
+        }
+    }
+
+    (kept, removed)
+}
+
+#[cfg(unix)]
+fn dedup_hardlinks(files: Vec<PathBuf>) -> (Vec<PathBuf>, HashMap<String, Vec<String>>) {
+    use std::os::unix::fs::MetadataExt;
+    let mut seen: HashMap<(u64, u64), String> = HashMap::new();
+    let mut links: HashMap<String, Vec<String>> = HashMap::new();
+    let mut kept = Vec::with_capacity(files.len());
+
+    for p in files {
+        let Some(meta) = fs::metadata(io_path(&p)).ok().filter(|m| m.nlink() > 1) else {
+            kept.push(p);
+            continue;
+        };
+        match seen.entry((meta.dev(), meta.ino())) {
+            std::collections::hash_map::Entry::Occupied(first) => {
+                links
+                    .entry(first.get().clone())
+                    .or_default()
+                    .push(display_path(&p));
+            }
+            std::collections::hash_map::Entry::Vacant(slot) => {
+                slot.insert(display_path(&p));
+                kept.push(p);
+            }
+        }
+    }
+
+    (kept, links)
+}
+
+#[cfg(not(unix))]
+fn dedup_hardlinks(files: Vec<PathBuf>) -> (Vec<PathBuf>, HashMap<String, Vec<String>>) {
+    (files, HashMap::new())
+}
+
+const SCHEDULES: [&str; 2] = ["size", "path"];
+
+const WALK_ORDERS: [&str; 3] = ["dfs", "bfs", "mtime"];
+
+fn schedule_largest_first(files: &mut Vec<PathBuf>) {
+    let mut sized: Vec<(u64, PathBuf)> = std::mem::take(files)
+        .into_par_iter()
+        .map(|p| (fs::metadata(&p).map(|m| m.len()).unwrap_or(0), p))
+        .collect();
+    sized.sort_by_key(|(size, _)| std::cmp::Reverse(*size));
+    *files = sized.into_iter().map(|(_, p)| p).collect();
+}
+
+fn ext_allowed(path: &Path, allowed: Option<&Vec<String>>, include_no_ext: bool) -> bool {
+    let Some(list) = allowed else { return true };
+    let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+        return include_no_ext || list.iter().any(|x| x == NO_EXT_TOKEN);
+    };
+    list.iter().any(|x| x == &ext.to_lowercase())
+}
+
+fn analyze_file(path: &Path, opts: &ScanOptions, counters: &Counters) -> Vec<MatchResult> {
+    let ScanOptions {
+        name_query,
+        unicode_fold,
+        not_name,
+        not_name_glob,
+        per_file_timeout,
+        ..
+    } = *opts;
+    let started = Instant::now();
+    FILE_STARTED.set(Some(started));
+    let deadline = started + per_file_timeout;
+
+    let Some(file_name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+        return Vec::new();
+    };
+    let ext = path.extension().and_then(|e| e.to_str());
+
+    let Some(matched_name) =
+        file_name_match(path, name_query, unicode_fold, not_name, not_name_glob)
+    else {
+        counters.excluded_by_name.fetch_add(1, Ordering::Relaxed);
+        return Vec::new();
+    };
+
+    let mime = if opts.show_mime || !opts.mime.is_empty() || !opts.exclude_mime.is_empty() {
+        let mime = sniff_mime(path);
+        if !mime_allowed(&mime, opts.mime, opts.exclude_mime) {
+            counters.excluded_by_mime.fetch_add(1, Ordering::Relaxed);
+            return Vec::new();
+        }
+        Some(mime)
+    } else {
+        None
+    };
+
+    let limit = size_limit(ext, opts);
+    let mut results = scan_content(path, ext, matched_name, deadline, &limit, opts, counters);
+    let name_range = name_query
+        .filter(|_| matched_name)
+        .and_then(|q| name_match_range(&file_name, q, unicode_fold));
+    for r in &mut results {
+        r.name_match_range = name_range;
+    }
+    if let Some(mime) = mime.filter(|_| opts.show_mime) {
+        for r in &mut results {
+            r.mime = Some(mime.clone());
+        }
+    }
+    results
+}
+
+struct SizeLimit {
+    bytes: u64,
+    ext: Option<String>,
+}
+
+impl SizeLimit {
+    fn describe(&self) -> String {
+        match &self.ext {
+            Some(ext) => format!("max_bytes_by_ext {ext} = {}", self.bytes),
+            None => format!("--max-bytes {}", self.bytes),
+        }
+    }
+}
+
+fn size_limit(ext: Option<&str>, opts: &ScanOptions) -> SizeLimit {
+    let ext = ext.map(str::to_lowercase);
+    let by_ext = ext
+        .as_deref()
+        .and_then(|e| opts.max_bytes_by_ext.get_key_value(e))
+        .or_else(|| opts.max_bytes_by_ext.get_key_value("*"));
+    match by_ext {
+        Some((ext, limit)) => SizeLimit {
+            bytes: limit.unwrap_or(u64::MAX),
+            ext: Some(ext.clone()),
+        },
+        None => SizeLimit {
+            bytes: opts.max_bytes,
+            ext: None,
+        },
+    }
+}
+
+fn parse_max_bytes_ext(entry: &str) -> Result<(String, Option<u64>), String> {
+    let Some((ext, size)) = entry.split_once('=') else {
+        return Err(format!(
+            "invalid `{entry}`: expected EXT=SIZE, e.g. json=256k"
+        ));
+    };
+    let ext = ext.trim().trim_start_matches('.').to_lowercase();
+    if ext.is_empty() {
+        return Err(format!("invalid `{entry}`: missing extension"));
+    }
+    if size.trim().eq_ignore_ascii_case("unlimited") {
+        return Ok((ext, None));
+    }
+    memory::parse_size(size).map(|bytes| (ext, Some(bytes)))
+}
+
+fn scan_content(
+    path: &Path,
+    ext: Option<&str>,
+    matched_name: bool,
+    deadline: Instant,
+    limit: &SizeLimit,
+    opts: &ScanOptions,
+    counters: &Counters,
+) -> Vec<MatchResult> {
+    let max_bytes = limit.bytes;
+    let ScanOptions {
+        name_query,
+        rules,
+        memory,
+        allowed_ext,
+        include_no_ext,
+        include_pdf,
+        verbose,
+        ..
+    } = *opts;
+
+    let mut content_hits: Vec<ContentHit> = Vec::new();
+    let mut changed = false;
+    let mut doc_meta = DocMeta::default();
+
+    let readable_link = rules.is_empty()
+        || symlink_target(path).is_none_or(|(_, exists)| exists && opts.follow_links);
+
+    if !rules.is_empty() && readable_link {
+        let pdf = !opts.binary && is_pdf(path);
+        let notebook = !opts.binary && !opts.raw_notebooks && is_notebook(path);
+        let email_file = !opts.binary && opts.include_email && email::is_email(ext);
+        let extractor = ext
+            .filter(|_| !opts.binary)
+            .and_then(|e| opts.extractors.get(&e.to_lowercase()));
+
+        if !opts.binary && extractor.is_none() && allowed_ext.is_none() {
+            if pdf {
+                if !include_pdf {
+                    counters.pdf_disabled.fetch_add(1, Ordering::Relaxed);
+                    counters.skip(SkipReason::NonText, path, || {
+                        "pdf content search disabled".to_string()
+                    });
+                    return name_only_result(path, name_query, matched_name);
+                }
+            } else if !is_probably_text(path, include_no_ext) && !is_notebook(path) && !email_file {
+                counters.skip(SkipReason::NonText, path, || "not a text file".to_string());
+                return name_only_result(path, name_query, matched_name);
+            }
+        } else if extractor.is_none() && pdf && !include_pdf {
+            counters.pdf_disabled.fetch_add(1, Ordering::Relaxed);
+            counters.skip(SkipReason::NonText, path, || {
+                "pdf content search disabled".to_string()
+            });
+            return name_only_result(path, name_query, matched_name);
+        }
+
+        if extractor.is_none()
+            && !notebook
+            && !email_file
+            && opts.index.is_some_and(|i| i.rules_out(path))
+        {
+            return name_only_result(path, name_query, matched_name);
+        }
+
+        if let Some(before) = opts.snapshots.get(path) {
+            let now = fs::metadata(io_path(path))
+                .ok()
+                .map(|m| FileSnapshot::of(&m));
+            if now.as_ref() != Some(before) {
+                if opts.strict_snapshot {
+                    counters.skip(SkipReason::ChangedDuringScan, path, || {
+                        "size or modification time changed since discovery".to_string()
+                    });
+                    if verbose {
+                        eprintln!("[changed] skipped: {}", path.display());
+                    }
+                    return name_only_result(path, name_query, matched_name);
+                }
+                counters.changed.fetch_add(1, Ordering::Relaxed);
+                changed = true;
+            }
+        }
+
+        if let Some(template) = extractor {
+            let text = match run_extractor(template, path, max_bytes, deadline) {
+                Ok(t) => {
+                    counters.scanned_extractor.fetch_add(1, Ordering::Relaxed);
+                    t
+                }
+                Err(e) => {
+                    counters.skip(SkipReason::ExtractorFailed, path, || e.to_string());
+                    if verbose {
+                        eprintln!("[extractor] {}: {e}", path.display());
+                    }
+                    return name_only_result(path, name_query, matched_name);
+                }
+            };
+
+            let Ok(hits) = match_rules(
+                &text,
+                None,
+                0,
+                true,
+                opts.normalize_extracted,
+                deadline,
+                opts,
+                counters,
+            ) else {
+                report_timeout(path, verbose, counters);
+                return name_only_result(path, name_query, matched_name);
+            };
+            content_hits = hits;
+        } else if pdf {
+            let size = match fs::metadata(io_path(path)) {
+                Ok(m) => m.len(),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    counters.skip(SkipReason::Vanished, path, || io_detail(&e));
+                    return name_only_result(path, name_query, matched_name);
+                }
+                Err(_) => 0,
+            };
+            counters.scanned_pdf.fetch_add(1, Ordering::Relaxed);
+            let _permit = memory.acquire(size);
+            opts.io.consume(size);
+            counters
+                .bytes_read
+                .fetch_add(size as usize, Ordering::Relaxed);
+            let _lock = match PDF_EXTRACT_LOCK.lock() {
+                Ok(g) => g,
+                Err(_) => {
+                    counters.skip(SkipReason::UnreadablePdf, path, || {
+                        "pdf extraction lock poisoned".to_string()
+                    });
+                    return name_only_result(path, name_query, matched_name);
+                }
+            };
+
+            let pdf_text_result = if opts.no_catch {
+                Ok(pdf_document(&io_path(path), opts.pdf_max_pages))
+            } else {
+                SILENCE_PANICS.store(true, Ordering::Relaxed);
+                let result = catch_unwind(AssertUnwindSafe(|| {
+                    let _gag_out = Gag::stdout().ok();
+                    let _gag_err = gag::Gag::stderr().ok();
+                    pdf_document(&io_path(path), opts.pdf_max_pages)
+                }));
+                SILENCE_PANICS.store(false, Ordering::Relaxed);
+                result
+            };
+
+            let pdf_text = match pdf_text_result {
+                Ok(Ok((t, meta, truncated))) => {
+                    doc_meta = meta;
+                    if let Some((scanned, total)) = truncated {
+                        counters.pdf_truncated.fetch_add(1, Ordering::Relaxed);
+                        if verbose {
+                            eprintln!(
+                                "[pdf] scanned first {scanned} of {total} pages: {}",
+                                path.display()
+                            );
+                        }
+                    }
+                    counters
+                        .bytes_pdf_text
+                        .fetch_add(t.len(), Ordering::Relaxed);
+                    t
+                }
+                Err(payload) => {
+                    let message = counters.panicked(path, payload.as_ref(), verbose);
+                    counters.skip(SkipReason::UnreadablePdf, path, || {
+                        format!("panic: {message}")
+                    });
+                    return name_only_result(path, name_query, matched_name);
+                }
+                Ok(Err(e)) => {
+                    counters.skip(SkipReason::UnreadablePdf, path, || e.to_string());
+
+                    if verbose {
+                        eprintln!("[pdf] unreadable: {}", path.display());
+                    }
+
+                    return name_only_result(path, name_query, matched_name);
+                }
+            };
+
+            let Ok(hits) = match_rules(
+                &pdf_text,
+                None,
+                0,
+                true,
+                opts.normalize_extracted,
+                deadline,
+                opts,
+                counters,
+            ) else {
+                report_timeout(path, verbose, counters);
+                return name_only_result(path, name_query, matched_name);
+            };
+            content_hits = hits;
+        } else if notebook {
+            let size = fs::metadata(io_path(path)).map_or(0, |m| m.len());
+            let raw_limit = raw_size_limit(max_bytes);
+            if size > raw_limit as u64 {
+                counters
+                    .bytes_too_large
+                    .fetch_add(size as usize, Ordering::Relaxed);
+                counters.skip(SkipReason::TooLarge, path, || {
+                    format!("{size} bytes of notebook JSON exceeds {raw_limit}")
+                });
+                return name_only_result(path, name_query, matched_name);
+            }
+            let _permit = memory.acquire(size);
+            let raw = match fs::read_to_string(io_path(path)) {
+                Ok(v) => v,
+                Err(e) => {
+                    counters.skip(io_skip_reason(&e), path, || io_detail(&e));
+                    return name_only_result(path, name_query, matched_name);
+                }
+            };
+            opts.io.consume(raw.len() as u64);
+            counters.bytes_read.fetch_add(raw.len(), Ordering::Relaxed);
+
+            let Some((source, cells)) = notebook_source(&raw) else {
+                counters.skip(SkipReason::UnreadableText, path, || {
+                    "not a valid notebook".to_string()
+                });
+                if verbose {
+                    eprintln!("[ipynb] unreadable: {}", path.display());
+                }
+                return name_only_result(path, name_query, matched_name);
+            };
+
+            if source.len() as u64 > max_bytes {
+                counters
+                    .bytes_too_large
+                    .fetch_add(source.len(), Ordering::Relaxed);
+                counters.skip(SkipReason::TooLarge, path, || {
+                    format!("{} bytes exceeds {}", source.len(), limit.describe())
+                });
+                return name_only_result(path, name_query, matched_name);
+            }
+
+            counters.scanned_text.fetch_add(1, Ordering::Relaxed);
+
+            let Ok(mut hits) = match_rules(
+                &source,
+                None,
+                0,
+                true,
+                opts.normalize_text,
+                deadline,
+                opts,
+                counters,
+            ) else {
+                report_timeout(path, verbose, counters);
+                return name_only_result(path, name_query, matched_name);
+            };
+            for h in &mut hits {
+                let Some((start, end, cell)) = cells
+                    .iter()
+                    .rev()
+                    .find(|(start, _, _)| *start <= h.offsets.match_start)
+                else {
+                    continue;
+                };
+                clip_to_span(h, &source, *start, *end, opts);
+                h.cell = Some(cell.clone());
+            }
+            content_hits = hits;
+        } else if email_file {
+            let file = match fs::File::open(io_path(path)) {
+                Ok(f) => f,
+                Err(e) => {
+                    counters.skip(io_skip_reason(&e), path, || io_detail(&e));
+                    return name_only_result(path, name_query, matched_name);
+                }
+            };
+            let raw_limit = raw_size_limit(max_bytes);
+            let _permit = memory.acquire(raw_limit as u64);
+
+            counters.scanned_text.fetch_add(1, Ordering::Relaxed);
+
+            let reader = io::BufReader::new(file);
+            let messages: Box<dyn Iterator<Item = io::Result<email::RawMessage>>> =
+                if email::is_mbox(ext) {
+                    Box::new(email::Mbox::new(reader, raw_limit))
+                } else {
+                    Box::new(std::iter::once(email::single(reader, raw_limit)))
+                };
+            let (source, parts) = email_source(path, messages, max_bytes, opts, counters);
+
+            let Ok(mut hits) = match_rules(
+                &source,
+                None,
+                0,
+                true,
+                opts.normalize_text,
+                deadline,
+                opts,
+                counters,
+            ) else {
+                report_timeout(path, verbose, counters);
+                return name_only_result(path, name_query, matched_name);
+            };
+            for h in &mut hits {
+                let Some((start, end, part)) = parts
+                    .iter()
+                    .rev()
+                    .find(|(start, _, _)| *start <= h.offsets.match_start)
+                else {
+                    continue;
+                };
+                clip_to_span(h, &source, *start, *end, opts);
+                h.email = Some(part.clone());
+            }
+            content_hits = hits;
+        } else {
+            let meta = match fs::metadata(io_path(path)) {
+                Ok(v) => v,
+                Err(e) => {
+                    counters.skip(io_skip_reason(&e), path, || io_detail(&e));
+                    return name_only_result(path, name_query, matched_name);
+                }
+            };
+
+            if meta.len() > max_bytes {
+                counters
+                    .bytes_too_large
+                    .fetch_add(meta.len() as usize, Ordering::Relaxed);
+                counters.skip(SkipReason::TooLarge, path, || {
+                    format!("{} bytes exceeds {}", meta.len(), limit.describe())
+                });
+                return name_only_result(path, name_query, matched_name);
+            }
+
+            let f = match fs::File::open(io_path(path)) {
+                Ok(v) => opts.io.reader(v),
+                Err(e) => {
+                    counters.skip(io_skip_reason(&e), path, || io_detail(&e));
+                    return name_only_result(path, name_query, matched_name);
+                }
+            };
+
+            counters.scanned_text.fetch_add(1, Ordering::Relaxed);
+
+            if opts.binary {
+                let _permit = memory.acquire(meta.len());
+                let scanned = scratch::with_buffer(meta.len() as usize, |buf| {
+                    let read = f.take(max_bytes).read_to_end(buf);
+                    counters.bytes_read.fetch_add(buf.len(), Ordering::Relaxed);
+                    if let Err(e) = read {
+                        counters.skip(io_skip_reason(&e), path, || io_detail(&e));
+                        return None;
+                    }
+                    Some(match_rules_bytes(buf, deadline, opts))
+                });
+                let Some(scanned) = scanned else {
+                    return name_only_result(path, name_query, matched_name);
+                };
+                let Ok(hits) = scanned else {
+                    report_timeout(path, verbose, counters);
+                    return name_only_result(path, name_query, matched_name);
+                };
+                return content_results(
+                    path,
+                    matched_name,
+                    hits,
+                    name_query,
+                    changed,
+                    opts.all_matches,
+                );
+            }
+
+            let _permit = match (memory.try_acquire(meta.len()), opts.stream_overlap) {
+                (Some(permit), _) => permit,
+                (None, None) => memory.acquire(meta.len()),
+                (None, Some(overlap)) => {
+                    counters.streamed.fetch_add(1, Ordering::Relaxed);
+                    match stream_match_rules(
+                        f.take(max_bytes),
+                        ext,
+                        overlap,
+                        deadline,
+                        opts,
+                        counters,
+                    ) {
+                        StreamOutcome::Hits(hits, transcoded) => {
+                            if transcoded {
+                                counters.transcoded_utf16.fetch_add(1, Ordering::Relaxed);
+                            }
+                            content_hits = hits;
+                        }
+                        StreamOutcome::NonUtf8 => {
+                            counters.skip(SkipReason::NonUtf8, path, || "invalid UTF-8".to_string())
+                        }
+                        StreamOutcome::Unreadable => {
+                            counters.skip(SkipReason::UnreadableText, path, || {
+                                "read failed while streaming".to_string()
+                            })
+                        }
+                        StreamOutcome::TimedOut => {
+                            report_timeout(path, verbose, counters);
+                            return name_only_result(path, name_query, matched_name);
+                        }
+                    }
+                    return content_results(
+                        path,
+                        matched_name,
+                        content_hits,
+                        name_query,
+                        changed,
+                        opts.all_matches,
+                    );
+                }
+            };
+
+            let scanned = scratch::with_buffer(meta.len() as usize, |buf| {
+                let read = f.take(max_bytes).read_to_end(buf);
+                counters.bytes_read.fetch_add(buf.len(), Ordering::Relaxed);
+                if let Err(e) = read {
+                    counters.skip(io_skip_reason(&e), path, || io_detail(&e));
+                    return Ok(Vec::new());
+                }
+                let Some((text, transcoded)) = decode_text(buf) else {
+                    counters.skip(SkipReason::NonUtf8, path, || "invalid UTF-8".to_string());
+                    return Ok(Vec::new());
+                };
+                if transcoded {
+                    counters.transcoded_utf16.fetch_add(1, Ordering::Relaxed);
+                }
+                let text = text.as_ref();
+                let base = if transcoded {
+                    0
+                } else {
+                    buf.len() - text.len()
+                };
+                match_rules(
+                    text,
+                    ext,
+                    base,
+                    transcoded,
+                    opts.normalize_text,
+                    deadline,
+                    opts,
+                    counters,
+                )
+            });
+            let Ok(hits) = scanned else {
+                report_timeout(path, verbose, counters);
+                return name_only_result(path, name_query, matched_name);
+            };
+            content_hits = hits;
+        }
+    }
+
+    let mut results = content_results(
+        path,
+        matched_name,
+        content_hits,
+        name_query,
+        changed,
+        opts.all_matches,
+    );
+    for r in &mut results {
+        r.doc_title.clone_from(&doc_meta.title);
+        r.doc_author.clone_from(&doc_meta.author);
+        r.doc_created.clone_from(&doc_meta.created);
+    }
+    results
+}
+
+#[derive(Default)]
+struct DocMeta {
+    title: Option<String>,
+    author: Option<String>,
+    created: Option<String>,
+}
+
+type PdfPages = Option<(usize, usize)>;
+
+fn pdf_document(path: &Path, max_pages: usize) -> Result<(String, DocMeta, PdfPages), OutputError> {
+    let mut doc = Document::load(path)?;
+    if doc.is_encrypted() {
+        doc.decrypt("").map_err(OutputError::PdfError)?;
+    }
+    let meta = catch_unwind(AssertUnwindSafe(|| pdf_metadata(&doc))).unwrap_or_default();
+
+    let mut text = String::new();
+    let pages = doc.get_pages();
+    if max_pages == 0 || pages.len() <= max_pages {
+        pdf_extract::output_doc(&doc, &mut PlainTextOutput::new(&mut text))?;
+        return Ok((text, meta, None));
+    }
+    let mut output = PlainTextOutput::new(&mut text);
+    for &page in pages.keys().take(max_pages) {
+        pdf_extract::output_doc_page(&doc, &mut output, page)?;
+    }
+    Ok((text, meta, Some((max_pages, pages.len()))))
+}
+
+fn pdf_metadata(doc: &Document) -> DocMeta {
+    let info = doc
+        .trailer
+        .get(b"Info")
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .ok();
+    let field = |key: &[u8]| {
+        let value = info?.get(key).ok()?;
+        let (_, value) = doc.dereference(value).ok()?;
+        let text = pdf_extract::decode_text_string(value).ok()?;
+        let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+        (!text.is_empty()).then(|| text.to_string())
+    };
+    let xmp = pdf_xmp(doc);
+    let from_xmp = |tag: &str| xmp.as_deref().and_then(|x| xmp_value(x, tag));
+
+    DocMeta {
+        title: field(b"Title").or_else(|| from_xmp("dc:title")),
+        author: field(b"Author").or_else(|| from_xmp("dc:creator")),
+        created: field(b"CreationDate")
+            .map(|d| pdf_date(&d))
+            .or_else(|| from_xmp("xmp:CreateDate")),
+    }
+}
+
+fn pdf_xmp(doc: &Document) -> Option<String> {
+    let catalog = doc.catalog().ok()?;
+    let (_, metadata) = doc.dereference(catalog.get(b"Metadata").ok()?).ok()?;
+    let Object::Stream(stream) = metadata else {
+        return None;
+    };
+    let bytes = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    String::from_utf8(bytes).ok()
+}
+
+fn xmp_value(xmp: &str, tag: &str) -> Option<String> {
+    let open = format!("<{tag}");
+    let close = format!("</{tag}>");
+    let start = xmp.find(&open)?;
+    let body_start = start + xmp[start..].find('>')? + 1;
+    let body = &xmp[body_start..body_start + xmp[body_start..].find(&close)?];
+    let text = match body.find("<rdf:li") {
+        Some(li) => {
+            let from = li + body[li..].find('>')? + 1;
+            &body[from..from + body[from..].find("</rdf:li>")?]
+        }
+        None => body,
+    };
+    let text = text
+        .trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    (!text.is_empty()).then_some(text)
+}
+
+fn pdf_date(raw: &str) -> String {
+    let digits: String = raw
+        .trim_start_matches("D:")
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    match digits.len() {
+        n if n >= 8 => format!("{}-{}-{}", &digits[..4], &digits[4..6], &digits[6..8]),
+        4 => digits,
+        _ => raw.to_string(),
+    }
+}
+
+fn content_results(
+    path: &Path,
+    matched_name: bool,
+    content_hits: Vec<ContentHit>,
+    name_query: Option<&str>,
+    changed: bool,
+    all_matches: bool,
+) -> Vec<MatchResult> {
+    let warnings = if changed {
+        vec![FILE_CHANGED_WARNING.to_string()]
+    } else {
+        Vec::new()
+    };
+    if content_hits.is_empty() {
+        let mut results = name_only_result(path, name_query, matched_name);
+        for r in &mut results {
+            r.warnings = warnings.clone();
+        }
+        return results;
+    }
+
+    let path = display_path(path);
+    let more: usize = content_hits.iter().map(|h| h.more).sum();
+    let hits = merge_overlapping_hits(content_hits);
+    let occurrences = all_matches.then_some(hits.len() + more);
+    hits.into_iter()
+        .map(|h| MatchResult {
+            path: path.clone(),
+            fingerprint: String::new(),
+            matched_name,
+            name_match_range: None,
+            matched_content: true,
+            rule: h.rule,
+            rules: h.rules,
+            matched_patterns: h.patterns,
+            snippet: Some(h.snippet),
+            snippet_kind: h.snippet_kind,
+            context: h.context,
+            notebook_cell: h.cell,
+            email: h.email,
+            offsets: Some(h.offsets),
+            doc_title: None,
+            doc_author: None,
+            doc_created: None,
+            mime: None,
+            kind: String::new(),
+            provenance: String::new(),
+            language: None,
+            syntax: None,
+            phase: None,
+            symlink_target: None,
+            target_exists: None,
+            warnings: warnings.clone(),
+            also_at: Vec::new(),
+            copied_to: None,
+            duplicates: None,
+            original_path: None,
+            file_size: None,
+            file_mtime_ns: None,
+            occurrences,
+            matches_truncated: more > 0,
+            deprioritized: false,
+        })
+        .collect()
+}
+
+fn merge_overlapping_hits(mut hits: Vec<ContentHit>) -> Vec<ContentHit> {
+    hits.sort_by_key(|h| (h.offsets.match_start, h.offsets.match_end));
+
+    let mut merged: Vec<ContentHit> = Vec::with_capacity(hits.len());
+    for h in hits {
+        if let Some(last) = merged.last_mut() {
+            let (start, end) = (last.offsets.match_start, last.offsets.match_end);
+            if h.offsets.match_start < end
+                || (h.offsets.match_start, h.offsets.match_end) == (start, end)
+            {
+                last.offsets.match_end = end.max(h.offsets.match_end);
+                last.more += h.more;
+                for label in h.rules {
+                    if !last.rules.contains(&label) {
+                        last.rules.push(label);
+                    }
+                }
+                for p in h.patterns {
+                    if !last.patterns.contains(&p) {
+                        last.patterns.push(p);
+                    }
+                }
+                continue;
+            }
+        }
+        merged.push(h);
+    }
+    merged
+}
+
+const FILE_CHANGED_WARNING: &str = "file_changed_during_scan";
+
+struct ContentHit {
+    rule: Option<String>,
+    rules: Vec<String>,
+    patterns: Vec<String>,
+    snippet: String,
+    snippet_kind: SnippetKind,
+    context: Option<String>,
+    cell: Option<NotebookCell>,
+    email: Option<EmailPart>,
+    offsets: MatchOffsets,
+    more: usize,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+struct NotebookCell {
+    index: usize,
+    cell_type: String,
+}
+
+type NotebookSpan = (usize, usize, NotebookCell);
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+struct EmailPart {
+    message: usize,
+    part: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    header: Option<String>,
+}
+
+fn notebook_source(raw: &str) -> Option<(String, Vec<NotebookSpan>)> {
+    let nb: serde_json::Value = serde_json::from_str(raw).ok()?;
+    let cells = nb.get("cells")?.as_array()?;
+
+    let mut source = String::new();
+    let mut spans = Vec::new();
+    for (index, cell) in cells.iter().enumerate() {
+        let cell_type = cell.get("cell_type").and_then(|t| t.as_str()).unwrap_or("");
+        if cell_type != "code" && cell_type != "markdown" {
+            continue;
+        }
+
+        if !source.is_empty() {
+            source.push('\n');
+        }
+        let start = source.len();
+        match cell.get("source") {
+            Some(serde_json::Value::String(s)) => source.push_str(s),
+            Some(serde_json::Value::Array(lines)) => {
+                for l in lines.iter().filter_map(|l| l.as_str()) {
+                    source.push_str(l);
+                }
+            }
+            _ => {}
+        }
+        spans.push((
+            start,
+            source.len(),
+            NotebookCell {
+                index,
+                cell_type: cell_type.to_string(),
+            },
+        ));
+    }
+    Some((source, spans))
+}
+
+type EmailSpan = (usize, usize, EmailPart);
+
+const RAW_MARKUP_SLACK: u64 = 64 * 1024;
+
+fn raw_size_limit(max_bytes: u64) -> usize {
+    usize::try_from(max_bytes.saturating_mul(4).saturating_add(RAW_MARKUP_SLACK))
+        .unwrap_or(usize::MAX)
+}
+
+fn email_source(
+    path: &Path,
+    messages: impl Iterator<Item = io::Result<email::RawMessage>>,
+    max_bytes: u64,
+    opts: &ScanOptions,
+    counters: &Counters,
+) -> (String, Vec<EmailSpan>) {
+    let mut source = String::new();
+    let mut spans = Vec::new();
+    for (index, raw) in messages.enumerate() {
+        let raw = match raw {
+            Ok(raw) => raw,
+            Err(e) => {
+                counters.skip(io_skip_reason(&e), path, || io_detail(&e));
+                break;
+            }
+        };
+        opts.io.consume(raw.read as u64);
+        counters.bytes_read.fetch_add(raw.read, Ordering::Relaxed);
+        let message = match email::parse(&raw.bytes) {
+            Ok(m) => m,
+            Err(e) => {
+                counters.skip(SkipReason::UnreadableText, path, || {
+                    format!("message {index}: {e}")
+                });
+                if opts.verbose {
+                    eprintln!("[email] unparseable message {index}: {}", path.display());
+                }
+                continue;
+            }
+        };
+        if !message.admits(opts.email_header) {
+            continue;
+        }
+        counters.email_messages.fetch_add(1, Ordering::Relaxed);
+
+        let mut budget = usize::try_from(max_bytes).unwrap_or(usize::MAX);
+        let mut truncated = raw.truncated;
+        let texts = message
+            .headers
+            .iter()
+            .map(|(name, value)| (format!("{name}: {value}"), Some(name.clone())))
+            .chain(std::iter::once((message.body, None)));
+        for (text, header) in texts {
+            let cut = clamp_to_char_boundary(&text, budget);
+            truncated |= cut < text.len();
+            budget -= cut;
+            if cut == 0 {
+                continue;
+            }
+            if !source.is_empty() {
+                source.push('\n');
+            }
+            let start = source.len();
+            source.push_str(&text[..cut]);
+            let part = if header.is_some() { "header" } else { "body" };
+            spans.push((
+                start,
+                source.len(),
+                EmailPart {
+                    message: index,
+                    part: part.to_string(),
+                    header,
+                },
+            ));
+        }
+        if truncated {
+            counters.email_truncated.fetch_add(1, Ordering::Relaxed);
+            if opts.verbose {
+                eprintln!(
+                    "[email] message {index} truncated to {max_bytes} bytes: {}",
+                    path.display()
+                );
+            }
+        }
+    }
+    (source, spans)
+}
+
+fn clip_to_span(h: &mut ContentHit, source: &str, start: usize, end: usize, opts: &ScanOptions) {
+    let (snippet_start, snippet, snippet_kind) = snippet_around_match(
+        &source[start..end],
+        start,
+        h.offsets.match_start.min(end) - start,
+        h.offsets.match_end.min(end) - start,
+        opts.snippet_context,
+        opts.snippet_max,
+        opts.skip_long_lines,
+    );
+    h.snippet = snippet;
+    h.snippet_kind = snippet_kind;
+    h.offsets.snippet_start = start + snippet_start;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn match_rules(
+    text: &str,
+    ext: Option<&str>,
+    base: usize,
+    extracted: bool,
+    normalize: bool,
+    deadline: Instant,
+    opts: &ScanOptions,
+    counters: &Counters,
+) -> Result<Vec<ContentHit>, ScanTimedOut> {
+    let masked = scope::mask(text, ext, opts.scope);
+    let haystack = masked.as_deref().unwrap_or(text);
+    let trimmed = skip_long_lines(text, haystack, 0, opts, counters);
+    let haystack = trimmed.as_deref().unwrap_or(haystack);
+    let normalized = prepare_haystack(haystack, normalize, opts.trim_for_anchors, true);
+    let haystack = normalized.as_ref().map_or(haystack, |n| n.text.as_str());
+    let mut hits = Vec::new();
+    if opts.all_matches {
+        let mut left = opts.max_snippets_per_file;
+        for rule in opts.rules {
+            match_rule_all(
+                rule,
+                text,
+                haystack,
+                normalized.as_ref(),
+                ext,
+                (base, extracted),
+                0,
+                deadline,
+                opts,
+                counters,
+                &mut left,
+                &mut hits,
+            )?;
+        }
+        return Ok(hits);
+    }
+    for rule in opts.rules {
+        if let Some(hit) = match_rule(
+            rule,
+            text,
+            haystack,
+            normalized.as_ref(),
+            ext,
+            (base, extracted),
+            0,
+            deadline,
+            opts,
+            counters,
+        )? {
+            hits.push(hit);
+        }
+    }
+    Ok(hits)
+}
+
+struct Normalized {
+    text: String,
+    map: OffsetMap,
+}
+
+impl Normalized {
+    fn original(&self, start: usize, end: usize) -> (usize, usize) {
+        self.map.original(start, end)
+    }
+}
+
+#[derive(Default)]
+struct OffsetMap {
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Copy)]
+struct Segment {
+    text: usize,
+    len: usize,
+    start: usize,
+    end: usize,
+    verbatim: bool,
+}
+
+impl OffsetMap {
+    fn verbatim(&mut self, text: usize, start: usize, len: usize) {
+        if let Some(last) = self.segments.last_mut() {
+            if last.verbatim && last.end == start && last.text + last.len == text {
+                last.len += len;
+                last.end += len;
+                return;
+            }
+        }
+        self.segments.push(Segment {
+            text,
+            len,
+            start,
+            end: start + len,
+            verbatim: true,
+        });
+    }
+
+    fn replaced(&mut self, text: usize, len: usize, start: usize, end: usize) {
+        self.segments.push(Segment {
+            text,
+            len,
+            start,
+            end,
+            verbatim: false,
+        });
+    }
+
+    fn segment(&self, at: usize) -> Option<&Segment> {
+        let i = self.segments.partition_point(|s| s.text <= at);
+        self.segments.get(i.checked_sub(1)?)
+    }
+
+    fn original(&self, start: usize, end: usize) -> (usize, usize) {
+        let from = self.segment(start).map_or(0, |s| {
+            if s.verbatim {
+                s.start + (start - s.text)
+            } else {
+                s.start
+            }
+        });
+        let to = end
+            .checked_sub(1)
+            .and_then(|i| self.segment(i))
+            .map_or(from, |s| {
+                if s.verbatim {
+                    s.start + (end - s.text)
+                } else {
+                    s.end
+                }
+            });
+        (from, to.max(from))
+    }
+
+    fn through(&self, inner: &OffsetMap) -> OffsetMap {
+        let mut map = OffsetMap::default();
+        for seg in &self.segments {
+            if !seg.verbatim {
+                let (start, end) = inner.original(seg.start, seg.end);
+                map.replaced(seg.text, seg.len, start, end);
+                continue;
+            }
+            let mut at = seg.start;
+            while at < seg.end {
+                let Some(s) = inner.segment(at) else { break };
+                let piece = (s.text + s.len).min(seg.end).max(at + 1) - at;
+                let text = seg.text + (at - seg.start);
+                if s.verbatim {
+                    map.verbatim(text, s.start + (at - s.text), piece);
+                } else {
+                    map.replaced(text, piece, s.start, s.end);
+                }
+                at += piece;
+            }
+        }
+        map
+    }
+}
+
+fn prepare_haystack(
+    haystack: &str,
+    normalize: bool,
+    trim: bool,
+    at_end: bool,
+) -> Option<Normalized> {
+    if !trim {
+        return normalize.then(|| normalize_whitespace(haystack));
+    }
+    let trimmed = trim_for_anchors(haystack, at_end);
+    if !normalize {
+        return Some(trimmed);
+    }
+    let normalized = normalize_whitespace(&trimmed.text);
+    Some(Normalized {
+        map: normalized.map.through(&trimmed.map),
+        text: normalized.text,
+    })
+}
+
+fn trim_for_anchors(s: &str, at_end: bool) -> Normalized {
+    let body = s.strip_prefix('\u{feff}').unwrap_or(s);
+    let mut line_start = s.len() - body.len();
+    let body = if at_end { body.trim_end() } else { body };
+    let mut text = String::with_capacity(body.len());
+    let mut map = OffsetMap::default();
+    for line in body.split_inclusive('\n') {
+        let (kept, newline) = match line.strip_suffix('\n') {
+            Some(content) => (content.trim_end(), true),
+            None => (line, false),
+        };
+        map.verbatim(text.len(), line_start, kept.len());
+        text.push_str(kept);
+        if newline {
+            map.verbatim(text.len(), line_start + line.len() - 1, 1);
+            text.push('\n');
+        }
+        line_start += line.len();
+    }
+    Normalized { text, map }
+}
+
+fn normalize_whitespace(s: &str) -> Normalized {
+    let mut text = String::with_capacity(s.len());
+    let mut map = OffsetMap::default();
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let at = text.len();
+        if c.is_whitespace() {
+            let mut end = i + c.len_utf8();
+            while let Some(&(j, w)) = chars.peek().filter(|(_, w)| w.is_whitespace()) {
+                end = j + w.len_utf8();
+                chars.next();
+            }
+            text.push(' ');
+            if end - i == 1 {
+                map.verbatim(at, i, 1);
+            } else {
+                map.replaced(at, 1, i, end);
+            }
+            continue;
+        }
+        let end = i + c.len_utf8();
+        let expanded = match c {
+            'ﬀ' => "ff",
+            'ﬁ' => "fi",
+            'ﬂ' => "fl",
+            'ﬃ' => "ffi",
+            'ﬄ' => "ffl",
+            'ﬅ' | 'ﬆ' => "st",
+            _ => {
+                text.push(c);
+                map.verbatim(at, i, c.len_utf8());
+                continue;
+            }
+        };
+        text.push_str(expanded);
+        map.replaced(at, expanded.len(), i, end);
+    }
+    Normalized { text, map }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn match_rule(
+    rule: &ContentRule,
+    text: &str,
+    haystack: &str,
+    normalized: Option<&Normalized>,
+    ext: Option<&str>,
+    origin: (usize, bool),
+    seen_until: usize,
+    deadline: Instant,
+    opts: &ScanOptions,
+    counters: &Counters,
+) -> Result<Option<ContentHit>, ScanTimedOut> {
+    let mut pos = 0;
+    let found = next_match(
+        rule, text, haystack, normalized, &mut pos, seen_until, deadline, opts, counters,
+    )?;
+    Ok(found.map(|m| rule_hit(rule, text, normalized, m, ext, origin, deadline, opts)))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn match_rule_all(
+    rule: &ContentRule,
+    text: &str,
+    haystack: &str,
+    normalized: Option<&Normalized>,
+    ext: Option<&str>,
+    origin: (usize, bool),
+    seen_until: usize,
+    deadline: Instant,
+    opts: &ScanOptions,
+    counters: &Counters,
+    left: &mut usize,
+    hits: &mut Vec<ContentHit>,
+) -> Result<(), ScanTimedOut> {
+    let mut pos = 0;
+    while let Some(m) = next_match(
+        rule, text, haystack, normalized, &mut pos, seen_until, deadline, opts, counters,
+    )? {
+        if *left == 0 {
+            if let Some(last) = hits.last_mut() {
+                last.more += 1;
+            }
+            continue;
+        }
+        *left -= 1;
+        hits.push(rule_hit(
+            rule, text, normalized, m, ext, origin, deadline, opts,
+        ));
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn next_match(
+    rule: &ContentRule,
+    text: &str,
+    haystack: &str,
+    normalized: Option<&Normalized>,
+    pos: &mut usize,
+    seen_until: usize,
+    deadline: Instant,
+    opts: &ScanOptions,
+    counters: &Counters,
+) -> Result<Option<MatcherHit>, ScanTimedOut> {
+    while *pos <= haystack.len() {
+        let Some(m) = find_matcher(&rule.matcher, haystack, *pos, deadline)? else {
+            break;
+        };
+        *pos = if m.end > m.start {
+            m.end
+        } else {
+            m.start + haystack[m.start..].chars().next().map_or(1, char::len_utf8)
+        };
+        let (start, end) = normalized.map_or((m.start, m.end), |n| n.original(m.start, m.end));
+        if start < seen_until && end <= seen_until {
+            continue;
+        }
+        if opts
+            .script
+            .is_some_and(|re| !in_script(text, start, end, re))
+        {
+            counters.script_rejected.fetch_add(1, Ordering::Relaxed);
+            continue;
+        }
+        return Ok(Some(m));
+    }
+    Ok(None)
+}
+
+const SCRIPT_WINDOW_CHARS: usize = 16;
+
+fn in_script(text: &str, start: usize, end: usize, script: &Regex) -> bool {
+    let from = text[..start]
+        .char_indices()
+        .rev()
+        .nth(SCRIPT_WINDOW_CHARS - 1)
+        .map_or(0, |(i, _)| i);
+    let to = text[end..]
+        .char_indices()
+        .nth(SCRIPT_WINDOW_CHARS)
+        .map_or(text.len(), |(i, _)| end + i);
+    let window = &text[from..to];
+    let letters = window.chars().filter(|c| c.is_alphabetic()).count();
+    let matching = script
+        .find_iter(window)
+        .filter(|m| m.as_str().chars().all(char::is_alphabetic))
+        .count();
+    letters > 0 && matching * 2 >= letters
+}
+
+fn script_regex(names: &[String]) -> Result<Regex, String> {
+    let mut class = String::new();
+    for name in names {
+        let item = format!(r"\p{{scx={}}}", name.trim());
+        Regex::new(&item)
+            .map_err(|_| format!("unknown script `{}` for --require-script", name.trim()))?;
+        class.push_str(&item);
+    }
+    Regex::new(&format!("[{class}]")).map_err(|e| e.to_string())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn rule_hit(
+    rule: &ContentRule,
+    text: &str,
+    normalized: Option<&Normalized>,
+    mut m: MatcherHit,
+    ext: Option<&str>,
+    (base, extracted): (usize, bool),
+    deadline: Instant,
+    opts: &ScanOptions,
+) -> ContentHit {
+    if let Some(n) = normalized {
+        (m.start, m.end) = n.original(m.start, m.end);
+    }
+    let (mut snippet_start, mut snippet, snippet_kind) = snippet_around_match(
+        text,
+        base,
+        m.start,
+        m.end,
+        opts.snippet_context,
+        opts.snippet_max,
+        opts.skip_long_lines,
+    );
+    if opts.redact && snippet_kind == SnippetKind::Text {
+        (snippet_start, snippet) = trim_partial_matches(
+            text,
+            snippet_start,
+            snippet,
+            (m.start, m.end),
+            opts.rules,
+            deadline,
+        );
+    }
+    let context = if opts.show_context_heading {
+        context::enclosing_heading(text, m.start, ext)
+    } else {
+        None
+    };
+    ContentHit {
+        rule: rule.label.clone(),
+        rules: rule.label.iter().cloned().collect(),
+        patterns: m.patterns,
+        snippet,
+        snippet_kind,
+        context,
+        cell: None,
+        email: None,
+        offsets: MatchOffsets {
+            match_start: base + m.start,
+            match_end: base + m.end,
+            snippet_start: base + snippet_start,
+            offsets_are_extracted_text: extracted,
+        },
+        more: 0,
+    }
+}
+
+const DEFAULT_MEMORY_LIMIT: u64 = 512 << 20;
+
+const STREAM_OVERLAP_BYTES: usize = 4 * 1024;
+
+enum StreamOutcome {
+    Hits(Vec<ContentHit>, bool),
+    NonUtf8,
+    Unreadable,
+    TimedOut,
+}
+
+fn stream_overlap(rules: &[ContentRule]) -> Option<usize> {
+    rules
+        .iter()
+        .try_fold(STREAM_OVERLAP_BYTES, |overlap, rule| {
+            Some(overlap.max(rule.matcher.max_len()?))
+        })
+}
+
+fn stream_match_rules(
+    mut reader: impl Read,
+    ext: Option<&str>,
+    overlap: usize,
+    deadline: Instant,
+    opts: &ScanOptions,
+    counters: &Counters,
+) -> StreamOutcome {
+    let mut raw = vec![0u8; SCAN_CHUNK_BYTES];
+    let mut decoder = None;
+    let mut bom_len = 0;
+    let mut transcoded = false;
+
+    let mut pending = String::new();
+    let mut carry = String::new();
+    let mut offset = 0;
+    let mut found: Vec<Option<ContentHit>> = opts.rules.iter().map(|_| None).collect();
+    let mut all = Vec::new();
+    let mut left = opts.max_snippets_per_file;
+
+    loop {
+        if Instant::now() >= deadline {
+            return StreamOutcome::TimedOut;
+        }
+
+        let n = match reader.read(&mut raw) {
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return StreamOutcome::Unreadable,
+        };
+        counters.bytes_read.fetch_add(n, Ordering::Relaxed);
+        let last = n == 0;
+        let mut src = &raw[..n];
+
+        let dec = decoder.get_or_insert_with(|| {
+            let (encoding, len) = Encoding::for_bom(src).unwrap_or((UTF_8, 0));
+            bom_len = len;
+            transcoded = encoding != UTF_8;
+            src = &src[len..];
+            encoding.new_decoder_without_bom_handling()
+        });
+        loop {
+            pending.reserve(
+                dec.max_utf8_buffer_length_without_replacement(src.len())
+                    .unwrap_or(src.len() * 3),
+            );
+            let (res, read) = dec.decode_to_string_without_replacement(src, &mut pending, last);
+            src = &src[read..];
+            match res {
+                DecoderResult::InputEmpty => break,
+                DecoderResult::OutputFull => continue,
+                DecoderResult::Malformed(..) => return StreamOutcome::NonUtf8,
+            }
+        }
+
+        let cut = if last || pending.len() >= 4 * SCAN_CHUNK_BYTES {
+            pending.len()
+        } else {
+            pending.rfind('\n').map_or(0, |i| i + 1)
+        };
+        if cut > 0 {
+            let window_start = offset - carry.len();
+            let carried = carry.len();
+            let mut window = std::mem::take(&mut carry);
+            window.extend(pending.drain(..cut));
+            offset += cut;
+
+            let base = if transcoded {
+                window_start
+            } else {
+                bom_len + window_start
+            };
+            let masked = scope::mask(&window, ext, opts.scope);
+            let haystack = masked.as_deref().unwrap_or(&window);
+            let trimmed = skip_long_lines(&window, haystack, carried, opts, counters);
+            let haystack = trimmed.as_deref().unwrap_or(haystack);
+            let normalized =
+                prepare_haystack(haystack, opts.normalize_text, opts.trim_for_anchors, last);
+            let haystack = normalized.as_ref().map_or(haystack, |n| n.text.as_str());
+            for rule in opts.rules.iter().filter(|_| opts.all_matches) {
+                if match_rule_all(
+                    rule,
+                    &window,
+                    haystack,
+                    normalized.as_ref(),
+                    ext,
+                    (base, transcoded),
+                    carried,
+                    deadline,
+                    opts,
+                    counters,
+                    &mut left,
+                    &mut all,
+                )
+                .is_err()
+                {
+                    return StreamOutcome::TimedOut;
+                }
+            }
+            for (rule, slot) in opts.rules.iter().zip(found.iter_mut()) {
+                if opts.all_matches || slot.is_some() {
+                    continue;
+                }
+                match match_rule(
+                    rule,
+                    &window,
+                    haystack,
+                    normalized.as_ref(),
+                    ext,
+                    (base, transcoded),
+                    carried,
+                    deadline,
+                    opts,
+                    counters,
+                ) {
+                    Ok(hit) => *slot = hit,
+                    Err(ScanTimedOut) => return StreamOutcome::TimedOut,
+                }
+            }
+
+            let keep = clamp_to_char_boundary(&window, window.len().saturating_sub(overlap));
+            carry = window.split_off(keep);
+        }
+
+        if last || (!opts.all_matches && found.iter().all(Option::is_some)) {
+            break;
+        }
+    }
+
+    all.extend(found.into_iter().flatten());
+    StreamOutcome::Hits(all, transcoded)
+}
+
+fn skip_long_lines(
+    text: &str,
+    haystack: &str,
+    count_from: usize,
+    opts: &ScanOptions,
+    counters: &Counters,
+) -> Option<String> {
+    let max = opts.skip_long_lines?;
+    let mut masked: Option<Vec<u8>> = None;
+    let mut start = 0;
+    for line in text.split_inclusive(['\n', '\r']) {
+        let body = line.trim_end_matches(['\n', '\r']);
+        if body.len() > max && body.chars().count() > max {
+            if start >= count_from {
+                counters.long_lines.fetch_add(1, Ordering::Relaxed);
+            }
+            let buf = masked.get_or_insert_with(|| haystack.as_bytes().to_vec());
+            buf[start..start + body.len()].fill(b' ');
+        }
+        start += line.len();
+    }
+    masked.and_then(|b| String::from_utf8(b).ok())
+}
+
+fn decode_text(buf: &[u8]) -> Option<(Cow<'_, str>, bool)> {
+    match buf {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => std::str::from_utf8(rest)
+            .ok()
+            .map(|t| (Cow::Borrowed(t), false)),
+        [0xFF, 0xFE, rest @ ..] => UTF_16LE
+            .decode_without_bom_handling_and_without_replacement(rest)
+            .map(|t| (t, true)),
+        [0xFE, 0xFF, rest @ ..] => UTF_16BE
+            .decode_without_bom_handling_and_without_replacement(rest)
+            .map(|t| (t, true)),
+        _ => std::str::from_utf8(buf)
+            .ok()
+            .map(|t| (Cow::Borrowed(t), false)),
+    }
+}
+
+const SCAN_CHUNK_BYTES: usize = 256 * 1024;
+
+struct ScanTimedOut;
+
+const WINDOW_LOOKAROUND_BYTES: usize = 8;
+
+fn find_windowed<T>(
+    bytes: &[u8],
+    from: usize,
+    max_len: Option<usize>,
+    deadline: Instant,
+    is_boundary: impl Fn(usize) -> bool,
+    mut find: impl FnMut(usize, usize) -> Option<(usize, T)>,
+) -> Result<Option<T>, ScanTimedOut> {
+    let mut start = from;
+    while start < bytes.len() {
+        if Instant::now() >= deadline {
+            return Err(ScanTimedOut);
+        }
+        let end = (start + SCAN_CHUNK_BYTES).min(bytes.len());
+        let end = memchr::memchr(b'\n', &bytes[end..]).map_or(bytes.len(), |i| end + i + 1);
+        let search_end = match max_len {
+            Some(n) => {
+                let mut e = end
+                    .saturating_add(n)
+                    .saturating_add(WINDOW_LOOKAROUND_BYTES)
+                    .min(bytes.len());
+                while !is_boundary(e) {
+                    e += 1;
+                }
+                e
+            }
+            None => bytes.len(),
+        };
+        let found = find(start, search_end);
+        if search_end == bytes.len() || found.as_ref().is_some_and(|(s, _)| *s < end) {
+            return Ok(found.map(|(_, v)| v));
+        }
+        start = end;
+    }
+    Ok(None)
+}
+
+fn max_match_len(pattern: &str, text: bool, unicode: bool) -> Option<usize> {
+    regex_syntax::ParserBuilder::new()
+        .unicode(unicode)
+        .utf8(text)
+        .build()
+        .parse(pattern)
+        .ok()
+        .and_then(|hir| hir.properties().maximum_len())
+}
+
+fn find_with_deadline(
+    re: &Regex,
+    max_len: Option<usize>,
+    text: &str,
+    from: usize,
+    deadline: Instant,
+) -> Result<Option<(usize, usize)>, ScanTimedOut> {
+    find_windowed(
+        text.as_bytes(),
+        from,
+        max_len,
+        deadline,
+        |i| text.is_char_boundary(i),
+        |start, end| {
+            re.find_at(&text[..end], start)
+                .map(|m| (m.start(), (m.start(), m.end())))
+        },
+    )
+}
+
+fn build_matcher(
+    patterns: &[String],
+    fixed: bool,
+    binary: bool,
+    unicode_words: bool,
+    size_limit: Option<usize>,
+) -> Result<Matcher, String> {
+    if binary {
+        return patterns
+            .iter()
+            .map(|p| {
+                let p = if fixed { regex::escape(p) } else { p.clone() };
+                let mut builder = regex::bytes::RegexBuilder::new(&p);
+                builder.unicode(unicode_words);
+                if let Some(limit) = size_limit {
+                    builder.size_limit(limit).dfa_size_limit(limit);
+                }
+                let re = builder.build().map_err(|e| e.to_string())?;
+                Ok((re, max_match_len(&p, false, unicode_words)))
+            })
+            .collect::<Result<Vec<_>, String>>()
+            .map(Matcher::Bytes);
+    }
+
+    let literal = fixed || patterns.iter().all(|p| regex::escape(p) == *p);
+
+    if literal {
+        return match patterns {
+            [single] => Ok(Matcher::Literal(
+                Box::new(memmem::Finder::new(single.as_bytes()).into_owned()),
+                single.clone(),
+            )),
+            _ => AhoCorasick::builder()
+                .match_kind(MatchKind::LeftmostFirst)
+                .kind(Some(AhoCorasickKind::DFA))
+                .build(patterns)
+                .map(|ac| Matcher::MultiLiteral(ac, patterns.to_vec()))
+                .map_err(|e| e.to_string()),
+        };
+    }
+
+    patterns
+        .iter()
+        .map(|p| {
+            let mut builder = RegexBuilder::new(p);
+            builder.unicode(true);
+            if let Some(limit) = size_limit {
+                builder.size_limit(limit).dfa_size_limit(limit);
+            }
+            let re = builder.build().map_err(|e| e.to_string())?;
+            Ok((re, max_match_len(p, true, true)))
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map(Matcher::Regex)
+}
+
+fn rule_query(matcher: &Matcher) -> Query {
+    match matcher {
+        Matcher::Regex(list) => Query::Or(
+            list.iter()
+                .map(|(re, _)| Query::regex(re.as_str()))
+                .collect(),
+        ),
+        Matcher::Literal(_, lit) => Query::literals(std::slice::from_ref(lit)),
+        Matcher::MultiLiteral(_, list) => Query::literals(list),
+        Matcher::Bytes(_) => Query::Any,
+    }
+}
+
+fn find_matcher(
+    matcher: &Matcher,
+    text: &str,
+    from: usize,
+    deadline: Instant,
+) -> Result<Option<MatcherHit>, ScanTimedOut> {
+    match matcher {
+        Matcher::Regex(list) => {
+            let mut first: Option<(usize, usize)> = None;
+            let mut patterns = Vec::new();
+            for (re, max_len) in list {
+                let Some((start, end)) = find_with_deadline(re, *max_len, text, from, deadline)?
+                else {
+                    continue;
+                };
+                patterns.push(re.as_str().to_string());
+                if first.is_none_or(|(s, _)| start < s) {
+                    first = Some((start, end));
+                }
+            }
+            Ok(first.map(|(start, end)| MatcherHit {
+                start,
+                end,
+                patterns,
+            }))
+        }
+        Matcher::Literal(finder, lit) => {
+            let found = find_windowed(
+                text.as_bytes(),
+                from,
+                Some(lit.len()),
+                deadline,
+                |i| text.is_char_boundary(i),
+                |start, end| {
+                    let at = start + finder.find(&text.as_bytes()[start..end])?;
+                    Some((at, at))
+                },
+            )?;
+            Ok(found.map(|start| MatcherHit {
+                start,
+                end: start + lit.len(),
+                patterns: vec![lit.clone()],
+            }))
+        }
+        Matcher::MultiLiteral(ac, literals) => {
+            let longest = literals.iter().map(String::len).max();
+            let found = find_windowed(
+                text.as_bytes(),
+                from,
+                longest,
+                deadline,
+                |i| text.is_char_boundary(i),
+                |start, end| {
+                    let m = ac.find(&text[start..end])?;
+                    let at = start + m.start();
+                    Some((at, (at, start + m.end(), m.pattern().as_usize())))
+                },
+            )?;
+            Ok(found.map(|(start, end, idx)| MatcherHit {
+                start,
+                end,
+                patterns: vec![literals[idx].clone()],
+            }))
+        }
+        Matcher::Bytes(list) => Ok(find_bytes(list, text.as_bytes(), from, deadline)?.map(|h| {
+            let start = clamp_to_char_boundary(text, h.start);
+            MatcherHit {
+                start,
+                end: clamp_to_char_boundary(text, h.end).max(start),
+                patterns: h.patterns,
+            }
+        })),
+    }
+}
+
+fn find_bytes(
+    list: &[(regex::bytes::Regex, Option<usize>)],
+    bytes: &[u8],
+    from: usize,
+    deadline: Instant,
+) -> Result<Option<MatcherHit>, ScanTimedOut> {
+    let mut first: Option<(usize, usize)> = None;
+    let mut patterns = Vec::new();
+    for (re, max_len) in list {
+        let found = find_windowed(
+            bytes,
+            from,
+            *max_len,
+            deadline,
+            |_| true,
+            |start, end| {
+                re.find_at(&bytes[..end], start)
+                    .map(|m| (m.start(), (m.start(), m.end())))
+            },
+        )?;
+        let Some((start, end)) = found else {
+            continue;
+        };
+        patterns.push(re.as_str().to_string());
+        if first.is_none_or(|(s, _)| start < s) {
+            first = Some((start, end));
+        }
+    }
+    Ok(first.map(|(start, end)| MatcherHit {
+        start,
+        end,
+        patterns,
+    }))
+}
+
+fn match_rules_bytes(
+    buf: &[u8],
+    deadline: Instant,
+    opts: &ScanOptions,
+) -> Result<Vec<ContentHit>, ScanTimedOut> {
+    let mut hits = Vec::new();
+    for rule in opts.rules {
+        let Matcher::Bytes(list) = &rule.matcher else {
+            continue;
+        };
+        let Some(m) = find_bytes(list, buf, 0, deadline)? else {
+            continue;
+        };
+        let (snippet_start, snippet) = hex_dump_around(buf, 0, m.start);
+        hits.push(ContentHit {
+            rule: rule.label.clone(),
+            rules: rule.label.iter().cloned().collect(),
+            patterns: m.patterns,
+            snippet,
+            snippet_kind: SnippetKind::Hex,
+            context: None,
+            cell: None,
+            email: None,
+            offsets: MatchOffsets {
+                match_start: m.start,
+                match_end: m.end,
+                snippet_start,
+                offsets_are_extracted_text: false,
+            },
+            more: 0,
+        });
+    }
+    Ok(hits)
+}
+
+fn report_timeout(path: &Path, verbose: bool, counters: &Counters) {
+    counters.skip(SkipReason::Timeout, path, || {
+        "per-file timeout exceeded".to_string()
+    });
+    if verbose {
+        eprintln!("[timeout] slow file: {}", path.display());
+    }
+}
+
+fn io_skip_reason(e: &io::Error) -> SkipReason {
+    if e.kind() == io::ErrorKind::NotFound {
+        SkipReason::Vanished
     } else {
-        None
+        SkipReason::UnreadableText
+    }
+}
+
+fn io_detail(e: &io::Error) -> String {
+    format!("{:?}: {e}", e.kind())
+}
+
+fn fold_name(s: &str, unicode: bool) -> String {
+    if !unicode {
+        return s.to_lowercase();
+    }
+
+    let mut folded = String::with_capacity(s.len());
+    for c in s.nfc() {
+        match c {
+            'ß' | 'ẞ' => folded.push_str("ss"),
+            'ς' => folded.push('σ'),
+            _ => folded.extend(c.to_lowercase()),
+        }
+    }
+    folded.nfc().collect()
+}
+
+fn name_match_range(file_name: &str, query: &str, unicode: bool) -> Option<(usize, usize)> {
+    let mut folded = String::with_capacity(file_name.len());
+    let mut starts = Vec::new();
+    for c in file_name.chars() {
+        starts.push(folded.len());
+        folded.push_str(&fold_name(c.encode_utf8(&mut [0; 4]), unicode));
+    }
+    let at = folded.find(query)?;
+    let end = at + query.len();
+    let first = starts.iter().rposition(|&s| s <= at)?;
+    let last = starts
+        .iter()
+        .position(|&s| s >= end)
+        .unwrap_or(starts.len());
+    Some((first, last.max(first)))
+}
+
+fn file_name_match(
+    path: &Path,
+    name_query: Option<&str>,
+    unicode_fold: bool,
+    not_name: &[String],
+    not_name_glob: &[Regex],
+) -> Option<bool> {
+    let file_name = path.file_name()?.to_string_lossy();
+    let folded_name = fold_name(&file_name, unicode_fold);
+    if name_excluded(&file_name, &folded_name, not_name, not_name_glob) {
+        return None;
+    }
+    Some(name_query.is_some_and(|q| folded_name.contains(q)))
+}
+
+fn name_excluded(
+    file_name: &str,
+    folded_name: &str,
+    not_name: &[String],
+    not_name_glob: &[Regex],
+) -> bool {
+    not_name.iter().any(|n| folded_name.contains(n.as_str()))
+        || not_name_glob.iter().any(|g| g.is_match(file_name))
+}
+
+fn scan_stdin(opts: &ScanOptions, counters: &Counters) -> Vec<MatchResult> {
+    let path = Path::new(STDIN_PATH);
+    let started = Instant::now();
+    FILE_STARTED.set(Some(started));
+    let deadline = started + opts.per_file_timeout;
+
+    let mut buf = Vec::new();
+    let read = opts
+        .io
+        .reader(io::stdin().lock())
+        .take(opts.max_bytes)
+        .read_to_end(&mut buf);
+    if let Err(e) = read {
+        counters.skip(SkipReason::UnreadableText, path, || io_detail(&e));
+        return Vec::new();
+    }
+    counters.scanned_text.fetch_add(1, Ordering::Relaxed);
+    counters.bytes_read.fetch_add(buf.len(), Ordering::Relaxed);
+
+    if opts.binary {
+        let Ok(hits) = match_rules_bytes(&buf, deadline, opts) else {
+            report_timeout(path, opts.verbose, counters);
+            return Vec::new();
+        };
+        return content_results(path, false, hits, None, false, opts.all_matches);
+    }
+
+    let Some((text, transcoded)) = decode_text(&buf) else {
+        counters.skip(SkipReason::NonUtf8, path, || "invalid UTF-8".to_string());
+        return Vec::new();
+    };
+    if transcoded {
+        counters.transcoded_utf16.fetch_add(1, Ordering::Relaxed);
+    }
+    let text = text.as_ref();
+    let base = if transcoded {
+        0
+    } else {
+        buf.len() - text.len()
+    };
+    let Ok(hits) = match_rules(
+        text,
+        None,
+        base,
+        transcoded,
+        opts.normalize_text,
+        deadline,
+        opts,
+        counters,
+    ) else {
+        report_timeout(path, opts.verbose, counters);
+        return Vec::new();
+    };
+    content_results(path, false, hits, None, false, opts.all_matches)
+}
+
+fn name_only_result(path: &Path, name_query: Option<&str>, matched_name: bool) -> Vec<MatchResult> {
+    let any = name_query.is_some() && matched_name;
+
+    if any {
+        vec![MatchResult {
+            path: display_path(path),
+            fingerprint: String::new(),
+            matched_name,
+            name_match_range: None,
+            matched_content: false,
+            rule: None,
+            rules: Vec::new(),
+            matched_patterns: Vec::new(),
+            snippet: None,
+            snippet_kind: SnippetKind::Text,
+            context: None,
+            notebook_cell: None,
+            email: None,
+            offsets: None,
+            doc_title: None,
+            doc_author: None,
+            doc_created: None,
+            mime: None,
+            kind: String::new(),
+            provenance: String::new(),
+            language: None,
+            syntax: None,
+            phase: None,
+            symlink_target: None,
+            target_exists: None,
+            warnings: Vec::new(),
+            also_at: Vec::new(),
+            copied_to: None,
+            duplicates: None,
+            original_path: None,
+            file_size: None,
+            file_mtime_ns: None,
+            occurrences: None,
+            matches_truncated: false,
+            deprioritized: false,
+        }]
+    } else {
+        Vec::new()
+    }
+}
+
+fn print_markdown(
+    out: &mut impl Write,
+    args: &SearchArgs,
+    outcome: &SearchOutcome,
+    layout: &MarkdownLayout,
+) -> io::Result<()> {
+    let SearchOutcome {
+        stats,
+        effective_patterns,
+        results,
+        suppressed_per_dir,
+        hotspots,
+        meta,
+        directories,
+        cooccurrence,
+        comparison,
+        queries,
+        diagnosis,
+        ..
+    } = outcome;
+    writeln!(out, "# RustFileFinder results\n")?;
+    let dirs: Vec<String> = args
+        .dir
+        .iter()
+        .map(|d| d.to_string_lossy().to_string())
+        .collect();
+    match &args.within {
+        Some(report) => writeln!(out, "- Candidates: matches of `{}`", report.display())?,
+        None if reads_stdin(args) => writeln!(out, "- Input: `{STDIN_PATH}`")?,
+        None => writeln!(out, "- Base dir: `{}`", dirs.join("`, `"))?,
+    }
+    if let Some(n) = &args.name {
+        writeln!(out, "- Name query: `{}`", n)?;
+    }
+    if !args.not_name.is_empty() {
+        writeln!(out, "- Excluded names: `{}`", args.not_name.join("`, `"))?;
+    }
+    if !args.not_name_glob.is_empty() {
+        writeln!(
+            out,
+            "- Excluded name globs: `{}`",
+            args.not_name_glob.join("`, `")
+        )?;
+    }
+    let shown = |p: &String| {
+        if args.redact {
+            REDACTED.to_string()
+        } else {
+            p.clone()
+        }
+    };
+    for c in &args.content {
+        if args.fixed_strings {
+            writeln!(out, "- Content literal: `{}`", shown(c))?;
+        } else {
+            writeln!(out, "- Content regex: `{}`", shown(c))?;
+        }
+    }
+    if let Some(p) = &args.content_file {
+        writeln!(out, "- Content patterns file: `{}`", p.display())?;
+    }
+    if args.scope != "all" {
+        writeln!(out, "- Content scope: `{}`", args.scope)?;
+    }
+    if args.binary {
+        writeln!(out, "- Content matched against raw bytes (`--binary`)")?;
+    }
+    for r in &args.rule {
+        match r.split_once('=').filter(|_| args.redact) {
+            Some((label, _)) => writeln!(out, "- Rule: `{label}={REDACTED}`")?,
+            None => writeln!(out, "- Rule: `{}`", r)?,
+        }
+    }
+    for e in effective_patterns {
+        let flags = if e.flags.is_empty() {
+            String::new()
+        } else {
+            format!(", {}", e.flags.join(", "))
+        };
+        writeln!(
+            out,
+            "- Effective pattern{}: `{}` ({}{})",
+            e.rule
+                .as_deref()
+                .map(|r| format!(" [{r}]"))
+                .unwrap_or_default(),
+            e.patterns.join("`, `"),
+            e.engine,
+            flags
+        )?;
+    }
+    if let Some(ext) = &args.ext {
+        writeln!(out, "- Extensions: `{}`", ext)?;
+    } else if !args.content.is_empty() || args.content_file.is_some() {
+        writeln!(out, "- Extensions: *(default text set for content search)*")?;
+    }
+    if args.include_no_ext {
+        writeln!(out, "- Files without extension: `included`")?;
+    }
+    if !args.mime.is_empty() {
+        writeln!(out, "- MIME types: `{}`", args.mime.join("`, `"))?;
+    }
+    if let Some(kind) = &args.kind {
+        writeln!(out, "- Kinds: `{kind}`")?;
+    }
+    if let Some(lang) = &args.lang {
+        writeln!(out, "- Languages: `{lang}`")?;
+    }
+    if args.two_phase {
+        writeln!(out, "- Two-phase scan: `enabled`")?;
+    }
+    if !args.provenance.is_empty() {
+        writeln!(out, "- Provenance: `{}`", args.provenance.join("`, `"))?;
+    }
+    if !args.deprioritize.is_empty() {
+        writeln!(out, "- Listed last: `{}`", args.deprioritize.join("`, `"))?;
+    }
+    if !args.require_script.is_empty() {
+        writeln!(
+            out,
+            "- Required script: `{}`",
+            args.require_script.join("`, `")
+        )?;
+    }
+    if !args.exclude_mime.is_empty() {
+        writeln!(
+            out,
+            "- Excluded MIME types: `{}`",
+            args.exclude_mime.join("`, `")
+        )?;
+    }
+    if args.include_pdf && args.pdf_max_pages > 0 {
+        writeln!(
+            out,
+            "- PDF content search: `enabled` (first {} pages)",
+            args.pdf_max_pages
+        )?;
+    } else if args.include_pdf {
+        writeln!(out, "- PDF content search: `enabled`")?;
+    } else {
+        writeln!(out, "- PDF content search: `disabled`")?;
+    }
+    if stats.pdfs_skipped_disabled > 0 {
+        writeln!(
+            out,
+            "- **Note: {} PDF files were not searched; pass --include-pdf to include them**",
+            stats.pdfs_skipped_disabled
+        )?;
+    }
+    if args.include_email {
+        writeln!(out, "- Email content search: `enabled`")?;
+        for (name, needle) in &args.email_header {
+            writeln!(out, "- Email header filter: `{name}` contains `{needle}`")?;
+        }
+    }
+
+    writeln!(out)?;
+    writeln!(out, "## Run statistics")?;
+    writeln!(out, "- Files discovered: **{}**", stats.files_discovered)?;
+    if stats.files_deduplicated > 0 {
+        writeln!(
+            out,
+            "- Duplicates removed (same physical file): **{}**",
+            stats.files_deduplicated
+        )?;
+    }
+    if stats.files_deduped_hardlinks > 0 {
+        writeln!(
+            out,
+            "- Hard links scanned once: **{}** extra paths",
+            stats.files_deduped_hardlinks
+        )?;
+    }
+    if stats.files_excluded_by_ext > 0 {
+        writeln!(
+            out,
+            "- Excluded by extension filter: **{}**",
+            stats.files_excluded_by_ext
+        )?;
+    }
+    if stats.files_excluded_by_name > 0 {
+        writeln!(
+            out,
+            "- Excluded by name filter: **{}**",
+            stats.files_excluded_by_name
+        )?;
+    }
+    if stats.files_excluded_by_mime > 0 {
+        writeln!(
+            out,
+            "- Excluded by MIME filter: **{}**",
+            stats.files_excluded_by_mime
+        )?;
+    }
+    if stats.files_excluded_self > 0 {
+        writeln!(
+            out,
+            "- Excluded own reports and state files: **{}**",
+            stats.files_excluded_self
+        )?;
+    }
+    if stats.files_excluded_by_kind > 0 {
+        writeln!(
+            out,
+            "- Excluded by kind filter: **{}**",
+            stats.files_excluded_by_kind
+        )?;
+    }
+    if stats.files_excluded_by_lang > 0 {
+        writeln!(
+            out,
+            "- Excluded by language filter: **{}**",
+            stats.files_excluded_by_lang
+        )?;
+    }
+    if stats.matches_collapsed > 0 {
+        writeln!(
+            out,
+            "- Collapsed as duplicate snippets: **{}**",
+            stats.matches_collapsed
+        )?;
+    }
+    if let Some(c) = &stats.copied {
+        writeln!(
+            out,
+            "- Copied to `{}`: **{}** files ({})",
+            c.dir,
+            c.files,
+            memory::format_size(c.bytes)
+        )?;
+        if c.failed > 0 {
+            writeln!(out, "- **Warning: {} files could not be copied**", c.failed)?;
+        }
+        if let Some(e) = &c.error {
+            writeln!(out, "- **Error: nothing was copied: {e}**")?;
+        }
+    }
+    if !stats.matches_by_language.is_empty() {
+        let counts: Vec<String> = stats
+            .matches_by_language
+            .iter()
+            .map(|(l, n)| format!("{l} **{n}**"))
+            .collect();
+        writeln!(out, "- Matches by language: {}", counts.join(", "))?;
+    }
+    if !stats.matches_by_provenance.is_empty() {
+        let counts: Vec<String> = provenance::ALL
+            .iter()
+            .map(|p| {
+                let n = stats.matches_by_provenance.get(*p).copied().unwrap_or(0);
+                format!("{p} **{n}**")
+            })
+            .collect();
+        writeln!(out, "- Matches by provenance: {}", counts.join(", "))?;
+    }
+    if stats.matches_excluded_by_provenance > 0 {
+        writeln!(
+            out,
+            "- Excluded by provenance filter: **{}**",
+            stats.matches_excluded_by_provenance
+        )?;
+    }
+    for d in &stats.pruned_dirs {
+        writeln!(
+            out,
+            "- **Warning: skipped `{}` (over --max-dir-bytes after {})**",
+            d.dir,
+            memory::format_size(d.bytes)
+        )?;
+    }
+    if stats.files_missing > 0 {
+        writeln!(
+            out,
+            "- **Warning: {} files from the --within report no longer exist**",
+            stats.files_missing
+        )?;
+    }
+    if stats.paths_ignored > 0 {
+        writeln!(
+            out,
+            "- Skipped by ignore files: **{}**",
+            stats.paths_ignored
+        )?;
+    }
+    if stats.dirs_depth_limited > 0 {
+        writeln!(
+            out,
+            "- **Warning: {} directories not descended past --max-depth = {}**",
+            stats.dirs_depth_limited, args.max_depth
+        )?;
+    }
+    if stats.paths_too_long > 0 {
+        writeln!(
+            out,
+            "- **Warning: {} paths skipped over --max-path-len = {} bytes**",
+            stats.paths_too_long, args.max_path_len
+        )?;
+    }
+    if let Some(cutoff) = &stats.changed_since {
+        writeln!(
+            out,
+            "- Changed since `{cutoff}`: skipped **{}** unchanged files and **{}** unchanged directories",
+            stats.files_unchanged, stats.dirs_unchanged
+        )?;
+    }
+    if stats.discovery_truncated {
+        writeln!(
+            out,
+            "- **Warning: discovery stopped at --max-total-bytes = {}; results are incomplete**",
+            memory::format_size(args.max_total_bytes.unwrap_or_default())
+        )?;
+    }
+    writeln!(
+        out,
+        "- Files scanned for content (text): **{}**",
+        stats.files_scanned_text
+    )?;
+    writeln!(
+        out,
+        "- Files scanned for content (pdf): **{}**",
+        stats.files_scanned_pdf
+    )?;
+    if stats.files_pdf_truncated > 0 {
+        writeln!(
+            out,
+            "- PDFs scanned partially (--pdf-max-pages): **{}**",
+            stats.files_pdf_truncated
+        )?;
+    }
+    if args.include_email {
+        writeln!(
+            out,
+            "- Email messages scanned: **{}**",
+            stats.email_messages_scanned
+        )?;
+    }
+    if stats.email_messages_truncated > 0 {
+        writeln!(
+            out,
+            "- Email messages truncated to {}: **{}**",
+            args.max_bytes, stats.email_messages_truncated
+        )?;
+    }
+    if stats.files_scanned_extractor > 0 {
+        writeln!(
+            out,
+            "- Files scanned for content (extractor): **{}**",
+            stats.files_scanned_extractor
+        )?;
+    }
+    if stats.files_transcoded_utf16 > 0 {
+        writeln!(
+            out,
+            "- Files transcoded from UTF-16: **{}**",
+            stats.files_transcoded_utf16
+        )?;
+    }
+    if stats.files_changed_during_scan > 0 {
+        writeln!(
+            out,
+            "- Files changed during scan (reported with a warning): **{}**",
+            stats.files_changed_during_scan
+        )?;
+    }
+    if stats.files_panicked > 0 {
+        writeln!(
+            out,
+            "- **Warning: {} files crashed the scanner (see Crashes)**",
+            stats.files_panicked
+        )?;
+    }
+    if !args.require_script.is_empty() {
+        writeln!(
+            out,
+            "- Matches rejected by script filter: **{}**",
+            stats.matches_rejected_by_script
+        )?;
+    }
+    if stats.long_lines_skipped > 0 {
+        writeln!(
+            out,
+            "- Long lines skipped (over {} chars): **{}**",
+            args.skip_long_lines.unwrap_or_default(),
+            stats.long_lines_skipped
+        )?;
+    }
+    if stats.files_streamed > 0 {
+        writeln!(
+            out,
+            "- Files streamed (over memory budget): **{}**",
+            stats.files_streamed
+        )?;
+    }
+    writeln!(
+        out,
+        "- Peak buffered memory: **{}** (limit {})",
+        memory::format_size(stats.memory_peak_bytes),
+        memory::format_size(args.memory_limit)
+    )?;
+    match args.throttle {
+        Some(mb) => writeln!(
+            out,
+            "- Read: **{}** at **{}/s** (throttled to {mb} MB/s)",
+            memory::format_size(stats.bytes_read),
+            memory::format_size(stats.read_bytes_per_sec)
+        )?,
+        None => writeln!(
+            out,
+            "- Read: **{}** at **{}/s**",
+            memory::format_size(stats.bytes_read),
+            memory::format_size(stats.read_bytes_per_sec)
+        )?,
+    }
+    writeln!(
+        out,
+        "- PDF text extracted: **{}**, skipped over --max-bytes: **{}**",
+        memory::format_size(stats.bytes_extracted_pdf),
+        memory::format_size(stats.bytes_skipped_too_large)
+    )?;
+    if let Some(ix) = &stats.index {
+        writeln!(
+            out,
+            "- Index `{}`: **{:.1}%** hit rate ({} fresh, {} stale, {} not indexed), **{}** files ruled out without reading",
+            ix.dir,
+            ix.hit_rate * 100.0,
+            ix.fresh,
+            ix.stale,
+            ix.not_indexed,
+            ix.ruled_out
+        )?;
+    }
+    for reason in SkipReason::ALL {
+        writeln!(
+            out,
+            "- Skipped ({}): **{}**",
+            reason.label(),
+            stats.skips.get(reason.key()).copied().unwrap_or_default()
+        )?;
+        if reason == SkipReason::Vanished {
+            for p in &stats.vanished_paths {
+                writeln!(out, "  - `{p}`")?;
+            }
+        }
+    }
+    writeln!(out, "- Matches total: **{}**", stats.matches_total)?;
+    if stats.results_overflowed {
+        writeln!(
+            out,
+            "- **Warning: result collection stopped at --max-results = {}; narrow the query**",
+            args.max_results.unwrap_or_default()
+        )?;
+    }
+    if let Some(s) = &stats.sample {
+        writeln!(
+            out,
+            "- Matches printed: **{}** (uniform sample of {}, seed = {}; rerun with --seed {} to reproduce)",
+            stats.matches_printed, s.population, s.seed, s.seed
+        )?;
+    } else if let Some(l) = args.limit.filter(|&l| l > 0) {
+        writeln!(
+            out,
+            "- Matches printed: **{}** (limit = {})",
+            stats.matches_printed, l
+        )?;
+    } else {
+        writeln!(out, "- Matches printed: **{}**", stats.matches_printed)?;
+    }
+    if let Some(n) = args.limit_per_dir.filter(|&n| n > 0) {
+        let suppressed: usize = suppressed_per_dir.iter().map(|d| d.suppressed).sum();
+        writeln!(
+            out,
+            "- Suppressed by per-directory limit: **{}** (limit per dir = {})",
+            suppressed, n
+        )?;
+    }
+    writeln!(
+        out,
+        "- Elapsed: **{} ms** (discovery {} ms, scan {} ms)",
+        stats.elapsed_ms, stats.discovery_ms, stats.scan_ms
+    )?;
+    writeln!(out)?;
+
+    if let Some(v) = &stats.skip_verification {
+        writeln!(out, "## Skip verification\n")?;
+        writeln!(
+            out,
+            "- Sampled **{}** of {} files skipped as non-text (seed = {})",
+            v.sampled, v.population, v.seed
+        )?;
+        writeln!(out, "- Readable as text: **{}**", v.decodable)?;
+        writeln!(out, "- Would have matched: **{}**", v.would_match)?;
+        for p in &v.matched_paths {
+            writeln!(out, "  - `{p}`")?;
+        }
+        writeln!(out)?;
+    }
+    if !stats.panics.is_empty() {
+        writeln!(out, "## Crashes\n")?;
+        for p in &stats.panics {
+            writeln!(out, "- **Warning:** `{}` panicked: `{}`", p.path, p.message)?;
+        }
+        if stats.files_panicked > stats.panics.len() {
+            writeln!(
+                out,
+                "- … and {} more",
+                stats.files_panicked - stats.panics.len()
+            )?;
+        }
+        writeln!(
+            out,
+            "\n_Rerun with --no-catch and RUST_BACKTRACE=1 for a backtrace._\n"
+        )?;
+    }
+
+    if let Some(dirs) = directories {
+        writeln!(out, "## Directories\n")?;
+        writeln!(out, "| Directory | Files | Occurrences | Bytes |")?;
+        writeln!(out, "|---|---:|---:|---:|")?;
+        for d in dirs {
+            writeln!(
+                out,
+                "| `{}` | {} | {} | {} |",
+                d.dir,
+                d.files,
+                d.occurrences,
+                memory::format_size(d.bytes)
+            )?;
+        }
+    }
+
+    if let Some(queries) = queries {
+        writeln!(out, "## Queries\n")?;
+        writeln!(out, "| Query | Files | Matches | Reports |")?;
+        writeln!(out, "|---|---:|---:|---|")?;
+        for q in queries {
+            writeln!(
+                out,
+                "| `{}` | {} | {} | {} |",
+                q.label,
+                q.files,
+                q.matches,
+                q.reports
+                    .iter()
+                    .map(|r| format!("`{r}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        writeln!(out)?;
+    }
+
+    if let Some(c) = comparison {
+        let [a, b] = &c.presets;
+        writeln!(out, "## Preset comparison\n")?;
+        writeln!(out, "| Files | Count |")?;
+        writeln!(out, "|---|---:|")?;
+        writeln!(out, "| Only `{a}` | {} |", c.only_first.len())?;
+        writeln!(out, "| Only `{b}` | {} |", c.only_second.len())?;
+        writeln!(out, "| Both | {} |", c.common.len())?;
+        writeln!(out)?;
+        let lists = [
+            (format!("Only `{a}`"), &c.only_first),
+            (format!("Only `{b}`"), &c.only_second),
+            ("Both".to_string(), &c.common),
+        ];
+        for (title, paths) in lists.iter().filter(|(_, p)| !p.is_empty()) {
+            writeln!(out, "### {title} ({} files)\n", paths.len())?;
+            for path in paths.iter() {
+                writeln!(out, "- `{path}`")?;
+            }
+            writeln!(out)?;
+        }
+    }
+
+    if let Some(pairs) = cooccurrence {
+        writeln!(out, "## Co-occurrence\n")?;
+        writeln!(out, "| Patterns | Both | Only first | Only second |")?;
+        writeln!(out, "|---|---:|---:|---:|")?;
+        for p in pairs {
+            writeln!(
+                out,
+                "| `{}` + `{}` | {} | {} | {} |",
+                p.patterns[0], p.patterns[1], p.count, p.only[0], p.only[1]
+            )?;
+        }
+        writeln!(out)?;
+        for p in pairs.iter().filter(|p| p.count > 0) {
+            writeln!(
+                out,
+                "### `{}` + `{}` ({} files)\n",
+                p.patterns[0], p.patterns[1], p.count
+            )?;
+            for path in &p.paths {
+                writeln!(out, "- `{path}`")?;
+            }
+            writeln!(out)?;
+        }
+    }
+
+    let mut order: Vec<usize> = (0..results.len()).collect();
+    order.sort_by_key(|&i| {
+        (
+            results[i].deprioritized,
+            !matches!(results[i].provenance.as_str(), "" | provenance::FIRST_PARTY),
+        )
+    });
+    let mut last_in_dir: HashMap<String, usize> = HashMap::new();
+    let mut shown_in_file: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (i, r) in order.iter().map(|&i| (i, &results[i])) {
+        last_in_dir.insert(parent_dir_of(&r.path), i);
+        let shown = shown_in_file.entry(&r.path).or_default();
+        *shown = (i, shown.1 + 1);
+    }
+
+    let mut groups: Vec<(Option<&String>, bool)> = results
+        .iter()
+        .map(|r| (r.rule.as_ref(), r.matched_content))
+        .collect();
+    groups.sort();
+    groups.dedup();
+    let grouped = groups.iter().any(|(rule, _)| rule.is_some());
+    if let Some(d) = diagnosis {
+        print_diagnosis(out, d)?;
+    } else if !grouped && directories.is_none() {
+        writeln!(out, "## Matches\n")?;
+    }
+
+    for group in groups {
+        if grouped {
+            match group {
+                (Some(rule), _) => writeln!(
+                    out,
+                    "## Rule `{}` ({} matches)\n",
+                    rule,
+                    stats.rules.get(rule).copied().unwrap_or_default()
+                )?,
+                (None, true) => writeln!(out, "## Content matches\n")?,
+                (None, false) => writeln!(out, "## Name matches\n")?,
+            }
+        }
+        for (i, r) in order.iter().map(|&i| (i, &results[i])) {
+            if (r.rule.as_ref(), r.matched_content) != group {
+                continue;
+            }
+            print_markdown_result(out, r, layout)?;
+            if let Some(&(last, shown)) = shown_in_file.get(r.path.as_str()) {
+                let more = r.occurrences.unwrap_or(shown).saturating_sub(shown);
+                if last == i && r.matches_truncated && more > 0 {
+                    writeln!(out, "_… and {more} more matches in this file_\n")?;
+                }
+            }
+
+            let dir = parent_dir_of(&r.path);
+            if last_in_dir.get(&dir) == Some(&i) {
+                if let Some(d) = suppressed_per_dir.iter().find(|d| d.dir == dir) {
+                    writeln!(out, "_… and {} more in this directory_\n", d.suppressed)?;
+                }
+            }
+        }
+    }
+
+    if let Some(t) = &stats.truncation {
+        writeln!(
+            out,
+            "_{} more matches not shown ({} content, {} name-only) — rerun with --limit 0 or narrow the search_",
+            stats.matches_truncated, t.content, t.name_only
+        )?;
+        for d in &t.top_dirs {
+            writeln!(out, "- `{}`: {} not shown", d.dir, d.dropped)?;
+        }
+    }
+
+    if let Some(h) = hotspots.as_ref().filter(|h| !h.dirs.is_empty()) {
+        writeln!(out, "\n## Hotspots\n")?;
+        writeln!(out, "Top directories:")?;
+        for d in &h.dirs {
+            writeln!(out, "- `{}`: {} ({:.1}%)", d.key, d.matches, d.percent)?;
+        }
+        writeln!(out, "\nTop extensions:")?;
+        for e in &h.extensions {
+            writeln!(out, "- `{}`: {} ({:.1}%)", e.key, e.matches, e.percent)?;
+        }
+    }
+
+    if let Some(m) = meta {
+        writeln!(out, "\n## Provenance\n")?;
+        writeln!(out, "- Generated at: `{}`", m.generated_at)?;
+        if let Some(h) = &m.hostname {
+            writeln!(out, "- Host: `{h}`")?;
+        }
+        if let Some(d) = &m.cwd {
+            writeln!(out, "- Working dir: `{d}`")?;
+        }
+        if let Some(c) = &m.config {
+            writeln!(out, "- Config: `{c}`")?;
+        }
+        for r in &m.path_rewrites {
+            writeln!(out, "- Paths rewritten: `{r}`")?;
+        }
+        for g in &m.dir_globs {
+            if g.roots.is_empty() {
+                writeln!(out, "- Dir glob `{}`: *(no match)*", g.pattern)?;
+            } else {
+                writeln!(
+                    out,
+                    "- Dir glob `{}`: `{}`",
+                    g.pattern,
+                    g.roots.join("`, `")
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_diagnosis(out: &mut impl Write, d: &diagnose::Diagnosis) -> io::Result<()> {
+    writeln!(out, "## No matches: diagnosis\n")?;
+    for s in &d.stages {
+        writeln!(out, "- {}: **{}**", s.label, diagnose::grouped(s.files))?;
+    }
+    if !d.top_skips.is_empty() {
+        let skips: Vec<String> = d
+            .top_skips
+            .iter()
+            .map(|s| format!("{} **{}**", s.label, diagnose::grouped(s.files)))
+            .collect();
+        writeln!(out, "- Top skip reasons: {}", skips.join(", "))?;
+    }
+    if !d.hints.is_empty() {
+        writeln!(out)?;
+        for h in &d.hints {
+            writeln!(out, "> {h}")?;
+        }
+    }
+    Ok(())
+}
+
+fn print_markdown_result(
+    out: &mut impl Write,
+    r: &MatchResult,
+    layout: &MarkdownLayout,
+) -> io::Result<()> {
+    let path = match layout.width {
+        Some(w) if !layout.full_paths => shorten_path(&r.path, w.saturating_sub(6)),
+        _ => Cow::Borrowed(r.path.as_str()),
+    };
+    let mut tags = Vec::new();
+    if !r.provenance.is_empty() && r.provenance != provenance::FIRST_PARTY {
+        tags.push(r.provenance.as_str());
+    }
+    if r.deprioritized {
+        tags.push("deprioritized");
+    }
+    let tags = tags.join(", ");
+    match (layout.color, !tags.is_empty()) {
+        (true, true) => writeln!(out, "### `\x1b[2m{}\x1b[0m` _({})_", path, tags)?,
+        (true, false) => writeln!(out, "### `\x1b[1;36m{}\x1b[0m`", path)?,
+        (false, true) => writeln!(out, "### `{}` _({})_", path, tags)?,
+        (false, false) => writeln!(out, "### `{}`", path)?,
+    }
+    if let Some(style) = layout.show_meta {
+        let real = r.original_path.as_deref().unwrap_or(&r.path);
+        if let Ok(meta) = fs::metadata(io_path(Path::new(real))) {
+            let modified = meta.modified().ok().map(|t| {
+                if style == "absolute" {
+                    format!("modified {}", rfc3339_utc(t))
+                } else {
+                    format!("modified {}", relative_age(t, SystemTime::now()))
+                }
+            });
+            writeln!(
+                out,
+                "- file: {}{}",
+                memory::format_size(meta.len()),
+                modified.map(|m| format!(", {m}")).unwrap_or_default()
+            )?;
+        }
+    }
+    if !r.kind.is_empty() {
+        writeln!(out, "- kind: `{}`", r.kind)?;
+    }
+    if let Some(lang) = &r.language {
+        writeln!(out, "- language: `{lang}`")?;
+    }
+    if let Some(phase) = r.phase {
+        writeln!(out, "- phase: `{phase}`")?;
+    }
+    if !r.also_at.is_empty() {
+        writeln!(out, "- also at: `{}`", r.also_at.join("`, `"))?;
+    }
+    if let Some(to) = &r.copied_to {
+        writeln!(out, "- copied to: `{to}`")?;
+    }
+    if let Some(group) = &r.duplicates {
+        let shown = &group.paths[..group.paths.len().min(DUPLICATE_PATHS_SHOWN)];
+        let more = group.paths.len() - shown.len();
+        writeln!(
+            out,
+            "- duplicates: **{}** results in `{}`{}",
+            group.count,
+            shown.join("`, `"),
+            if more > 0 {
+                format!(" and {more} more")
+            } else {
+                String::new()
+            }
+        )?;
+    }
+    writeln!(out, "- matched_name: `{}`", r.matched_name)?;
+    if let Some((start, end)) = r.name_match_range {
+        let name = Path::new(&r.path)
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default();
+        let chars: Vec<char> = name.chars().collect();
+        if end <= chars.len() && start < end {
+            let part = |a: usize, b: usize| chars[a..b].iter().collect::<String>();
+            writeln!(
+                out,
+                "- name: {}**{}**{}",
+                part(0, start),
+                part(start, end),
+                part(end, chars.len())
+            )?;
+        }
+    }
+    writeln!(out, "- matched_content: `{}`", r.matched_content)?;
+    if r.rules.len() > 1 {
+        writeln!(out, "- rules: `{}`", r.rules.join("`, `"))?;
+    }
+    if let Some(t) = &r.doc_title {
+        writeln!(out, "- title: `{}`", t)?;
+    }
+    if let Some(a) = &r.doc_author {
+        writeln!(out, "- author: `{}`", a)?;
+    }
+    if let Some(d) = &r.doc_created {
+        writeln!(out, "- created: `{}`", d)?;
+    }
+    if let Some(m) = &r.mime {
+        writeln!(out, "- mime: `{}`", m)?;
+    }
+    if let Some(t) = &r.symlink_target {
+        if r.target_exists == Some(false) {
+            writeln!(out, "- target: `{}` (missing)", t)?;
+        } else {
+            writeln!(out, "- target: `{}`", t)?;
+        }
+    }
+    if let Some(c) = &r.context {
+        writeln!(out, "- context: `{}`", c)?;
+    }
+    for w in &r.warnings {
+        writeln!(out, "- warning: `{}`", w)?;
+    }
+    if let Some(s) = r
+        .snippet
+        .as_ref()
+        .filter(|_| r.snippet_kind == SnippetKind::Hex)
+    {
+        writeln!(out, "- snippet (hex):\n\n```text\n{}\n```", s)?;
+    } else if let Some((first, lines)) = layout.context_lines.and_then(|n| context_block(r, n)) {
+        let fence = if lines.iter().any(|l| l.contains("```")) {
+            "````"
+        } else {
+            "```"
+        };
+        let range = match lines.len() {
+            1 => format!("line {first}"),
+            n => format!("lines {first}-{}", first + n - 1),
+        };
+        writeln!(
+            out,
+            "- {range}:\n\n{fence}{}",
+            r.syntax.as_deref().unwrap_or_default()
+        )?;
+        for line in &lines {
+            writeln!(out, "{line}")?;
+        }
+        writeln!(out, "{fence}")?;
+    } else if let Some(s) = &r.snippet {
+        let label = match (&r.notebook_cell, &r.email) {
+            (Some(c), _) => format!("cell {} [{}]", c.index, c.cell_type),
+            (
+                None,
+                Some(EmailPart {
+                    message,
+                    header: Some(h),
+                    ..
+                }),
+            ) => {
+                format!("message {message} header {h}")
+            }
+            (None, Some(e)) => format!("message {} {}", e.message, e.part),
+            (None, None) => "snippet".to_string(),
+        };
+        let (cut, shown, lead, trail) = match layout.width {
+            Some(w) => fit_snippet(
+                s,
+                r.offsets.as_ref(),
+                w.saturating_sub(label.chars().count() + 6),
+            ),
+            None => (0, s.as_str(), false, false),
+        };
+        let lead = if lead { "…" } else { "" };
+        let trail = if trail { "…" } else { "" };
+        if layout.color {
+            let offsets = r.offsets.as_ref().map(|o| MatchOffsets {
+                snippet_start: o.snippet_start + cut,
+                ..o.clone()
+            });
+            writeln!(
+                out,
+                "- {}: `{}{}{}`",
+                label,
+                lead,
+                highlight_snippet(shown, offsets.as_ref()),
+                trail
+            )?;
+        } else {
+            writeln!(out, "- {}: `{}{}{}`", label, lead, shown, trail)?;
+        }
+    }
+    writeln!(out)
+}
+
+const MIN_LAYOUT_WIDTH: usize = 40;
+const COLOR_MODES: [&str; 3] = ["auto", "always", "never"];
+
+struct MarkdownLayout<'a> {
+    color: bool,
+    width: Option<usize>,
+    full_paths: bool,
+    show_meta: Option<&'a str>,
+    context_lines: Option<usize>,
+}
+
+fn terminal_width() -> Option<usize> {
+    if let Some((Width(cols), _)) = terminal_size_of(io::stdout()) {
+        if cols > 0 {
+            return Some(cols as usize);
+        }
+    }
+    std::env::var("COLUMNS").ok()?.parse().ok()
+}
+
+fn fit_snippet<'a>(
+    s: &'a str,
+    offsets: Option<&MatchOffsets>,
+    max_chars: usize,
+) -> (usize, &'a str, bool, bool) {
+    let total = s.chars().count();
+    if total <= max_chars {
+        return (0, s, false, false);
+    }
+
+    let char_pos = |b: usize| s[..clamp_to_char_boundary(s, b)].chars().count();
+    let byte_pos = |c: usize| s.char_indices().nth(c).map_or(s.len(), |(i, _)| i);
+    let (focus_start, focus_end) = offsets
+        .map(|o| {
+            (
+                char_pos(o.match_start.saturating_sub(o.snippet_start)),
+                char_pos(o.match_end.saturating_sub(o.snippet_start)),
+            )
+        })
+        .unwrap_or_default();
+
+    let mut budget = max_chars.saturating_sub(1);
+    let mut from = 0;
+    if focus_end > budget {
+        budget = max_chars.saturating_sub(2);
+        from = focus_start
+            .saturating_sub(budget / 3)
+            .min(total.saturating_sub(budget));
+    }
+    let to = (from + budget).min(total);
+    let (start, end) = (byte_pos(from), byte_pos(to));
+    (start, &s[start..end], from > 0, to < total)
+}
+
+fn shorten_path(path: &str, max_chars: usize) -> Cow<'_, str> {
+    if path.chars().count() <= max_chars {
+        return Cow::Borrowed(path);
+    }
+
+    let mut parts = path.rsplit(['/', '\\']);
+    let file = parts.next().unwrap_or(path);
+    let mut tail = file.to_string();
+    if tail.chars().count() + 2 > max_chars {
+        let keep = max_chars.saturating_sub(1);
+        let skip = tail.chars().count().saturating_sub(keep);
+        return Cow::Owned(format!("…{}", tail.chars().skip(skip).collect::<String>()));
     }
+    for part in parts {
+        if part.chars().count() + tail.chars().count() + 3 > max_chars {
+            break;
+        }
+        tail = format!("{part}/{tail}");
+    }
+    Cow::Owned(format!("…/{tail}"))
 }
 
-fn some_if_name_only(
-    path: &Path,
-    name_query: Option<&str>,
-    matched_name: bool,
-) -> Option<MatchResult> {
-    let any = name_query.is_some() && matched_name;
+fn highlight_snippet(snippet: &str, offsets: Option<&MatchOffsets>) -> String {
+    let Some(o) = offsets else {
+        return snippet.to_string();
+    };
+    let start = clamp_to_char_boundary(snippet, o.match_start.saturating_sub(o.snippet_start));
+    let end = clamp_to_char_boundary(snippet, o.match_end.saturating_sub(o.snippet_start));
+    if start >= end {
+        return snippet.to_string();
+    }
+    format!(
+        "{}\x1b[1;31m{}\x1b[0m{}",
+        &snippet[..start],
+        &snippet[start..end],
+        &snippet[end..]
+    )
+}
 
-    if any {
-        Some(MatchResult {
-            path: path.to_string_lossy().to_string(),
-            matched_name,
-            matched_content: false,
-            snippet: None,
-        })
-    } else {
-        None
+#[cfg(windows)]
+const WINDOWS_MAX_PATH: usize = 260;
+
+#[cfg(windows)]
+fn io_path(path: &Path) -> Cow<'_, Path> {
+    let raw = path.as_os_str().to_string_lossy();
+    if raw.len() < WINDOWS_MAX_PATH || raw.starts_with(r"\\?\") {
+        return Cow::Borrowed(path);
+    }
+    let Ok(abs) = std::path::absolute(path) else {
+        return Cow::Borrowed(path);
+    };
+    let abs = abs.to_string_lossy().to_string();
+    let prefixed = match abs.strip_prefix(r"\\") {
+        Some(unc) => format!(r"\\?\UNC\{unc}"),
+        None => format!(r"\\?\{abs}"),
+    };
+    Cow::Owned(PathBuf::from(prefixed))
+}
+
+#[cfg(not(windows))]
+fn io_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+#[cfg(windows)]
+fn display_path(path: &Path) -> String {
+    let raw = path.to_string_lossy();
+    let s = match raw.strip_prefix(r"\\?\") {
+        Some(rest) => match rest.strip_prefix(r"UNC\") {
+            Some(unc) => format!(r"\\{unc}"),
+            None => rest.to_string(),
+        },
+        None => raw.to_string(),
+    };
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(d), Some(':')) if d.is_ascii_alphabetic() => {
+            format!("{}{}", d.to_ascii_uppercase(), &s[1..])
+        }
+        _ => s,
     }
 }
 
-fn print_markdown(args: &SearchArgs, stats: &RunStats, results: &[MatchResult]) {
-    println!("# RustFileFinder results\n");
-    println!("- Base dir: `{}`", args.dir.to_string_lossy());
-    if let Some(n) = &args.name {
-        println!("- Name query: `{}`", n);
+#[cfg(not(windows))]
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+struct Artifacts {
+    files: Vec<PathBuf>,
+    dirs: Vec<PathBuf>,
+}
+
+impl Artifacts {
+    fn contains(&self, path: &Path) -> bool {
+        let name = path.file_name();
+        let by_name = self.files.iter().any(|f| f.file_name() == name)
+            || self.dirs.iter().any(|d| {
+                d.file_name()
+                    .is_some_and(|n| path.components().any(|c| c.as_os_str() == n))
+            });
+        if !by_name {
+            return false;
+        }
+        let Some(real) = resolved_path(path) else {
+            return false;
+        };
+        self.files.contains(&real) || self.dirs.iter().any(|d| real.starts_with(d))
     }
-    if let Some(c) = &args.content {
-        println!("- Content regex: `{}`", c);
+}
+
+fn resolved_path(path: &Path) -> Option<PathBuf> {
+    if let Ok(p) = fs::canonicalize(path) {
+        return Some(p);
     }
-    if let Some(ext) = &args.ext {
-        println!("- Extensions: `{}`", ext);
-    } else if args.content.is_some() {
-        println!("- Extensions: *(default text set for content search)*");
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    Some(fs::canonicalize(parent).ok()?.join(path.file_name()?))
+}
+
+fn report_artifacts(args: &SearchArgs) -> Artifacts {
+    let mut files: Vec<PathBuf> = [&args.output, &args.metrics_out]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    if let Some(cp) = &args.checkpoint {
+        let mut tmp = cp.as_os_str().to_owned();
+        tmp.push(".tmp");
+        files.push(cp.clone());
+        files.push(PathBuf::from(tmp));
     }
-    if args.include_pdf {
-        println!("- PDF content search: `enabled`");
-    } else {
-        println!("- PDF content search: `disabled`");
+    files.extend(last_run_file().filter(|_| args.changed_since.is_some()));
+    let dirs = [&args.output_dir, &args.use_index, &args.copy_matches]
+        .into_iter()
+        .flatten();
+    Artifacts {
+        files: files.iter().filter_map(|f| resolved_path(f)).collect(),
+        dirs: dirs.filter_map(|d| fs::canonicalize(d).ok()).collect(),
     }
+}
 
-    println!();
-    println!("## Run statistics");
-    println!("- Files discovered: **{}**", stats.files_discovered);
-    println!(
-        "- Files scanned for content (text): **{}**",
-        stats.files_scanned_text
-    );
-    println!(
-        "- Files scanned for content (pdf): **{}**",
-        stats.files_scanned_pdf
-    );
-    println!("- Skipped (non-text): **{}**", stats.files_skipped_non_text);
-    println!(
-        "- Skipped (too large): **{}**",
-        stats.files_skipped_too_large
-    );
-    println!("- Skipped (non-UTF8): **{}**", stats.files_skipped_non_utf8);
-    println!(
-        "- Skipped (unreadable text): **{}**",
-        stats.files_skipped_unreadable_text
-    );
-    println!(
-        "- Skipped (unreadable pdf): **{}**",
-        stats.files_skipped_unreadable_pdf
-    );
-    println!("- Matches total: **{}**", stats.matches_total);
-    if let Some(l) = args.limit {
-        println!(
-            "- Matches printed: **{}** (limit = {})",
-            stats.matches_printed, l
-        );
+struct Discovery {
+    files: Vec<PathBuf>,
+    snapshots: HashMap<PathBuf, FileSnapshot>,
+    pruned_dirs: Vec<PrunedDir>,
+    truncated: bool,
+    missing: usize,
+    unchanged_files: usize,
+    unchanged_dirs: usize,
+    ignored: usize,
+    excluded_ext: usize,
+    depth_limited: usize,
+    too_long: usize,
+}
+
+fn discover_files(
+    args: &SearchArgs,
+    allowed_ext: Option<&Vec<String>>,
+    snapshot: bool,
+    cutoff: Option<SystemTime>,
+) -> Discovery {
+    let track_sizes = args.max_dir_bytes.is_some() || args.max_total_bytes.is_some();
+    let mut files = Vec::new();
+    let mut snapshots = HashMap::new();
+    let mut pruned_dirs = Vec::new();
+    let mut total: u64 = 0;
+    let mut unchanged_files = 0;
+    let mut unchanged_dirs = 0;
+    let mut ignored = 0;
+    let mut excluded_ext = 0;
+    let mut depth_limited = 0;
+    let mut too_long = 0;
+    let mut entries = 0usize;
+    let mut ignores = Ignores::new(!args.no_ignore_global, !args.no_ignore_vcs);
+    let unchanged = |path: &Path| {
+        cutoff.is_some_and(|c| fs::metadata(io_path(path)).is_ok_and(|m| changed_at(&m) < c))
+    };
+
+    for root in &args.dir {
+        let mut dir_bytes: HashMap<PathBuf, u64> = HashMap::new();
+        let mut pruned: HashSet<PathBuf> = HashSet::new();
+        ignores.load(root);
+
+        let mut walker = WalkDir::new(root).follow_links(args.follow_links);
+        match args.walk_order.as_str() {
+            "bfs" => {
+                walker = walker.sort_by_key(|e| (e.file_type().is_dir(), e.file_name().to_owned()))
+            }
+            "mtime" => {
+                walker = walker.sort_by_key(|e| {
+                    let modified = e.metadata().ok().and_then(|m| m.modified().ok());
+                    (std::cmp::Reverse(modified), e.file_name().to_owned())
+                })
+            }
+            _ => {}
+        }
+        let mut walker = walker
+            .into_iter()
+            .filter_entry(|e| !is_ignored_dir(e.path(), &args.ignore_dir));
+        while let Some(entry) = walker.next() {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) => {
+                    let broken_link = e
+                        .path()
+                        .filter(|p| symlink_target(p).is_some_and(|(_, exists)| !exists));
+                    if let Some(p) = broken_link {
+                        if ext_allowed(p, allowed_ext, args.include_no_ext) {
+                            files.push(p.to_path_buf());
+                        }
+                    }
+                    continue;
+                }
+            };
+            entries += 1;
+            if args.max_entries.is_some_and(|cap| entries > cap) {
+                eprintln!(
+                    "Error: discovery walked more than --max-entries {} files and directories \
+                     under {}; narrow the search with --dir, --ext, --ignore-dir or an ignore file",
+                    args.max_entries.unwrap_or_default(),
+                    root.display()
+                );
+                std::process::exit(2);
+            }
+            let is_dir = entry.file_type().is_dir();
+            if entry.path().as_os_str().len() > args.max_path_len {
+                too_long += 1;
+                if args.verbose {
+                    eprintln!(
+                        "[path] skipped {} byte path under {}",
+                        entry.path().as_os_str().len(),
+                        root.display()
+                    );
+                }
+                if is_dir {
+                    walker.skip_current_dir();
+                }
+                continue;
+            }
+            let top = (entry.depth() >= 2 || (entry.depth() == 1 && is_dir))
+                .then(|| entry.path().strip_prefix(root).ok()?.components().next())
+                .flatten()
+                .map(|c| root.join(c));
+
+            if top.as_ref().is_some_and(|t| pruned.contains(t)) {
+                if is_dir {
+                    walker.skip_current_dir();
+                }
+                continue;
+            }
+            if entry.depth() > 0 && ignores.ignored(root, entry.path(), is_dir) {
+                ignored += 1;
+                if args.verbose {
+                    eprintln!("[ignore] skipped {}", entry.path().display());
+                }
+                if is_dir {
+                    walker.skip_current_dir();
+                }
+                continue;
+            }
+            if is_dir && entry.depth() > args.max_depth {
+                depth_limited += 1;
+                if args.verbose {
+                    eprintln!("[depth] not descending into {}", entry.path().display());
+                }
+                walker.skip_current_dir();
+                continue;
+            }
+            if is_dir && entry.depth() > 0 {
+                ignores.load(entry.path());
+            }
+            if is_dir && entry.depth() > 0 && args.trust_dir_mtime && unchanged(entry.path()) {
+                unchanged_dirs += 1;
+                walker.skip_current_dir();
+                continue;
+            }
+            let is_file = entry.file_type().is_file()
+                || (entry.path_is_symlink()
+                    && fs::metadata(entry.path()).map_or(true, |m| m.is_file()));
+            if !is_file {
+                continue;
+            }
+            if !ext_allowed(entry.path(), allowed_ext, args.include_no_ext) {
+                excluded_ext += 1;
+                continue;
+            }
+            if unchanged(entry.path()) {
+                unchanged_files += 1;
+                continue;
+            }
+
+            let meta = if track_sizes || snapshot {
+                entry.metadata().ok()
+            } else {
+                None
+            };
+            let size = meta.as_ref().map_or(0, |m| m.len());
+
+            if let (Some(limit), Some(top)) = (args.max_dir_bytes, top) {
+                let used = dir_bytes.entry(top.clone()).or_insert(0);
+                *used += size;
+                if *used > limit {
+                    total -= *used - size;
+                    files.retain(|f: &PathBuf| !f.starts_with(&top));
+                    pruned_dirs.push(PrunedDir {
+                        dir: display_path(&top),
+                        bytes: *used,
+                    });
+                    pruned.insert(top);
+                    continue;
+                }
+            }
+
+            if args.max_total_bytes.is_some_and(|cap| total + size > cap) {
+                if args.walk_order == "bfs" {
+                    files.sort_by_key(|f| f.components().count());
+                }
+                return Discovery {
+                    files,
+                    snapshots,
+                    pruned_dirs,
+                    truncated: true,
+                    missing: 0,
+                    unchanged_files,
+                    unchanged_dirs,
+                    ignored,
+                    excluded_ext,
+                    depth_limited,
+                    too_long,
+                };
+            }
+            total += size;
+            if let Some(m) = meta.filter(|_| snapshot) {
+                snapshots.insert(entry.path().to_path_buf(), FileSnapshot::of(&m));
+            }
+            files.push(entry.into_path());
+        }
+    }
+
+    if args.walk_order == "bfs" {
+        files.sort_by_key(|f| f.components().count());
+    }
+    Discovery {
+        files,
+        snapshots,
+        pruned_dirs,
+        truncated: false,
+        missing: 0,
+        unchanged_files,
+        unchanged_dirs,
+        ignored,
+        excluded_ext,
+        depth_limited,
+        too_long,
+    }
+}
+
+fn within_files(
+    report: &Path,
+    allowed_ext: Option<&Vec<String>>,
+    include_no_ext: bool,
+    snapshot: bool,
+) -> Result<Discovery, String> {
+    let data = compress::read(report)
+        .map_err(|e| format!("cannot read --within report {}: {e}", report.display()))?;
+    let value: serde_json::Value = serde_json::from_slice(&data).map_err(|e| {
+        format!(
+            "--within report {} is not valid JSON: {e}",
+            report.display()
+        )
+    })?;
+    let Some(results) = value.get("results").and_then(serde_json::Value::as_array) else {
+        return Err(format!(
+            "--within report {} has no `results` list; pass a report written with --format json",
+            report.display()
+        ));
+    };
+
+    let paths: BTreeSet<&str> = results
+        .iter()
+        .filter_map(|r| r.get("original_path").or_else(|| r.get("path"))?.as_str())
+        .collect();
+    let mut discovery = Discovery {
+        files: Vec::new(),
+        snapshots: HashMap::new(),
+        pruned_dirs: Vec::new(),
+        truncated: false,
+        missing: 0,
+        unchanged_files: 0,
+        unchanged_dirs: 0,
+        ignored: 0,
+        excluded_ext: 0,
+        depth_limited: 0,
+        too_long: 0,
+    };
+    for path in paths.into_iter().map(PathBuf::from) {
+        let Ok(meta) = fs::metadata(io_path(&path)) else {
+            discovery.missing += 1;
+            continue;
+        };
+        if !meta.is_file() {
+            continue;
+        }
+        if !ext_allowed(&path, allowed_ext, include_no_ext) {
+            discovery.excluded_ext += 1;
+            continue;
+        }
+        if snapshot {
+            discovery
+                .snapshots
+                .insert(path.clone(), FileSnapshot::of(&meta));
+        }
+        discovery.files.push(path);
+    }
+    Ok(discovery)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileSnapshot {
+    len: u64,
+    modified: Option<std::time::SystemTime>,
+}
+
+impl FileSnapshot {
+    fn of(meta: &fs::Metadata) -> Self {
+        FileSnapshot {
+            len: meta.len(),
+            modified: meta.modified().ok(),
+        }
+    }
+}
+
+const MIME_SNIFF_BYTES: u64 = 8192;
+
+fn sniff_mime(path: &Path) -> String {
+    let mut head = Vec::new();
+    let read =
+        fs::File::open(io_path(path)).and_then(|f| f.take(MIME_SNIFF_BYTES).read_to_end(&mut head));
+    if read.is_err() {
+        return "application/octet-stream".to_string();
+    }
+    if let Some(kind) = infer::get(&head) {
+        return kind.mime_type().to_string();
+    }
+    if !head.contains(&0) || decode_text(&head).is_some() {
+        "text/plain".to_string()
     } else {
-        println!("- Matches printed: **{}**", stats.matches_printed);
+        "application/octet-stream".to_string()
     }
-    println!("- Elapsed: **{} ms**", stats.elapsed_ms);
-    println!();
+}
 
-    println!("## Matches\n");
-    for r in results {
-        println!("### `{}`", r.path);
-        println!("- matched_name: `{}`", r.matched_name);
-        println!("- matched_content: `{}`", r.matched_content);
-        if let Some(s) = &r.snippet {
-            println!("- snippet: `{}`", s);
+fn mime_matches(mime: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    match pattern.split_once('/') {
+        Some(("*", "*")) => true,
+        Some((kind, "*")) => mime.split('/').next() == Some(kind),
+        _ => mime == pattern,
+    }
+}
+
+fn mime_allowed(mime: &str, include: &[String], exclude: &[String]) -> bool {
+    (include.is_empty() || include.iter().any(|p| mime_matches(mime, p)))
+        && !exclude.iter().any(|p| mime_matches(mime, p))
+}
+
+fn symlink_target(path: &Path) -> Option<(String, bool)> {
+    let meta = fs::symlink_metadata(io_path(path)).ok()?;
+    if !meta.file_type().is_symlink() {
+        return None;
+    }
+    let target = fs::read_link(io_path(path)).ok()?;
+    let exists = fs::metadata(io_path(path)).is_ok();
+    Some((target.to_string_lossy().to_string(), exists))
+}
+
+fn explain_file(args: &SearchArgs, path: &Path) -> String {
+    let target = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let root = args
+        .dir
+        .iter()
+        .filter_map(|d| std::path::absolute(d).ok())
+        .find(|d| target.starts_with(d));
+    let Some(root) = root else {
+        return "not under any --dir".to_string();
+    };
+
+    let mut ignores = Ignores::new(!args.no_ignore_global, !args.no_ignore_vcs);
+    ignores.load(&root);
+    let chain: Vec<&Path> = target
+        .ancestors()
+        .take_while(|a| *a != root.as_path())
+        .collect();
+    for p in chain.into_iter().rev() {
+        let is_dir = p != target || fs::metadata(p).is_ok_and(|m| m.is_dir());
+        let via = if p == target {
+            String::new()
+        } else {
+            format!(" (directory {})", p.display())
+        };
+        if is_dir && is_ignored_dir(p, &args.ignore_dir) {
+            return format!("skipped by --ignore-dir or a built-in directory exclude{via}");
+        }
+        match ignores.decide(&root, p, is_dir) {
+            Some(rule) if !rule.negated => {
+                return format!(
+                    "ignored by {}:{} `{}`{via}",
+                    rule.file.display(),
+                    rule.line,
+                    rule.text
+                );
+            }
+            Some(rule) if p == target => {
+                return format!(
+                    "not ignored; re-included by {}:{} `{}`",
+                    rule.file.display(),
+                    rule.line,
+                    rule.text
+                );
+            }
+            _ => {}
+        }
+        if is_dir {
+            ignores.load(p);
         }
-        println!();
     }
+    "not ignored".to_string()
 }
 
-fn is_ignored_dir(path: &Path) -> bool {
+fn is_ignored_dir(path: &Path, extra: &[String]) -> bool {
     let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
         return false;
     };
-    matches!(name, ".git" | "target" | "node_modules")
+    matches!(name, ".git" | "target" | "node_modules") || extra.iter().any(|d| d == name)
 }
 
-fn is_probably_text(path: &Path) -> bool {
+const NO_EXT_TOKEN: &str = "noext";
+
+const STDIN_PATH: &str = "<stdin>";
+
+const TEXT_FILE_NAMES: &[&str] = &[
+    "makefile",
+    "gnumakefile",
+    "dockerfile",
+    "containerfile",
+    "jenkinsfile",
+    "vagrantfile",
+    "license",
+    "licence",
+    "copying",
+    "readme",
+    "changelog",
+    "authors",
+];
+
+fn is_probably_text(path: &Path, include_no_ext: bool) -> bool {
     let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
-        return false;
+        return include_no_ext
+            || path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| TEXT_FILE_NAMES.contains(&n.to_lowercase().as_str()));
     };
     matches!(
         ext.to_lowercase().as_str(),
@@ -584,6 +9404,13 @@ fn is_probably_text(path: &Path) -> bool {
     )
 }
 
+fn is_notebook(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("ipynb"))
+        .unwrap_or(false)
+}
+
 fn is_pdf(path: &Path) -> bool {
     path.extension()
         .and_then(|e| e.to_str())
@@ -603,47 +9430,371 @@ fn clamp_to_char_boundary(s: &str, mut i: usize) -> usize {
 
 fn snippet_around_match(
     s: &str,
+    base: usize,
     m_start: usize,
     m_end: usize,
     context: usize,
     max_chars: usize,
-) -> String {
-    let start = m_start.saturating_sub(context);
-    let end = (m_end + context).min(s.len());
+    long_line: Option<usize>,
+) -> (usize, String, SnippetKind) {
+    let bytes = s.as_bytes();
+    let crlf = |i: usize| bytes[i] == b'\r' && bytes.get(i + 1) == Some(&b'\n');
+    let (mut start, mut taken) = (m_start.min(s.len()), 0);
+    while start > 0 && taken < context {
+        start -= 1;
+        taken += usize::from(!crlf(start));
+    }
+    let (mut end, mut taken) = (m_end.min(s.len()), 0);
+    while end < s.len() && taken < context {
+        taken += usize::from(!crlf(end));
+        end += 1;
+    }
+
+    let mut start = clamp_to_char_boundary(s, start);
+    let mut end = clamp_to_char_boundary(s, end);
+
+    if let Some(max) = long_line {
+        let line_at = |i: usize| {
+            let from = s[..i].rfind(['\n', '\r']).map_or(0, |p| p + 1);
+            let to = s[i..].find(['\n', '\r']).map_or(s.len(), |p| i + p);
+            let long = to - from > max && s[from..to].chars().count() > max;
+            (from, to, long)
+        };
+        if let (_, to, true) = line_at(start) {
+            if to < m_start {
+                start = to;
+            }
+        }
+        if let (from, _, true) = line_at(end) {
+            if from > m_end {
+                end = from;
+            }
+        }
+    }
+
+    let binary = s[start..end]
+        .chars()
+        .any(|c| matches!(c, '\0' | char::REPLACEMENT_CHARACTER));
+    if binary {
+        let (start, dump) = hex_dump_around(s.as_bytes(), base, m_start);
+        return (start, dump, SnippetKind::Hex);
+    }
+
+    let mut out = String::with_capacity((end - start).min(max_chars.saturating_mul(4)));
+    let mut chars = s[start..end].chars().peekable();
+    let mut taken = 0;
+    while let Some(c) = chars.next().filter(|_| taken < max_chars) {
+        if c == '\r' && chars.peek() == Some(&'\n') {
+            chars.next();
+        }
+        out.push(if matches!(c, '\r' | '\n') { ' ' } else { c });
+        taken += 1;
+    }
+    (start, out, SnippetKind::Text)
+}
+
+const HEX_ROW_BYTES: usize = 16;
+
+fn hex_dump_around(bytes: &[u8], base: usize, at: usize) -> (usize, String) {
+    let row = (base + at) / HEX_ROW_BYTES * HEX_ROW_BYTES;
+    let start = row.saturating_sub(HEX_ROW_BYTES).max(base) - base;
+    let end = (row + 2 * HEX_ROW_BYTES - base).min(bytes.len());
+
+    let mut out = String::new();
+    for (i, chunk) in bytes[start..end].chunks(HEX_ROW_BYTES).enumerate() {
+        let mut hex = String::new();
+        for (j, b) in chunk.iter().enumerate() {
+            if j > 0 && j % 2 == 0 {
+                hex.push(' ');
+            }
+            hex.push_str(&format!("{b:02x}"));
+        }
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!(
+            "{:08x}: {hex:<39}  {ascii}",
+            base + start + i * HEX_ROW_BYTES
+        ));
+    }
+    (start, out)
+}
+
+fn resolve_config_path(cli_path: &Option<PathBuf>) -> Option<PathBuf> {
+    if let Some(p) = cli_path {
+        return Some(p.clone());
+    }
+
+    let local = PathBuf::from("rustfilefinder.toml");
+    if local.exists() {
+        return Some(local);
+    }
+
+    if let Some(dir) = dirs::config_dir() {
+        let p = dir.join("rustfilefinder").join("config.toml");
+        if p.exists() {
+            return Some(p);
+        }
+    }
+
+    None
+}
+
+fn config_extractors(path: &Path, cfg: &AppConfig) -> BTreeMap<String, String> {
+    let Some(extractors) = cfg.extractors.as_ref().filter(|e| !e.is_empty()) else {
+        return BTreeMap::new();
+    };
+    if !owned_by_current_user(path) {
+        eprintln!(
+            "Warning: ignoring [extractors] from {}: the file is not owned by the current user",
+            path.display()
+        );
+        return BTreeMap::new();
+    }
+    extractors
+        .iter()
+        .map(|(ext, cmd)| (ext.trim_start_matches('.').to_lowercase(), cmd.clone()))
+        .collect()
+}
+
+#[cfg(unix)]
+fn owned_by_current_user(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).is_ok_and(|m| m.uid() == rustix::process::geteuid().as_raw())
+}
+
+#[cfg(not(unix))]
+fn owned_by_current_user(_path: &Path) -> bool {
+    true
+}
+
+fn run_extractor(
+    template: &str,
+    path: &Path,
+    max_bytes: u64,
+    deadline: Instant,
+) -> Result<String, String> {
+    let words =
+        shell_words::split(template).map_err(|e| format!("invalid extractor command: {e}"))?;
+    let mut argv = words.iter().map(|arg| {
+        if arg == "{path}" {
+            io_path(path).as_os_str().to_os_string()
+        } else {
+            arg.replace("{path}", &path.to_string_lossy()).into()
+        }
+    });
+    let program = argv.next().ok_or("empty extractor command")?;
+
+    let mut child = Command::new(program)
+        .args(argv)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("cannot start extractor: {e}"))?;
+
+    let mut stdout = child.stdout.take().ok_or("extractor has no stdout")?;
+    let reader = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        (&mut stdout).take(max_bytes).read_to_end(&mut buf)?;
+        io::copy(&mut stdout, &mut io::sink())?;
+        Ok::<_, io::Error>(buf)
+    });
+
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err("extractor timed out".to_string());
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(5)),
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("cannot wait for extractor: {e}"));
+            }
+        }
+    };
+
+    let buf = reader
+        .join()
+        .map_err(|_| "extractor output reader panicked".to_string())?
+        .map_err(|e| format!("cannot read extractor output: {e}"))?;
+    if !status.success() {
+        return Err(format!("extractor exited with {status}"));
+    }
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+const CONFIG_VERSION: u32 = 1;
+
+const CONFIG_RENAMES: &[(&str, &str)] = &[("ignore_dirs", "ignore_dir"), ("excludes", "not_name")];
+
+const CONFIG_LIST_KEYS: &[&str] = &[
+    "ignore_dir",
+    "not_name",
+    "not_name_glob",
+    "mime",
+    "exclude_mime",
+];
+
+fn load_config(path: &Path) -> Option<AppConfig> {
+    let s = std::fs::read_to_string(path).ok()?;
+    let mut value: toml::Value = toml::from_str(&s).ok()?;
+    let version = config_version(&value).unwrap_or_else(|e| {
+        eprintln!("Error: config {}: {e}", path.display());
+        std::process::exit(2);
+    });
+    if version > CONFIG_VERSION {
+        eprintln!(
+            "Error: config {} has version {version}, this build understands up to \
+             {CONFIG_VERSION}; upgrade rustfilefinder",
+            path.display()
+        );
+        std::process::exit(2);
+    }
+    if version < CONFIG_VERSION {
+        eprintln!(
+            "Warning: config {} uses format version {version} (current {CONFIG_VERSION}); \
+             run `rustfilefinder config migrate --path {}` to update it",
+            path.display(),
+            path.display()
+        );
+        migrate_config(&mut value);
+    }
+    value.try_into().ok()
+}
 
-    let start = clamp_to_char_boundary(s, start);
-    let end = clamp_to_char_boundary(s, end);
+fn config_version(value: &toml::Value) -> Result<u32, String> {
+    match value.get("version") {
+        None => Ok(0),
+        Some(v) => v
+            .as_integer()
+            .and_then(|n| u32::try_from(n).ok())
+            .ok_or_else(|| format!("`version` must be a non-negative integer, found {v}")),
+    }
+}
+
+fn migrate_config(value: &mut toml::Value) -> Vec<String> {
+    let mut changes = Vec::new();
+    let Some(root) = value.as_table_mut() else {
+        return changes;
+    };
+    migrate_config_layer(root, "", &mut changes);
+    if let Some(hosts) = root.get_mut("hosts").and_then(toml::Value::as_table_mut) {
+        for (host, layer) in hosts.iter_mut() {
+            if let Some(layer) = layer.as_table_mut() {
+                migrate_config_layer(layer, &format!("hosts.{host}."), &mut changes);
+            }
+        }
+    }
+    root.insert(
+        "version".to_string(),
+        toml::Value::Integer(i64::from(CONFIG_VERSION)),
+    );
+    changes
+}
 
-    let mut out = s[start..end].replace('\n', " ");
-    if out.chars().count() > max_chars {
-        out = out.chars().take(max_chars).collect();
+fn migrate_config_layer(layer: &mut toml::Table, prefix: &str, changes: &mut Vec<String>) {
+    if let Some(t) = layer
+        .get_mut("defaults")
+        .and_then(toml::Value::as_table_mut)
+    {
+        migrate_search_config(t, &format!("[{prefix}defaults]"), changes);
+    }
+    if let Some(presets) = layer.get_mut("presets").and_then(toml::Value::as_table_mut) {
+        for (name, preset) in presets.iter_mut() {
+            if let Some(t) = preset.as_table_mut() {
+                migrate_search_config(t, &format!("[{prefix}presets.{name}]"), changes);
+            }
+        }
     }
-    out
 }
 
-fn resolve_config_path(cli_path: &Option<PathBuf>) -> Option<PathBuf> {
-    if let Some(p) = cli_path {
-        return Some(p.clone());
+fn migrate_search_config(table: &mut toml::Table, at: &str, changes: &mut Vec<String>) {
+    for (old, new) in CONFIG_RENAMES {
+        let Some(v) = table.remove(*old) else {
+            continue;
+        };
+        if table.contains_key(*new) {
+            changes.push(format!("{at}: dropped `{old}`, `{new}` is already set"));
+        } else {
+            table.insert(new.to_string(), v);
+            changes.push(format!("{at}: renamed `{old}` to `{new}`"));
+        }
     }
-
-    let local = PathBuf::from("rustfilefinder.toml");
-    if local.exists() {
-        return Some(local);
+    for key in CONFIG_LIST_KEYS {
+        let Some(toml::Value::String(s)) = table.get(*key) else {
+            continue;
+        };
+        let list = s
+            .split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(|x| toml::Value::String(x.to_string()))
+            .collect();
+        table.insert(key.to_string(), toml::Value::Array(list));
+        changes.push(format!("{at}: `{key}` is now a list"));
     }
+}
 
-    if let Some(dir) = dirs::config_dir() {
-        let p = dir.join("rustfilefinder").join("config.toml");
-        if p.exists() {
-            return Some(p);
-        }
+fn migrate_config_file(path: &Path) -> Result<(), String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let mut value: toml::Value = toml::from_str(text.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("cannot parse {}: {e}", path.display()))?;
+    let version = config_version(&value)?;
+    if version > CONFIG_VERSION {
+        return Err(format!(
+            "{} has version {version}, this build understands up to {CONFIG_VERSION}; \
+             upgrade rustfilefinder",
+            path.display()
+        ));
+    }
+    if version == CONFIG_VERSION {
+        eprintln!(
+            "{} is already at version {CONFIG_VERSION}; nothing to do",
+            path.display()
+        );
+        return Ok(());
     }
 
-    None
-}
+    let changes = migrate_config(&mut value);
+    let migrated = toml::to_string_pretty(&value)
+        .map_err(|e| format!("cannot serialize migrated config: {e}"))?;
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{version}.bak"));
+    let backup = PathBuf::from(backup);
+    fs::copy(path, &backup).map_err(|e| {
+        format!(
+            "cannot back up {} to {}: {e}",
+            path.display(),
+            backup.display()
+        )
+    })?;
+    fs::write(path, migrated).map_err(|e| format!("cannot write {}: {e}", path.display()))?;
 
-fn load_config(path: &Path) -> Option<AppConfig> {
-    let s = std::fs::read_to_string(path).ok()?;
-    toml::from_str(&s).ok()
+    eprintln!(
+        "Migrated {} from version {version} to {CONFIG_VERSION} (original kept at {}; comments are not carried over)",
+        path.display(),
+        backup.display()
+    );
+    for c in &changes {
+        eprintln!("  {c}");
+    }
+    Ok(())
 }
 
 fn write_sample_config(path: &Path) -> std::io::Result<()> {
@@ -656,9 +9807,69 @@ fn write_sample_config(path: &Path) -> std::io::Result<()> {
     }
 
     let sample = r#"
+version = 1
+
 [defaults]
 format = "json"
 max_bytes = 2000000
+memory_limit = "512M"
+# max_dir_bytes = "2G"
+# max_total_bytes = "20G"
+# max_depth = 512
+# max_path_len = 4096
+# max_entries = 5000000
+# throttle = 20
+# nice_io = true
+# output = "report.json"
+# output_dir = "reports"
+# fields = ["path", "matched_content"]
+# compress = "gzip"
+sort = "path"
+reverse = false
+# require_script = ["Cyrillic"]
+# path_rewrite = ["/data=//fileserver/corpus"]
+# include_email = true
+# case_insensitive_paths = "auto"
+# email_header = ["From:alice@"]
+# vendored_glob = ["vendor/", "third_party/"]
+# generated_glob = ["*.min.js", "*.pb.go"]
+# deprioritize = ["tests/", "*_test.go"]
+# provenance = ["first_party"]
+# lang = "python,rust"
+# two_phase = false
+# collapse_duplicates = true
+# strict = false
+# trim_for_anchors = false
+# copy_limit = "1000,1G"
+# progress_interval = 1000
+# all_matches = true
+# max_snippets_per_file = 5
+snippet_context = 40
+# context_lines = 2
+snippet_max = 120
+color = "auto"
+# width = 100
+full_paths = false
+quiet = false
+
+# [extractors]
+# mmap = "mmap2txt {path}"
+
+# [kinds]
+# proto = "code"
+
+# [max_bytes_by_ext]
+# json = "256k"
+# log = "10MB"
+# rs = "unlimited"
+
+# [presets.rust_todo]
+# language = "rust"
+# content = "TODO|FIXME"
+
+# [hosts.build-server.defaults]
+# dir = "${env:CORPUS_ROOT}/docs"
+# allow_empty_dir_glob = false
 
 [presets.demo_text]
 dir = "samples"
@@ -686,28 +9897,307 @@ format = "json"
     Ok(())
 }
 
+fn compare_presets_args(
+    args: SearchArgs,
+    spec: &str,
+    cfg: Option<AppConfig>,
+) -> Result<SearchArgs, String> {
+    let names: Vec<&str> = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .collect();
+    let [a, b] = names[..] else {
+        return Err(format!(
+            "--compare-presets expects two preset names like a,b, got `{spec}`"
+        ));
+    };
+    if a == b || a.contains('=') || b.contains('=') {
+        return Err(format!(
+            "--compare-presets needs two different preset names, got `{spec}`"
+        ));
+    }
+    if args.preset.is_some()
+        || args.name.is_some()
+        || !args.content.is_empty()
+        || args.content_file.is_some()
+        || !args.rule.is_empty()
+    {
+        return Err(
+            "--compare-presets takes the match criteria from the presets; drop --preset, \
+             --name, --content, --content-file and --rule"
+                .to_string(),
+        );
+    }
+    let Some(cfg) = cfg else {
+        return Err("--compare-presets needs a config file with [presets]".to_string());
+    };
+    let host = hostname().and_then(|h| cfg.hosts.as_ref()?.get(&h).cloned());
+    for name in [a, b] {
+        let defined = |presets: Option<&HashMap<String, SearchConfig>>| {
+            presets.is_some_and(|p| p.contains_key(name))
+        };
+        if !defined(cfg.presets.as_ref())
+            && !defined(host.as_ref().and_then(|h| h.presets.as_ref()))
+        {
+            return Err(format!("unknown preset `{name}`"));
+        }
+    }
+
+    let merged = [a, b].map(|name| {
+        let mut one = args.clone();
+        one.preset = Some(name.to_string());
+        merge_search_args(one, Some(cfg.clone()))
+    });
+
+    let mut rules = Vec::new();
+    for (name, m) in [a, b].iter().zip(&merged) {
+        let mut patterns = m.content.clone();
+        if let Some(p) = &m.content_file {
+            let list = fs::read_to_string(p)
+                .map_err(|e| format!("cannot read content_file {}: {e}", p.display()))?;
+            patterns.extend(
+                list.lines()
+                    .map(str::trim_end)
+                    .filter(|l| !l.is_empty())
+                    .map(str::to_string),
+            );
+        }
+        let before = rules.len();
+        for p in patterns {
+            let p = if m.fixed_strings {
+                regex::escape(&p)
+            } else {
+                p
+            };
+            rules.push(format!("{name}={p}"));
+        }
+        for r in &m.rule {
+            if let Some((_, pat)) = r.split_once('=') {
+                rules.push(format!("{name}={pat}"));
+            }
+        }
+        if rules.len() == before {
+            return Err(format!(
+                "preset `{name}` has no content patterns to compare"
+            ));
+        }
+    }
+
+    let scopes = [a, b].map(|name| {
+        let m = &merged[usize::from(name == b)];
+        PresetScope {
+            name: name.to_string(),
+            allowed_ext: m.ext.as_ref().map(|s| {
+                s.split(',')
+                    .map(|x| x.trim().to_lowercase())
+                    .filter(|x| !x.is_empty())
+                    .collect()
+            }),
+            include_no_ext: m.include_no_ext,
+            include_pdf: m.include_pdf,
+            name_query: m.name.clone(),
+            ignore_dir: m.ignore_dir.clone(),
+        }
+    });
+
+    let [first, second] = merged;
+    let mut combined = first;
+    combined.preset = None;
+    combined.name = None;
+    combined.content = Vec::new();
+    combined.content_file = None;
+    combined.fixed_strings = false;
+    combined.rule = rules;
+    combined.cooccurrence = false;
+    combined.ext = match (&combined.ext, &second.ext) {
+        (Some(x), Some(y)) => Some(format!("{x},{y}")),
+        _ => None,
+    };
+    combined.include_no_ext |= second.include_no_ext;
+    combined.include_pdf |= second.include_pdf;
+    combined
+        .ignore_dir
+        .retain(|d| second.ignore_dir.contains(d));
+    combined.compare = Some(scopes);
+    Ok(combined)
+}
+
+fn queries_file_args(mut args: SearchArgs, path: &Path) -> Result<SearchArgs, String> {
+    if args.name.is_some()
+        || !args.content.is_empty()
+        || args.content_file.is_some()
+        || !args.rule.is_empty()
+    {
+        return Err(
+            "--queries-file takes the match criteria from the file; drop --name, --content, \
+             --content-file and --rule (and presets that set them)"
+                .to_string(),
+        );
+    }
+    if args.output_dir.is_none() {
+        return Err("--queries-file requires --output-dir for the per-query reports".to_string());
+    }
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("cannot read --queries-file {}: {e}", path.display()))?;
+    let file: QueriesFile = toml::from_str(text.trim_start_matches('\u{feff}'))
+        .map_err(|e| format!("invalid --queries-file {}: {e}", path.display()))?;
+    if file.query.is_empty() {
+        return Err(format!("{} has no [[query]] tables", path.display()));
+    }
+
+    let split_ext = |s: &str| -> Vec<String> {
+        s.split(',')
+            .map(|x| x.trim().to_lowercase())
+            .filter(|x| !x.is_empty())
+            .collect()
+    };
+    let mut queries: Vec<NamedQuery> = Vec::new();
+    for (i, spec) in file.query.into_iter().enumerate() {
+        let label = spec
+            .label
+            .map(|l| l.trim().to_string())
+            .unwrap_or_else(|| format!("q{}", i + 1));
+        if label.is_empty()
+            || !label
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(format!(
+                "query label `{label}` must be letters, digits, `-`, `_` or `.`"
+            ));
+        }
+        if queries.iter().any(|q| q.scope.name == label) {
+            return Err(format!("query label `{label}` is used twice"));
+        }
+        if spec.content.is_empty() {
+            return Err(format!(
+                "query `{label}` has no content patterns; name-only searches do not read files, \
+                 run them with --name"
+            ));
+        }
+        let fixed = spec.fixed_strings.unwrap_or(args.fixed_strings);
+        queries.push(NamedQuery {
+            scope: PresetScope {
+                name: label,
+                allowed_ext: spec.ext.as_deref().or(args.ext.as_deref()).map(split_ext),
+                include_no_ext: spec.include_no_ext.unwrap_or(args.include_no_ext),
+                include_pdf: spec.include_pdf.unwrap_or(args.include_pdf),
+                name_query: spec.name,
+                ignore_dir: Vec::new(),
+            },
+            content: spec.content,
+            fixed_strings: fixed,
+        });
+    }
+
+    let all_ext: Option<Vec<String>> = queries
+        .iter()
+        .map(|q| q.scope.allowed_ext.clone())
+        .collect::<Option<Vec<_>>>()
+        .map(|lists| lists.concat());
+    args.ext = all_ext.map(|mut list| {
+        list.sort();
+        list.dedup();
+        list.join(",")
+    });
+    args.include_no_ext = queries.iter().any(|q| q.scope.include_no_ext);
+    args.include_pdf = queries.iter().any(|q| q.scope.include_pdf);
+    args.fixed_strings = false;
+    args.cooccurrence = false;
+    args.queries = queries;
+    Ok(args)
+}
+
 fn merge_search_args(mut args: SearchArgs, cfg: Option<AppConfig>) -> SearchArgs {
     let Some(cfg) = cfg else { return args };
 
-    if let Some(d) = cfg.defaults.clone() {
-        apply_cfg(&mut args, &d);
+    let host_name = hostname();
+    let host = host_name
+        .as_deref()
+        .and_then(|h| cfg.hosts.as_ref()?.get(h));
+    if args.explain {
+        eprintln!(
+            "[explain] config: format version {}",
+            cfg.version.unwrap_or(CONFIG_VERSION)
+        );
+        match (&host_name, host) {
+            (Some(h), Some(_)) => eprintln!("[explain] config: applying [hosts.{h}]"),
+            (Some(h), None) => eprintln!("[explain] config: no [hosts.{h}] section"),
+            (None, _) => eprintln!("[explain] config: hostname unknown, host sections skipped"),
+        }
     }
 
-    if let Some(preset_name) = args.preset.clone() {
-        if let Some(presets) = cfg.presets {
-            if let Some(p) = presets.get(&preset_name) {
-                apply_cfg(&mut args, p);
-            }
+    let preset = |presets: Option<&HashMap<String, SearchConfig>>| {
+        presets?.get(args.preset.as_deref()?).cloned()
+    };
+    let layers = [
+        host.and_then(|h| h.defaults.clone()),
+        cfg.defaults.clone(),
+        preset(host.and_then(|h| h.presets.as_ref())),
+        preset(cfg.presets.as_ref()),
+    ];
+    for layer in layers.iter().flatten() {
+        apply_cfg(&mut args, layer);
+    }
+    let language = layers
+        .iter()
+        .flatten()
+        .find_map(|c| c.language.as_deref())
+        .and_then(language::lookup);
+    for d in language.iter().flat_map(|l| &l.ignore_dirs) {
+        if !args.ignore_dir.iter().any(|x| x == d) {
+            args.ignore_dir.push(d.to_string());
         }
     }
 
     args
 }
 
+fn hostname() -> Option<String> {
+    if let Ok(h) = std::env::var("RUSTFILEFINDER_HOSTNAME") {
+        return Some(h).filter(|h| !h.is_empty());
+    }
+    system_hostname()
+}
+
+fn system_hostname() -> Option<String> {
+    let name = gethostname::gethostname().into_string().ok()?;
+    (!name.is_empty()).then_some(name)
+}
+
+fn expand_env(value: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(at) = rest.find("${env:") {
+        out.push_str(&rest[..at]);
+        let tail = &rest[at + 6..];
+        let Some(close) = tail.find('}') else {
+            return Err(format!("unterminated `${{env:` in `{value}`"));
+        };
+        let var = &tail[..close];
+        let v = std::env::var(var)
+            .map_err(|_| format!("environment variable {var} used in `{value}` is not set"))?;
+        out.push_str(&v);
+        rest = &tail[close + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
 fn apply_cfg(args: &mut SearchArgs, c: &SearchConfig) {
-    if args.dir == Path::new(".") {
+    if args.dir == [PathBuf::from(".")] {
         if let Some(v) = &c.dir {
-            args.dir = v.clone();
+            let dir = expand_env(&v.to_string_lossy()).unwrap_or_else(|e| {
+                eprintln!("Error: config `dir`: {e}");
+                std::process::exit(2);
+            });
+            args.dir = vec![PathBuf::from(dir)];
+        }
+    }
+    if !args.allow_empty_dir_glob {
+        if let Some(v) = c.allow_empty_dir_glob {
+            args.allow_empty_dir_glob = v;
         }
     }
     if !args.include_pdf {
@@ -715,26 +10205,445 @@ fn apply_cfg(args: &mut SearchArgs, c: &SearchConfig) {
             args.include_pdf = v;
         }
     }
+    if args.pdf_max_pages == 0 {
+        if let Some(v) = c.pdf_max_pages {
+            args.pdf_max_pages = v;
+        }
+    }
+    if !args.include_email {
+        if let Some(v) = c.include_email {
+            args.include_email = v;
+        }
+    }
+    if args.email_header.is_empty() {
+        for v in c.email_header.iter().flatten() {
+            let filter = email::parse_filter(v).unwrap_or_else(|e| {
+                eprintln!("Invalid email_header in config: {e}");
+                std::process::exit(2);
+            });
+            args.email_header.push(filter);
+        }
+    }
+    if args.normalize_whitespace == "pdf" {
+        if let Some(v) = &c.normalize_whitespace {
+            if !["pdf", "all", "off"].contains(&v.as_str()) {
+                eprintln!("Invalid normalize_whitespace in config: {v} (expected pdf, all or off)");
+                std::process::exit(2);
+            }
+            args.normalize_whitespace = v.clone();
+        }
+    }
+    if !args.trim_for_anchors {
+        if let Some(v) = c.trim_for_anchors {
+            args.trim_for_anchors = v;
+        }
+    }
     if args.name.is_none() {
         args.name = c.name.clone();
     }
-    if args.content.is_none() {
-        args.content = c.content.clone();
+    if args.not_name.is_empty() {
+        args.not_name = c.not_name.clone().unwrap_or_default();
+    }
+    if args.not_name_glob.is_empty() {
+        args.not_name_glob = c.not_name_glob.clone().unwrap_or_default();
+    }
+    if args.vendored_glob.is_empty() {
+        args.vendored_glob = c.vendored_glob.clone().unwrap_or_default();
+    }
+    if args.deprioritize.is_empty() {
+        args.deprioritize = c.deprioritize.clone().unwrap_or_default();
+    }
+    if args.generated_glob.is_empty() {
+        args.generated_glob = c.generated_glob.clone().unwrap_or_default();
+    }
+    if args.provenance.is_empty() {
+        if let Some(v) = &c.provenance {
+            if let Some(bad) = v.iter().find(|p| !provenance::ALL.contains(&p.as_str())) {
+                eprintln!(
+                    "Invalid provenance in config: {bad} (expected first_party, vendored or generated)"
+                );
+                std::process::exit(2);
+            }
+            args.provenance = v.clone();
+        }
+    }
+    if args.content.is_empty() {
+        args.content = c.content.clone().into_iter().collect();
+    }
+    if args.content_file.is_none() {
+        args.content_file = c.content_file.clone();
+    }
+    if !args.fixed_strings {
+        if let Some(v) = c.fixed_strings {
+            args.fixed_strings = v;
+        }
+    }
+    if args.scope == "all" {
+        if let Some(v) = &c.scope {
+            if Scope::parse(v).is_none() {
+                eprintln!("Invalid scope in config: {v} (expected all, comments, strings or code)");
+                std::process::exit(2);
+            }
+            args.scope = v.clone();
+        }
+    }
+    if args.rule.is_empty() {
+        if let Some(rules) = &c.rules {
+            args.rule = rules.iter().map(|(k, v)| format!("{k}={v}")).collect();
+        }
+    }
+    if args.format.is_none() {
+        args.format = c.format.clone();
+    }
+    if args.pipe_format == "jsonl" {
+        if let Some(v) = &c.pipe_format {
+            args.pipe_format = v.clone();
+        }
+    }
+    if args.output.is_none() {
+        args.output = c.output.clone();
+    }
+    if args.fields.is_empty() {
+        if let Some(v) = &c.fields {
+            args.fields.clone_from(v);
+        }
+    }
+    if args.output_dir.is_none() {
+        args.output_dir = c.output_dir.clone();
+    }
+    if args.compress.is_none() {
+        args.compress = c.compress.clone();
+    }
+    if args.sort == "path" {
+        if let Some(v) = &c.sort {
+            args.sort = v.clone();
+        }
+    }
+    if !args.reverse {
+        if let Some(v) = c.reverse {
+            args.reverse = v;
+        }
+    }
+    if !args.all_matches {
+        if let Some(v) = c.all_matches {
+            args.all_matches = v;
+        }
+    }
+    if args.max_snippets_per_file == 5 {
+        if let Some(v) = c.max_snippets_per_file {
+            args.max_snippets_per_file = v;
+        }
+    }
+    if args.snippet_context == 40 {
+        if let Some(v) = c.snippet_context {
+            args.snippet_context = v;
+        }
+    }
+    if args.snippet_max == 120 {
+        if let Some(v) = c.snippet_max {
+            args.snippet_max = v;
+        }
+    }
+    if args.context_lines.is_none() {
+        args.context_lines = c.context_lines;
+    }
+    if !args.show_context_heading {
+        if let Some(v) = c.show_context_heading {
+            args.show_context_heading = v;
+        }
+    }
+    if !args.raw {
+        if let Some(v) = c.raw {
+            args.raw = v;
+        }
+    }
+    if args.color == "auto" {
+        if let Some(v) = &c.color {
+            args.color = v.clone();
+        }
+    }
+    if args.width.is_none() {
+        args.width = c.width;
     }
-    if args.format == "md" {
-        if let Some(v) = &c.format {
-            args.format = v.clone();
+    if !args.full_paths {
+        if let Some(v) = c.full_paths {
+            args.full_paths = v;
         }
     }
+    if !args.quiet {
+        if let Some(v) = c.quiet {
+            args.quiet = v;
+        }
+    }
+    if args.summary_line.is_none() {
+        args.summary_line = c.summary_line.clone();
+    }
     if args.max_bytes == 2_000_000 {
         if let Some(v) = c.max_bytes {
             args.max_bytes = v;
         }
     }
+    let config_size = |key: &str, v: &String| {
+        memory::parse_size(v).unwrap_or_else(|e| {
+            eprintln!("Invalid {key} in config: {e}");
+            std::process::exit(2);
+        })
+    };
+    if args.memory_limit == DEFAULT_MEMORY_LIMIT {
+        if let Some(v) = &c.memory_limit {
+            args.memory_limit = config_size("memory_limit", v);
+        }
+    }
+    if args.paginate.is_none() {
+        args.paginate = c.paginate;
+    }
+    if args.skip_long_lines.is_none() {
+        args.skip_long_lines = c.skip_long_lines;
+    }
+    if args.throttle.is_none() {
+        args.throttle = c.throttle.filter(|v| v.is_finite() && *v > 0.0);
+    }
+    if !args.nice_io {
+        if let Some(v) = c.nice_io {
+            args.nice_io = v;
+        }
+    }
+    if args.max_dir_bytes.is_none() {
+        args.max_dir_bytes = c
+            .max_dir_bytes
+            .as_ref()
+            .map(|v| config_size("max_dir_bytes", v));
+    }
+    if args.max_total_bytes.is_none() {
+        args.max_total_bytes = c
+            .max_total_bytes
+            .as_ref()
+            .map(|v| config_size("max_total_bytes", v));
+    }
+    if args.max_depth == DEFAULT_MAX_DEPTH {
+        if let Some(v) = c.max_depth {
+            args.max_depth = v;
+        }
+    }
+    if args.max_path_len == DEFAULT_MAX_PATH_LEN {
+        if let Some(v) = c.max_path_len {
+            args.max_path_len = v;
+        }
+    }
+    if args.max_entries.is_none() {
+        args.max_entries = c.max_entries;
+    }
+    let language = c.language.as_deref().map(|name| {
+        language::lookup(name).unwrap_or_else(|| {
+            eprintln!("Unknown language in config: {name} (see `rustfilefinder languages`)");
+            std::process::exit(2);
+        })
+    });
     if args.ext.is_none() {
-        args.ext = c.ext.clone();
+        args.ext = c
+            .ext
+            .clone()
+            .or_else(|| language.as_ref().map(|l| l.extensions.join(",")));
+    }
+    if args.lang.is_none() {
+        args.lang = c.lang.clone();
+    }
+    if args.kind.is_none() {
+        args.kind = c.kind.clone();
+    }
+    if args.ignore_dir.is_empty() {
+        args.ignore_dir = c.ignore_dir.clone().unwrap_or_default();
+    }
+    if !args.include_no_ext {
+        if let Some(v) = c.include_no_ext {
+            args.include_no_ext = v;
+        }
+    }
+    if args.mime.is_empty() {
+        if let Some(v) = &c.mime {
+            args.mime = v.clone();
+        }
+    }
+    if args.exclude_mime.is_empty() {
+        if let Some(v) = &c.exclude_mime {
+            args.exclude_mime = v.clone();
+        }
+    }
+    if !args.show_mime {
+        if let Some(v) = c.show_mime {
+            args.show_mime = v;
+        }
+    }
+    if args.show_meta.is_none() {
+        if let Some(v) = &c.show_meta {
+            if v != "relative" && v != "absolute" {
+                eprintln!("Invalid show_meta in config: {v} (expected relative or absolute)");
+                std::process::exit(2);
+            }
+            args.show_meta = Some(v.clone());
+        }
     }
     if args.limit.is_none() {
         args.limit = c.limit;
     }
+    if args.sample.is_none() {
+        args.sample = c.sample;
+    }
+    if !args.summarize_dirs {
+        if let Some(v) = c.summarize_dirs {
+            args.summarize_dirs = v;
+        }
+    }
+    if args.summarize_depth == 1 {
+        if let Some(v) = c.summarize_depth {
+            args.summarize_depth = v;
+        }
+    }
+    if args.seed.is_none() {
+        args.seed = c.seed;
+    }
+    if args.verify_skips.is_none() {
+        args.verify_skips = c.verify_skips;
+    }
+    if args.use_index.is_none() {
+        args.use_index = c.use_index.clone();
+    }
+    if args.changed_since.is_none() {
+        args.changed_since = c.changed_since.clone();
+    }
+    if !args.trust_dir_mtime {
+        if let Some(v) = c.trust_dir_mtime {
+            args.trust_dir_mtime = v;
+        }
+    }
+    if !args.dedup_hardlinks {
+        if let Some(v) = c.dedup_hardlinks {
+            args.dedup_hardlinks = v;
+        }
+    }
+    if !args.no_ignore_global {
+        if let Some(v) = c.no_ignore_global {
+            args.no_ignore_global = v;
+        }
+    }
+    if !args.no_ignore_vcs {
+        if let Some(v) = c.no_ignore_vcs {
+            args.no_ignore_vcs = v;
+        }
+    }
+    if !args.cooccurrence {
+        if let Some(v) = c.cooccurrence {
+            args.cooccurrence = v;
+        }
+    }
+    if args.limit_per_dir.is_none() {
+        args.limit_per_dir = c.limit_per_dir;
+    }
+    if !args.collapse_duplicates {
+        if let Some(v) = c.collapse_duplicates {
+            args.collapse_duplicates = v;
+        }
+    }
+    if args.max_results.is_none() {
+        args.max_results = c.max_results;
+    }
+    if !args.follow_links {
+        if let Some(v) = c.follow_links {
+            args.follow_links = v;
+        }
+    }
+    if args.schedule == "size" {
+        if let Some(v) = &c.schedule {
+            args.schedule = v.clone();
+        }
+    }
+    if args.walk_order == "dfs" {
+        if let Some(v) = &c.walk_order {
+            args.walk_order = v.clone();
+        }
+    }
+    if args.case_insensitive_paths == "auto" {
+        if let Some(v) = &c.case_insensitive_paths {
+            args.case_insensitive_paths = v.clone();
+        }
+    }
+    if args.regex_size_limit.is_none() {
+        args.regex_size_limit = c.regex_size_limit;
+    }
+    if args.per_file_timeout == 5 {
+        if let Some(v) = c.per_file_timeout {
+            args.per_file_timeout = v;
+        }
+    }
+    if !args.strict_snapshot {
+        if let Some(v) = c.strict_snapshot {
+            args.strict_snapshot = v;
+        }
+    }
+    if !args.strict {
+        if let Some(v) = c.strict {
+            args.strict = v;
+        }
+    }
+    if args.progress_interval == progress::DEFAULT_INTERVAL_MS {
+        if let Some(v) = c.progress_interval {
+            args.progress_interval = v;
+        }
+    }
+    if args.copy_limit.is_none() {
+        if let Some(v) = &c.copy_limit {
+            args.copy_limit = Some(staging::parse_limit(v).unwrap_or_else(|e| {
+                eprintln!("Invalid copy_limit in config: {e}");
+                std::process::exit(2);
+            }));
+        }
+    }
+    if args.checkpoint.is_none() {
+        args.checkpoint = c.checkpoint.clone();
+    }
+    if args.checkpoint_every == 1000 {
+        if let Some(v) = c.checkpoint_every {
+            args.checkpoint_every = v;
+        }
+    }
+    if args.metrics_out.is_none() {
+        args.metrics_out = c.metrics_out.clone();
+    }
+    if args.path_rewrite.is_empty() {
+        for v in c.path_rewrite.iter().flatten() {
+            let rewrite = parse_path_rewrite(v).unwrap_or_else(|e| {
+                eprintln!("Invalid path_rewrite in config: {e}");
+                std::process::exit(2);
+            });
+            args.path_rewrite.push(rewrite);
+        }
+    }
+    if !args.no_meta {
+        if let Some(v) = c.no_meta {
+            args.no_meta = v;
+        }
+    }
+    if !args.two_phase {
+        if let Some(v) = c.two_phase {
+            args.two_phase = v;
+        }
+    }
+    if !args.redact {
+        if let Some(v) = c.redact {
+            args.redact = v;
+        }
+    }
+    if !args.binary {
+        if let Some(v) = c.binary {
+            args.binary = v;
+        }
+    }
+    if !args.unicode_word_boundaries {
+        if let Some(v) = c.unicode_word_boundaries {
+            args.unicode_word_boundaries = v;
+        }
+    }
+    if args.require_script.is_empty() {
+        args.require_script = c.require_script.clone().unwrap_or_default();
+    }
 }
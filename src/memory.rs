@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+pub struct MemoryBudget {
+    limit: u64,
+    in_use: Mutex<u64>,
+    released: Condvar,
+    peak: AtomicU64,
+}
+
+pub struct MemoryPermit<'a> {
+    budget: &'a MemoryBudget,
+    bytes: u64,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: u64) -> Self {
+        MemoryBudget {
+            limit,
+            in_use: Mutex::new(0),
+            released: Condvar::new(),
+            peak: AtomicU64::new(0),
+        }
+    }
+
+    pub fn try_acquire(&self, bytes: u64) -> Option<MemoryPermit<'_>> {
+        let mut used = self.lock();
+        if *used + bytes > self.limit {
+            return None;
+        }
+        Some(self.grant(&mut used, bytes))
+    }
+
+    pub fn acquire(&self, bytes: u64) -> MemoryPermit<'_> {
+        let mut used = self.lock();
+        while *used > 0 && *used + bytes > self.limit {
+            used = self.released.wait(used).unwrap_or_else(|e| e.into_inner());
+        }
+        self.grant(&mut used, bytes)
+    }
+
+    pub fn peak(&self) -> u64 {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    fn grant(&self, used: &mut u64, bytes: u64) -> MemoryPermit<'_> {
+        *used += bytes;
+        self.peak.fetch_max(*used, Ordering::Relaxed);
+        MemoryPermit {
+            budget: self,
+            bytes,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, u64> {
+        self.in_use.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MemoryPermit<'_> {
+    fn drop(&mut self) {
+        let mut used = self.budget.lock();
+        *used -= self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let t = s.trim();
+    let split = t.find(|c: char| !c.is_ascii_digit()).unwrap_or(t.len());
+    let (digits, unit) = t.split_at(split);
+    let n: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid size `{s}` (expected e.g. 512M, 2G or 1048576)"))?;
+    let shift = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        "T" | "TB" | "TIB" => 40,
+        _ => return Err(format!("invalid size unit in `{s}` (use K, M, G or T)")),
+    };
+    n.checked_mul(1 << shift)
+        .ok_or_else(|| format!("size `{s}` is too large"))
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{compress, MatchResult};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+const MAX_KEYS: &[&str] = &["elapsed_ms", "discovery_ms", "scan_ms", "memory_peak_bytes"];
+const SUMMED_MAPS: &[&str] = &[
+    "skips",
+    "rules",
+    "matches_by_language",
+    "matches_by_provenance",
+];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Source {
+    pub report: String,
+    pub schema_version: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    pub results: usize,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DuplicatePath {
+    pub path: String,
+    pub reports: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MergeMeta {
+    pub generated_at: String,
+    pub hostname: Option<String>,
+    pub sources: Vec<Source>,
+}
+
+#[derive(Serialize)]
+pub struct MergedReport {
+    pub schema_version: u32,
+    pub stats: Value,
+    pub results: Vec<MatchResult>,
+    pub meta: MergeMeta,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub duplicate_paths: Vec<DuplicatePath>,
+}
+
+pub struct Shard {
+    pub source: Source,
+    pub stats: Value,
+    pub results: Vec<MatchResult>,
+}
+
+pub fn load(path: &Path) -> Result<Shard, String> {
+    let name = path.display().to_string();
+    let data = compress::read(path).map_err(|e| format!("cannot read report {name}: {e}"))?;
+    let report: Value = serde_json::from_slice(&data)
+        .map_err(|e| format!("report {name} is not valid JSON: {e}"))?;
+    let schema_version = report
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| {
+            format!("report {name} has no schema_version; pass reports written with --format json")
+        })?;
+    let results: Vec<MatchResult> = report
+        .get("results")
+        .cloned()
+        .and_then(|r| serde_json::from_value(r).ok())
+        .ok_or_else(|| format!("report {name} has no readable `results` list"))?;
+    let stats = report.get("stats").cloned().unwrap_or(Value::Null);
+    let meta = |key: &str| {
+        report
+            .pointer(&format!("/meta/{key}"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let source = Source {
+        report: name,
+        schema_version,
+        generated_at: meta("generated_at"),
+        hostname: meta("hostname"),
+        cwd: meta("cwd"),
+        results: results.len(),
+        elapsed_ms: stats
+            .get("elapsed_ms")
+            .and_then(Value::as_u64)
+            .unwrap_or_default(),
+    };
+    Ok(Shard {
+        source,
+        stats,
+        results,
+    })
+}
+
+pub fn check_versions(shards: &[Shard], supported: u32) -> Result<(), String> {
+    let versions: BTreeSet<u64> = shards.iter().map(|s| s.source.schema_version).collect();
+    if versions.len() > 1 {
+        let listed: Vec<String> = shards
+            .iter()
+            .map(|s| format!("{} has {}", s.source.report, s.source.schema_version))
+            .collect();
+        return Err(format!(
+            "reports have different schema versions: {}",
+            listed.join(", ")
+        ));
+    }
+    match versions.into_iter().next() {
+        Some(v) if v != u64::from(supported) => Err(format!(
+            "reports have schema_version {v}, this build reads schema_version {supported}"
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub fn merge_stats(shards: &[Shard]) -> Value {
+    let all: Vec<&Map<String, Value>> = shards.iter().filter_map(|s| s.stats.as_object()).collect();
+    let mut merged = merge_objects(&all, false);
+    let total: u64 = shards.iter().map(|s| s.source.elapsed_ms).sum();
+    merged.insert("elapsed_ms_total".to_string(), total.into());
+    Value::Object(merged)
+}
+
+fn merge_objects(all: &[&Map<String, Value>], sum_only: bool) -> Map<String, Value> {
+    let keys: BTreeSet<&String> = all.iter().flat_map(|o| o.keys()).collect();
+    let mut merged = Map::new();
+    for key in keys {
+        let values: Vec<&Value> = all
+            .iter()
+            .filter_map(|o| o.get(key))
+            .filter(|v| !v.is_null())
+            .collect();
+        let max = !sum_only && MAX_KEYS.contains(&key.as_str());
+        let summed_map = !sum_only && SUMMED_MAPS.contains(&key.as_str());
+        if let Some(v) = combine(&values, max, summed_map) {
+            merged.insert(key.clone(), v);
+        }
+    }
+    merged
+}
+
+fn combine(values: &[&Value], max: bool, summed_map: bool) -> Option<Value> {
+    match values {
+        [] => return Some(Value::Null),
+        [only] => return Some((*only).clone()),
+        _ => {}
+    }
+    if values.iter().all(|v| v.is_u64()) {
+        let nums = values.iter().filter_map(|v| v.as_u64());
+        let n = if max {
+            nums.max().unwrap_or_default()
+        } else {
+            nums.sum()
+        };
+        return Some(n.into());
+    }
+    if values.iter().all(|v| v.is_boolean()) {
+        return Some(values.iter().any(|v| v.as_bool() == Some(true)).into());
+    }
+    if values.iter().all(|v| v.is_array()) {
+        let items = values
+            .iter()
+            .filter_map(|v| v.as_array())
+            .flatten()
+            .cloned();
+        return Some(Value::Array(items.collect()));
+    }
+    if summed_map {
+        let maps: Vec<&Map<String, Value>> = values.iter().filter_map(|v| v.as_object()).collect();
+        return Some(Value::Object(merge_objects(&maps, true)));
+    }
+    values
+        .windows(2)
+        .all(|w| w[0] == w[1])
+        .then(|| values[0].clone())
+}
+
+pub fn duplicate_paths(shards: &[Shard]) -> Vec<DuplicatePath> {
+    let mut seen: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for shard in shards {
+        let paths: BTreeSet<&str> = shard
+            .results
+            .iter()
+            .map(|r| r.path.trim_start_matches("./"))
+            .collect();
+        for path in paths {
+            seen.entry(path)
+                .or_default()
+                .push(shard.source.report.clone());
+        }
+    }
+    seen.into_iter()
+        .filter(|(_, reports)| reports.len() > 1)
+        .map(|(path, reports)| DuplicatePath {
+            path: path.to_string(),
+            reports,
+        })
+        .collect()
+}
+
+pub fn flag_duplicates(results: &mut [(String, MatchResult)], duplicates: &[DuplicatePath]) {
+    let by_path: BTreeMap<&str, &[String]> = duplicates
+        .iter()
+        .map(|d| (d.path.as_str(), &d.reports[..]))
+        .collect();
+    for (report, r) in results {
+        let Some(reports) = by_path.get(r.path.trim_start_matches("./")) else {
+            continue;
+        };
+        let others: Vec<&str> = reports
+            .iter()
+            .filter(|other| *other != report)
+            .map(String::as_str)
+            .collect();
+        r.warnings
+            .push(format!("path also reported by {}", others.join(", ")));
+    }
+}
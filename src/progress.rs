@@ -0,0 +1,115 @@
+use serde_json::{json, Value};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::flag;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_INTERVAL_MS: u64 = 1000;
+pub const CANCELLED_EXIT: i32 = 130;
+
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+const MIN_INTERVAL: Duration = Duration::from_millis(10);
+const INTERRUPTS: [i32; 2] = [SIGINT, SIGTERM];
+
+pub fn emit(event: &Value) {
+    let mut err = io::stderr().lock();
+    let _ = writeln!(err, "{event}").and_then(|_| err.flush());
+}
+
+pub struct Reporter {
+    total: usize,
+    processed: AtomicUsize,
+    matches: AtomicUsize,
+    started: Instant,
+    cancelled: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+    stopped: Mutex<bool>,
+    wake: Condvar,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Reporter {
+    pub fn spawn(total: usize, interval: Duration, started: Instant) -> Arc<Reporter> {
+        let reporter = Arc::new(Reporter {
+            total,
+            processed: AtomicUsize::new(0),
+            matches: AtomicUsize::new(0),
+            started,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            finished: Arc::new(AtomicBool::new(false)),
+            stopped: Mutex::new(false),
+            wake: Condvar::new(),
+            thread: Mutex::new(None),
+        });
+        reporter.watch_interrupts();
+        let worker = Arc::clone(&reporter);
+        let handle = thread::spawn(move || worker.run(interval.max(MIN_INTERVAL)));
+        *reporter.thread.lock().unwrap_or_else(|e| e.into_inner()) = Some(handle);
+        reporter
+    }
+
+    pub fn file_done(&self, matches: usize) {
+        self.processed.fetch_add(1, Ordering::Relaxed);
+        self.matches.fetch_add(matches, Ordering::Relaxed);
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn stop(&self) -> bool {
+        self.finished.store(true, Ordering::Relaxed);
+        *self.stopped.lock().unwrap_or_else(|e| e.into_inner()) = true;
+        self.wake.notify_all();
+        let handle = self.thread.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(handle) = handle {
+            let _ = handle.join();
+        }
+        let cancelled = self.cancelled();
+        emit(&self.snapshot(if cancelled { "cancelled" } else { "progress" }));
+        cancelled
+    }
+
+    fn watch_interrupts(&self) {
+        for signal in INTERRUPTS {
+            let _ = flag::register_conditional_default(signal, Arc::clone(&self.finished));
+            let _ = flag::register_conditional_default(signal, Arc::clone(&self.cancelled));
+            let _ = flag::register(signal, Arc::clone(&self.cancelled));
+        }
+    }
+
+    fn run(&self, interval: Duration) {
+        let mut next = Instant::now() + interval;
+        let mut stopped = self.stopped.lock().unwrap_or_else(|e| e.into_inner());
+        while !*stopped && !self.cancelled() {
+            let now = Instant::now();
+            if now >= next {
+                self.report();
+                next = now + interval;
+            }
+            let wait = (next - now.min(next)).min(CANCEL_POLL);
+            stopped = self
+                .wake
+                .wait_timeout(stopped, wait)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    fn snapshot(&self, event: &str) -> Value {
+        json!({
+            "event": event,
+            "processed": self.processed.load(Ordering::Relaxed),
+            "total": self.total,
+            "matches": self.matches.load(Ordering::Relaxed),
+            "elapsed_ms": self.started.elapsed().as_millis(),
+        })
+    }
+
+    fn report(&self) {
+        emit(&self.snapshot("progress"));
+    }
+}
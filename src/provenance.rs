@@ -0,0 +1,76 @@
+use crate::glob;
+use regex::Regex;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+pub const FIRST_PARTY: &str = "first_party";
+pub const VENDORED: &str = "vendored";
+pub const GENERATED: &str = "generated";
+pub const ALL: [&str; 3] = [FIRST_PARTY, VENDORED, GENERATED];
+
+pub const VENDORED_GLOBS: &[&str] = &[
+    "vendor/",
+    "vendors/",
+    "third_party/",
+    "third-party/",
+    "node_modules/",
+    "bower_components/",
+];
+
+pub const GENERATED_GLOBS: &[&str] = &[
+    "*.min.js",
+    "*.min.css",
+    "*.bundle.js",
+    "*.pb.go",
+    "*.pb.cc",
+    "*.pb.h",
+    "*_pb2.py",
+    "*.g.dart",
+    "*.generated.*",
+    "*.designer.cs",
+];
+
+const MARKERS: &str = r"(?i)@generated\b|do not edit|code generated|auto-?generated";
+const PROBE_BYTES: u64 = 1024;
+
+pub struct Classifier {
+    vendored: Vec<Regex>,
+    generated: Vec<Regex>,
+    marker: Regex,
+}
+
+impl Classifier {
+    pub fn new(vendored: &[String], generated: &[String]) -> Result<Classifier, String> {
+        let compile = |list: &[String], defaults: &[&str]| -> Result<Vec<Regex>, String> {
+            if list.is_empty() {
+                defaults.iter().map(|g| glob::path_regex(g)).collect()
+            } else {
+                list.iter().map(|g| glob::path_regex(g)).collect()
+            }
+        };
+        Ok(Classifier {
+            vendored: compile(vendored, VENDORED_GLOBS)?,
+            generated: compile(generated, GENERATED_GLOBS)?,
+            marker: Regex::new(MARKERS).map_err(|e| e.to_string())?,
+        })
+    }
+
+    pub fn classify(&self, relative: &str, file: &Path) -> &'static str {
+        if self.generated.iter().any(|g| g.is_match(relative)) || self.has_marker(file) {
+            GENERATED
+        } else if self.vendored.iter().any(|g| g.is_match(relative)) {
+            VENDORED
+        } else {
+            FIRST_PARTY
+        }
+    }
+
+    fn has_marker(&self, file: &Path) -> bool {
+        let mut head = Vec::new();
+        File::open(file)
+            .and_then(|f| f.take(PROBE_BYTES).read_to_end(&mut head))
+            .is_ok()
+            && self.marker.is_match(&String::from_utf8_lossy(&head))
+    }
+}
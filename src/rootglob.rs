@@ -0,0 +1,92 @@
+use crate::glob;
+use std::fs;
+use std::path::{is_separator, Component, Path, PathBuf};
+
+pub fn is_glob(raw: &str) -> bool {
+    raw.contains(['*', '?', '['])
+}
+
+pub fn expand_home(raw: &str) -> PathBuf {
+    let rest = match raw.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(is_separator) => rest,
+        _ => return PathBuf::from(raw),
+    };
+    let Some(home) = dirs::home_dir() else {
+        return PathBuf::from(raw);
+    };
+    match rest.trim_start_matches(is_separator) {
+        "" => home,
+        rest => home.join(rest),
+    }
+}
+
+pub fn expand(raw: &str) -> Result<Vec<PathBuf>, String> {
+    let mut current = vec![PathBuf::new()];
+    for component in Path::new(raw).components() {
+        let Component::Normal(name) = component else {
+            for p in &mut current {
+                p.push(component);
+            }
+            continue;
+        };
+        let name = name.to_string_lossy();
+        if name == "**" {
+            current = current.iter().flat_map(|p| descendants(p)).collect();
+        } else if is_glob(&name) {
+            let re = glob::regex(&name, false).map_err(|e| format!("{e} in {raw}"))?;
+            let hidden = name.starts_with('.');
+            current = current
+                .iter()
+                .flat_map(|p| children(p))
+                .filter(|(_, n)| (hidden || !n.starts_with('.')) && re.is_match(n))
+                .map(|(p, _)| p)
+                .collect();
+        } else {
+            for p in &mut current {
+                p.push(&*name);
+            }
+        }
+    }
+    current.retain(|p| p.is_dir());
+    current.sort();
+    current.dedup();
+    Ok(current)
+}
+
+fn listed(dir: &Path) -> &Path {
+    if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    }
+}
+
+fn children(dir: &Path) -> Vec<(PathBuf, String)> {
+    let Ok(entries) = fs::read_dir(listed(dir)) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .filter_map(|e| {
+            let name = e.file_name().into_string().ok()?;
+            Some((dir.join(&name), name))
+        })
+        .collect()
+}
+
+fn descendants(dir: &Path) -> Vec<PathBuf> {
+    let mut out = vec![dir.to_path_buf()];
+    let mut i = 0;
+    while i < out.len() {
+        let found = children(&out[i]);
+        out.extend(
+            found
+                .into_iter()
+                .filter(|(_, n)| !n.starts_with('.'))
+                .map(|(p, _)| p),
+        );
+        i += 1;
+    }
+    out
+}
@@ -0,0 +1,310 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    All,
+    Comments,
+    Strings,
+    Code,
+}
+
+impl Scope {
+    pub fn parse(s: &str) -> Option<Scope> {
+        match s {
+            "all" => Some(Scope::All),
+            "comments" => Some(Scope::Comments),
+            "strings" => Some(Scope::Strings),
+            "code" => Some(Scope::Code),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Region {
+    Code,
+    Comment,
+    Str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharLiterals {
+    Quoted,
+    RustChars,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Syntax {
+    CLike {
+        chars: CharLiterals,
+        backtick_strings: bool,
+        raw_strings: bool,
+    },
+    Python,
+}
+
+fn syntax_for(ext: &str) -> Option<Syntax> {
+    let c_like = |chars, backtick_strings, raw_strings| Syntax::CLike {
+        chars,
+        backtick_strings,
+        raw_strings,
+    };
+    match ext.to_lowercase().as_str() {
+        "rs" => Some(c_like(CharLiterals::RustChars, false, true)),
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => {
+            Some(c_like(CharLiterals::Quoted, true, false))
+        }
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" | "java" => {
+            Some(c_like(CharLiterals::Quoted, false, false))
+        }
+        "py" | "pyi" => Some(Syntax::Python),
+        _ => None,
+    }
+}
+
+pub fn mask(text: &str, ext: Option<&str>, scope: Scope) -> Option<String> {
+    let keep = match scope {
+        Scope::All => return None,
+        Scope::Comments => Region::Comment,
+        Scope::Strings => Region::Str,
+        Scope::Code => Region::Code,
+    };
+    let syntax = syntax_for(ext?)?;
+
+    let regions = classify(text.as_bytes(), syntax);
+    let mut out = String::with_capacity(text.len());
+    for (i, c) in text.char_indices() {
+        if regions[i] == keep || c == '\n' {
+            out.push(c);
+        } else {
+            out.extend(std::iter::repeat_n(' ', c.len_utf8()));
+        }
+    }
+    Some(out)
+}
+
+fn classify(b: &[u8], syntax: Syntax) -> Vec<Region> {
+    let mut regions = vec![Region::Code; b.len()];
+    let mut i = 0;
+    while i < b.len() {
+        let (len, region) = match syntax {
+            Syntax::CLike {
+                chars,
+                backtick_strings,
+                raw_strings,
+            } => c_like_token(b, i, chars, backtick_strings, raw_strings),
+            Syntax::Python => python_token(b, i),
+        };
+        let end = (i + len.max(1)).min(b.len());
+        if region != Region::Code {
+            regions[i..end].fill(region);
+        }
+        i = end;
+    }
+    regions
+}
+
+fn c_like_token(
+    b: &[u8],
+    i: usize,
+    chars: CharLiterals,
+    backtick_strings: bool,
+    raw_strings: bool,
+) -> (usize, Region) {
+    let next = b.get(i + 1).copied();
+    match b[i] {
+        b'/' if next == Some(b'/') => (until_newline(b, i), Region::Comment),
+        b'/' if next == Some(b'*') => {
+            let end = find(b, i + 2, b"*/").map_or(b.len(), |p| p + 2);
+            (end - i, Region::Comment)
+        }
+        b'"' => (quoted(b, i, b'"', true), Region::Str),
+        b'`' if backtick_strings => (quoted(b, i, b'`', true), Region::Str),
+        b'\'' if chars == CharLiterals::Quoted => (quoted(b, i, b'\'', false), Region::Str),
+        b'\'' => match rust_char_literal(b, i) {
+            Some(len) => (len, Region::Str),
+            None => (1, Region::Code),
+        },
+        b'r' if raw_strings && !is_ident_byte(i.checked_sub(1).map(|p| b[p])) => {
+            match rust_raw_string(b, i) {
+                Some(len) => (len, Region::Str),
+                None => (1, Region::Code),
+            }
+        }
+        _ => (1, Region::Code),
+    }
+}
+
+fn python_token(b: &[u8], i: usize) -> (usize, Region) {
+    match b[i] {
+        b'#' => (until_newline(b, i), Region::Comment),
+        q @ (b'"' | b'\'') => {
+            if b[i..].starts_with(&[q, q, q]) {
+                let end = (i + 3..b.len())
+                    .find(|&p| b[p..].starts_with(&[q, q, q]) && !escaped(b, i + 3, p))
+                    .map_or(b.len(), |p| p + 3);
+                (end - i, Region::Str)
+            } else {
+                (quoted(b, i, q, false), Region::Str)
+            }
+        }
+        _ => (1, Region::Code),
+    }
+}
+
+fn until_newline(b: &[u8], i: usize) -> usize {
+    b[i..]
+        .iter()
+        .position(|&c| c == b'\n')
+        .unwrap_or(b.len() - i)
+}
+
+fn find(b: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    (from..b.len()).find(|&p| b[p..].starts_with(needle))
+}
+
+fn escaped(b: &[u8], start: usize, p: usize) -> bool {
+    b[start..p]
+        .iter()
+        .rev()
+        .take_while(|&&c| c == b'\\')
+        .count()
+        % 2
+        == 1
+}
+
+fn quoted(b: &[u8], i: usize, quote: u8, multiline: bool) -> usize {
+    let mut p = i + 1;
+    while p < b.len() {
+        match b[p] {
+            b'\\' => p += 2,
+            b'\n' if !multiline => return p - i,
+            c if c == quote => return p + 1 - i,
+            _ => p += 1,
+        }
+    }
+    b.len() - i
+}
+
+fn rust_char_literal(b: &[u8], i: usize) -> Option<usize> {
+    let first = *b.get(i + 1)?;
+    if first == b'\\' {
+        let close = (i + 3..b.len().min(i + 12)).find(|&p| b[p] == b'\'')?;
+        return Some(close + 1 - i);
+    }
+    let width = match first {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    };
+    (b.get(i + 1 + width) == Some(&b'\'')).then_some(width + 2)
+}
+
+fn rust_raw_string(b: &[u8], i: usize) -> Option<usize> {
+    let hashes = b[i + 1..].iter().take_while(|&&c| c == b'#').count();
+    let open = i + 1 + hashes;
+    if b.get(open) != Some(&b'"') {
+        return None;
+    }
+    let mut close = vec![b'"'];
+    close.extend(std::iter::repeat_n(b'#', hashes));
+    let end = find(b, open + 1, &close).map_or(b.len(), |p| p + close.len());
+    Some(end - i)
+}
+
+fn is_ident_byte(c: Option<u8>) -> bool {
+    c.is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kept(text: &str, ext: &str, scope: Scope) -> Vec<String> {
+        mask(text, Some(ext), scope)
+            .unwrap()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn all_and_unknown_languages_are_not_masked() {
+        assert_eq!(mask("x // y", Some("rs"), Scope::All), None);
+        assert_eq!(mask("x // y", Some("txt"), Scope::Comments), None);
+        assert_eq!(mask("x // y", None, Scope::Comments), None);
+    }
+
+    #[test]
+    fn masking_keeps_byte_offsets() {
+        let text = "let é = \"ü\"; // ñ\n";
+        let masked = mask(text, Some("rs"), Scope::Comments).unwrap();
+        assert_eq!(masked.len(), text.len());
+        assert_eq!(masked.find('ñ'), text.find('ñ'));
+        assert!(!masked.contains('é'));
+        assert_eq!(masked.matches('\n').count(), 1);
+    }
+
+    #[test]
+    fn rust_tokens() {
+        let text = "let c = '\"'; // note\nlet s = r#\"raw \" // not\"#; /* block */ 'a' x";
+        assert_eq!(
+            kept(text, "rs", Scope::Comments),
+            ["//", "note", "/*", "block", "*/"]
+        );
+        assert_eq!(
+            kept(text, "rs", Scope::Strings),
+            ["'\"'", "r#\"raw", "\"", "//", "not\"#", "'a'"]
+        );
+        assert_eq!(
+            kept(text, "rs", Scope::Code),
+            ["let", "c", "=", ";", "let", "s", "=", ";", "x"]
+        );
+    }
+
+    #[test]
+    fn rust_lifetimes_are_code() {
+        let text = "fn f<'a>(x: &'a str) {}";
+        assert!(kept(text, "rs", Scope::Strings).is_empty());
+    }
+
+    #[test]
+    fn javascript_tokens() {
+        let text = "const t = `multi\nline // no`; // yes\nconst q = 'it\\'s';";
+        assert_eq!(kept(text, "js", Scope::Comments), ["//", "yes"]);
+        assert_eq!(
+            kept(text, "ts", Scope::Strings),
+            ["`multi", "line", "//", "no`", "'it\\'s'"]
+        );
+    }
+
+    #[test]
+    fn c_and_java_tokens() {
+        let text = "char c = '/'; /* a\n b */ int x; // end";
+        assert_eq!(
+            kept(text, "c", Scope::Comments),
+            ["/*", "a", "b", "*/", "//", "end"]
+        );
+        assert_eq!(kept(text, "java", Scope::Strings), ["'/'"]);
+        assert_eq!(
+            kept(text, "cpp", Scope::Code),
+            ["char", "c", "=", ";", "int", "x;"]
+        );
+    }
+
+    #[test]
+    fn python_tokens() {
+        let text = "s = '''doc # no\n'''  # yes\nt = \"a#b\" # c";
+        assert_eq!(kept(text, "py", Scope::Comments), ["#", "yes", "#", "c"]);
+        assert_eq!(
+            kept(text, "py", Scope::Strings),
+            ["'''doc", "#", "no", "'''", "\"a#b\""]
+        );
+        assert_eq!(kept(text, "pyi", Scope::Code), ["s", "=", "t", "="]);
+    }
+
+    #[test]
+    fn unterminated_tokens_run_to_the_end() {
+        assert_eq!(kept("x /* open", "c", Scope::Comments), ["/*", "open"]);
+        assert_eq!(kept("x = \"open", "js", Scope::Strings), ["\"open"]);
+    }
+}
@@ -0,0 +1,40 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+const RETAIN_BYTES: usize = 1 << 20;
+
+static REUSE: AtomicBool = AtomicBool::new(true);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn set_reuse(on: bool) {
+    REUSE.store(on, Ordering::Relaxed);
+}
+
+pub fn take_allocations() -> usize {
+    ALLOCATIONS.swap(0, Ordering::Relaxed)
+}
+
+pub fn with_buffer<R>(capacity: usize, f: impl FnOnce(&mut Vec<u8>) -> R) -> R {
+    let reuse = REUSE.load(Ordering::Relaxed);
+    let mut buf = if reuse {
+        BUFFER.with(RefCell::take)
+    } else {
+        Vec::new()
+    };
+    buf.clear();
+    if buf.capacity() < capacity {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        buf.reserve(capacity);
+    }
+    let out = f(&mut buf);
+    if reuse {
+        buf.clear();
+        buf.shrink_to(RETAIN_BYTES);
+        BUFFER.with(|b| b.replace(buf));
+    }
+    out
+}
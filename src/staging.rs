@@ -0,0 +1,97 @@
+use crate::memory;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+pub const DEFAULT_LIMIT: Limit = Limit {
+    files: 1000,
+    bytes: 1 << 30,
+};
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct Limit {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} files", self.files)?;
+        if self.bytes != u64::MAX {
+            write!(f, " or {}", memory::format_size(self.bytes))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_limit(s: &str) -> Result<Limit, String> {
+    let (files, bytes) = match s.split_once(',') {
+        Some((files, bytes)) => (files, Some(bytes)),
+        None => (s, None),
+    };
+    let files = files
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid file count `{files}` (expected FILES[,SIZE])"))?;
+    let bytes = match bytes {
+        Some(b) => memory::parse_size(b.trim())?,
+        None => u64::MAX,
+    };
+    Ok(Limit { files, bytes })
+}
+
+pub struct Planner<'a> {
+    roots: &'a [PathBuf],
+    claimed: HashMap<PathBuf, usize>,
+}
+
+impl<'a> Planner<'a> {
+    pub fn new(roots: &'a [PathBuf]) -> Self {
+        Planner {
+            roots,
+            claimed: HashMap::new(),
+        }
+    }
+
+    pub fn destination(&mut self, path: &Path) -> PathBuf {
+        let (index, rel) = self
+            .roots
+            .iter()
+            .enumerate()
+            .find_map(|(i, root)| Some((i, path.strip_prefix(root).ok()?)))
+            .unwrap_or((usize::MAX, path));
+        let rel: PathBuf = rel
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect();
+        let owner = *self.claimed.entry(rel.clone()).or_insert(index);
+        if owner == index || index == usize::MAX {
+            rel
+        } else {
+            PathBuf::from((index + 1).to_string()).join(rel)
+        }
+    }
+}
+
+pub fn copy(from: &Path, to: &Path) -> io::Result<u64> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut source = fs::File::open(from)?;
+    let permissions = source.metadata()?.permissions();
+    let mut dest = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => {
+                io::Error::new(e.kind(), "destination already exists; not overwritten")
+            }
+            _ => e,
+        })?;
+    let n = io::copy(&mut source, &mut dest)?;
+    dest.set_permissions(permissions)?;
+    Ok(n)
+}
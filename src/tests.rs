@@ -0,0 +1,354 @@
+use super::*;
+
+fn search_args(argv: &[&str]) -> SearchArgs {
+    SearchArgs::try_parse_from(std::iter::once("search").chain(argv.iter().copied()))
+        .expect("valid search arguments")
+}
+
+#[test]
+fn default_format_switches_only_for_pipes() {
+    for (stdout, want, note) in [
+        (
+            StdoutKind::Terminal,
+            "md",
+            Some("stdout is a terminal, using --format md"),
+        ),
+        (StdoutKind::Other, "md", None),
+        (
+            StdoutKind::Pipe,
+            "jsonl",
+            Some("stdout is a pipe, using --format jsonl"),
+        ),
+    ] {
+        let mut args = search_args(&["--name", "x"]);
+        let printed = resolve_default_format(&mut args, stdout);
+        assert_eq!(args.format.as_deref(), Some(want), "{stdout:?}");
+        match (printed, note) {
+            (Some(printed), Some(note)) => {
+                assert!(printed.contains(note), "{printed}");
+                assert!(printed.contains("pass --format"), "{printed}");
+                assert!(printed.contains("RUSTFILEFINDER_FORMAT"), "{printed}");
+            }
+            (printed, note) => assert_eq!(printed.is_some(), note.is_some(), "{stdout:?}"),
+        }
+    }
+}
+
+#[test]
+fn default_format_keeps_explicit_choices() {
+    let mut args = search_args(&["--name", "x", "--output", "report.md"]);
+    resolve_default_format(&mut args, StdoutKind::Pipe);
+    assert_eq!(args.format.as_deref(), Some("md"));
+
+    let mut args = search_args(&["--name", "x", "--format", "paths"]);
+    assert!(resolve_default_format(&mut args, StdoutKind::Pipe).is_none());
+    assert_eq!(args.format.as_deref(), Some("paths"));
+}
+
+#[test]
+fn checkpoint_copies_are_redacted() {
+    let matcher = build_matcher(&["hunter2".to_string()], false, false, false, None).unwrap();
+    let rules = vec![ContentRule {
+        label: Some("password".to_string()),
+        matcher,
+    }];
+    let mut r = name_only_result(Path::new("cfg.txt"), Some("cfg"), true).remove(0);
+    r.matched_content = true;
+    r.snippet = Some("password = hunter2".to_string());
+    r.context = Some("[db] password = hunter2".to_string());
+    r.matched_patterns = vec!["hunter2".to_string()];
+    r.offsets = Some(MatchOffsets {
+        match_start: 11,
+        match_end: 18,
+        snippet_start: 0,
+        offsets_are_extracted_text: false,
+    });
+    let args = search_args(&["--content", "hunter2", "--redact"]);
+    let mut checkpoint = Checkpoint::new(String::new());
+    checkpoint.results.push(redacted_copy(&r, &rules, &args));
+    let saved = serde_json::to_string(&checkpoint).unwrap();
+    assert!(!saved.contains("hunter2"), "{saved}");
+    assert!(saved.contains("[REDACTED:password]"), "{saved}");
+}
+
+#[test]
+fn control_characters_in_valid_text_keep_a_text_snippet() {
+    for text in [
+        "\x1b[31merror\x1b[0m: disk full",
+        "int a;\n\x0c\nint needle;",
+        "col1\tcol2\r\nerror",
+    ] {
+        let at = text.find(['e', 'n']).unwrap();
+        let (_, snippet, kind) = snippet_around_match(text, 0, at, at + 1, 40, 200, None);
+        assert_eq!(kind, SnippetKind::Text, "{text:?}");
+        assert!(!snippet.is_empty());
+    }
+}
+
+#[test]
+fn nul_or_undecodable_bytes_get_a_hex_snippet() {
+    for text in ["head\0\0\0error tail", "head\u{fffd}\u{fffd}error"] {
+        let at = text.find("error").unwrap();
+        let (_, _, kind) = snippet_around_match(text, 0, at, at + 5, 40, 200, None);
+        assert_eq!(kind, SnippetKind::Hex, "{text:?}");
+    }
+}
+
+fn merged(argv: &[&str], config: &str) -> SearchArgs {
+    let cfg: AppConfig = toml::from_str(config).expect("valid config");
+    merge_search_args(search_args(argv), Some(cfg))
+}
+
+#[test]
+fn language_ignore_dirs_merge_with_explicit_ones() {
+    let args = merged(
+        &["--name", "x", "--preset", "p"],
+        "[defaults]\nlanguage = \"python\"\n[presets.p]\nignore_dir = [\"build\"]\n",
+    );
+    assert!(args.ignore_dir.contains(&"build".to_string()));
+    assert!(args.ignore_dir.contains(&"__pycache__".to_string()));
+
+    let args = merged(
+        &[
+            "--name",
+            "x",
+            "--ignore-dir",
+            "target",
+            "--ignore-dir",
+            "out",
+        ],
+        "[defaults]\nlanguage = \"rust\"\n",
+    );
+    assert_eq!(args.ignore_dir, vec!["target", "out"]);
+}
+
+#[test]
+fn explicit_ext_outranks_language() {
+    let config = "[presets.rust]\nlanguage = \"rust\"\n\
+                  [presets.notes]\nlanguage = \"python\"\next = \"md\"\n";
+    let cases: [(&[&str], &str); 4] = [
+        (&["--name", "x", "--preset", "rust"], "rs"),
+        (&["--name", "x", "--preset", "rust", "--ext", "txt"], "txt"),
+        (&["--name", "x", "--preset", "notes"], "md"),
+        (
+            &["--name", "x", "--preset", "notes", "--ext", "toml"],
+            "toml",
+        ),
+    ];
+    for (argv, want) in cases {
+        let args = merged(argv, config);
+        assert_eq!(args.ext.as_deref(), Some(want), "{argv:?}");
+    }
+    let args = merged(&["--name", "x", "--preset", "notes"], config);
+    assert!(args.ignore_dir.contains(&"__pycache__".to_string()));
+}
+
+fn per_byte_spans(original: &str, normalized: &Normalized) -> Vec<(usize, usize)> {
+    (0..normalized.text.len())
+        .map(|i| normalized.original(i, i + 1))
+        .inspect(|&(start, end)| assert!(start <= end && end <= original.len()))
+        .collect()
+}
+
+#[test]
+fn normalized_offsets_map_back_to_the_source() {
+    let text = "a  ﬁle\r\n\twith\u{a0}\u{a0}ﬀ  tabs  \n\nend";
+    let n = normalize_whitespace(text);
+    assert_eq!(n.text, "a file with ff tabs end");
+    let spans = per_byte_spans(text, &n);
+    let at = n.text.find("file").unwrap();
+    assert_eq!(n.original(at, at + 4), (3, 8));
+    assert_eq!(&text[spans[1].0..spans[1].1], "  ");
+    let ff = n.text.find("ff").unwrap();
+    assert_eq!(n.original(ff, ff + 1), n.original(ff + 1, ff + 2));
+}
+
+#[test]
+fn single_byte_whitespace_keeps_one_offset_segment() {
+    let text = "some words on a line\n".repeat(10_000);
+    assert_eq!(normalize_whitespace(&text).map.segments.len(), 1);
+    assert_eq!(trim_for_anchors(&text, true).map.segments.len(), 1);
+}
+
+#[test]
+fn trimmed_offsets_skip_bom_and_line_ends() {
+    let text = "\u{feff}title\r\nbody  \r\nend\r\n";
+    let t = trim_for_anchors(text, true);
+    assert_eq!(t.text, "title\nbody\nend");
+    let body = t.text.find("body").unwrap();
+    assert_eq!(
+        &text[t.original(body, body + 4).0..t.original(body, body + 4).1],
+        "body"
+    );
+    assert_eq!(t.original(0, 5), (3, 8));
+    let end = t.text.find("end").unwrap();
+    assert_eq!(
+        &text[t.original(end, end + 3).0..t.original(end, end + 3).1],
+        "end"
+    );
+}
+
+#[test]
+fn trimmed_and_normalized_offsets_compose() {
+    let text = "\u{feff}one  ﬁ\r\ntwo \r\n";
+    let n = prepare_haystack(text, true, true, true).unwrap();
+    assert_eq!(n.text, "one fi two");
+    let fi = n.text.find("fi").unwrap();
+    assert_eq!(
+        &text[n.original(fi, fi + 2).0..n.original(fi, fi + 2).1],
+        "ﬁ"
+    );
+    let two = n.text.find("two").unwrap();
+    assert_eq!(
+        &text[n.original(two, two + 3).0..n.original(two, two + 3).1],
+        "two"
+    );
+    let gap = n.text.find(" two").unwrap();
+    assert_eq!(
+        &text[n.original(gap, gap + 1).0..n.original(gap, gap + 1).1],
+        "\n"
+    );
+}
+
+fn output_settings(args: &SearchArgs) -> String {
+    format!(
+        "output={:?} sort={} reverse={} snippet_context={} snippet_max={} color={} quiet={}",
+        args.output.as_deref().map(Path::to_string_lossy),
+        args.sort,
+        args.reverse,
+        args.snippet_context,
+        args.snippet_max,
+        args.color,
+        args.quiet,
+    )
+}
+
+#[test]
+fn output_settings_merge_matrix() {
+    let layer = |output: &str, sort: &str, context: usize, max: usize, color: &str| {
+        format!(
+            "output = \"{output}\"\nsort = \"{sort}\"\nreverse = true\n\
+             snippet_context = {context}\nsnippet_max = {max}\ncolor = \"{color}\"\nquiet = true\n"
+        )
+    };
+    let defaults = format!("[defaults]\n{}", layer("d.json", "size", 10, 100, "never"));
+    let preset = format!(
+        "[presets.p]\n{}",
+        layer("p.json", "mtime", 20, 200, "always")
+    );
+    let cli = [
+        "--output",
+        "c.json",
+        "--sort",
+        "name",
+        "--snippet-context",
+        "30",
+        "--snippet-max",
+        "300",
+        "--color",
+        "always",
+    ];
+    let from = |out, sort, context, max, color, flags: bool| {
+        format!(
+            "output=Some({out:?}) sort={sort} reverse={flags} snippet_context={context} \
+             snippet_max={max} color={color} quiet={flags}"
+        )
+    };
+    let with_preset = |extra: &[&'static str]| {
+        let mut argv = vec!["--name", "x", "--preset", "p"];
+        argv.extend_from_slice(extra);
+        argv
+    };
+
+    let cases: Vec<(Vec<&str>, String, String)> = vec![
+        (
+            vec!["--name", "x"],
+            defaults.clone(),
+            from("d.json", "size", 10, 100, "never", true),
+        ),
+        (
+            with_preset(&[]),
+            preset.clone(),
+            from("p.json", "mtime", 20, 200, "always", true),
+        ),
+        (
+            with_preset(&[]),
+            format!("{defaults}{preset}"),
+            from("d.json", "size", 10, 100, "never", true),
+        ),
+        (
+            with_preset(&cli),
+            format!("{defaults}{preset}"),
+            from("c.json", "name", 30, 300, "always", true),
+        ),
+        (
+            with_preset(&cli),
+            String::new(),
+            from("c.json", "name", 30, 300, "always", false),
+        ),
+    ];
+    for (argv, config, expected) in cases {
+        assert_eq!(
+            output_settings(&merged(&argv, &config)),
+            expected,
+            "argv {argv:?} with config:\n{config}"
+        );
+    }
+}
+
+#[test]
+fn fold_name_expands_sharp_s() {
+    assert_eq!(fold_name("STRASSE", true), "strasse");
+    assert_eq!(fold_name("straße", true), "strasse");
+    assert_eq!(fold_name("STRAẞE", true), "strasse");
+    assert_ne!(fold_name("straße", false), fold_name("STRASSE", false));
+}
+
+#[test]
+fn fold_name_keeps_turkish_dotted_and_dotless_i_apart() {
+    assert_eq!(fold_name("I", true), "i");
+    assert_eq!(fold_name("İ", true), "i\u{307}");
+    assert_eq!(fold_name("ı", true), "ı");
+    assert_ne!(fold_name("ılık", true), fold_name("ILIK", true));
+    assert_eq!(fold_name("İSTANBUL", true), fold_name("i̇stanbul", true));
+}
+
+#[test]
+fn fold_name_composes_decomposed_names() {
+    let nfd = "Cafe\u{301}.txt";
+    let nfc = "Caf\u{e9}.txt";
+    assert_eq!(fold_name(nfd, true), fold_name(nfc, true));
+    assert_eq!(fold_name(nfd, true), "caf\u{e9}.txt");
+    assert_ne!(fold_name(nfd, false), fold_name(nfc, false));
+}
+
+#[test]
+fn relative_age_boundaries() {
+    let now = SystemTime::UNIX_EPOCH + Duration::from_secs(10 * 31_536_000);
+    let ago = |secs: u64| relative_age(now - Duration::from_secs(secs), now);
+
+    assert_eq!(ago(0), "just now");
+    assert_eq!(ago(59), "just now");
+    assert_eq!(ago(60), "1 minute ago");
+    assert_eq!(ago(59 * 60 + 59), "59 minutes ago");
+    assert_eq!(ago(3_600), "1 hour ago");
+    assert_eq!(ago(86_399), "23 hours ago");
+    assert_eq!(ago(86_400), "1 day ago");
+    assert_eq!(ago(2_592_000), "1 month ago");
+    assert_eq!(ago(31_536_000), "1 year ago");
+    assert_eq!(ago(3 * 31_536_000), "3 years ago");
+}
+
+#[test]
+fn relative_age_future_mtime_is_clock_skew() {
+    let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    assert_eq!(
+        relative_age(now + Duration::from_secs(1), now),
+        "in the future (clock skew?)"
+    );
+    assert_eq!(
+        relative_age(now + Duration::from_secs(86_400), now),
+        "in the future (clock skew?)"
+    );
+    assert_eq!(relative_age(now, now), "just now");
+}
@@ -0,0 +1,98 @@
+use std::io::{self, Read};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub struct IoThrottle {
+    rate: Option<f64>,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+pub struct Throttled<'a, R> {
+    inner: R,
+    throttle: &'a IoThrottle,
+}
+
+impl IoThrottle {
+    pub fn new(bytes_per_sec: Option<f64>) -> Self {
+        IoThrottle {
+            rate: bytes_per_sec.filter(|r| *r > 0.0),
+            bucket: Mutex::new(Bucket {
+                tokens: 0.0,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn consume(&self, bytes: u64) {
+        let Some(rate) = self.rate else { return };
+
+        let wait = {
+            let mut b = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let refill = now.duration_since(b.refilled).as_secs_f64() * rate;
+            b.tokens = (b.tokens + refill).min(rate) - bytes as f64;
+            b.refilled = now;
+            if b.tokens < 0.0 {
+                Duration::from_secs_f64(-b.tokens / rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    pub fn reader<R: Read>(&self, inner: R) -> Throttled<'_, R> {
+        Throttled {
+            inner,
+            throttle: self,
+        }
+    }
+}
+
+impl<R: Read> Read for Throttled<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.throttle.consume(n as u64);
+        Ok(n)
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn set_idle_io_priority() -> io::Result<()> {
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+
+    ioprio_set(
+        IOPRIO_WHO_PROCESS,
+        0,
+        IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn ioprio_set(which: libc::c_long, who: libc::c_long, ioprio: libc::c_long) -> io::Result<()> {
+    // SAFETY: ioprio_set takes three integers and no pointers, so no memory is
+    // shared with the kernel; a bad argument only makes the call fail with EINVAL.
+    let ret = unsafe { libc::syscall(libc::SYS_ioprio_set, which, who, ioprio) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_idle_io_priority() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "idle I/O priority is only available on Linux",
+    ))
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::{results, Fixture};
+
+fn occurrences(fx: &Fixture, pattern: &str) -> u64 {
+    let report = fx.search_json(&["--content", pattern, "--all-matches"]);
+    let hits = results(&report);
+    assert_eq!(
+        hits.len() as u64,
+        hits[0]["occurrences"].as_u64().unwrap_or(1)
+    );
+    hits.len() as u64
+}
+
+#[test]
+fn anchors_see_the_whole_haystack_after_the_first_match() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "foofoo\nxfoo\n");
+    assert_eq!(occurrences(&fx, "^foo"), 1);
+    assert_eq!(occurrences(&fx, r"\bfoo"), 1);
+    assert_eq!(occurrences(&fx, "(?m)foo$"), 2);
+    assert_eq!(occurrences(&fx, "foo"), 3);
+}
+
+#[test]
+fn redaction_masks_every_occurrence_with_context() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "foofoo\n");
+    let report = fx.search_json(&["--content", r"\bfoo", "--redact"]);
+    let snippet = results(&report)[0]["snippet"].as_str().unwrap().to_string();
+    assert_eq!(snippet.matches("[REDACTED]").count(), 1, "{snippet}");
+}
+
+#[test]
+fn script_rejection_resumes_from_the_full_haystack_offset() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "kotkot abc кот привет мир дом лес\n");
+    let report = fx.search_json(&[
+        "--content",
+        r"\b(kot|кот)",
+        "--all-matches",
+        "--require-script",
+        "Cyrillic",
+    ]);
+    assert_eq!(results(&report).len(), 1);
+    assert_eq!(report["stats"]["matches_rejected_by_script"], 1);
+}
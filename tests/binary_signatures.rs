@@ -0,0 +1,58 @@
+mod common;
+
+use common::{result_paths, results, Fixture};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const PATTERN: &str = r"\x89PNG\r\n\x1a\n";
+
+fn png() -> Vec<u8> {
+    let mut image = PNG_SIGNATURE.to_vec();
+    image.extend_from_slice(b"\x00\x00\x00\x0dIHDR\x00\x00\x00\x01\x00\x00\x00\x01\x08\x02");
+    image
+}
+
+#[test]
+fn finds_the_png_header_in_an_image() {
+    let fx = Fixture::new();
+    fx.file("pixel.png", png());
+    fx.file("notes.txt", "PNG files start with 89 50 4e 47\n");
+
+    let report = fx.search_json(&["--binary", "--content", PATTERN]);
+    assert_eq!(result_paths(&report), ["pixel.png"]);
+    let hit = &results(&report)[0];
+    assert_eq!(hit["snippet_kind"], "hex");
+    assert_eq!(hit["match_start"], 0);
+    assert_eq!(hit["match_end"], 8);
+    assert!(hit["snippet"]
+        .as_str()
+        .unwrap()
+        .starts_with("00000000: 8950 4e47 0d0a 1a0a"));
+}
+
+#[test]
+fn finds_an_embedded_signature_at_its_offset() {
+    let fx = Fixture::new();
+    let mut blob = vec![0u8; 40];
+    blob.extend_from_slice(&png());
+    fx.file("bundle.bin", blob);
+
+    let report = fx.search_json(&["--binary", "--content", PATTERN]);
+    let hit = &results(&report)[0];
+    assert_eq!(hit["match_start"], 40);
+    assert_eq!(hit["match_end"], 48);
+}
+
+#[test]
+fn signature_search_needs_binary_mode_and_keeps_filters() {
+    let fx = Fixture::new();
+    fx.file("pixel.png", png());
+
+    let report = fx.search_json(&["--content", PATTERN]);
+    assert!(results(&report).is_empty());
+
+    let report = fx.search_json(&["--binary", "--content", PATTERN, "--ext", "txt"]);
+    assert!(results(&report).is_empty());
+
+    let report = fx.search_json(&["--binary", "--content", PATTERN, "--max-bytes", "8"]);
+    assert!(results(&report).is_empty());
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::Fixture;
+use std::path::Path;
+
+#[test]
+fn byte_totals_match_the_fixture_sizes() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "invoice 1\n");
+    fx.file("b.txt", format!("invoice 2\n{}", "x".repeat(990)));
+    fx.file("big.txt", "y".repeat(5000));
+
+    let report = fx.search_json(&["--content", "invoice", "--max-bytes", "4096"]);
+    let stats = &report["stats"];
+    assert_eq!(stats["bytes_read"], 10 + 1000);
+    assert_eq!(stats["bytes_skipped_too_large"], 5000);
+    assert_eq!(stats["bytes_extracted_pdf"], 0);
+    assert!(stats.get("bytes_read_text").is_none());
+}
+
+#[test]
+fn pdf_bytes_are_read_once_and_extracted_text_is_counted_apart() {
+    let fx = Fixture::new();
+    let sample = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples_pdf/00.introduzione.pdf");
+    let pdf = std::fs::read(sample).expect("read sample pdf");
+    fx.file("doc.pdf", &pdf);
+    fx.file("note.txt", "invoice\n");
+
+    let report = fx.search_json(&["--content", "invoice", "--include-pdf"]);
+    let stats = &report["stats"];
+    assert_eq!(stats["bytes_read"], pdf.len() as u64 + 8);
+    assert!(stats["bytes_extracted_pdf"].as_u64().unwrap() > 0);
+    assert_eq!(stats["bytes_skipped_too_large"], 0);
+}
@@ -0,0 +1,24 @@
+mod common;
+
+use common::{result_paths, Fixture};
+use std::thread::sleep;
+use std::time::Duration;
+
+#[test]
+fn unrelated_query_does_not_move_the_last_run_cutoff() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "alpha\n");
+    sleep(Duration::from_millis(1100));
+    let nightly = ["--content", "alpha", "--changed-since", "last-run"];
+    assert_eq!(result_paths(&fx.search_json(&nightly)), ["a.txt"]);
+
+    fx.file("b.txt", "alpha beta\n");
+    sleep(Duration::from_millis(1100));
+    let adhoc = ["--content", "beta", "--changed-since", "last-run"];
+    assert_eq!(result_paths(&fx.search_json(&adhoc)), ["b.txt"]);
+
+    assert_eq!(result_paths(&fx.search_json(&nightly)), ["b.txt"]);
+    let runs = std::fs::read(fx.home.path().join(".cache/rustfilefinder/last-runs.json")).unwrap();
+    let runs: serde_json::Value = serde_json::from_slice(&runs).unwrap();
+    assert_eq!(runs.as_object().unwrap().len(), 2);
+}
@@ -0,0 +1,86 @@
+#![allow(dead_code)]
+
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+pub struct Fixture {
+    pub dir: TempDir,
+    pub home: TempDir,
+}
+
+impl Fixture {
+    pub fn new() -> Fixture {
+        Fixture {
+            dir: tempfile::tempdir().expect("create fixture dir"),
+            home: tempfile::tempdir().expect("create fixture home"),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn file(&self, name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.path().join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("create fixture subdir");
+        }
+        fs::write(&path, contents).expect("write fixture file");
+        path
+    }
+
+    pub fn command(&self) -> Command {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_rustfilefinder"));
+        cmd.current_dir(self.path())
+            .env("HOME", self.home.path())
+            .env("XDG_CONFIG_HOME", self.home.path().join(".config"))
+            .env("XDG_CACHE_HOME", self.home.path().join(".cache"))
+            .env_remove("RUSTFILEFINDER_FORMAT");
+        cmd
+    }
+
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command()
+            .args(args)
+            .output()
+            .expect("run rustfilefinder")
+    }
+
+    pub fn search(&self, args: &[&str]) -> Output {
+        let dir = self.path().to_str().expect("utf-8 fixture path");
+        let mut full = vec!["search", "--dir", dir];
+        full.extend_from_slice(args);
+        self.run(&full)
+    }
+
+    pub fn search_json(&self, args: &[&str]) -> Value {
+        let mut full = args.to_vec();
+        full.extend_from_slice(&["--format", "json"]);
+        let out = self.search(&full);
+        assert!(
+            out.status.success(),
+            "search failed: {}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        serde_json::from_slice(&out.stdout).expect("search output is JSON")
+    }
+}
+
+pub fn results(report: &Value) -> &Vec<Value> {
+    report["results"].as_array().expect("results array")
+}
+
+pub fn result_paths(report: &Value) -> Vec<String> {
+    results(report)
+        .iter()
+        .map(|r| {
+            let path = r["path"].as_str().expect("result path");
+            Path::new(path)
+                .file_name()
+                .map_or(path.to_string(), |n| n.to_string_lossy().into_owned())
+        })
+        .collect()
+}
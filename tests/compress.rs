@@ -0,0 +1,36 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn zstd_report_gets_extension_and_reads_back() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "alpha needle\n");
+    let out = fx.run(&[
+        "search",
+        "--content",
+        "needle",
+        "--format",
+        "json",
+        "--output",
+        "report.json",
+        "--compress",
+        "zstd",
+    ]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let report = fx.path().join("report.json.zst");
+    let bytes = std::fs::read(&report).unwrap();
+    assert!(bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]));
+
+    let show = fx.run(&["show", report.to_str().unwrap(), "1"]);
+    assert!(
+        show.status.success(),
+        "{}",
+        String::from_utf8_lossy(&show.stderr)
+    );
+    assert!(String::from_utf8_lossy(&show.stdout).contains("alpha needle"));
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::Fixture;
+
+fn rejected(config: &str, expected: &str) {
+    let fx = Fixture::new();
+    fx.file("rustfilefinder.toml", config);
+    fx.file("a.txt", "needle");
+
+    let out = fx.search(&["--content", "needle"]);
+    assert_eq!(out.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains(expected), "stderr: {stderr}");
+}
+
+#[test]
+fn bad_sort_in_config_is_rejected() {
+    rejected(
+        "[defaults]\nsort = \"largest\"\n",
+        "unknown sort key `largest` in the config",
+    );
+}
+
+#[test]
+fn bad_color_in_config_is_rejected() {
+    rejected(
+        "[defaults]\ncolor = \"yes\"\n",
+        "unknown color mode `yes` in the config",
+    );
+}
+
+#[test]
+fn bad_schedule_in_config_is_rejected() {
+    rejected(
+        "[defaults]\nschedule = \"sizes\"\n",
+        "unknown schedule `sizes` in the config",
+    );
+}
+
+#[test]
+fn bad_walk_order_in_config_is_rejected() {
+    rejected(
+        "[defaults]\nwalk_order = \"bfz\"\n",
+        "unknown walk order `bfz` in the config",
+    );
+}
+
+#[test]
+fn bad_case_insensitive_paths_in_config_is_rejected() {
+    rejected(
+        "[defaults]\ncase_insensitive_paths = \"maybe\"\n",
+        "unknown case_insensitive_paths mode `maybe` in the config",
+    );
+}
+
+#[test]
+fn valid_config_values_are_accepted() {
+    let fx = Fixture::new();
+    fx.file(
+        "rustfilefinder.toml",
+        "[defaults]\nsort = \"size\"\ncolor = \"never\"\nschedule = \"path\"\nwalk_order = \"bfs\"\n\
+         case_insensitive_paths = \"off\"\n",
+    );
+    fx.file("a.txt", "needle");
+    assert!(fx.search(&["--content", "needle"]).status.success());
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::{results, Fixture};
+
+#[test]
+fn over_limit_skips_the_copy_but_writes_the_report() {
+    let fx = Fixture::new();
+    fx.file("src/a.txt", "needle\n");
+    fx.file("src/b.txt", "needle\n");
+    let staging = fx.path().join("staging");
+    let src = fx.path().join("src");
+    let out = fx.run(&[
+        "search",
+        "--dir",
+        src.to_str().unwrap(),
+        "--content",
+        "needle",
+        "--copy-matches",
+        staging.to_str().unwrap(),
+        "--copy-limit",
+        "1",
+        "--format",
+        "json",
+    ]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--copy-limit"));
+    let report: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(results(&report).len(), 2);
+    let copied = &report["stats"]["copied"];
+    assert_eq!(copied["files"], 0);
+    assert!(copied["error"].as_str().unwrap().contains("--copy-limit"));
+    assert!(!staging.exists());
+}
+
+#[test]
+fn existing_staged_files_are_not_overwritten() {
+    let fx = Fixture::new();
+    fx.file("src/a.txt", "needle new\n");
+    fx.file("src/b.txt", "needle\n");
+    fx.file("staging/src/a.txt", "keep me\n");
+    let staging = fx.path().join("staging");
+    let report = fx.search_json(&[
+        "--content",
+        "needle",
+        "--copy-matches",
+        staging.to_str().unwrap(),
+    ]);
+    let copied = &report["stats"]["copied"];
+    assert_eq!(copied["files"], 1);
+    assert_eq!(copied["failed"], 1);
+    let a = results(&report)
+        .iter()
+        .find(|r| r["path"].as_str().unwrap().ends_with("a.txt"))
+        .unwrap();
+    assert!(a["warnings"][0]
+        .as_str()
+        .unwrap()
+        .contains("already exists"));
+    assert_eq!(
+        std::fs::read_to_string(staging.join("src/a.txt")).unwrap(),
+        "keep me\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(staging.join("src/b.txt")).unwrap(),
+        "needle\n"
+    );
+}
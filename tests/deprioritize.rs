@@ -0,0 +1,60 @@
+mod common;
+
+use common::{result_paths, results, Fixture};
+
+fn corpus() -> Fixture {
+    let fx = Fixture::new();
+    fx.file("a_test.txt", "invoice\n");
+    fx.file("b.txt", "invoice\n");
+    fx.file("c_test.txt", "invoice\n");
+    fx.file("d.txt", "invoice\n");
+    fx
+}
+
+fn search(fx: &Fixture, extra: &[&str]) -> serde_json::Value {
+    let mut args = vec![
+        "--content",
+        "invoice",
+        "--sort",
+        "path",
+        "--deprioritize",
+        "*_test.txt",
+    ];
+    args.extend_from_slice(extra);
+    fx.search_json(&args)
+}
+
+#[test]
+fn limit_fills_from_the_prioritized_group_first() {
+    let fx = corpus();
+
+    let report = search(&fx, &["--limit", "2"]);
+    assert_eq!(result_paths(&report), ["b.txt", "d.txt"]);
+    assert!(results(&report)
+        .iter()
+        .all(|r| r.get("deprioritized").is_none_or(|d| d == false)));
+
+    let report = search(&fx, &["--limit", "3"]);
+    assert_eq!(result_paths(&report), ["b.txt", "d.txt", "a_test.txt"]);
+    assert_eq!(results(&report)[2]["deprioritized"], true);
+}
+
+#[test]
+fn reverse_sort_keeps_deprioritized_results_last() {
+    let fx = corpus();
+
+    let report = search(&fx, &["--reverse", "--limit", "3"]);
+    assert_eq!(result_paths(&report), ["d.txt", "b.txt", "c_test.txt"]);
+}
+
+#[test]
+fn limit_larger_than_the_prioritized_group_keeps_everything_in_order() {
+    let fx = corpus();
+
+    let report = search(&fx, &["--limit", "10"]);
+    assert_eq!(
+        result_paths(&report),
+        ["b.txt", "d.txt", "a_test.txt", "c_test.txt"]
+    );
+    assert_eq!(report["stats"]["matches_total"], 4);
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::{result_paths, Fixture};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+const VOLATILE_STATS: [&str; 5] = [
+    "discovery_ms",
+    "scan_ms",
+    "elapsed_ms",
+    "read_bytes_per_sec",
+    "memory_peak_bytes",
+];
+
+fn corpus() -> Fixture {
+    let fx = Fixture::new();
+    let pdf = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples_pdf/00.introduzione.pdf");
+    fs::create_dir_all(fx.path().join("docs")).unwrap();
+    fs::copy(&pdf, fx.path().join("docs/linguaggi.pdf")).expect("copy sample pdf");
+    fx.file(
+        "mail/inbox.mbox",
+        "From a@b Mon Jan  1 00:00:00 2024\nSubject: linguaggi\n\nlinguaggi one\n\n\
+From c@d Mon Jan  1 00:00:00 2024\nSubject: other\n\nlinguaggi two\nlinguaggi three\n",
+    );
+    fx.file(
+        "nb/analysis.ipynb",
+        json!({
+            "cells": [
+                {"cell_type": "markdown", "source": ["linguaggi\n"]},
+                {"cell_type": "code", "source": ["x = 'linguaggi'\n"]},
+            ],
+            "nbformat": 4,
+        })
+        .to_string(),
+    );
+    fx.file("bundle.zip", b"PK\x03\x04linguaggi");
+    for i in 0..40 {
+        fx.file(
+            &format!("notes/n{:02}.txt", 39 - i),
+            "linguaggi\nlinguaggi again\n",
+        );
+    }
+    fx.file("notes/same.txt", "linguaggi");
+    fx.file("other/same.txt", "linguaggi");
+    fx
+}
+
+fn run(fx: &Fixture, threads: &str) -> String {
+    let dir = fx.path().to_str().unwrap();
+    let out = fx
+        .command()
+        .env("RAYON_NUM_THREADS", threads)
+        .args([
+            "search",
+            "--dir",
+            dir,
+            "--content",
+            "(?i)linguaggi",
+            "--name",
+            "same",
+            "--include-pdf",
+            "--include-email",
+            "--all-matches",
+            "--format",
+            "json",
+        ])
+        .output()
+        .expect("run rustfilefinder");
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let mut report: Value = serde_json::from_slice(&out.stdout).expect("search output is JSON");
+    for ext in [".pdf", ".mbox", ".ipynb", ".txt"] {
+        assert!(
+            result_paths(&report).iter().any(|p| p.ends_with(ext)),
+            "no {ext} result"
+        );
+    }
+    let stats = report["stats"].as_object_mut().unwrap();
+    for key in VOLATILE_STATS {
+        stats.remove(key);
+    }
+    report["meta"]
+        .as_object_mut()
+        .unwrap()
+        .remove("generated_at");
+    serde_json::to_string_pretty(&report).unwrap()
+}
+
+#[test]
+fn three_runs_give_identical_json() {
+    let fx = corpus();
+    let first = run(&fx, "1");
+    assert_eq!(run(&fx, "4"), first);
+    assert_eq!(run(&fx, "16"), first);
+}
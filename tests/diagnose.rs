@@ -0,0 +1,106 @@
+mod common;
+
+use common::Fixture;
+use serde_json::Value;
+
+fn diagnosis(fx: &Fixture, args: &[&str]) -> Value {
+    let report = fx.search_json(args);
+    assert_eq!(report["stats"]["matches_total"], 0);
+    report["diagnosis"].clone()
+}
+
+fn stages(diagnosis: &Value) -> Vec<u64> {
+    diagnosis["stages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["files"].as_u64().unwrap())
+        .collect()
+}
+
+fn hints(diagnosis: &Value) -> Vec<&str> {
+    diagnosis["hints"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|h| h.as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn ext_filter_that_excludes_everything() {
+    let fx = Fixture::new();
+    fx.file("notes.md", "hello\n");
+    fx.file("todo.txt", "hello\n");
+
+    let d = diagnosis(&fx, &["--content", "invoice", "--ext", "rs"]);
+    assert_eq!(stages(&d), [2, 0, 0, 0, 0]);
+    assert_eq!(
+        hints(&d),
+        ["all 2 candidate files were excluded by --ext rs"]
+    );
+}
+
+#[test]
+fn pdfs_without_include_pdf() {
+    let fx = Fixture::new();
+    for i in 0..3 {
+        fx.file(&format!("scan{i}.pdf"), "%PDF-1.4\n");
+    }
+
+    let d = diagnosis(&fx, &["--content", "invoice"]);
+    assert_eq!(stages(&d), [3, 3, 3, 3, 0]);
+    assert_eq!(d["top_skips"][0]["label"], "non_text");
+    assert_eq!(d["top_skips"][0]["files"], 3);
+    assert_eq!(
+        hints(&d),
+        [
+            "content search was requested but 100% of files were skipped as non-text; \
+             pass --binary to match raw bytes",
+            "3 PDF files were not searched; pass --include-pdf to include them",
+        ]
+    );
+}
+
+#[test]
+fn files_larger_than_max_bytes() {
+    let fx = Fixture::new();
+    fx.file("big.txt", "x".repeat(4096));
+    fx.file("huge.txt", "x".repeat(8192));
+
+    let d = diagnosis(&fx, &["--content", "invoice", "--max-bytes", "1024"]);
+    assert_eq!(stages(&d), [2, 2, 2, 2, 0]);
+    assert_eq!(d["top_skips"][0]["label"], "too_large");
+    assert_eq!(
+        hints(&d),
+        ["2 files were larger than --max-bytes 1.0 KiB; raise --max-bytes"]
+    );
+}
+
+#[test]
+fn empty_directory_and_plain_miss() {
+    let fx = Fixture::new();
+    let d = diagnosis(&fx, &["--content", "invoice"]);
+    assert_eq!(stages(&d), [0, 0, 0, 0, 0]);
+    assert!(hints(&d)[0].starts_with("no files were found under `"));
+
+    let fx = Fixture::new();
+    fx.file("a.txt", "receipt\n");
+    let d = diagnosis(&fx, &["--content", "invoice"]);
+    assert_eq!(stages(&d), [1, 1, 1, 1, 1]);
+    assert_eq!(
+        hints(&d),
+        ["1 files were read and none matched the pattern"]
+    );
+
+    let d = diagnosis(&fx, &["--content", "invoice.*total"]);
+    assert!(hints(&d)[0].contains("try --fixed-strings"));
+}
+
+#[test]
+fn matches_skip_the_diagnosis() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "invoice\n");
+    let report = fx.search_json(&["--content", "invoice"]);
+    assert!(report.get("diagnosis").is_none());
+}
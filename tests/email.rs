@@ -0,0 +1,27 @@
+mod common;
+
+use common::{result_paths, Fixture};
+
+#[test]
+fn mbox_messages_are_decoded_and_filtered_by_header() {
+    let fx = Fixture::new();
+    fx.file(
+        "inbox.mbox",
+        "From a@b Mon Jan  1 00:00:00 2024\nSubject: invoice\nContent-Transfer-Encoding: base64\n\n\
+bmVlZGxlIGluIGJhc2U2NA==\n\n\
+From c@d Mon Jan  1 00:00:00 2024\nSubject: other\n\nneedle in plain text\n",
+    );
+    let all = fx.search_json(&["--include-email", "--content", "needle"]);
+    assert_eq!(result_paths(&all), vec!["inbox.mbox".to_string()]);
+    assert_eq!(all["stats"]["email_messages_scanned"], 2);
+
+    let filtered = fx.search_json(&[
+        "--include-email",
+        "--email-header",
+        "subject:invoice",
+        "--content",
+        "needle in base64",
+    ]);
+    assert_eq!(result_paths(&filtered), vec!["inbox.mbox".to_string()]);
+    assert_eq!(filtered["stats"]["email_messages_scanned"], 1);
+}
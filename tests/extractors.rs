@@ -0,0 +1,44 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{result_paths, Fixture};
+
+fn with_extractor(command: &str) -> Fixture {
+    let fx = Fixture::new();
+    let toml = format!(
+        "version = 1\n\n[extractors]\nxyz = {}\n",
+        toml_string(command)
+    );
+    fx.file("rustfilefinder.toml", toml);
+    fx.file("my notes.xyz", "hello from the archive\n");
+    fx
+}
+
+fn toml_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[test]
+fn path_with_spaces_is_one_argument() {
+    let fx = with_extractor("cat {path}");
+    let report = fx.search_json(&["--content", "archive", "--ext", "xyz"]);
+    assert_eq!(result_paths(&report), ["my notes.xyz"]);
+    assert_eq!(report["stats"]["files_scanned_extractor"], 1);
+}
+
+#[test]
+fn quoted_arguments_stay_together() {
+    let fx = with_extractor(r#"sh -c 'printf "%s\n" "$0"; cat "$1"' 'two words' {path}"#);
+    let report = fx.search_json(&["--content", "two words", "--ext", "xyz"]);
+    assert_eq!(result_paths(&report), ["my notes.xyz"]);
+}
+
+#[test]
+fn failed_extractor_is_only_counted_as_skipped() {
+    let fx = with_extractor("false {path}");
+    let report = fx.search_json(&["--content", "archive", "--ext", "xyz"]);
+    assert!(result_paths(&report).is_empty());
+    assert_eq!(report["stats"]["files_scanned_extractor"], 0);
+    assert_eq!(report["stats"]["skips"]["extractor_failed"], 1);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{results, Fixture};
+
+fn fingerprint(fx: &Fixture, contents: &str) -> String {
+    fx.file("src/lib.rs", contents);
+    let report = fx.search_json(&["--rule", "todo=TODO\\(\\w+\\)"]);
+    let found = results(&report);
+    assert_eq!(found.len(), 1);
+    let fp = found[0]["fingerprint"].as_str().unwrap().to_string();
+    assert_eq!(fp.len(), 16);
+    fp
+}
+
+#[test]
+fn fingerprint_survives_a_line_move_and_follows_the_matched_text() {
+    let fx = Fixture::new();
+    let original = fingerprint(&fx, "fn a() {}\n// TODO(ana) fix\n");
+
+    let moved = fingerprint(
+        &fx,
+        "use x;\n\n\nfn a() {}\nfn b() {}\n  // TODO(ana) fix\n",
+    );
+    assert_eq!(moved, original);
+
+    let changed = fingerprint(&fx, "fn a() {}\n// TODO(bob) fix\n");
+    assert_ne!(changed, original);
+}
+
+#[test]
+fn fingerprint_depends_on_the_path_and_rule() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "TODO(ana)\n");
+    fx.file("b.txt", "TODO(ana)\n");
+    let report = fx.search_json(&["--rule", "todo=TODO\\(\\w+\\)"]);
+    let found = results(&report);
+    assert_ne!(found[0]["fingerprint"], found[1]["fingerprint"]);
+
+    let relabelled = fx.search_json(&["--rule", "task=TODO\\(\\w+\\)"]);
+    assert_ne!(
+        results(&relabelled)[0]["fingerprint"],
+        found[0]["fingerprint"]
+    );
+}
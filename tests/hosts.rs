@@ -0,0 +1,102 @@
+mod common;
+
+use common::{result_paths, Fixture};
+use serde_json::Value;
+use std::process::Output;
+
+const CONFIG: &str = r#"version = 1
+
+[defaults]
+ext = "txt"
+
+[hosts.laptop.defaults]
+dir = "${env:CORPUS_ROOT}/docs"
+
+[hosts.server.defaults]
+dir = "${env:CORPUS_ROOT}/archive"
+ext = "md"
+
+[presets.notes]
+content = "invoice"
+
+[hosts.server.presets.notes]
+content = "receipt"
+"#;
+
+fn corpus() -> Fixture {
+    let fx = Fixture::new();
+    fx.file("rustfilefinder.toml", CONFIG);
+    fx.file("docs/a.txt", "invoice receipt");
+    fx.file("docs/b.md", "invoice receipt");
+    fx.file("archive/c.txt", "invoice receipt");
+    fx.file("archive/d.md", "invoice receipt");
+    fx
+}
+
+fn run_as(fx: &Fixture, host: &str, args: &[&str]) -> Output {
+    fx.command()
+        .env("RUSTFILEFINDER_HOSTNAME", host)
+        .env("CORPUS_ROOT", fx.path())
+        .arg("search")
+        .args(args)
+        .output()
+        .expect("run rustfilefinder")
+}
+
+fn names(out: &Output) -> Vec<String> {
+    assert!(
+        out.status.success(),
+        "search failed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let report: Value = serde_json::from_slice(&out.stdout).expect("search output is JSON");
+    let mut names: Vec<String> = result_paths(&report)
+        .iter()
+        .map(|p| p.rsplit(['/', '\\']).next().unwrap().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn host_section_picks_the_search_root() {
+    let fx = corpus();
+    let args = ["--content", "invoice", "--format", "json"];
+
+    assert_eq!(names(&run_as(&fx, "laptop", &args)), ["a.txt"]);
+    assert_eq!(names(&run_as(&fx, "server", &args)), ["d.md"]);
+}
+
+#[test]
+fn unknown_host_uses_the_global_layers_only() {
+    let fx = corpus();
+    let out = run_as(
+        &fx,
+        "desktop",
+        &["--content", "invoice", "--format", "json"],
+    );
+    assert_eq!(names(&out), ["a.txt", "c.txt"]);
+}
+
+#[test]
+fn host_preset_outranks_the_global_preset() {
+    let fx = corpus();
+    fx.file("archive/e.md", "invoice only");
+    fx.file("archive/f.md", "receipt only");
+    let args = ["--preset", "notes", "--format", "json"];
+
+    assert_eq!(names(&run_as(&fx, "server", &args)), ["d.md", "f.md"]);
+    assert_eq!(names(&run_as(&fx, "laptop", &args)), ["a.txt"]);
+}
+
+#[test]
+fn explain_names_the_host_section() {
+    let fx = corpus();
+    let args = ["--content", "invoice", "--format", "json", "--explain"];
+
+    let out = run_as(&fx, "laptop", &args);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("applying [hosts.laptop]"));
+
+    let out = run_as(&fx, "desktop", &args);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("no [hosts.desktop] section"));
+}
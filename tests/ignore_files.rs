@@ -0,0 +1,37 @@
+mod common;
+
+use common::{result_paths, Fixture};
+
+fn found(fx: &Fixture) -> Vec<String> {
+    let mut paths = result_paths(&fx.search_json(&["--content", "secret"]));
+    paths.sort();
+    paths
+}
+
+#[test]
+fn gitignore_outside_a_work_tree_is_not_applied() {
+    let fx = Fixture::new();
+    fx.file(".gitignore", "cfg.txt\n");
+    fx.file("cfg.txt", "secret\n");
+    fx.file("app.txt", "secret\n");
+    assert_eq!(found(&fx), ["app.txt", "cfg.txt"]);
+}
+
+#[test]
+fn gitignore_inside_a_work_tree_is_applied() {
+    let fx = Fixture::new();
+    std::fs::create_dir(fx.path().join(".git")).unwrap();
+    fx.file("sub/.gitignore", "cfg.txt\n");
+    fx.file("sub/cfg.txt", "secret\n");
+    fx.file("sub/app.txt", "secret\n");
+    assert_eq!(found(&fx), ["app.txt"]);
+}
+
+#[test]
+fn ignore_files_apply_without_git() {
+    let fx = Fixture::new();
+    fx.file(".ignore", "cfg.txt\n");
+    fx.file("cfg.txt", "secret\n");
+    fx.file("app.txt", "secret\n");
+    assert_eq!(found(&fx), ["app.txt"]);
+}
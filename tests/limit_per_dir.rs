@@ -0,0 +1,80 @@
+mod common;
+
+use common::{results, Fixture};
+use serde_json::{json, Value};
+
+fn corpus() -> Fixture {
+    let fx = Fixture::new();
+    for name in ["a/1.txt", "a/2.txt", "a/3.txt", "a/4.txt", "b/1.txt"] {
+        fx.file(name, "invoice\n");
+    }
+    fx
+}
+
+fn paths(report: &Value) -> Vec<&str> {
+    results(report)
+        .iter()
+        .map(|r| r["path"].as_str().unwrap())
+        .collect()
+}
+
+fn search(fx: &Fixture, args: &[&str]) -> String {
+    let mut full = vec![
+        "search",
+        "--dir",
+        ".",
+        "--content",
+        "invoice",
+        "--sort",
+        "path",
+    ];
+    full.extend_from_slice(args);
+    let out = fx.run(&full);
+    assert!(
+        out.status.success(),
+        "search failed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout).expect("utf-8 output")
+}
+
+#[test]
+fn json_keeps_the_first_results_of_each_directory() {
+    let fx = corpus();
+    let report: Value =
+        serde_json::from_str(&search(&fx, &["--limit-per-dir", "2", "--format", "json"])).unwrap();
+
+    assert_eq!(paths(&report), ["./a/1.txt", "./a/2.txt", "./b/1.txt"]);
+    assert_eq!(
+        report["suppressed_per_dir"],
+        json!([{"dir": "./a", "suppressed": 2}])
+    );
+    assert_eq!(report["stats"]["matches_total"], 5);
+    assert_eq!(report["stats"]["matches_printed"], 3);
+}
+
+#[test]
+fn markdown_reports_the_suppressed_counts() {
+    let fx = corpus();
+    let md = search(&fx, &["--limit-per-dir", "2", "--format", "md"]);
+
+    assert!(
+        md.contains("- Suppressed by per-directory limit: **2** (limit per dir = 2)"),
+        "{md}"
+    );
+    assert_eq!(md.matches("_… and 2 more in this directory_").count(), 1);
+    assert!(!md.contains("a/3.txt"));
+    assert!(md.contains("b/1.txt"));
+}
+
+#[test]
+fn zero_means_no_cap() {
+    let fx = corpus();
+    let report: Value =
+        serde_json::from_str(&search(&fx, &["--limit-per-dir", "0", "--format", "json"])).unwrap();
+    assert_eq!(paths(&report).len(), 5);
+    assert!(report.get("suppressed_per_dir").is_none());
+
+    let md = search(&fx, &["--limit-per-dir", "0", "--format", "md"]);
+    assert!(!md.contains("per-directory limit"));
+}
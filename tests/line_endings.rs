@@ -0,0 +1,104 @@
+mod common;
+
+use common::{results, Fixture};
+use std::collections::BTreeMap;
+
+const VARIANTS: [(&str, &str); 4] = [
+    ("lf.txt", "alpha\nbeta invoice gamma\ndelta\n"),
+    ("crlf.txt", "alpha\r\nbeta invoice gamma\r\ndelta\r\n"),
+    ("cr.txt", "alpha\rbeta invoice gamma\rdelta\r"),
+    ("mixed.txt", "alpha\r\nbeta invoice gamma\ndelta\r"),
+];
+
+fn corpus() -> Fixture {
+    let fx = Fixture::new();
+    for (name, text) in VARIANTS {
+        fx.file(name, text);
+    }
+    fx
+}
+
+fn search(fx: &Fixture, args: &[&str]) -> String {
+    let mut full = vec!["search", "--dir", "."];
+    full.extend_from_slice(args);
+    stdout(fx.run(&full))
+}
+
+fn stdout(out: std::process::Output) -> String {
+    assert!(
+        out.status.success(),
+        "run failed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout).expect("utf-8 output")
+}
+
+fn show(fx: &Fixture, path: &str) -> String {
+    stdout(fx.run(&["show", "report.json", "--path", path, "-B", "1", "-A", "1"]))
+}
+
+#[test]
+fn snippets_match_the_lf_version() {
+    let fx = corpus();
+    let report = fx.search_json(&["--content", "invoice"]);
+    assert_eq!(results(&report).len(), VARIANTS.len());
+    for hit in results(&report) {
+        let snippet = hit["snippet"].as_str().unwrap();
+        assert_eq!(
+            snippet, "alpha beta invoice gamma delta ",
+            "{}",
+            hit["path"]
+        );
+        assert!(!snippet.contains('\r'));
+    }
+}
+
+#[test]
+fn show_reports_lf_line_and_column() {
+    let fx = corpus();
+    let report = search(&fx, &["--content", "invoice", "--format", "json"]);
+    fx.file("report.json", report);
+
+    let expected = show(&fx, "lf.txt");
+    assert!(expected.starts_with("./lf.txt:2:6\n"), "{expected}");
+    for (name, _) in &VARIANTS[1..] {
+        let shown = show(&fx, name);
+        assert!(!shown.contains('\r'));
+        assert_eq!(shown.replacen(name, "lf.txt", 1), expected, "{name}");
+    }
+}
+
+#[test]
+fn markdown_context_lines_match_the_lf_version() {
+    let fx = corpus();
+    let md = search(
+        &fx,
+        &[
+            "--content",
+            "invoice",
+            "--context-lines",
+            "1",
+            "--format",
+            "md",
+        ],
+    );
+    assert!(!md.contains('\r'));
+
+    let sections: BTreeMap<&str, &str> = md
+        .split("### `")
+        .skip(1)
+        .map(|s| s.split_once("`\n").unwrap())
+        .map(|(path, body)| {
+            let end = body.rfind("```").unwrap();
+            (path.trim_start_matches("./"), &body[..end])
+        })
+        .collect();
+    let lf = sections["lf.txt"];
+    assert!(
+        lf.ends_with("```text\nalpha\nbeta invoice gamma\ndelta\n"),
+        "{lf}"
+    );
+    for (name, _) in VARIANTS {
+        assert_eq!(sections[name], lf, "{name}");
+    }
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::Fixture;
+
+fn markdown(fx: &Fixture, args: &[&str]) -> String {
+    let mut full = args.to_vec();
+    full.extend_from_slice(&["--format", "md"]);
+    let out = fx.search(&full);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout).unwrap()
+}
+
+fn headings(md: &str) -> Vec<&str> {
+    md.lines().filter(|l| l.starts_with("## ")).collect()
+}
+
+#[test]
+fn unlabelled_content_hits_next_to_rules_are_content_matches() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "alpha\n");
+    fx.file("b.txt", "token=1\n");
+    let md = markdown(&fx, &["--content", "alpha", "--rule", "tok=token"]);
+    let headings = headings(&md);
+    assert!(headings.contains(&"## Content matches"), "{headings:?}");
+    assert!(
+        headings.contains(&"## Rule `tok` (1 matches)"),
+        "{headings:?}"
+    );
+    assert!(!headings.contains(&"## Name matches"), "{headings:?}");
+}
+
+#[test]
+fn redirect_to_a_file_stays_markdown() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "alpha\n");
+    let report = fx.path().join("out").join("report.md");
+    std::fs::create_dir(report.parent().unwrap()).unwrap();
+    let status = fx
+        .command()
+        .args(["search", "--dir", fx.path().join("a.txt").to_str().unwrap()])
+        .args(["--content", "alpha"])
+        .stdout(std::fs::File::create(&report).unwrap())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let text = std::fs::read_to_string(&report).unwrap();
+    assert!(text.contains("## Matches"), "{text}");
+}
+
+#[test]
+fn format_env_outranks_config() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "alpha\n");
+    fx.file(
+        "rustfilefinder.toml",
+        "version = 1\n\n[defaults]\nformat = \"json\"\n",
+    );
+    let out = fx
+        .command()
+        .env("RUSTFILEFINDER_FORMAT", "paths")
+        .args(["search", "--dir", "a.txt", "--content", "alpha"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&out.stdout).trim(), "a.txt");
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{results, Fixture};
+use serde_json::Value;
+
+const FILES: usize = 100_000;
+
+#[test]
+fn max_results_stops_a_100k_match_tree() {
+    let fx = Fixture::new();
+    for dir in 0..FILES / 1000 {
+        for file in 0..1000 {
+            fx.file(&format!("d{dir:03}/f{file:04}.txt"), "hit\n");
+        }
+    }
+
+    let out = fx.search(&[
+        "--content",
+        "hit",
+        "--max-results",
+        "500",
+        "--format",
+        "json",
+    ]);
+    assert_eq!(out.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("stopped after 500 results (--max-results)"));
+
+    let report: Value = serde_json::from_slice(&out.stdout).expect("search output is JSON");
+    assert_eq!(report["stats"]["results_overflowed"], true);
+    assert_eq!(results(&report).len(), 500);
+    assert!(report["stats"]["files_scanned_text"].as_u64().unwrap() < FILES as u64);
+}
+
+#[test]
+fn max_results_above_the_match_count_exits_normally() {
+    let fx = Fixture::new();
+    for i in 0..10 {
+        fx.file(&format!("f{i}.txt"), "hit\n");
+    }
+    let report = fx.search_json(&["--content", "hit", "--max-results", "10"]);
+    assert_eq!(report["stats"]["results_overflowed"], false);
+    assert_eq!(results(&report).len(), 10);
+}
@@ -0,0 +1,17 @@
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use common::{result_paths, Fixture};
+
+#[test]
+fn nice_io_lowers_priority_without_a_warning() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "invoice");
+
+    let out = fx.search(&["--content", "invoice", "--nice-io", "--format", "json"]);
+    assert!(out.status.success());
+    assert!(!String::from_utf8_lossy(&out.stderr).contains("--nice-io ignored"));
+    let report = serde_json::from_slice(&out.stdout).expect("search output is JSON");
+    assert_eq!(result_paths(&report).len(), 1);
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::{results, Fixture};
+
+fn single_match(report: &serde_json::Value) -> (usize, usize) {
+    let hits = results(report);
+    assert_eq!(hits.len(), 1, "{report}");
+    let at = |k: &str| hits[0][k].as_u64().unwrap() as usize;
+    (at("match_start"), at("match_end"))
+}
+
+#[test]
+fn ligatures_and_whitespace_runs_map_to_original_bytes() {
+    let fx = Fixture::new();
+    let text = "the \u{fb01}nal\n   report\n";
+    fx.file("a.txt", text);
+    let report = fx.search_json(&["--content", "final report", "--normalize-whitespace", "all"]);
+    let (start, end) = single_match(&report);
+    assert_eq!(&text[start..end], "\u{fb01}nal\n   report");
+}
+
+#[test]
+fn bom_file_matches_start_anchor() {
+    let fx = Fixture::new();
+    let text = "\u{feff}title line\nbody\n";
+    fx.file("a.txt", text);
+    let report = fx.search_json(&["--content", "^title", "--trim-for-anchors"]);
+    let (start, end) = single_match(&report);
+    assert_eq!(&text[start..end], "title");
+}
+
+#[test]
+fn crlf_file_matches_end_anchor() {
+    let fx = Fixture::new();
+    let text = "first end\r\nsecond\r\n";
+    fx.file("a.txt", text);
+    let plain = fx.search_json(&["--content", "(?m)end$"]);
+    assert!(results(&plain).is_empty());
+    let report = fx.search_json(&["--content", "(?m)end$", "--trim-for-anchors"]);
+    let (start, end) = single_match(&report);
+    assert_eq!(&text[start..end], "end");
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::{results, Fixture};
+use serde_json::{json, Value};
+
+fn notebook(source: &str, output: &str) -> String {
+    json!({
+        "cells": [
+            {"cell_type": "markdown", "source": ["# Title\n"]},
+            {
+                "cell_type": "code",
+                "source": [source],
+                "outputs": [{"output_type": "stream", "text": [output]}],
+            },
+        ],
+        "nbformat": 4,
+    })
+    .to_string()
+}
+
+fn content_results(report: &Value) -> Vec<&Value> {
+    results(report)
+        .iter()
+        .filter(|r| r["matched_content"] == true)
+        .collect()
+}
+
+#[test]
+fn notebook_cells_are_searched() {
+    let fx = Fixture::new();
+    fx.file("nb.ipynb", notebook("total = needle()\n", "ok"));
+
+    let report = fx.search_json(&["--content", "needle"]);
+    let found = content_results(&report);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0]["notebook_cell"]["index"], 1);
+    assert_eq!(found[0]["notebook_cell"]["cell_type"], "code");
+    assert_eq!(report["stats"]["files_scanned_text"], 1);
+}
+
+#[test]
+fn oversized_notebook_is_skipped_before_reading() {
+    let fx = Fixture::new();
+    fx.file("nb.ipynb", notebook("needle\n", &"x".repeat(200 * 1024)));
+
+    let report = fx.search_json(&["--content", "needle", "--max-bytes", "16"]);
+    assert!(content_results(&report).is_empty());
+    assert_eq!(report["stats"]["skips"]["too_large"], 1);
+    assert_eq!(report["stats"]["files_scanned_text"], 0);
+    assert_eq!(report["stats"]["bytes_read"], 0);
+}
+
+#[test]
+fn large_outputs_within_the_raw_limit_still_search_the_source() {
+    let fx = Fixture::new();
+    fx.file("nb.ipynb", notebook("needle\n", &"x".repeat(32 * 1024)));
+
+    let report = fx.search_json(&["--content", "needle", "--max-bytes", "1024"]);
+    assert_eq!(content_results(&report).len(), 1);
+}
+
+#[test]
+fn invalid_notebook_counts_once_as_unreadable() {
+    let fx = Fixture::new();
+    fx.file("broken.ipynb", "{\"cells\": [needle");
+
+    let report = fx.search_json(&["--content", "needle"]);
+    assert!(content_results(&report).is_empty());
+    assert_eq!(report["stats"]["skips"]["unreadable_text"], 1);
+    assert_eq!(report["stats"]["files_scanned_text"], 0);
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::{results, Fixture};
+use serde_json::json;
+
+#[test]
+fn two_patterns_on_the_same_literal_give_one_span_with_two_labels() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "a token here\n");
+
+    for extra in [&[][..], &["--all-matches"][..]] {
+        let mut args = vec!["--rule", "exact=token", "--rule", "prefix=tok(en)?"];
+        args.extend_from_slice(extra);
+        let report = fx.search_json(&args);
+
+        let found = results(&report);
+        assert_eq!(found.len(), 1, "{extra:?}");
+        assert_eq!(found[0]["rules"], json!(["exact", "prefix"]));
+        assert_eq!(found[0]["match_start"], 2);
+        assert_eq!(found[0]["match_end"], 7);
+        assert_eq!(report["stats"]["matches_total"], 1);
+        assert_eq!(report["stats"]["rules"], json!({"exact": 1, "prefix": 1}));
+    }
+}
+
+#[test]
+fn separate_spans_stay_separate() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "token\nother\n");
+
+    let report = fx.search_json(&[
+        "--rule",
+        "first=token",
+        "--rule",
+        "second=other",
+        "--all-matches",
+    ]);
+    let found = results(&report);
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0]["rules"], json!(["first"]));
+    assert_eq!(found[1]["rules"], json!(["second"]));
+}
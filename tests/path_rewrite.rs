@@ -0,0 +1,69 @@
+mod common;
+
+use common::{result_paths, Fixture};
+use serde_json::Value;
+
+fn strip_originals(v: &mut Value) {
+    match v {
+        Value::Object(map) => {
+            map.remove("original_path");
+            map.remove("meta");
+            map.values_mut().for_each(strip_originals);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_originals),
+        _ => {}
+    }
+}
+
+#[test]
+fn rewrite_covers_every_path_field() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "needle same\n");
+    fx.file("sub/b.txt", "needle same\n");
+    fx.file("sub/c.txt", "needle other\n");
+    let real = fx.path().to_str().unwrap().to_string();
+    let rewrite = format!("{real}=//srv/corpus");
+    let mut report = fx.search_json(&[
+        "--content",
+        "needle",
+        "--path-rewrite",
+        &rewrite,
+        "--collapse-duplicates",
+    ]);
+    assert!(report["hotspots"]["dirs"]
+        .as_array()
+        .is_some_and(|d| !d.is_empty()));
+    strip_originals(&mut report);
+    let text = report.to_string();
+    assert!(!text.contains(&real), "{text}");
+    assert!(text.contains("//srv/corpus"));
+}
+
+#[test]
+fn within_reads_back_a_rewritten_report() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "needle\n");
+    fx.file("b.txt", "needle\n");
+    fx.file("c.txt", "other\n");
+    let rewrite = format!("{}=//srv/corpus", fx.path().display());
+    let first = fx.run(&[
+        "search",
+        "--content",
+        "needle",
+        "--path-rewrite",
+        &rewrite,
+        "--format",
+        "json",
+        "--output",
+        "first.json",
+    ]);
+    assert!(
+        first.status.success(),
+        "{}",
+        String::from_utf8_lossy(&first.stderr)
+    );
+    let report = fx.path().join("first.json");
+    let again = fx.search_json(&["--content", "needle", "--within", report.to_str().unwrap()]);
+    assert_eq!(result_paths(&again), vec!["a.txt", "b.txt"]);
+    assert_eq!(again["stats"]["files_missing"], 0);
+}
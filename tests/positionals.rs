@@ -0,0 +1,68 @@
+mod common;
+
+use common::{result_paths, Fixture};
+
+#[test]
+fn names_only_pattern_combines_with_content() {
+    let fx = Fixture::new();
+    fx.file("foo.txt", "bar\n");
+    fx.file("foo.md", "baz\n");
+    fx.file("other.txt", "baz\n");
+    let dir = fx.path().to_str().unwrap();
+    let positional = fx.run(&[
+        "search",
+        "foo",
+        dir,
+        "--names-only",
+        "--content",
+        "bar",
+        "--format",
+        "json",
+    ]);
+    assert!(
+        positional.status.success(),
+        "{}",
+        String::from_utf8_lossy(&positional.stderr)
+    );
+    let flags = fx.search_json(&["--name", "foo", "--content", "bar"]);
+    let report: serde_json::Value = serde_json::from_slice(&positional.stdout).unwrap();
+    assert_eq!(result_paths(&report), result_paths(&flags));
+    assert!(result_paths(&report).contains(&"foo.md".to_string()));
+}
+
+#[test]
+fn pattern_and_content_without_names_only_conflict() {
+    let fx = Fixture::new();
+    let out = fx.run(&["search", "foo", ".", "--content", "bar"]);
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("not both"));
+}
+
+#[test]
+fn optional_value_flags_leave_positionals_alone() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "hello\n");
+    let out = fx.run(&["search", "--show-meta", "hello", ".", "--format", "md"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(String::from_utf8_lossy(&out.stdout).contains("a.txt"));
+
+    let out = fx.run(&["search", "--summary-line", "hello", "."]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(String::from_utf8_lossy(&out.stdout).starts_with("rff: 1 match"));
+
+    let out = fx.run(&["search", "--summary-line=stderr", "hello", "."]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(String::from_utf8_lossy(&out.stderr).contains("rff: 1 match"));
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use common::Fixture;
+use serde_json::Value;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+fn events(stderr: &[u8]) -> Vec<Value> {
+    String::from_utf8_lossy(stderr)
+        .lines()
+        .filter(|l| l.starts_with('{'))
+        .map(|l| serde_json::from_str(l).expect("progress event is JSON"))
+        .collect()
+}
+
+fn kinds(events: &[Value]) -> Vec<&str> {
+    events
+        .iter()
+        .map(|e| e["event"].as_str().expect("event name"))
+        .collect()
+}
+
+fn corpus(fx: &Fixture, files: usize, size: usize) {
+    for i in 0..files {
+        fx.file(
+            &format!("docs/note{i:03}.txt"),
+            format!("invoice {i}\n{}", "x".repeat(size)),
+        );
+    }
+}
+
+#[test]
+fn event_stream_starts_reports_and_finishes() {
+    let fx = Fixture::new();
+    corpus(&fx, 12, 64);
+
+    let out: Output = fx.search(&[
+        "--content",
+        "invoice",
+        "--format",
+        "json",
+        "--progress-json",
+        "--progress-interval",
+        "10",
+    ]);
+    assert!(out.status.success());
+    let events = events(&out.stderr);
+    let kinds = kinds(&events);
+
+    assert_eq!(kinds.first(), Some(&"start"));
+    assert_eq!(kinds.last(), Some(&"done"));
+    assert!(kinds.contains(&"progress"));
+    assert!(!kinds.contains(&"cancelled"));
+    assert_eq!(events[0]["args"]["content"][0], "invoice");
+
+    let progress: Vec<&Value> = events.iter().filter(|e| e["event"] == "progress").collect();
+    let mut last = 0;
+    for p in &progress {
+        let processed = p["processed"].as_u64().unwrap();
+        assert!(processed >= last);
+        assert!(processed <= p["total"].as_u64().unwrap());
+        assert!(p["elapsed_ms"].is_u64());
+        last = processed;
+    }
+    let final_progress = progress.last().unwrap();
+    assert_eq!(final_progress["processed"], 12);
+    assert_eq!(final_progress["total"], 12);
+    assert_eq!(final_progress["matches"], 12);
+
+    let done = events.last().unwrap();
+    assert!(done["stats"].is_object());
+
+    let report: Value = serde_json::from_slice(&out.stdout).expect("stdout stays pure JSON");
+    assert_eq!(report["results"].as_array().unwrap().len(), 12);
+}
+
+#[test]
+fn redacted_start_event_hides_patterns() {
+    let fx = Fixture::new();
+    corpus(&fx, 1, 8);
+
+    let out = fx.search(&["--content", "invoice", "--progress-json", "--redact"]);
+    let events = events(&out.stderr);
+    assert_eq!(events[0]["event"], "start");
+    assert_ne!(events[0]["args"]["content"][0], "invoice");
+}
+
+#[cfg(unix)]
+#[test]
+fn interrupt_ends_with_cancelled_event_and_status_130() {
+    let fx = Fixture::new();
+    corpus(&fx, 40, 1024);
+
+    let dir = fx.path().to_str().unwrap();
+    let mut child = fx
+        .command()
+        .args([
+            "search",
+            "--dir",
+            dir,
+            "--content",
+            "invoice",
+            "--throttle",
+            "0.002",
+            "--progress-json",
+            "--progress-interval",
+            "10",
+        ])
+        .env("RAYON_NUM_THREADS", "2")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn rustfilefinder");
+
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut seen = Vec::new();
+    loop {
+        let mut line = String::new();
+        assert!(
+            stderr.read_line(&mut line).unwrap() > 0,
+            "no progress event"
+        );
+        seen.extend_from_slice(line.as_bytes());
+        if line.contains("\"progress\"") {
+            break;
+        }
+    }
+    let sent = Instant::now();
+    let kill = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .expect("send SIGINT");
+    assert!(kill.success());
+
+    std::io::Read::read_to_end(&mut stderr, &mut seen).unwrap();
+    let status = child.wait().expect("wait for rustfilefinder");
+    assert!(sent.elapsed() < Duration::from_secs(10));
+    assert_eq!(status.code(), Some(130));
+
+    let events = events(&seen);
+    let kinds = kinds(&events);
+    assert_eq!(kinds.last(), Some(&"cancelled"));
+    assert!(!kinds.contains(&"done"));
+    let cancelled = events.last().unwrap();
+    assert!(cancelled["processed"].as_u64().unwrap() < 40);
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::Fixture;
+
+const SECRET: &str = "hunter2-s3cret";
+
+#[test]
+fn secret_appears_in_no_output() {
+    let fx = Fixture::new();
+    fx.file("config.txt", format!("[db]\npassword = {SECRET}\n"));
+    fx.file("notes.md", format!("# Notes\n\nrotate {SECRET} soon\n"));
+    let state = tempfile::tempdir().unwrap();
+    let checkpoint = state.path().join("scan.checkpoint");
+    for format in ["md", "json", "jsonl", "paths"] {
+        let out = fx.search(&[
+            "--content",
+            SECRET,
+            "--redact",
+            "--all-matches",
+            "--show-context-heading",
+            "--progress-json",
+            "--checkpoint",
+            checkpoint.to_str().unwrap(),
+            "--checkpoint-every",
+            "1",
+            "--format",
+            format,
+        ]);
+        assert!(
+            out.status.success(),
+            "{}",
+            String::from_utf8_lossy(&out.stderr)
+        );
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert!(stdout.contains("config.txt"), "{format}: {stdout}");
+        assert!(!stdout.contains(SECRET), "{format} stdout: {stdout}");
+        assert!(!stderr.contains(SECRET), "{format} stderr: {stderr}");
+        assert!(stderr.contains("\"event\":\"start\""), "{stderr}");
+        assert!(!checkpoint.exists(), "checkpoint left behind");
+    }
+}
+
+#[test]
+fn two_phase_progress_lines_are_redacted() {
+    let fx = Fixture::new();
+    fx.file("d/a.txt", format!("password={SECRET}\n"));
+    for extra in [&[][..], &["--limit", "5"][..]] {
+        let mut args = vec![
+            "--name",
+            "a",
+            "--content",
+            r"hunter2\S+",
+            "--two-phase",
+            "--redact",
+        ];
+        args.extend_from_slice(extra);
+        let out = fx.search(&args);
+        assert!(out.status.success());
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        assert!(stderr.contains("[phase 2]"), "{extra:?}: {stderr}");
+        assert!(stderr.contains("[REDACTED]"), "{extra:?}: {stderr}");
+        assert!(!stderr.contains("hunter2"), "{extra:?}: {stderr}");
+    }
+}
+
+#[test]
+fn per_query_reports_are_redacted() {
+    let fx = Fixture::new();
+    fx.file("d/a.txt", format!("password={SECRET}\n"));
+    fx.file("queries.toml", "[[query]]\ncontent = ['hunter2\\S+']\n");
+    let out = fx.search(&[
+        "--queries-file",
+        "queries.toml",
+        "--output-dir",
+        "out",
+        "--format",
+        "md,json",
+        "--redact",
+    ]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    for name in ["q1.json", "q1.md"] {
+        let report = std::fs::read_to_string(fx.path().join("out").join(name)).unwrap();
+        assert!(report.contains("a.txt"), "{name}: {report}");
+        assert!(report.contains("[REDACTED"), "{name}: {report}");
+        assert!(!report.contains("hunter2"), "{name}: {report}");
+    }
+}
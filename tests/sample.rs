@@ -0,0 +1,48 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn sample_and_skip_verification_share_one_seed() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "needle\n");
+    fx.file("b.txt", "needle\n");
+    fx.file("c.txt", "needle\n");
+    std::fs::write(fx.path().join("blob.bin"), b"needle\0\0\x01\x02").unwrap();
+    let report = fx.search_json(&[
+        "--content",
+        "needle",
+        "--sample",
+        "1",
+        "--verify-skips",
+        "5",
+    ]);
+    let stats = &report["stats"];
+    let seed = stats["sample"]["seed"].as_u64().unwrap();
+    assert_eq!(stats["skip_verification"]["seed"].as_u64(), Some(seed));
+}
+
+#[test]
+fn sample_rejects_limit_but_overrides_config_limit() {
+    let fx = Fixture::new();
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fx.file(name, "needle\n");
+    }
+    let out = fx.run(&[
+        "search",
+        "--content",
+        "needle",
+        "--sample",
+        "2",
+        "--limit",
+        "1",
+    ]);
+    assert_eq!(out.status.code(), Some(2));
+
+    fx.file(
+        "rustfilefinder.toml",
+        "version = 1\n[defaults]\nlimit = 1\n",
+    );
+    let report = fx.search_json(&["--content", "needle", "--sample", "2", "--seed", "7"]);
+    assert_eq!(report["results"].as_array().unwrap().len(), 2);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::{result_paths, Fixture};
+
+const WINDOW: usize = 256 * 1024;
+
+fn straddling_text() -> String {
+    let mut text = "a".repeat(WINDOW - 4);
+    text.push('\n');
+    text.push_str("foo\nbar\n");
+    text.push_str(&"x".repeat(100));
+    text.push_str("end\n");
+    text
+}
+
+#[test]
+fn regex_match_across_window_boundary_is_found() {
+    let fx = Fixture::new();
+    fx.file("big.txt", straddling_text());
+    for pattern in ["foo\\nbar", "(?s)foo.*end"] {
+        let report = fx.search_json(&["--content", pattern]);
+        assert_eq!(result_paths(&report), ["big.txt"], "pattern {pattern}");
+    }
+}
+
+#[test]
+fn literal_match_across_window_boundary_is_found() {
+    let fx = Fixture::new();
+    fx.file("big.txt", straddling_text());
+    let report = fx.search_json(&["--content", "foo\nbar", "--fixed-strings"]);
+    assert_eq!(result_paths(&report), ["big.txt"]);
+}
+
+#[test]
+fn binary_signature_across_window_boundary_is_found() {
+    let fx = Fixture::new();
+    let mut bytes = vec![0u8; WINDOW - 3];
+    bytes.push(b'\n');
+    bytes.extend_from_slice(b"\x89PNG\r\n\x1a\n");
+    bytes.extend_from_slice(&[0; 16]);
+    fx.file("image.bin", bytes);
+    let report = fx.search_json(&["--content", r"\x89PNG\r\n\x1a\n", "--binary"]);
+    assert_eq!(result_paths(&report), ["image.bin"]);
+}
+
+#[test]
+fn zero_per_file_timeout_disables_the_deadline() {
+    let fx = Fixture::new();
+    fx.file("notes.txt", "needle\n");
+    let report = fx.search_json(&["--content", "needle", "--per-file-timeout", "0"]);
+    assert_eq!(result_paths(&report), ["notes.txt"]);
+    assert_eq!(report["stats"]["skips"]["timeout"], 0);
+}
+
+fn long_match_across_stream_cut() -> String {
+    let start = 4 * WINDOW - 5000;
+    let mut text = "a".repeat(start);
+    text.push_str(&"x".repeat(6000));
+    text.push_str("END");
+    text.push_str(&"a".repeat(WINDOW));
+    text
+}
+
+#[test]
+fn streamed_match_longer_than_the_default_overlap_is_found() {
+    let fx = Fixture::new();
+    fx.file("big.txt", long_match_across_stream_cut());
+    let report = fx.search_json(&["--content", "x{6000}END", "--memory-limit", "64K"]);
+    assert_eq!(result_paths(&report), ["big.txt"]);
+    assert_eq!(report["stats"]["files_streamed"], 1);
+}
+
+#[test]
+fn unbounded_pattern_scans_the_whole_file_instead_of_streaming() {
+    let fx = Fixture::new();
+    fx.file("big.txt", long_match_across_stream_cut());
+    let report = fx.search_json(&["--content", "x+END", "--memory-limit", "64K"]);
+    assert_eq!(result_paths(&report), ["big.txt"]);
+    assert_eq!(report["stats"]["files_streamed"], 0);
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::Fixture;
+use std::time::{Duration, Instant};
+
+const SMALL_FILES: usize = 4_000;
+const LARGE_BYTES: usize = 96 * 1024 * 1024;
+const RUNS: usize = 3;
+
+fn skewed_tree() -> Fixture {
+    let fx = Fixture::new();
+    for i in 0..SMALL_FILES {
+        fx.file(
+            &format!("src/m{:02}/f{i:05}.txt", i % 50),
+            format!("line {i}\n{}", "lorem ipsum dolor sit amet\n".repeat(40)),
+        );
+    }
+    let line = "lorem ipsum dolor sit amet consectetur adipiscing elit\n";
+    fx.file("zz/huge.txt", line.repeat(LARGE_BYTES / line.len()));
+    fx
+}
+
+fn fastest(fx: &Fixture, schedule: &str) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let started = Instant::now();
+            let out = fx.search(&[
+                "--content",
+                r"(?i)adipiscing\s+\w+\s+needle",
+                "--schedule",
+                schedule,
+                "--max-bytes",
+                "200000000",
+                "--format",
+                "json",
+            ]);
+            assert!(out.status.success());
+            started.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+#[test]
+#[ignore = "benchmark: run with `cargo test --release --test schedule -- --ignored --nocapture`"]
+fn largest_first_shortens_the_tail_on_a_skewed_tree() {
+    let fx = skewed_tree();
+    let path = fastest(&fx, "path");
+    let size = fastest(&fx, "size");
+    println!("schedule path: {path:?}, schedule size: {size:?}");
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if threads < 2 {
+        println!("single core: largest-first cannot overlap the large file, skipping the check");
+        return;
+    }
+    assert!(
+        size <= path.mul_f64(1.05),
+        "largest-first ({size:?}) is slower than path order ({path:?})"
+    );
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::Fixture;
+use serde_json::Value;
+
+fn schema(fx: &Fixture) -> Value {
+    let out = fx.run(&["schema"]);
+    assert!(out.status.success());
+    serde_json::from_slice(&out.stdout).expect("schema is JSON")
+}
+
+fn assert_valid(schema: &Value, report: &Value) {
+    let validator = jsonschema::validator_for(schema).expect("schema compiles");
+    let errors: Vec<String> = validator
+        .iter_errors(report)
+        .map(|e| format!("{} at {}", e, e.instance_path()))
+        .collect();
+    assert!(
+        errors.is_empty(),
+        "report does not match the schema: {errors:#?}"
+    );
+}
+
+#[test]
+fn schema_has_no_made_up_id() {
+    let fx = Fixture::new();
+    let schema = schema(&fx);
+    assert!(schema.get("$id").is_none());
+    assert!(schema["title"].as_str().unwrap().contains("schema_version"));
+}
+
+#[test]
+fn real_report_validates_against_the_schema() {
+    let fx = Fixture::new();
+    fx.file("src/main.rs", "fn main() {\n    // TODO: invoice\n}\n");
+    fx.file("notes/invoice.txt", "nothing here\n");
+    fx.file("notes/todo.md", "- TODO call back\n");
+    fx.file("blob.bin", [0u8, 159, 146, 150, 0, 1]);
+
+    let schema = schema(&fx);
+    let report = fx.search_json(&["--content", "TODO", "--name", "invoice", "--verbose"]);
+    assert!(!report["results"].as_array().unwrap().is_empty());
+    assert_valid(&schema, &report);
+
+    let mut report = fx.search_json(&["--content", "absent"]);
+    assert_valid(&schema, &report);
+
+    report["stats"]["files_scanned_text"] = "many".into();
+    let validator = jsonschema::validator_for(&schema).unwrap();
+    assert!(!validator.is_valid(&report));
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::{result_paths, Fixture};
+use serde_json::Value;
+use std::fs;
+
+fn run_into_tree(fx: &Fixture) -> Value {
+    let out = fx.search(&[
+        "--content",
+        "invoice",
+        "--format",
+        "json",
+        "--output",
+        "report.json",
+    ]);
+    assert!(
+        out.status.success(),
+        "search failed: {}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let report = fs::read(fx.path().join("report.json")).expect("report written");
+    serde_json::from_slice(&report).expect("report is JSON")
+}
+
+#[test]
+fn second_run_ignores_the_first_runs_report() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "invoice 1\n");
+    fx.file("b.md", "invoice 2\n");
+
+    let first = run_into_tree(&fx);
+    assert!(fs::read_to_string(fx.path().join("report.json"))
+        .unwrap()
+        .contains("invoice"));
+    let second = run_into_tree(&fx);
+
+    let mut paths = result_paths(&second);
+    paths.sort();
+    assert_eq!(paths, ["a.txt", "b.md"]);
+    assert_eq!(result_paths(&first).len(), result_paths(&second).len());
+    assert_eq!(
+        first["stats"]["files_scanned_text"],
+        second["stats"]["files_scanned_text"]
+    );
+    assert_eq!(second["stats"]["files_excluded_self"], 1);
+}
+
+#[test]
+fn markdown_report_in_the_tree_is_not_matched() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "invoice\n");
+    let args = ["--content", "invoice", "--output", "report.md"];
+
+    assert!(fx.search(&args).status.success());
+    let first = fs::read_to_string(fx.path().join("report.md")).unwrap();
+    assert!(fx.search(&args).status.success());
+    let second = fs::read_to_string(fx.path().join("report.md")).unwrap();
+
+    assert!(!second.contains("report.md"));
+    assert_eq!(
+        first.matches("a.txt").count(),
+        second.matches("a.txt").count()
+    );
+}
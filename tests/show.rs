@@ -0,0 +1,41 @@
+mod common;
+
+use common::Fixture;
+
+fn write_report(fx: &Fixture) -> String {
+    let out = fx.run(&[
+        "search",
+        "--content",
+        "needle",
+        "--format",
+        "json",
+        "--output",
+        "report.json",
+    ]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    fx.path().join("report.json").to_str().unwrap().to_string()
+}
+
+#[test]
+fn untouched_file_written_just_before_the_report_is_not_stale() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "one\nneedle\nthree\n");
+    let report = write_report(&fx);
+    let show = fx.run(&["show", &report, "1"]);
+    assert!(show.status.success());
+    assert!(!String::from_utf8_lossy(&show.stderr).contains("changed since"));
+}
+
+#[test]
+fn resized_file_is_reported_as_stale() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "one\nneedle\nthree\n");
+    let report = write_report(&fx);
+    fx.file("a.txt", "one\nneedle\nthree\nfour\n");
+    let show = fx.run(&["show", &report, "1"]);
+    assert!(String::from_utf8_lossy(&show.stderr).contains("changed since"));
+}
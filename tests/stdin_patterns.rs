@@ -0,0 +1,90 @@
+mod common;
+
+use common::{result_paths, Fixture};
+use serde_json::Value;
+use std::io::Write;
+use std::process::{Output, Stdio};
+
+const TRICKY: &str = "say \"hi\" and 'bye' in C:\\temp\\new\n  next line\n";
+
+fn with_stdin(fx: &Fixture, stdin: &str, args: &[&str]) -> Output {
+    let mut child = fx
+        .command()
+        .arg("search")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn rustfilefinder");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().expect("wait for rustfilefinder")
+}
+
+fn matched(fx: &Fixture, stdin: &str, args: &[&str]) -> Vec<String> {
+    let dir = fx.path().to_str().unwrap();
+    let mut full = vec!["--dir", dir, "--format", "json"];
+    full.extend_from_slice(args);
+    let out = with_stdin(fx, stdin, &full);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let report: Value = serde_json::from_slice(&out.stdout).expect("search output is JSON");
+    result_paths(&report)
+}
+
+#[test]
+fn literal_pattern_with_quotes_backslashes_and_a_newline() {
+    let fx = Fixture::new();
+    fx.file("tricky.txt", TRICKY);
+    fx.file(
+        "near.txt",
+        "say \"hi\" and 'bye' in C:\\temp\\new next line\n",
+    );
+
+    let pattern = "\"hi\" and 'bye' in C:\\temp\\new\n  next\n";
+    let found = matched(&fx, pattern, &["--content", "-", "--fixed-strings"]);
+    assert_eq!(found, ["tricky.txt"]);
+}
+
+#[test]
+fn regex_pattern_keeps_internal_whitespace_exactly() {
+    let fx = Fixture::new();
+    fx.file("tricky.txt", TRICKY);
+
+    let found = matched(&fx, "C:\\\\temp\\\\new\n  next\n", &["--content", "-"]);
+    assert_eq!(found, ["tricky.txt"]);
+    let found = matched(&fx, "C:\\\\temp\\\\new\n next\n", &["--content", "-"]);
+    assert!(found.is_empty());
+}
+
+#[test]
+fn content_file_from_stdin_reads_one_pattern_per_line() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "alpha \"quoted\"\n");
+    fx.file("b.txt", "back\\slash\n");
+    fx.file("c.txt", "neither\n");
+
+    let mut found = matched(
+        &fx,
+        "\"quoted\"\nback\\slash\n",
+        &["--content-file", "-", "--fixed-strings"],
+    );
+    found.sort();
+    assert_eq!(found, ["a.txt", "b.txt"]);
+}
+
+#[test]
+fn two_stdin_readers_are_rejected() {
+    let fx = Fixture::new();
+    let out = with_stdin(&fx, "x", &["--content", "-", "--dir", "-"]);
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("can read from stdin"));
+}
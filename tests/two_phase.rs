@@ -0,0 +1,71 @@
+mod common;
+
+use common::Fixture;
+
+fn phase_lines(stderr: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stderr)
+        .lines()
+        .filter(|l| l.starts_with("[phase "))
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn phase_one_skips_files_dropped_by_candidate_filters() {
+    let fx = Fixture::new();
+    fx.file("config.rs", "fn main() {}\n");
+    fx.file("config.py", "print(1)\n");
+    fx.file("config.txt", "notes\n");
+    let out = fx.run(&[
+        "search",
+        "--name",
+        "config",
+        "--content",
+        "zzz",
+        "--two-phase",
+        "--lang",
+        "rust",
+        "--kind",
+        "code",
+        "--format",
+        "json",
+    ]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let lines = phase_lines(&out.stderr);
+    assert_eq!(lines.len(), 1, "{lines:?}");
+    assert!(lines[0].ends_with("config.rs"));
+}
+
+#[test]
+fn limit_announces_only_kept_results() {
+    let fx = Fixture::new();
+    for name in ["config_a.txt", "config_b.txt", "config_c.txt"] {
+        fx.file(name, "plain\n");
+    }
+    let out = fx.run(&[
+        "search",
+        "--name",
+        "config",
+        "--content",
+        "zzz",
+        "--two-phase",
+        "--limit",
+        "1",
+        "--sort",
+        "path",
+        "--format",
+        "json",
+    ]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let report: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    let kept = report["results"][0]["path"].as_str().unwrap().to_string();
+    assert_eq!(phase_lines(&out.stderr), vec![format!("[phase 1] {kept}")]);
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::{result_paths, Fixture};
+
+fn names(fx: &Fixture, args: &[&str]) -> Vec<String> {
+    let mut full = vec!["--name"];
+    full.extend_from_slice(args);
+    let report = fx.search_json(&full);
+    let mut names: Vec<String> = result_paths(&report)
+        .iter()
+        .map(|p| p.rsplit('/').next().unwrap().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn sharp_s_matches_double_s() {
+    let fx = Fixture::new();
+    fx.file("Straße.txt", "");
+    fx.file("STRASSE.md", "");
+
+    assert_eq!(names(&fx, &["strasse"]), ["STRASSE.md", "Straße.txt"]);
+    assert_eq!(names(&fx, &["straße"]), ["STRASSE.md", "Straße.txt"]);
+    assert_eq!(names(&fx, &["straße", "--no-unicode-fold"]), ["Straße.txt"]);
+}
+
+#[test]
+fn decomposed_file_name_matches_composed_query() {
+    let fx = Fixture::new();
+    fx.file("Cafe\u{301}.txt", "");
+
+    assert_eq!(names(&fx, &["caf\u{e9}"]), ["Cafe\u{301}.txt"]);
+    assert!(names(&fx, &["caf\u{e9}", "--no-unicode-fold"]).is_empty());
+}
+
+#[test]
+fn dotless_i_is_not_a_plain_i() {
+    let fx = Fixture::new();
+    fx.file("ılık.txt", "");
+    fx.file("ILIK.txt", "");
+
+    assert_eq!(names(&fx, &["ılık"]), ["ılık.txt"]);
+    assert_eq!(names(&fx, &["ilik"]), ["ILIK.txt"]);
+}
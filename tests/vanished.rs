@@ -0,0 +1,103 @@
+mod common;
+
+use common::Fixture;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Stdio};
+
+fn start_slow_scan(fx: &Fixture, format: &str) -> Child {
+    fx.file("big.txt", format!("invoice\n{}", "x".repeat(64 * 1024)));
+    fx.file("gone.txt", "invoice\n");
+    let dir = fx.path().to_str().unwrap();
+    fx.command()
+        .args([
+            "search",
+            "--dir",
+            dir,
+            "--content",
+            "invoice",
+            "--schedule",
+            "size",
+            "--throttle",
+            "0.02",
+            "--progress-json",
+            "--progress-interval",
+            "10",
+            "--show-skipped",
+            "--format",
+            format,
+        ])
+        .env("RAYON_NUM_THREADS", "1")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn rustfilefinder")
+}
+
+fn delete_once_scanning(fx: &Fixture, child: &mut Child) -> String {
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    loop {
+        let mut line = String::new();
+        assert!(
+            stderr.read_line(&mut line).unwrap() > 0,
+            "no progress event"
+        );
+        if line.contains("\"progress\"") {
+            break;
+        }
+    }
+    std::fs::remove_file(fx.path().join("gone.txt")).expect("delete gone.txt");
+
+    let mut stdout = String::new();
+    child
+        .stdout
+        .take()
+        .unwrap()
+        .read_to_string(&mut stdout)
+        .unwrap();
+    std::io::copy(&mut stderr, &mut std::io::sink()).unwrap();
+    assert!(child.wait().unwrap().success());
+    stdout
+}
+
+#[test]
+fn file_deleted_after_discovery_counts_as_vanished() {
+    let fx = Fixture::new();
+    let mut child = start_slow_scan(&fx, "json");
+    let report: Value = serde_json::from_str(&delete_once_scanning(&fx, &mut child)).unwrap();
+    let stats = &report["stats"];
+
+    assert_eq!(stats["files_vanished"], 1);
+    assert_eq!(stats["skips"]["vanished"], 1);
+    assert_eq!(stats["skips"]["unreadable_text"], 0);
+    let vanished = stats["vanished_paths"].as_array().unwrap();
+    assert_eq!(vanished.len(), 1);
+    assert!(vanished[0].as_str().unwrap().ends_with("gone.txt"));
+    assert_eq!(report["results"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn markdown_lists_vanished_paths() {
+    let fx = Fixture::new();
+    let mut child = start_slow_scan(&fx, "md");
+    let md = delete_once_scanning(&fx, &mut child);
+
+    let line = md
+        .lines()
+        .position(|l| l.starts_with("- Skipped (vanished before scan): **1**"))
+        .expect("vanished count");
+    let next = md.lines().nth(line + 1).unwrap();
+    assert!(
+        next.starts_with("  - `") && next.ends_with("gone.txt`"),
+        "{md}"
+    );
+}
+
+#[test]
+fn vanished_paths_need_show_skipped() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "invoice\n");
+    let report = fx.search_json(&["--content", "invoice"]);
+    assert_eq!(report["stats"]["files_vanished"], 0);
+    assert!(report["stats"].get("vanished_paths").is_none());
+}
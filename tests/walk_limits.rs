@@ -0,0 +1,44 @@
+mod common;
+
+use common::{result_paths, Fixture};
+
+fn nested(fx: &Fixture) {
+    fx.file("root.txt", "needle\n");
+    fx.file("a/one.txt", "needle\n");
+    fx.file("a/b/two.txt", "needle\n");
+    fx.file("a/b/c/three.txt", "needle\n");
+}
+
+#[test]
+fn max_depth_counts_levels_below_the_root() {
+    let fx = Fixture::new();
+    nested(&fx);
+    let cases: [(&str, &[&str], u64); 4] = [
+        ("0", &["root.txt"], 1),
+        ("1", &["one.txt", "root.txt"], 1),
+        ("2", &["one.txt", "root.txt", "two.txt"], 1),
+        ("3", &["one.txt", "root.txt", "three.txt", "two.txt"], 0),
+    ];
+    for (depth, want, limited) in cases {
+        let report = fx.search_json(&["--content", "needle", "--max-depth", depth]);
+        let mut paths = result_paths(&report);
+        paths.sort();
+        assert_eq!(paths, want, "--max-depth {depth}");
+        assert_eq!(
+            report["stats"]["dirs_depth_limited"], limited,
+            "--max-depth {depth}"
+        );
+    }
+}
+
+#[test]
+fn max_entries_aborts_discovery() {
+    let fx = Fixture::new();
+    nested(&fx);
+    let over = fx.search(&["--content", "needle", "--max-entries", "3"]);
+    assert_eq!(over.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&over.stderr).contains("--max-entries 3"));
+
+    let report = fx.search_json(&["--content", "needle", "--max-entries", "100"]);
+    assert_eq!(result_paths(&report).len(), 4);
+}
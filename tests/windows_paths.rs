@@ -0,0 +1,55 @@
+#![cfg(windows)]
+
+mod common;
+
+use common::{result_paths, results, Fixture};
+use std::fs;
+use std::path::PathBuf;
+
+fn verbatim(fx: &Fixture, rel: &str) -> PathBuf {
+    PathBuf::from(format!(r"\\?\{}\{rel}", fx.path().display()))
+}
+
+#[test]
+fn files_past_max_path_are_scanned() {
+    let fx = Fixture::new();
+    let deep = (0..12)
+        .map(|i| format!("node_modules_{i:02}_{}", "x".repeat(16)))
+        .collect::<Vec<_>>()
+        .join(r"\");
+    let dir = verbatim(&fx, &deep);
+    fs::create_dir_all(&dir).expect("create deep dir");
+    fs::write(dir.join("deep.txt"), "needle\n").expect("write deep file");
+    assert!(fx.path().join(&deep).join("deep.txt").as_os_str().len() > 260);
+
+    let report = fx.search_json(&["--content", "needle"]);
+    let paths = result_paths(&report);
+    assert_eq!(paths.len(), 1);
+    assert!(paths[0].ends_with("deep.txt"));
+    assert!(!paths[0].starts_with(r"\\?\"));
+    assert_eq!(results(&report)[0]["matched_content"], true);
+    assert_eq!(report["stats"]["files_scanned_text"], 1);
+}
+
+#[test]
+fn drive_letters_are_upper_case_in_results() {
+    let fx = Fixture::new();
+    fx.file("a.txt", "needle\n");
+    let root = fx.path().to_string_lossy().to_string();
+    let lower = format!("{}{}", root[..1].to_ascii_lowercase(), &root[1..]);
+
+    let out = fx.run(&[
+        "search",
+        "--dir",
+        &lower,
+        "--content",
+        "needle",
+        "--format",
+        "json",
+    ]);
+    assert!(out.status.success());
+    let report = serde_json::from_slice(&out.stdout).expect("search output is JSON");
+    let paths = result_paths(&report);
+    assert_eq!(paths.len(), 1);
+    assert!(paths[0].chars().next().unwrap().is_ascii_uppercase());
+}